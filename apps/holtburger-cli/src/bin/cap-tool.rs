@@ -0,0 +1,311 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use holtburger_core::protocol::messages::{GameMessage, flags, opcodes};
+use holtburger_core::session::capture::{CaptureReader, Direction};
+use holtburger_core::session::decode::{CaptureDecoder, DecodedPacket};
+use holtburger_core::session::{pcap, scrub};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// Capture file (v1/v2 capture, pcap or pcapng)
+    #[arg(value_name = "FILE")]
+    capture: String,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Print every packet and the messages it completes
+    Timeline {
+        /// One JSON object per packet instead of text
+        #[arg(long)]
+        json: bool,
+        /// Skip packets that complete no messages
+        #[arg(long)]
+        messages_only: bool,
+    },
+    /// Count messages and bytes per opcode
+    Stats,
+    /// List opcodes that GameMessage::unpack does not decode, with sample bytes
+    Unknown {
+        /// Sample bytes to show per opcode
+        #[arg(short, long, default_value_t = 32)]
+        bytes: usize,
+    },
+    /// Write raw message bodies to a directory
+    Extract {
+        #[arg(short, long, value_name = "DIR")]
+        output: PathBuf,
+        /// Only extract this opcode (hex)
+        #[arg(long)]
+        opcode: Option<String>,
+    },
+    /// Convert the capture to pcapng for Wireshark
+    ExportPcapng {
+        #[arg(short, long, value_name = "OUT")]
+        output: String,
+        /// Address to give the client side, which captures do not record
+        #[arg(long, default_value = pcap::DEFAULT_CLIENT_ADDR)]
+        client: SocketAddr,
+    },
+    /// Write a copy with the account, character names and GUIDs replaced and the password
+    /// redacted, safe to share
+    Anonymize {
+        #[arg(short, long, value_name = "OUT")]
+        output: String,
+        #[arg(long)]
+        compress: bool,
+    },
+    /// Convert a pcap or pcapng file into a capture
+    ImportPcap {
+        #[arg(short, long, value_name = "OUT")]
+        output: String,
+        #[arg(long)]
+        compress: bool,
+    },
+}
+
+/// All packets in a capture, decoded, along with the time they are measured from.
+fn decode_capture(path: &str) -> Result<(u64, Vec<DecodedPacket>)> {
+    let mut reader = CaptureReader::open(path)?;
+    let mut decoder = CaptureDecoder::new();
+    let mut packets = Vec::new();
+    while let Some(entry) = reader.read_next()? {
+        packets.extend(decoder.decode(&entry));
+    }
+    let start = reader
+        .header()
+        .map(|h| h.started_at_us)
+        .or_else(|| packets.first().map(|p| p.timestamp_us))
+        .unwrap_or(0);
+    Ok((start, packets))
+}
+
+fn message_opcode(body: &[u8]) -> u32 {
+    body.get(..4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .unwrap_or(0)
+}
+
+fn opcode_name(opcode: u32) -> &'static str {
+    opcodes::name(opcode).unwrap_or("?")
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Inbound => "in",
+        Direction::Outbound => "out",
+    }
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn parse_hex(value: &str) -> Result<u32> {
+    Ok(u32::from_str_radix(
+        value.trim_start_matches("0x").trim_start_matches("0X"),
+        16,
+    )?)
+}
+
+fn print_timeline(start: u64, packets: &[DecodedPacket], as_json: bool, messages_only: bool) {
+    for packet in packets {
+        if messages_only && packet.messages.is_empty() {
+            continue;
+        }
+        let time = packet.timestamp_us.saturating_sub(start) as f64 / 1_000_000.0;
+        let header = &packet.header;
+
+        if as_json {
+            let messages: Vec<_> = packet
+                .messages
+                .iter()
+                .map(|body| {
+                    let opcode = message_opcode(body);
+                    json!({
+                        "opcode": format!("0x{:04X}", opcode),
+                        "name": opcodes::name(opcode),
+                        "size": body.len(),
+                        "decoded": format!("{:?}", GameMessage::unpack(body)),
+                    })
+                })
+                .collect();
+            let line = json!({
+                "direction": direction_name(packet.direction),
+                "time": time,
+                "timestamp_us": packet.timestamp_us,
+                "addr": packet.addr.to_string(),
+                "sequence": header.sequence,
+                "id": header.id,
+                "flags": flags::names(header.flags),
+                "duplicate": packet.duplicate,
+                "messages": messages,
+            });
+            println!("{}", line);
+            continue;
+        }
+
+        println!(
+            "{:>10.3} {} {} Seq={} ID={} Flags=[{}]{}",
+            time,
+            match packet.direction {
+                Direction::Inbound => "<<<",
+                Direction::Outbound => ">>>",
+            },
+            packet.addr,
+            header.sequence,
+            header.id,
+            flags::names(header.flags).join("|"),
+            if packet.duplicate {
+                " (retransmit)"
+            } else {
+                ""
+            }
+        );
+        for body in &packet.messages {
+            let opcode = message_opcode(body);
+            println!(
+                "{:>14} 0x{:04X} {} {:?}",
+                "",
+                opcode,
+                opcode_name(opcode),
+                GameMessage::unpack(body)
+            );
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Commands::Timeline {
+            json,
+            messages_only,
+        } => {
+            let (start, packets) = decode_capture(&cli.capture)?;
+            print_timeline(start, &packets, json, messages_only);
+        }
+        Commands::Stats => {
+            let (_, packets) = decode_capture(&cli.capture)?;
+            let mut counts: BTreeMap<(&str, u32), (usize, usize)> = BTreeMap::new();
+            for packet in &packets {
+                for body in &packet.messages {
+                    let entry = counts
+                        .entry((direction_name(packet.direction), message_opcode(body)))
+                        .or_default();
+                    entry.0 += 1;
+                    entry.1 += body.len();
+                }
+            }
+
+            let retransmits = packets.iter().filter(|p| p.duplicate).count();
+            println!(
+                "Packets: {} ({} retransmits), Messages: {}",
+                packets.len(),
+                retransmits,
+                counts.values().map(|c| c.0).sum::<usize>()
+            );
+            let mut rows: Vec<_> = counts.into_iter().collect();
+            rows.sort_by(|a, b| b.1.0.cmp(&a.1.0).then(a.0.cmp(&b.0)));
+            println!(
+                "{:<4} {:<8} {:<36} {:>8} {:>10}",
+                "Dir", "Opcode", "Name", "Count", "Bytes"
+            );
+            for ((direction, opcode), (count, bytes)) in rows {
+                println!(
+                    "{:<4} 0x{:04X}   {:<36} {:>8} {:>10}",
+                    direction,
+                    opcode,
+                    opcode_name(opcode),
+                    count,
+                    bytes
+                );
+            }
+        }
+        Commands::Unknown { bytes } => {
+            let (_, packets) = decode_capture(&cli.capture)?;
+            // Opcode -> (count, first body seen)
+            let mut unknown: BTreeMap<u32, (usize, &[u8])> = BTreeMap::new();
+            for body in packets.iter().flat_map(|p| &p.messages) {
+                if let GameMessage::Unknown { .. } = GameMessage::unpack(body) {
+                    unknown.entry(message_opcode(body)).or_insert((0, body)).0 += 1;
+                }
+            }
+
+            if unknown.is_empty() {
+                println!("No unknown opcodes.");
+            }
+            for (opcode, (count, sample)) in unknown {
+                println!(
+                    "0x{:04X} {:<36} x{:<6} ({} bytes) {}",
+                    opcode,
+                    opcode_name(opcode),
+                    count,
+                    sample.len(),
+                    hex(&sample[..sample.len().min(bytes)])
+                );
+            }
+        }
+        Commands::Extract { output, opcode } => {
+            let filter = opcode.as_deref().map(parse_hex).transpose()?;
+            let (_, packets) = decode_capture(&cli.capture)?;
+            std::fs::create_dir_all(&output)?;
+
+            let mut written = 0;
+            let messages = packets
+                .iter()
+                .flat_map(|p| p.messages.iter().map(move |body| (p.direction, body)));
+            for (index, (direction, body)) in messages.enumerate() {
+                let opcode = message_opcode(body);
+                if filter.is_some_and(|f| f != opcode) {
+                    continue;
+                }
+                let name = format!(
+                    "{:05}-{}-{:04X}.bin",
+                    index,
+                    direction_name(direction),
+                    opcode
+                );
+                std::fs::write(output.join(name), body)?;
+                written += 1;
+            }
+            println!("Extracted {} messages to {:?}", written, output);
+        }
+        Commands::ExportPcapng { output, client } => {
+            let count = pcap::export_file(&cli.capture, &output, client)?;
+            println!("Exported {} packets to {}", count, output);
+        }
+        Commands::Anonymize { output, compress } => {
+            let report = scrub::anonymize_file(&cli.capture, &output, compress)?;
+            println!(
+                "Anonymized {} characters{} ({} replacements, {} packets rewritten) to {}",
+                report.characters,
+                report
+                    .account
+                    .map(|a| format!(" and the account (now {})", a))
+                    .unwrap_or_default(),
+                report.replacements,
+                report.packets_rewritten,
+                output
+            );
+        }
+        Commands::ImportPcap { output, compress } => {
+            let count = pcap::import_file(&cli.capture, &output, compress)?;
+            println!("Imported {} packets to {}", count, output);
+        }
+    }
+
+    Ok(())
+}
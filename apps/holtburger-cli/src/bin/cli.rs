@@ -1,6 +1,8 @@
 use anyhow::Result;
 use clap::Parser;
-use holtburger_core::{Client, ClientCommand, ClientEvent};
+use holtburger_core::session::replay::ReplaySpeed;
+use holtburger_core::session::scrub::ScrubLevel;
+use holtburger_core::{Client, ClientCommand, ClientEvent, ReconnectPolicy};
 use tokio::sync::mpsc;
 
 #[derive(clap::Subcommand, Debug, Clone)]
@@ -29,8 +31,20 @@ struct Args {
     verbose: bool,
     #[arg(long)]
     capture: Option<String>,
+    /// Compress the capture file
+    #[arg(long)]
+    compress_capture: bool,
+    /// What to redact from captures: off, password or account
+    #[arg(long, default_value = "password")]
+    scrub: ScrubLevel,
     #[arg(long)]
     replay: Option<String>,
+    /// Replay pacing: "real", a factor such as "10x", or "max"
+    #[arg(long, default_value = "max")]
+    replay_speed: ReplaySpeed,
+    /// Reconnect and re-enter the world automatically if the connection drops
+    #[arg(long)]
+    reconnect: bool,
     #[command(subcommand)]
     command: Commands,
 }
@@ -54,7 +68,11 @@ async fn main() -> Result<()> {
     };
 
     let mut client = if let Some(replay_path) = args.replay {
-        Client::new_replay(&replay_path, &args.account, character_pref.clone())?
+        let client = Client::new_replay(&replay_path, &args.account, character_pref.clone())?;
+        if let Some(control) = client.replay_control() {
+            control.set_speed(args.replay_speed);
+        }
+        client
     } else {
         Client::new(
             &args.server,
@@ -76,10 +94,18 @@ async fn main() -> Result<()> {
             capture_path = format!("caps/{}", capture_path);
         }
 
-        client.session.set_capture(&capture_path)?;
+        client.session.set_capture(
+            &capture_path,
+            &args.account,
+            args.compress_capture,
+            args.scrub,
+        )?;
     }
     client.set_event_tx(event_tx);
     client.set_command_rx(command_rx);
+    if args.reconnect {
+        client.set_reconnect_policy(ReconnectPolicy::default());
+    }
 
     let password = args.password.clone();
     let client_handle = tokio::spawn(async move {
@@ -121,6 +147,7 @@ async fn main() -> Result<()> {
                     Some(event) = event_rx.recv() => {
                         match event {
                             ClientEvent::Message(msg) => { println!("{}", msg.text); }
+                            ClientEvent::Reconnect(stage) => { println!("{}", stage); }
                             ClientEvent::CharacterList(chars) => {
                                 println!("Available characters: {:?}", chars.iter().map(|c| &c.1).collect::<Vec<_>>());
                                 if character_pref.is_none() {
@@ -10,7 +10,11 @@ use crossterm::{
 use holtburger_cli::classification::{self};
 use holtburger_cli::ui::{self, AppState};
 use holtburger_core::protocol::properties::*;
-use holtburger_core::{Client, ClientCommand, ClientEvent, ClientState};
+use holtburger_core::session::replay::ReplaySpeed;
+use holtburger_core::session::scrub::ScrubLevel;
+use holtburger_core::{
+    Client, ClientCommand, ClientEvent, ClientState, ReconnectPolicy, ReplayCommand,
+};
 use ratatui::{Terminal, backend::CrosstermBackend};
 use std::fs::File;
 use std::io::{self, Write};
@@ -80,12 +84,27 @@ struct Args {
     character: Option<String>,
     #[arg(long)]
     capture: Option<String>,
+    /// Compress the capture file
+    #[arg(long)]
+    compress_capture: bool,
+    /// What to redact from captures: off, password or account
+    #[arg(long, default_value = "password")]
+    scrub: ScrubLevel,
     #[arg(short, long)]
     log: Option<String>,
     #[arg(short, long)]
     verbose: bool,
     #[arg(long)]
     no_emojis: bool,
+    /// Reconnect and re-enter the world automatically if the connection drops
+    #[arg(long)]
+    reconnect: bool,
+    /// Replay a capture instead of connecting to a server
+    #[arg(long)]
+    replay: Option<String>,
+    /// Replay pacing: "real", a factor such as "10x", or "max"
+    #[arg(long, default_value = "real")]
+    replay_speed: ReplaySpeed,
 }
 
 fn refresh_context_buffer(state: &mut AppState) {
@@ -95,6 +114,42 @@ fn refresh_context_buffer(state: &mut AppState) {
     state.context_buffer.clear();
 }
 
+/// Parses replay commands typed into the input line: `/pause`, `/step`, `/speed <speed>`,
+/// `/seek mm:ss` (or seconds) and `/seek #N` (message index).
+fn parse_replay_input(input: &str) -> Option<Result<ReplayCommand, String>> {
+    let mut parts = input.split_whitespace();
+    let command = match parts.next()? {
+        "/pause" => Ok(ReplayCommand::TogglePause),
+        "/step" => Ok(ReplayCommand::Step),
+        "/speed" => match parts.next() {
+            Some(speed) => speed
+                .parse()
+                .map(ReplayCommand::SetSpeed)
+                .map_err(|e: anyhow::Error| e.to_string()),
+            None => Err("Usage: /speed <real|max|10x>".to_string()),
+        },
+        "/seek" => match parts.next() {
+            Some(index) if index.starts_with('#') => index[1..]
+                .parse()
+                .map(ReplayCommand::SeekMessage)
+                .map_err(|_| format!("Invalid message index '{}'", index)),
+            Some(time) => {
+                let (mins, secs) = time.split_once(':').unwrap_or(("0", time));
+                match (mins.parse::<u64>(), secs.parse::<f64>()) {
+                    (Ok(mins), Ok(secs)) if secs >= 0.0 => Ok(ReplayCommand::SeekTime(
+                        std::time::Duration::from_secs(mins * 60)
+                            + std::time::Duration::from_secs_f64(secs),
+                    )),
+                    _ => Err(format!("Invalid time '{}'", time)),
+                }
+            }
+            None => Err("Usage: /seek <mm:ss|#message>".to_string()),
+        },
+        _ => return None,
+    };
+    Some(command)
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
@@ -130,13 +185,23 @@ async fn main() -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut client = Client::new(
-        &args.server,
-        args.port,
-        &args.account,
-        args.character.clone(),
-    )
-    .await?;
+    let mut client = if let Some(replay_path) = &args.replay {
+        let client = Client::new_replay(replay_path, &args.account, args.character.clone())?;
+        if let Some(control) = client.replay_control() {
+            control.set_speed(args.replay_speed);
+            // Keep the UI around at the end so the replay can still be rewound
+            control.set_hold_at_end(true);
+        }
+        client
+    } else {
+        Client::new(
+            &args.server,
+            args.port,
+            &args.account,
+            args.character.clone(),
+        )
+        .await?
+    };
 
     if let Some(mut capture_path) = args.capture {
         // Ensure caps directory exists
@@ -151,10 +216,18 @@ async fn main() -> Result<()> {
             capture_path = format!("caps/{}", capture_path);
         }
 
-        client.session.set_capture(&capture_path)?;
+        client.session.set_capture(
+            &capture_path,
+            &args.account,
+            args.compress_capture,
+            args.scrub,
+        )?;
     }
     client.set_event_tx(event_tx);
     client.set_command_rx(command_rx);
+    if args.reconnect {
+        client.set_reconnect_policy(ReconnectPolicy::default());
+    }
 
     let mut app_state = AppState {
         account_name: args.account.clone(),
@@ -187,6 +260,8 @@ async fn main() -> Result<()> {
         player_enchantments: Vec::new(),
         entities: std::collections::HashMap::new(),
         server_time: None,
+        connection_stats: None,
+        replay: None,
         use_emojis: !args.no_emojis,
     };
 
@@ -291,7 +366,20 @@ async fn main() -> Result<()> {
                                     }
                                     app_state.input_history.push(input.clone());
                                     app_state.history_index = None;
-                                    let _ = command_tx.send(ClientCommand::Talk(input));
+                                    match parse_replay_input(&input) {
+                                        Some(Ok(cmd)) => {
+                                            let _ = command_tx.send(ClientCommand::Replay(cmd));
+                                        }
+                                        Some(Err(e)) => {
+                                            app_state.messages.push(holtburger_core::ChatMessage {
+                                                kind: holtburger_core::MessageKind::Error,
+                                                text: e,
+                                            });
+                                        }
+                                        None => {
+                                            let _ = command_tx.send(ClientCommand::Talk(input));
+                                        }
+                                    }
                                     app_state.scroll_offset = 0;
                                     app_state.focused_pane = app_state.previous_focused_pane;
                                 } else {
@@ -514,6 +602,29 @@ async fn main() -> Result<()> {
                                                             "GfxID:  {:?}",
                                                             e.gfx_id
                                                         ));
+                                                        lines.push(format!(
+                                                            "Setup:  {:?}",
+                                                            e.physics.setup_id
+                                                        ));
+                                                        if let Some(value) = e.value() {
+                                                            lines
+                                                                .push(format!("Value:  {}", value));
+                                                        }
+                                                        if let Some(burden) = e.burden() {
+                                                            lines.push(format!(
+                                                                "Burden: {}",
+                                                                burden
+                                                            ));
+                                                        }
+                                                        if let Some(stack) = e.stack_size() {
+                                                            lines.push(format!(
+                                                                "Stack:  {}/{}",
+                                                                stack,
+                                                                e.weenie
+                                                                    .max_stack_size
+                                                                    .map_or(stack, u32::from)
+                                                            ));
+                                                        }
                                                         lines.push(format!(
                                                             "Vel:    {:?}",
                                                             e.velocity
@@ -703,13 +814,30 @@ async fn main() -> Result<()> {
                                 }
                             }
                         }
-                        KeyCode::Backspace => {
-                            if app_state.state == ui::UIState::Chat
-                                && app_state.focused_pane == ui::FocusedPane::Input
-                            {
-                                app_state.input.pop();
+                        KeyCode::F(5) => {
+                            let _ =
+                                command_tx.send(ClientCommand::Replay(ReplayCommand::TogglePause));
+                        }
+                        KeyCode::F(6) => {
+                            let _ = command_tx.send(ClientCommand::Replay(ReplayCommand::Step));
+                        }
+                        KeyCode::F(7) | KeyCode::F(8) => {
+                            if let Some((status, _)) = &app_state.replay {
+                                let speed = if key.code == KeyCode::F(7) {
+                                    status.speed.slower()
+                                } else {
+                                    status.speed.faster()
+                                };
+                                let _ = command_tx
+                                    .send(ClientCommand::Replay(ReplayCommand::SetSpeed(speed)));
                             }
                         }
+                        KeyCode::Backspace
+                            if app_state.state == ui::UIState::Chat
+                                && app_state.focused_pane == ui::FocusedPane::Input =>
+                        {
+                            app_state.input.pop();
+                        }
                         KeyCode::Up => match app_state.state {
                             ui::UIState::Chat => match app_state.focused_pane {
                                 ui::FocusedPane::Input => {
@@ -1054,6 +1182,18 @@ async fn main() -> Result<()> {
                         WorldEvent::EntityDespawned(guid) => {
                             app_state.entities.remove(&guid);
                         }
+                        WorldEvent::ContainerContents { container, items } => {
+                            for guid in items {
+                                if let Some(entity) = app_state.entities.get_mut(&guid) {
+                                    entity.container_id = Some(container);
+                                }
+                            }
+                        }
+                        WorldEvent::EntityMotionChanged { guid, movement } => {
+                            if let Some(entity) = app_state.entities.get_mut(&guid) {
+                                entity.movement = Some(movement);
+                            }
+                        }
                         WorldEvent::EntityMoved { guid, pos } => {
                             if let Some(entity) = app_state.entities.get_mut(&guid) {
                                 entity.position = pos;
@@ -1107,6 +1247,26 @@ async fn main() -> Result<()> {
                     app_state.logon_retry = logon_retry;
                     app_state.enter_retry = enter_retry;
                 }
+                ClientEvent::ConnectionStats(stats) => {
+                    app_state.connection_stats = Some(stats);
+                }
+                ClientEvent::Reconnect(stage) => {
+                    app_state.messages.push(holtburger_core::ChatMessage {
+                        kind: holtburger_core::MessageKind::Warning,
+                        text: stage.to_string(),
+                    });
+                }
+                ClientEvent::Replay {
+                    status,
+                    message_index,
+                } => {
+                    app_state.replay = Some((status, message_index));
+                }
+                ClientEvent::WorldReset => {
+                    app_state.reset_world();
+                    refresh_context_buffer(&mut app_state);
+                }
+                ClientEvent::Custom(_) => {}
             }
         }
     }
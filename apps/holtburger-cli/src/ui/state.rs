@@ -2,6 +2,8 @@ use super::types::{ContextView, FocusedPane, NearbyTab, UIState, WIDTH_BREAKPOIN
 use crate::classification;
 use crate::ui::widgets::effects::get_enchantment_name;
 use holtburger_core::protocol::messages::Enchantment;
+use holtburger_core::session::replay::ReplayStatus;
+use holtburger_core::session::stats::ConnectionStats;
 use holtburger_core::world::entity::Entity;
 use holtburger_core::world::position::WorldPosition;
 use holtburger_core::world::stats::{Attribute, Skill, Vital};
@@ -40,10 +42,29 @@ pub struct AppState {
     pub player_enchantments: Vec<Enchantment>,
     pub entities: HashMap<u32, Entity>,
     pub server_time: Option<(f64, Instant)>,
+    pub connection_stats: Option<ConnectionStats>,
+    /// Replay progress and message index, when replaying a capture.
+    pub replay: Option<(ReplayStatus, usize)>,
     pub use_emojis: bool,
 }
 
 impl AppState {
+    /// Forgets everything learned from the server, e.g. when a replay is rewound.
+    pub fn reset_world(&mut self) {
+        self.character_name = None;
+        self.player_guid = None;
+        self.attributes.clear();
+        self.vitals.clear();
+        self.skills.clear();
+        self.characters.clear();
+        self.player_pos = None;
+        self.player_enchantments.clear();
+        self.entities.clear();
+        self.server_time = None;
+        self.selected_nearby_index = 0;
+        self.state = UIState::Chat;
+    }
+
     pub fn current_server_time(&self) -> f64 {
         match self.server_time {
             Some((server_val, local_then)) => {
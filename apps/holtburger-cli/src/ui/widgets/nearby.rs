@@ -15,6 +15,7 @@ pub fn get_nearby_list_items(state: &AppState) -> Vec<ListItem<'static>> {
                 .int_properties
                 .get(&(PropertyInt::RadarBlipColor as u32))
                 .cloned()
+                .or(e.weenie.radar_blip_color.map(i32::from))
                 .unwrap_or(0);
             let color = match color_val as u8 {
                 c if c == RadarColor::Blue as u8 => Color::Blue,
@@ -39,11 +40,14 @@ pub fn get_nearby_list_items(state: &AppState) -> Vec<ListItem<'static>> {
                 class.label()
             };
 
-            let display_name = if e.name.trim().is_empty() {
+            let mut display_name = if e.name.trim().is_empty() {
                 format!("<{:08X}>", e.guid)
             } else {
                 e.name.clone()
             };
+            if let Some(stack) = e.stack_size().filter(|&stack| stack > 1) {
+                display_name = format!("{} x{}", display_name, stack);
+            }
 
             let indent = "  ".repeat(*depth);
 
@@ -75,6 +75,34 @@ pub fn render_status_bar(f: &mut Frame, state: &AppState, area: Rect) {
         retry_info.push_str(&format!("[Enter:{}/{} {}s] ", current, max, secs));
     }
 
+    let net_info = match &state.connection_stats {
+        Some(stats) => match stats.smoothed_rtt {
+            Some(rtt) => format!(
+                "[{}ms {:.1}%] ",
+                rtt.as_millis(),
+                stats.loss_ratio() * 100.0
+            ),
+            None => format!("[--ms {:.1}%] ", stats.loss_ratio() * 100.0),
+        },
+        None => String::new(),
+    };
+
+    let replay_info = match &state.replay {
+        Some((status, message_index)) => {
+            let clock =
+                |d: std::time::Duration| format!("{}:{:02}", d.as_secs() / 60, d.as_secs() % 60);
+            format!(
+                "[{} {}/{} #{} {}] ",
+                if status.paused { "⏸" } else { "▶" },
+                clock(status.position),
+                clock(status.duration),
+                message_index,
+                status.speed
+            )
+        }
+        None => String::new(),
+    };
+
     let status_emoji = match state.core_state {
         ClientState::Connected => "🔌",
         ClientState::CharacterSelection(_) => "👥",
@@ -84,8 +112,8 @@ pub fn render_status_bar(f: &mut Frame, state: &AppState, area: Rect) {
 
     let current_char = state.character_name.as_deref().unwrap_or("Selecting...");
     let info_line = format!(
-        "{}:{} <{}> {} {}",
-        state.account_name, current_char, pos_info, status_emoji, retry_info
+        "{}:{} <{}> {} {}{}{}",
+        state.account_name, current_char, pos_info, status_emoji, replay_info, net_info, retry_info
     );
 
     let info_para = Paragraph::new(info_line)
@@ -0,0 +1,212 @@
+//! Decoders for messages the client does not understand itself.
+//!
+//! A `HandlerRegistry` maps opcodes (and 0xF7B0 game-event types) to closures that are
+//! handed the raw bytes and may produce an event of their own type. Those events reach the
+//! `ClientEvent` stream as `ClientEvent::Custom`, so servers with custom messages can be
+//! supported without touching `protocol::messages`.
+
+use crate::protocol::bodies::GameEventHeader;
+use crate::protocol::messages::opcodes;
+use crate::protocol::parse::Stream;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// An event produced by a registered handler. Receivers downcast it to the type the handler
+/// returned.
+#[derive(Clone)]
+pub struct CustomEvent {
+    type_name: &'static str,
+    payload: Arc<dyn Any + Send + Sync>,
+}
+
+impl CustomEvent {
+    pub fn new<T: Any + Send + Sync>(payload: T) -> Self {
+        Self {
+            type_name: std::any::type_name::<T>(),
+            payload: Arc::new(payload),
+        }
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.payload.downcast_ref()
+    }
+
+    pub fn is<T: Any>(&self) -> bool {
+        self.payload.is::<T>()
+    }
+
+    /// Name of the payload type, for logging.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl std::fmt::Debug for CustomEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CustomEvent({})", self.type_name)
+    }
+}
+
+/// A 0xF7B0 game event as handed to a game-event handler.
+#[derive(Debug, Clone, Copy)]
+pub struct RawGameEvent<'a> {
+    pub guid: u32,
+    pub sequence: u32,
+    pub event_type: u32,
+    /// The event body, after the game event header.
+    pub data: &'a [u8],
+}
+
+type MessageHandler = Box<dyn FnMut(&[u8]) -> Option<CustomEvent> + Send>;
+type GameEventHandler = Box<dyn FnMut(&RawGameEvent) -> Option<CustomEvent> + Send>;
+
+/// Handlers for raw messages, keyed by opcode or game-event type.
+///
+/// Handlers run alongside the client's own processing of a message, so they can also be
+/// used to observe messages the client already understands.
+#[derive(Default)]
+pub struct HandlerRegistry {
+    messages: HashMap<u32, Vec<MessageHandler>>,
+    game_events: HashMap<u32, Vec<GameEventHandler>>,
+}
+
+impl HandlerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `handler` with every message carrying `opcode`, opcode included.
+    pub fn on_message<T, F>(&mut self, opcode: u32, mut handler: F)
+    where
+        T: Any + Send + Sync,
+        F: FnMut(&[u8]) -> Option<T> + Send + 'static,
+    {
+        self.messages
+            .entry(opcode)
+            .or_default()
+            .push(Box::new(move |data| handler(data).map(CustomEvent::new)));
+    }
+
+    /// Calls `handler` with every game event of type `event_type`.
+    pub fn on_game_event<T, F>(&mut self, event_type: u32, mut handler: F)
+    where
+        T: Any + Send + Sync,
+        F: FnMut(&RawGameEvent) -> Option<T> + Send + 'static,
+    {
+        self.game_events
+            .entry(event_type)
+            .or_default()
+            .push(Box::new(move |event| handler(event).map(CustomEvent::new)));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty() && self.game_events.is_empty()
+    }
+
+    /// Runs the handlers for a whole message, and for the game event it carries if it is
+    /// one, returning the events they produced.
+    pub fn dispatch(&mut self, data: &[u8]) -> Vec<CustomEvent> {
+        let mut s = Stream::new(data, 0);
+        let Ok(opcode) = s.read::<u32>() else {
+            return Vec::new();
+        };
+        let mut events: Vec<CustomEvent> = self
+            .messages
+            .get_mut(&opcode)
+            .into_iter()
+            .flatten()
+            .filter_map(|handler| handler(data))
+            .collect();
+
+        if opcode == opcodes::GAME_EVENT
+            && !self.game_events.is_empty()
+            && let Ok(header) = s.read::<GameEventHeader>()
+        {
+            let event = RawGameEvent {
+                guid: header.guid,
+                sequence: header.sequence,
+                event_type: header.event_type,
+                data: s.rest(),
+            };
+            events.extend(self.dispatch_game_event(&event));
+        }
+        events
+    }
+
+    /// Runs the handlers for a game event, returning the events they produced.
+    fn dispatch_game_event(&mut self, event: &RawGameEvent) -> Vec<CustomEvent> {
+        self.game_events
+            .get_mut(&event.event_type)
+            .into_iter()
+            .flatten()
+            .filter_map(|handler| handler(event))
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for HandlerRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HandlerRegistry")
+            .field("messages", &self.messages.keys().collect::<Vec<_>>())
+            .field("game_events", &self.game_events.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Ping(u32);
+
+    #[test]
+    fn test_dispatch_message_by_opcode() {
+        let mut registry = HandlerRegistry::new();
+        registry.on_message(0xCAFE, |data| {
+            Some(Ping(u32::from_le_bytes(data.get(4..8)?.try_into().ok()?)))
+        });
+
+        let mut data = 0xCAFEu32.to_le_bytes().to_vec();
+        data.extend_from_slice(&7u32.to_le_bytes());
+        let events = registry.dispatch(&data);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].downcast_ref::<Ping>(), Some(&Ping(7)));
+        assert!(events[0].downcast_ref::<u32>().is_none());
+
+        // Other opcodes and handlers that decline produce nothing
+        assert!(registry.dispatch(&0xBEEFu32.to_le_bytes()).is_empty());
+        assert!(registry.dispatch(&0xCAFEu32.to_le_bytes()).is_empty());
+        assert!(registry.dispatch(&[0xFE]).is_empty());
+    }
+
+    #[test]
+    fn test_dispatch_game_event_runs_every_handler() {
+        let mut registry = HandlerRegistry::new();
+        let mut seen = 0;
+        registry.on_game_event(0x0999, move |event| {
+            seen += 1;
+            Some(Ping(event.sequence + seen))
+        });
+        registry.on_game_event(0x0999, |event| Some(event.data.to_vec()));
+        registry.on_game_event(0x0999, |event| Some(event.guid));
+
+        let mut data = Vec::new();
+        for word in [opcodes::GAME_EVENT, 0x50000001, 10, 0x0999] {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        data.extend_from_slice(&[1, 2]);
+        let events = registry.dispatch(&data);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].downcast_ref::<Ping>(), Some(&Ping(11)));
+        assert_eq!(events[1].downcast_ref::<Vec<u8>>(), Some(&vec![1, 2]));
+        // GUIDs are handed over as the u32 they are on the wire
+        assert_eq!(events[2].downcast_ref::<u32>(), Some(&0x50000001));
+        let events = registry.dispatch(&data);
+        assert_eq!(events[0].downcast_ref::<Ping>(), Some(&Ping(12)));
+
+        // A truncated header reaches no game-event handler
+        assert!(registry.dispatch(&data[..12]).is_empty());
+    }
+}
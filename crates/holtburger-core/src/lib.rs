@@ -1,17 +1,27 @@
 pub mod dat;
+pub mod handlers;
 pub mod math;
 pub mod protocol;
 pub mod session;
 pub mod world;
 
-use crate::protocol::crypto::Isaac;
+use crate::handlers::{CustomEvent, HandlerRegistry};
+use crate::math::Vector3;
+use crate::protocol::crypto::{Isaac, IsaacVerifier};
 use crate::protocol::messages::*;
 use crate::session::Session;
+use crate::session::replay::{ReplayControl, ReplaySpeed, ReplayStatus};
+use crate::session::scrub::{self, ScrubLevel};
+use crate::session::stats::{ConnectionStats, ECHO_INTERVAL};
+use crate::world::movement::MotionInput;
 use anyhow::{Result, anyhow};
 use std::net::SocketAddr;
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
+/// How often the player's position is sent while they are moving.
+const POSITION_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Debug, Clone)]
 pub enum MessageKind {
     Info,
@@ -51,6 +61,62 @@ pub enum ClientEvent {
         enter_retry: Option<(u32, u32, Option<Instant>)>,
     },
     World(Box<crate::world::WorldEvent>),
+    ConnectionStats(ConnectionStats),
+    Reconnect(ReconnectStage),
+    /// Replay progress, sent after each replay command and once a second while replaying.
+    Replay {
+        status: ReplayStatus,
+        /// Game messages handled since the start of the capture.
+        message_index: usize,
+    },
+    /// A replay was rewound; everything learned from it so far is gone.
+    WorldReset,
+    /// Produced by a handler registered in `Client::handlers`.
+    Custom(CustomEvent),
+}
+
+/// Progress of an automatic reconnect, from losing the session to being back in world.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReconnectStage {
+    ConnectionLost(String),
+    Attempting { attempt: u32, max_attempts: u32 },
+    LoggedIn,
+    EnteringWorld,
+    Restored,
+    GaveUp,
+}
+
+impl std::fmt::Display for ReconnectStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReconnectStage::ConnectionLost(reason) => write!(f, "Connection lost: {}", reason),
+            ReconnectStage::Attempting {
+                attempt,
+                max_attempts,
+            } => write!(f, "Reconnecting ({}/{})...", attempt, max_attempts),
+            ReconnectStage::LoggedIn => write!(f, "Reconnected, logged in"),
+            ReconnectStage::EnteringWorld => write!(f, "Re-entering world..."),
+            ReconnectStage::Restored => write!(f, "Connection restored"),
+            ReconnectStage::GaveUp => write!(f, "Gave up reconnecting"),
+        }
+    }
+}
+
+/// When and how hard `Client` tries to re-establish a dead session.
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// Treat the session as dead after this long without any inbound packet.
+    pub idle_timeout: Duration,
+    pub max_attempts: u32,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::from_secs(30),
+            max_attempts: 10,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -60,10 +126,34 @@ pub enum ClientCommand {
     Talk(String),
     Identify(u32),
     Use(u32),
+    /// A melee attack at medium height and full power.
     Attack(u32),
+    /// Any other game action. Actions are only sent once in world.
+    Action(GameActionKind),
+    /// Hold down a motion, replacing whatever the player was doing.
+    Move(MotionInput),
+    StopMoving,
+    /// Jump, charged from 0 to 1.
+    Jump(f32),
+    Replay(ReplayCommand),
     Quit,
 }
 
+/// Playback controls for a client replaying a capture.
+#[derive(Debug, Clone)]
+pub enum ReplayCommand {
+    Pause,
+    Resume,
+    TogglePause,
+    /// Deliver exactly one more packet, pausing first if needed.
+    Step,
+    SetSpeed(ReplaySpeed),
+    /// Seek to a point in the capture, relative to its first packet.
+    SeekTime(Duration),
+    /// Seek to just after the given number of game messages.
+    SeekMessage(usize),
+}
+
 #[derive(Debug, Clone)]
 struct RetryState {
     active: bool,
@@ -121,6 +211,8 @@ impl RetryState {
 pub struct Client {
     pub session: Session,
     pub world: crate::world::WorldState,
+    /// Decoders for custom or otherwise unhandled messages, run on every inbound message.
+    pub handlers: HandlerRegistry,
     account_name: String,
     characters: Vec<(u32, String)>,
     character_id: Option<u32>,
@@ -133,7 +225,20 @@ pub struct Client {
     logon_retry: RetryState,
     enter_retry: RetryState,
     pub message_dump_dir: Option<std::path::PathBuf>,
+    /// What to redact from dumped messages.
+    pub message_dump_scrub: ScrubLevel,
     message_counter: usize,
+    /// Pause the replay once `message_counter` reaches this.
+    replay_stop_at: Option<usize>,
+    /// Login server address for live sessions; replays cannot reconnect.
+    login_addr: Option<SocketAddr>,
+    reconnect_policy: Option<ReconnectPolicy>,
+    reconnect_retry: RetryState,
+    reconnecting: bool,
+    session_dead: bool,
+    last_packet_at: Instant,
+    /// When the player's position was last sent while moving.
+    last_position_report: Instant,
 }
 
 impl Client {
@@ -145,7 +250,9 @@ impl Client {
     ) -> Result<Self> {
         let target = format!("{}:{}", server_ip, server_port).parse::<SocketAddr>()?;
         let session = Session::new(target).await?;
-        Self::create_with_session(session, account_name, character_preference)
+        let mut client = Self::create_with_session(session, account_name, character_preference)?;
+        client.login_addr = Some(target);
+        Ok(client)
     }
 
     pub fn new_replay(
@@ -153,9 +260,10 @@ impl Client {
         account_name: &str,
         character_preference: Option<String>,
     ) -> Result<Self> {
-        // Replay doesn't strictly need a target addr, but we can use a dummy one
-        // Use 9001 for World server traffic (player spawns!)
-        let target = "127.0.0.1:9001".parse::<SocketAddr>().unwrap();
+        // Target the server the capture first heard from; referrals in the capture
+        // move us on to the world server just like a live session.
+        let target = crate::session::capture::first_inbound_addr(replay_path)?
+            .unwrap_or_else(|| "127.0.0.1:9000".parse().unwrap());
         let session = Session::new_replay(replay_path, target)?;
         Self::create_with_session(session, account_name, character_preference)
     }
@@ -182,6 +290,7 @@ impl Client {
         Ok(Client {
             session,
             world: crate::world::WorldState::new(portal_dat),
+            handlers: HandlerRegistry::new(),
             account_name: account_name.to_string(),
             characters: Vec::new(),
             character_id: None,
@@ -194,7 +303,16 @@ impl Client {
             logon_retry: RetryState::new(5),
             enter_retry: RetryState::new(5),
             message_dump_dir: None,
+            message_dump_scrub: ScrubLevel::default(),
             message_counter: 0,
+            replay_stop_at: None,
+            login_addr: None,
+            reconnect_policy: None,
+            reconnect_retry: RetryState::new(0),
+            reconnecting: false,
+            session_dead: false,
+            last_packet_at: Instant::now(),
+            last_position_report: Instant::now(),
         })
     }
 
@@ -206,6 +324,84 @@ impl Client {
         self.command_rx = Some(rx);
     }
 
+    /// Enables automatic reconnects. Without a policy a dead session ends `run`.
+    pub fn set_reconnect_policy(&mut self, policy: ReconnectPolicy) {
+        self.reconnect_retry = RetryState::new(policy.max_attempts);
+        self.reconnect_policy = Some(policy);
+    }
+
+    /// Playback controls, when this client is replaying a capture.
+    pub fn replay_control(&self) -> Option<ReplayControl> {
+        self.session.replay.clone()
+    }
+
+    fn send_reconnect_event(&self, stage: ReconnectStage) {
+        if let Some(tx) = &self.event_tx {
+            let _ = tx.send(ClientEvent::Reconnect(stage));
+        }
+    }
+
+    /// Whether a lost session should be recovered rather than ending `run`.
+    fn can_reconnect(&self) -> bool {
+        self.reconnect_policy.is_some() && self.login_addr.is_some()
+    }
+
+    /// Hands an error from the run loop to `connection_lost` when we can reconnect, so a
+    /// failed send or read does not end the client.
+    fn recover(&mut self, result: Result<()>) -> Result<()> {
+        match result {
+            Err(e) if self.can_reconnect() => {
+                self.connection_lost(&format!("Session error: {}", e));
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Marks the session as dead and schedules a reconnect attempt.
+    fn connection_lost(&mut self, reason: &str) {
+        if self.session_dead {
+            return;
+        }
+        log::warn!("Connection lost: {}", reason);
+        self.session_dead = true;
+        // A failed attempt leaves the existing schedule (and its attempt count) in charge
+        if !self.reconnecting {
+            self.reconnecting = true;
+            self.send_reconnect_event(ReconnectStage::ConnectionLost(reason.to_string()));
+            self.reconnect_retry.schedule();
+        }
+    }
+
+    /// Rebuilds the session against the login server and starts logging in again.
+    /// The character we were playing is re-entered once the character list arrives.
+    async fn attempt_reconnect(&mut self) -> Result<()> {
+        let Some(login_addr) = self.login_addr else {
+            return Ok(());
+        };
+        self.send_reconnect_event(ReconnectStage::Attempting {
+            attempt: self.reconnect_retry.attempts,
+            max_attempts: self.reconnect_retry.max_attempts,
+        });
+
+        let mut session = Session::new(login_addr).await?;
+        session.capture = self.session.capture.take();
+        self.session = session;
+        self.world = crate::world::WorldState::new(self.world.dat.clone());
+        self.state = ClientState::Connected;
+        self.connection_cookie = 0;
+        self.logon_retry.reset();
+        self.enter_retry.reset();
+        self.session_dead = false;
+        self.last_packet_at = Instant::now();
+        self.send_status_event();
+
+        if let Some(pw) = self.password.clone() {
+            self.send_login_request(&pw).await?;
+        }
+        Ok(())
+    }
+
     fn send_message_event(&self, kind: MessageKind, text: &str) {
         if let Some(tx) = &self.event_tx {
             let _ = tx.send(ClientEvent::Message(ChatMessage {
@@ -243,6 +439,16 @@ impl Client {
         }
     }
 
+    /// Handles `cmd` and any commands already waiting behind it, so their messages are
+    /// queued together and share packets.
+    async fn handle_commands(&mut self, cmd: ClientCommand) -> Result<()> {
+        self.handle_command(cmd).await?;
+        while let Some(cmd) = self.command_rx.as_mut().and_then(|rx| rx.try_recv().ok()) {
+            self.handle_command(cmd).await?;
+        }
+        Ok(())
+    }
+
     async fn handle_command(&mut self, cmd: ClientCommand) -> Result<()> {
         match cmd {
             ClientCommand::SelectCharacter(id) => self.select_character(id).await,
@@ -253,35 +459,27 @@ impl Client {
                 }
                 _ => Ok(()),
             },
-            ClientCommand::Talk(text) => {
-                if matches!(self.state, ClientState::InWorld) {
-                    return self.send_talk(&text).await;
-                }
-                Ok(())
-            }
-            ClientCommand::Identify(guid) => {
-                self.session
-                    .send_message(&crate::protocol::messages::GameMessage::GameAction {
-                        action: crate::protocol::messages::actions::IDENTIFY_OBJECT,
-                        data: guid.to_le_bytes().to_vec(),
-                    })
+            ClientCommand::Talk(text) => self.send_talk(&text).await,
+            ClientCommand::Identify(target) => {
+                self.send_action(GameActionKind::IdentifyObject { target })
                     .await
             }
-            ClientCommand::Use(guid) => {
-                self.session
-                    .send_message(&crate::protocol::messages::GameMessage::GameAction {
-                        action: crate::protocol::messages::actions::USE_ITEM,
-                        data: guid.to_le_bytes().to_vec(),
-                    })
-                    .await
+            ClientCommand::Use(item) => self.send_action(GameActionKind::UseItem { item }).await,
+            ClientCommand::Attack(target) => {
+                self.send_action(GameActionKind::TargetedMeleeAttack {
+                    target,
+                    height: attack_heights::MEDIUM,
+                    power: 1.0,
+                })
+                .await
             }
-            ClientCommand::Attack(guid) => {
-                self.session
-                    .send_message(&crate::protocol::messages::GameMessage::GameAction {
-                        action: 0x0002,
-                        data: guid.to_le_bytes().to_vec(),
-                    })
-                    .await
+            ClientCommand::Action(action) => self.send_action(action).await,
+            ClientCommand::Move(motion) => self.set_motion(motion).await,
+            ClientCommand::StopMoving => self.set_motion(MotionInput::default()).await,
+            ClientCommand::Jump(extent) => self.jump(extent).await,
+            ClientCommand::Replay(cmd) => {
+                self.handle_replay_command(cmd);
+                Ok(())
             }
             ClientCommand::Quit => {
                 self.disconnect().await?;
@@ -290,7 +488,70 @@ impl Client {
         }
     }
 
+    fn handle_replay_command(&mut self, cmd: ReplayCommand) {
+        let Some(control) = self.replay_control() else {
+            self.send_message_event(MessageKind::Warning, "Not replaying a capture");
+            return;
+        };
+        match cmd {
+            ReplayCommand::Pause => control.pause(),
+            ReplayCommand::Resume => control.resume(),
+            ReplayCommand::TogglePause => control.toggle_pause(),
+            ReplayCommand::Step => control.step(),
+            ReplayCommand::SetSpeed(speed) => control.set_speed(speed),
+            ReplayCommand::SeekTime(position) => {
+                self.replay_stop_at = None;
+                if !control.seek(position) {
+                    self.rewind_replay(&control);
+                    control.seek(position);
+                }
+            }
+            ReplayCommand::SeekMessage(index) => {
+                if index < self.message_counter {
+                    self.rewind_replay(&control);
+                }
+                if index == self.message_counter {
+                    control.pause();
+                } else {
+                    self.replay_stop_at = Some(index);
+                    control.fast_forward();
+                }
+            }
+        }
+        self.send_replay_event();
+    }
+
+    /// Restarts the replay from the first packet with a blank slate, as if freshly opened.
+    fn rewind_replay(&mut self, control: &ReplayControl) {
+        control.rewind();
+        if let Some(start) = control.start_addr() {
+            self.session.switch_server(start);
+        }
+        self.world = crate::world::WorldState::new(self.world.dat.clone());
+        self.characters.clear();
+        self.state = ClientState::Connected;
+        self.connection_cookie = 0;
+        self.logon_retry.reset();
+        self.enter_retry.reset();
+        self.message_counter = 0;
+        self.replay_stop_at = None;
+        if let Some(tx) = &self.event_tx {
+            let _ = tx.send(ClientEvent::WorldReset);
+        }
+        self.send_status_event();
+    }
+
+    fn send_replay_event(&self) {
+        if let (Some(tx), Some(control)) = (&self.event_tx, &self.session.replay) {
+            let _ = tx.send(ClientEvent::Replay {
+                status: control.status(),
+                message_index: self.message_counter,
+            });
+        }
+    }
+
     pub async fn disconnect(&mut self) -> Result<()> {
+        self.session.flush().await?;
         let header = PacketHeader {
             flags: flags::DISCONNECT,
             sequence: self.session.packet_sequence,
@@ -313,24 +574,40 @@ impl Client {
 
         let mut retry_tick = tokio::time::interval(Duration::from_secs(1));
         let mut physics_tick = tokio::time::interval(Duration::from_millis(30));
+        let mut echo_tick = tokio::time::interval(ECHO_INTERVAL);
         let mut last_physics_time = Instant::now();
 
         loop {
             tokio::select! {
-                res = self.session.recv_message() => {
+                res = self.session.recv_message(), if !self.session_dead => {
                     use crate::session::SessionEvent;
                     match res {
                         Ok(events) => {
+                            self.last_packet_at = Instant::now();
                             for event in events {
-                                match event {
+                                if self.session_dead {
+                                    break;
+                                }
+                                let result = match event {
                                     SessionEvent::Message(msg_data) => {
-                                        self.handle_message(&msg_data).await?;
+                                        self.handle_message(&msg_data).await
                                     }
                                     SessionEvent::HandshakeRequest(crd) => {
-                                        self.handle_handshake_request(crd).await?;
+                                        self.handle_handshake_request(crd).await
                                     }
                                     SessionEvent::HandshakeResponse { cookie, client_id } => {
-                                        self.handle_handshake_response(cookie, client_id).await?;
+                                        self.handle_handshake_response(cookie, client_id).await
+                                    }
+                                    SessionEvent::ServerSwitch(referral) => {
+                                        self.handle_server_switch(referral).await
+                                    }
+                                    SessionEvent::Disconnect => {
+                                        if self.can_reconnect() {
+                                            self.connection_lost("Server closed the connection");
+                                        } else {
+                                            self.send_message_event(MessageKind::Error, "Server closed the connection");
+                                        }
+                                        Ok(())
                                     }
                                     SessionEvent::TimeSync(server_time) => {
                                         self.world.server_time = Some(crate::world::state::ServerTimeSync {
@@ -340,10 +617,15 @@ impl Client {
                                         if let Some(tx) = &self.event_tx {
                                             let _ = tx.send(ClientEvent::World(Box::new(crate::world::WorldEvent::ServerTimeUpdate(server_time))));
                                         }
+                                        Ok(())
                                     }
-                                }
+                                };
+                                self.recover(result)?;
                             }
                         }
+                        Err(e) if self.can_reconnect() => {
+                            self.connection_lost(&format!("Session error: {}", e));
+                        }
                         Err(e) => {
                             log::error!("Session error: {}", e);
                             return Err(e);
@@ -357,7 +639,8 @@ impl Client {
                         None
                     }
                 } => {
-                    self.handle_command(cmd).await?;
+                    let result = self.handle_commands(cmd).await;
+                    self.recover(result)?;
                 }
                 _ = physics_tick.tick() => {
                     let now = Instant::now();
@@ -366,9 +649,42 @@ impl Client {
 
                     // TODO: Use actual player radius from DAT/Properties
                     self.world.tick(dt, 0.35);
+
+                    if !self.world.player.motion.is_idle()
+                        && now.duration_since(self.last_position_report) >= POSITION_REPORT_INTERVAL
+                    {
+                        let result = self.send_autonomous_position().await;
+                        self.recover(result)?;
+                    }
+                }
+                _ = echo_tick.tick() => {
+                    // Echo requests are only meaningful once the handshake has completed
+                    if self.session.isaac_c2s.is_some() {
+                        let _ = self.session.send_echo_request().await;
+                    }
+                    if let Some(tx) = &self.event_tx {
+                        let _ = tx.send(ClientEvent::ConnectionStats(self.session.stats.clone()));
+                    }
                 }
                 _ = retry_tick.tick() => {
                     let now = Instant::now();
+                    self.send_replay_event();
+                    if let Some(policy) = &self.reconnect_policy
+                        && self.can_reconnect()
+                        && now.duration_since(self.last_packet_at) > policy.idle_timeout
+                    {
+                        let idle = policy.idle_timeout.as_secs();
+                        self.connection_lost(&format!("No packets for {}s", idle));
+                    }
+                    if self.session_dead && self.reconnect_retry.tick(now) {
+                        if let Err(e) = self.attempt_reconnect().await {
+                            log::warn!("Reconnect attempt failed: {}", e);
+                            self.session_dead = true;
+                        }
+                    } else if self.session_dead && !self.reconnect_retry.active {
+                        self.send_reconnect_event(ReconnectStage::GaveUp);
+                        return Err(anyhow!("Gave up reconnecting"));
+                    }
                     if self.logon_retry.tick(now) {
                         self.send_status_event();
                         if let Some(pw) = self.password.clone() {
@@ -383,17 +699,41 @@ impl Client {
                     }
                 }
             }
+
+            // Whatever this wakeup queued goes out together
+            if !self.session_dead {
+                let result = self.session.flush().await;
+                self.recover(result)?;
+            }
         }
     }
 
     async fn handle_message(&mut self, data: &[u8]) -> Result<()> {
         if let Some(ref dump_dir) = self.message_dump_dir {
             let path = dump_dir.join(format!("{:05}.bin", self.message_counter));
-            std::fs::write(path, data)?;
-            self.message_counter += 1;
+            let mut body = data.to_vec();
+            scrub::scrub_message(&mut body, &self.account_name, self.message_dump_scrub);
+            std::fs::write(path, body)?;
+        }
+        self.message_counter += 1;
+        if self
+            .replay_stop_at
+            .is_some_and(|stop| self.message_counter >= stop)
+        {
+            self.replay_stop_at = None;
+            if let Some(control) = &self.session.replay {
+                control.pause();
+            }
+            self.send_replay_event();
         }
 
         let message = GameMessage::unpack(data);
+        if let GameMessage::GameEvent { sequence, .. } = message
+            && !self.world.accept_game_event(sequence)
+        {
+            log::debug!("Dropping repeated game event {}", sequence);
+            return Ok(());
+        }
 
         // Pass to world state for tracking positioning and spawning
         let world_events = self.world.handle_message(message.clone());
@@ -402,6 +742,11 @@ impl Client {
                 let _ = tx.send(ClientEvent::World(Box::new(event)));
             }
         }
+        for event in self.handlers.dispatch(data) {
+            if let Some(tx) = &self.event_tx {
+                let _ = tx.send(ClientEvent::Custom(event));
+            }
+        }
 
         match message {
             GameMessage::CharacterList { characters } => {
@@ -452,6 +797,11 @@ impl Client {
                 self.logon_retry.reset();
                 self.enter_retry.reset();
                 self.send_status_event();
+                if self.reconnecting {
+                    self.reconnecting = false;
+                    self.reconnect_retry.reset();
+                    self.send_reconnect_event(ReconnectStage::Restored);
+                }
                 Ok(())
             }
             GameMessage::UpdatePropertyInt {
@@ -459,24 +809,24 @@ impl Client {
                 property: _,
                 value: _,
             } => Ok(()),
-            GameMessage::GameEvent {
-                event_type,
-                guid,
-                sequence,
-                data,
-            } => {
-                self.handle_game_event(event_type, guid, sequence, data)
-                    .await
-            }
-            GameMessage::GameAction { action, data } => self.handle_game_action(action, data).await,
+            GameMessage::GameEvent { event, .. } => self.handle_game_event(event),
+            GameMessage::GameAction { action, .. } => self.handle_game_action(action),
             GameMessage::ServerMessage { message } => {
                 self.send_message_event(MessageKind::System, &message);
                 Ok(())
             }
+            GameMessage::BootAccount { reason } => {
+                self.send_message_event(MessageKind::System, &format!("Terminated: {}", reason));
+                if self.can_reconnect() {
+                    self.connection_lost(&format!("Booted: {}", reason));
+                }
+                Ok(())
+            }
             GameMessage::CharacterError { error_code } => self.handle_character_error(error_code),
             GameMessage::DddInterrogation => {
                 let resp = GameMessage::DddInterrogationResponse { language: 1 };
-                self.session.send_message(&resp).await
+                self.session.queue_message(resp);
+                Ok(())
             }
             GameMessage::ServerName {
                 name, online_count, ..
@@ -505,6 +855,14 @@ impl Client {
         self.logon_retry.reset();
         self.enter_retry.reset();
         self.characters = characters.clone();
+        if self.reconnecting {
+            self.send_reconnect_event(ReconnectStage::LoggedIn);
+            if let Some(char_id) = self.character_id
+                && characters.iter().any(|(id, _)| *id == char_id)
+            {
+                return self.select_character(char_id).await;
+            }
+        }
         if let Some(pref) = &self.character_preference {
             if let Ok(idx) = pref.parse::<usize>()
                 && idx > 0
@@ -527,50 +885,45 @@ impl Client {
         Ok(())
     }
 
-    async fn handle_game_event(
-        &mut self,
-        event_type: u32,
-        _guid: u64,
-        _sequence: u32,
-        data: Vec<u8>,
-    ) -> Result<()> {
-        match event_type {
-            game_event_opcodes::PLAYER_DESCRIPTION | game_event_opcodes::START_GAME => {
-                if self.state == ClientState::EnteringWorld {
-                    self.state = ClientState::InWorld;
-                    self.enter_retry.reset();
-                    self.send_status_event();
-                }
+    fn handle_game_event(&mut self, event: GameEventKind) -> Result<()> {
+        match event {
+            GameEventKind::PlayerDescription { .. } | GameEventKind::StartGame
+                if self.state == ClientState::EnteringWorld =>
+            {
+                self.state = ClientState::InWorld;
+                self.enter_retry.reset();
+                self.send_status_event();
             }
-            game_event_opcodes::CHANNEL_BROADCAST => {
-                let mut offset = 0;
-                if data.len() >= 4 {
-                    offset += 4;
-                    let sender = read_string16(&data, &mut offset);
-                    let message = read_string16(&data, &mut offset);
-                    self.send_message_event(
-                        MessageKind::Chat,
-                        &format!(
-                            "{}: {}",
-                            if sender.is_empty() { "You" } else { &sender },
-                            message
-                        ),
-                    );
-                }
+            GameEventKind::ChannelBroadcast {
+                sender, message, ..
+            } => {
+                self.send_message_event(
+                    MessageKind::Chat,
+                    &format!(
+                        "{}: {}",
+                        if sender.is_empty() { "You" } else { &sender },
+                        message
+                    ),
+                );
             }
-            game_event_opcodes::TELL => {
-                let mut offset = 0;
-                let message = read_string16(&data, &mut offset);
-                let sender = read_string16(&data, &mut offset);
+            GameEventKind::Tell {
+                message, sender, ..
+            } => {
                 self.send_message_event(MessageKind::Tell, &format!("{}: {}", sender, message));
             }
+            GameEventKind::WeenieError { error } => {
+                self.send_message_event(MessageKind::Error, &format!("Error: 0x{:04X}", error));
+            }
+            GameEventKind::WeenieErrorWithString { text, .. } => {
+                self.send_message_event(MessageKind::Error, &text);
+            }
             _ => {}
         }
         Ok(())
     }
 
-    async fn handle_game_action(&mut self, action: u32, _data: Vec<u8>) -> Result<()> {
-        if action == action_opcodes::LOGIN_COMPLETE {
+    fn handle_game_action(&mut self, action: GameActionKind) -> Result<()> {
+        if action == GameActionKind::LoginComplete {
             self.state = ClientState::InWorld;
             self.send_status_event();
         }
@@ -595,9 +948,12 @@ impl Client {
     async fn select_character(&mut self, char_id: u32) -> Result<()> {
         self.character_id = Some(char_id);
         self.state = ClientState::EnteringWorld;
+        if self.reconnecting {
+            self.send_reconnect_event(ReconnectStage::EnteringWorld);
+        }
         self.send_status_event();
         let msg = GameMessage::CharacterEnterWorldRequest { char_id };
-        self.session.send_message(&msg).await?;
+        self.session.queue_message(msg);
         Ok(())
     }
 
@@ -606,30 +962,98 @@ impl Client {
             id: char_id,
             account: self.account_name.clone(),
         };
-        self.session.send_message(&msg).await?;
+        self.session.queue_message(msg);
         Ok(())
     }
 
     async fn send_login_complete(&mut self) -> Result<()> {
-        let msg = GameMessage::GameAction {
-            action: action_opcodes::LOGIN_COMPLETE,
-            data: Vec::new(),
-        };
-        self.session.send_message(&msg).await?;
-        Ok(())
+        self.session.queue_action(GameActionKind::LoginComplete)
     }
 
     async fn send_talk(&mut self, text: &str) -> Result<()> {
-        let mut data = Vec::new();
-        write_string16(&mut data, text);
-        let msg = GameMessage::GameAction {
-            action: action_opcodes::TALK,
-            data,
-        };
-        self.session.send_message(&msg).await?;
+        self.send_action(GameActionKind::Talk {
+            message: text.to_string(),
+        })
+        .await
+    }
+
+    async fn send_action(&mut self, action: GameActionKind) -> Result<()> {
+        if !matches!(self.state, ClientState::InWorld) {
+            log::warn!("Not in world, dropping {:?}", action);
+            return Ok(());
+        }
+        if let Err(e) = self.session.queue_action(action) {
+            log::warn!("{}", e);
+        }
         Ok(())
     }
 
+    /// Predicts the player's new motion locally and tells the server about it.
+    async fn set_motion(&mut self, motion: MotionInput) -> Result<()> {
+        if !matches!(self.state, ClientState::InWorld) {
+            log::warn!("Not in world, ignoring {:?}", motion);
+            return Ok(());
+        }
+        self.world.set_player_motion(motion);
+        let Some(position) = self.player_position() else {
+            return Ok(());
+        };
+        self.last_position_report = Instant::now();
+        self.send_action(GameActionKind::MoveToState {
+            motion: motion.to_raw(),
+            position,
+            sequences: self.world.player_movement_sequences(),
+            contact: u8::from(!self.world.player_airborne()),
+        })
+        .await
+    }
+
+    async fn send_autonomous_position(&mut self) -> Result<()> {
+        let Some(position) = self.player_position() else {
+            return Ok(());
+        };
+        self.last_position_report = Instant::now();
+        self.send_action(GameActionKind::AutonomousPosition {
+            position,
+            sequences: self.world.player_movement_sequences(),
+            contact: u8::from(!self.world.player_airborne()),
+        })
+        .await
+    }
+
+    /// Jumps in the direction the player is moving, predicting the leap locally until the
+    /// player lands.
+    async fn jump(&mut self, extent: f32) -> Result<()> {
+        if !matches!(self.state, ClientState::InWorld) {
+            log::warn!("Not in world, ignoring jump");
+            return Ok(());
+        }
+        let Some(player) = self.world.entities.get(self.world.player.guid) else {
+            return Ok(());
+        };
+        let jump_skill = self
+            .world
+            .player_skill(crate::world::stats::SkillType::Jump);
+        let velocity = Vector3 {
+            z: crate::world::movement::jump_velocity(jump_skill, extent),
+            ..player.velocity
+        };
+        self.world.predict_player_jump(velocity.z);
+        self.send_action(GameActionKind::Jump {
+            extent: extent.clamp(0.0, 1.0),
+            velocity,
+            sequences: self.world.player_movement_sequences(),
+        })
+        .await
+    }
+
+    fn player_position(&self) -> Option<crate::world::position::WorldPosition> {
+        self.world
+            .entities
+            .get(self.world.player.guid)
+            .map(|player| player.position)
+    }
+
     async fn send_login_request(&mut self, password: &str) -> Result<()> {
         log::debug!(
             ">>> Sending Login Request for account {}",
@@ -655,7 +1079,7 @@ impl Client {
         self.connection_cookie = crd.cookie;
         self.session.client_id = crd.client_id;
         self.session.isaac_c2s = Some(Isaac::new(crd.client_seed));
-        self.session.isaac_s2c = Some(Isaac::new(crd.server_seed));
+        self.session.isaac_s2c = Some(IsaacVerifier::new(crd.server_seed));
 
         let resp_header = PacketHeader {
             flags: flags::CONNECT_RESPONSE,
@@ -681,6 +1105,21 @@ impl Client {
         Ok(())
     }
 
+    async fn handle_server_switch(
+        &mut self,
+        referral: crate::protocol::messages::ReferralHeader,
+    ) -> Result<()> {
+        log::debug!(
+            "<<< Referral to {} (server {}) Cookie={:016X}",
+            referral.address,
+            referral.server_id,
+            referral.cookie
+        );
+        self.connection_cookie = referral.cookie;
+        self.session.switch_server(SocketAddr::V4(referral.address));
+        self.session.send_world_login_request(referral.cookie).await
+    }
+
     async fn handle_handshake_response(&mut self, cookie: u64, client_id: u16) -> Result<()> {
         log::debug!(
             "<<< Handshake Response: Cookie={:016X} NetID={:04X}",
@@ -698,3 +1137,356 @@ impl Client {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Quaternion;
+    use crate::session::Transport;
+    use crate::session::loopback::LoopbackTransport;
+    use crate::world::entity::Entity;
+    use crate::world::position::WorldPosition;
+
+    /// A client on a loopback session, and the server end that sees what it sends.
+    fn loopback_client() -> (Client, LoopbackTransport) {
+        let server_addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let (transport, server) =
+            LoopbackTransport::pair("127.0.0.1:50000".parse().unwrap(), server_addr);
+        let session = Session::from_transport(Box::new(transport), server_addr);
+        let client = Client::create_with_session(session, "acct", None).unwrap();
+        (client, server)
+    }
+
+    /// Flushes the client and decodes the game actions in everything it sent.
+    async fn sent_actions(client: &mut Client, server: &LoopbackTransport) -> Vec<GameActionKind> {
+        client.session.flush().await.unwrap();
+        let mut actions = Vec::new();
+        let mut buf = [0u8; 1024];
+        while let Ok(Ok((len, _))) =
+            tokio::time::timeout(Duration::from_millis(20), server.recv_from(&mut buf)).await
+        {
+            let header = PacketHeader::unpack(&buf[..HEADER_SIZE]);
+            for (_, data) in crate::session::split_fragments(header.flags, &buf[HEADER_SIZE..len]) {
+                if let GameMessage::GameAction { action, .. } = GameMessage::unpack(data) {
+                    actions.push(action);
+                }
+            }
+        }
+        actions
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_reenters_previous_character() {
+        let mut client = Client::create_with_session(Session::new_test(), "acct", None).unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        client.set_event_tx(tx);
+        client.set_reconnect_policy(ReconnectPolicy::default());
+        client.login_addr = Some("127.0.0.1:9000".parse().unwrap());
+        client.character_id = Some(0x50000002);
+
+        client.connection_lost("test");
+        client.connection_lost("test again");
+        assert!(client.session_dead);
+        assert!(client.reconnect_retry.active);
+
+        client.session_dead = false;
+        client
+            .handle_character_list(vec![
+                (0x50000001, "Alt".into()),
+                (0x50000002, "Main".into()),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(client.state, ClientState::EnteringWorld);
+        assert_eq!(client.character_id, Some(0x50000002));
+
+        let stages: Vec<ReconnectStage> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|e| match e {
+                ClientEvent::Reconnect(stage) => Some(stage),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            stages,
+            vec![
+                ReconnectStage::ConnectionLost("test".into()),
+                ReconnectStage::LoggedIn,
+                ReconnectStage::EnteringWorld,
+            ]
+        );
+    }
+
+    /// Sends through a loopback link until its budget runs out, then fails every send.
+    struct FailingTransport {
+        inner: LoopbackTransport,
+        sends_left: std::sync::atomic::AtomicUsize,
+    }
+
+    #[crate::session::async_trait]
+    impl Transport for FailingTransport {
+        async fn send_to(&self, buf: &[u8], addr: SocketAddr) -> Result<usize> {
+            use std::sync::atomic::Ordering;
+            if self
+                .sends_left
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_err()
+            {
+                return Err(anyhow!("Network is unreachable"));
+            }
+            self.inner.send_to(buf, addr).await
+        }
+
+        async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+            self.inner.recv_from(buf).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_send_mid_session_reconnects() {
+        let server_addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let (transport, _server) =
+            LoopbackTransport::pair("127.0.0.1:50000".parse().unwrap(), server_addr);
+        // The login request goes out, then the network drops
+        let transport = FailingTransport {
+            inner: transport,
+            sends_left: 1.into(),
+        };
+        let session = Session::from_transport(Box::new(transport), server_addr);
+        let mut client = Client::create_with_session(session, "acct", None).unwrap();
+        let (event_tx, mut events) = mpsc::unbounded_channel();
+        client.set_event_tx(event_tx);
+        client.set_reconnect_policy(ReconnectPolicy::default());
+        client.login_addr = Some(server_addr);
+        client.state = ClientState::InWorld;
+        let (tx, rx) = mpsc::unbounded_channel();
+        tx.send(ClientCommand::Talk("hello".into())).unwrap();
+        client.set_command_rx(rx);
+
+        // The run loop outlives the failed flush instead of returning its error
+        let run = tokio::time::timeout(Duration::from_millis(300), client.run("pw")).await;
+        assert!(run.is_err(), "run ended: {:?}", run);
+        assert!(client.session_dead);
+        let lost = std::iter::from_fn(|| events.try_recv().ok()).any(|e| {
+            matches!(e, ClientEvent::Reconnect(ReconnectStage::ConnectionLost(reason))
+                if reason.contains("Network is unreachable"))
+        });
+        assert!(lost);
+    }
+
+    #[tokio::test]
+    async fn test_custom_handler_emits_client_event() {
+        #[derive(Debug, PartialEq)]
+        struct Shout(String);
+
+        let mut client = Client::create_with_session(Session::new_test(), "acct", None).unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        client.set_event_tx(tx);
+        client.handlers.on_message(0xAB01, |data| {
+            Some(Shout(String::from_utf8_lossy(&data[4..]).into_owned()))
+        });
+
+        let mut data = 0xAB01u32.to_le_bytes().to_vec();
+        data.extend_from_slice(b"hi");
+        client.handle_message(&data).await.unwrap();
+
+        let shouts: Vec<CustomEvent> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|e| match e {
+                ClientEvent::Custom(event) => Some(event),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(shouts.len(), 1);
+        assert_eq!(shouts[0].downcast_ref(), Some(&Shout("hi".into())));
+    }
+
+    #[tokio::test]
+    async fn test_repeated_game_events_are_dropped() {
+        let mut client = Client::create_with_session(Session::new_test(), "acct", None).unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        client.set_event_tx(tx);
+        client.world.player.guid = 0x50000001;
+        client
+            .handlers
+            .on_game_event(game_event_opcodes::CHARACTER_TITLE, |event| {
+                Some(event.sequence)
+            });
+        let title = |sequence: u32, current: u32| {
+            let mut data = Vec::new();
+            for value in [
+                opcodes::GAME_EVENT,
+                0x50000001,
+                sequence,
+                game_event_opcodes::CHARACTER_TITLE,
+                1,
+                current,
+                0,
+            ] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            data
+        };
+
+        for (sequence, current) in [(5, 1), (5, 2), (4, 2), (6, 3)] {
+            client
+                .handle_message(&title(sequence, current))
+                .await
+                .unwrap();
+        }
+        assert_eq!(client.world.player.current_title, Some(3));
+        let sequences: Vec<u32> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|e| match e {
+                ClientEvent::Custom(event) => event.downcast_ref().copied(),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sequences, vec![5, 6]);
+    }
+
+    #[tokio::test]
+    async fn test_waiting_commands_share_a_packet() {
+        let (mut client, server) = loopback_client();
+        client.state = ClientState::InWorld;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        for text in ["one", "two", "three"] {
+            tx.send(ClientCommand::Talk(text.into())).unwrap();
+        }
+        tx.send(ClientCommand::Use(0x80000001)).unwrap();
+
+        // As the run loop does: take the first command, then flush once handled
+        let first = rx.recv().await.unwrap();
+        client.set_command_rx(rx);
+        client.handle_commands(first).await.unwrap();
+        client.session.flush().await.unwrap();
+
+        let mut buf = [0u8; 1024];
+        let (len, _) = server.recv_from(&mut buf).await.unwrap();
+        let header = PacketHeader::unpack(&buf[..HEADER_SIZE]);
+        let actions: Vec<GameActionKind> =
+            crate::session::split_fragments(header.flags, &buf[HEADER_SIZE..len])
+                .into_iter()
+                .filter_map(|(_, data)| match GameMessage::unpack(data) {
+                    GameMessage::GameAction { action, .. } => Some(action),
+                    _ => None,
+                })
+                .collect();
+        assert_eq!(actions.len(), 4);
+        assert_eq!(actions[3], GameActionKind::UseItem { item: 0x80000001 });
+        // Nothing else was sent
+        let more =
+            tokio::time::timeout(Duration::from_millis(20), server.recv_from(&mut buf)).await;
+        assert!(more.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unparsed_actions_are_not_sent() {
+        let mut client = Client::create_with_session(Session::new_test(), "acct", None).unwrap();
+        client.state = ClientState::InWorld;
+        let unknown = GameActionKind::Unknown {
+            action: action_opcodes::USE_ITEM,
+            data: vec![0xFF; 3],
+        };
+        client
+            .handle_command(ClientCommand::Action(unknown.clone()))
+            .await
+            .unwrap();
+        assert_eq!(client.session.action_sequence, 0);
+        assert!(client.session.send_action(unknown).await.is_err());
+        assert_eq!(client.session.action_sequence, 0);
+    }
+
+    #[tokio::test]
+    async fn test_move_commands_predict_and_send() {
+        let (mut client, server) = loopback_client();
+        let start = WorldPosition {
+            landblock_id: 0xA9B40021,
+            coords: Vector3::new(10.0, 20.0, 30.0),
+            rotation: Quaternion::identity(),
+        };
+        client.world.player.guid = 0x50000001;
+        client
+            .world
+            .add_entity(Entity::new(0x50000001, "Player".into(), start));
+        let player = |client: &Client| client.world.entities.get(0x50000001).unwrap().clone();
+
+        // Nothing moves or is sent before entering the world
+        client
+            .handle_command(ClientCommand::Move(MotionInput::run_forward()))
+            .await
+            .unwrap();
+        client
+            .handle_command(ClientCommand::Jump(1.0))
+            .await
+            .unwrap();
+        assert!(sent_actions(&mut client, &server).await.is_empty());
+        assert_eq!(player(&client).velocity, Vector3::zero());
+
+        client.state = ClientState::InWorld;
+        client
+            .handle_command(ClientCommand::Move(MotionInput::run_forward()))
+            .await
+            .unwrap();
+        let sequences = client.world.player_movement_sequences();
+        assert_eq!(
+            sent_actions(&mut client, &server).await,
+            vec![GameActionKind::MoveToState {
+                motion: MotionInput::run_forward().to_raw(),
+                position: start,
+                sequences,
+                contact: 1,
+            }]
+        );
+        let run_velocity = player(&client).velocity;
+        assert!(run_velocity.y > 0.0);
+
+        // The jump carries the run forward and lifts the player locally
+        client
+            .handle_command(ClientCommand::Jump(1.0))
+            .await
+            .unwrap();
+        let lift = crate::world::movement::jump_velocity(0, 1.0);
+        assert_eq!(
+            sent_actions(&mut client, &server).await,
+            vec![GameActionKind::Jump {
+                extent: 1.0,
+                velocity: Vector3 {
+                    z: lift,
+                    ..run_velocity
+                },
+                sequences,
+            }]
+        );
+        assert_eq!(player(&client).velocity.z, lift);
+
+        // Mid-jump reports put the player off the ground
+        client.world.tick(0.1, 0.35);
+        let airborne = player(&client).position;
+        assert!(airborne.coords.z > start.coords.z);
+        client.send_autonomous_position().await.unwrap();
+        assert_eq!(
+            sent_actions(&mut client, &server).await,
+            vec![GameActionKind::AutonomousPosition {
+                position: airborne,
+                sequences,
+                contact: 0,
+            }]
+        );
+
+        client
+            .handle_command(ClientCommand::StopMoving)
+            .await
+            .unwrap();
+        let actions = sent_actions(&mut client, &server).await;
+        let [
+            GameActionKind::MoveToState {
+                motion, contact, ..
+            },
+        ] = actions.as_slice()
+        else {
+            panic!("Expected MoveToState, got {:?}", actions);
+        };
+        assert_eq!((motion, *contact), (&MotionInput::default().to_raw(), 0));
+        let velocity = player(&client).velocity;
+        assert_eq!((velocity.x, velocity.y), (0.0, 0.0));
+        assert_eq!(client.session.action_sequence, 4);
+    }
+}
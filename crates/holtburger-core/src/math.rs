@@ -1,8 +1,8 @@
-use binrw::BinRead;
+use binrw::{BinRead, BinWrite};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, BinRead, PartialEq, Default)]
-#[br(little)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, BinRead, BinWrite, PartialEq, Default)]
+#[brw(little)]
 pub struct Vector3 {
     pub x: f32,
     pub y: f32,
@@ -96,8 +96,8 @@ impl std::ops::Div<f32> for Vector3 {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, BinRead, PartialEq, Default)]
-#[br(little)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, BinRead, BinWrite, PartialEq, Default)]
+#[brw(little)]
 pub struct Quaternion {
     pub w: f32,
     pub x: f32,
@@ -114,4 +114,54 @@ impl Quaternion {
             z: 0.0,
         }
     }
+
+    /// A rotation of `angle` radians about the vertical axis, counter-clockwise seen from
+    /// above.
+    pub fn from_yaw(angle: f32) -> Self {
+        Self::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), angle)
+    }
+
+    /// A rotation of `angle` radians about a unit `axis`.
+    pub fn from_axis_angle(axis: Vector3, angle: f32) -> Self {
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Self {
+            w: cos,
+            x: axis.x * sin,
+            y: axis.y * sin,
+            z: axis.z * sin,
+        }
+    }
+
+    pub fn normalize(&self) -> Self {
+        let len = (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        if len > 0.0 {
+            Self {
+                w: self.w / len,
+                x: self.x / len,
+                y: self.y / len,
+                z: self.z / len,
+            }
+        } else {
+            Self::identity()
+        }
+    }
+
+    /// Rotates a vector by this (unit) quaternion.
+    pub fn rotate(&self, v: Vector3) -> Vector3 {
+        let u = Vector3::new(self.x, self.y, self.z);
+        let t = u.cross(&v) * 2.0;
+        v + t * self.w + u.cross(&t)
+    }
+}
+
+impl std::ops::Mul for Quaternion {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        }
+    }
 }
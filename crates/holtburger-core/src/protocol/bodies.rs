@@ -0,0 +1,930 @@
+//! Wire layouts of message bodies, one binrw struct per body, read and written from the
+//! same definition.
+//!
+//! A body starts after the opcode. Alignment is relative to the start of the message, so
+//! bodies are read from a `parse::Stream` positioned past the opcode and written with
+//! `parse::pack_body`. `GameMessage` converts to and from these.
+
+use crate::math::Vector3;
+use crate::protocol::codec::{bytes, packed_did, packed_u32, string16, string16_unpadded};
+use crate::protocol::messages::{Enchantment, GameActionKind, LayeredSpell};
+use crate::protocol::movement::MovementData;
+use crate::world::position::{PositionPack, WorldPosition};
+use crate::world::properties::{
+    ItemType, ObjectDescriptionFlag, PhysicsDescriptionFlag, PhysicsState, WeenieHeaderFlag,
+    WeenieHeaderFlag2,
+};
+use binrw::{BinRead, BinWrite, binrw};
+
+/// Known types of the packed data IDs found in object descriptions.
+pub mod known_types {
+    pub const MODEL: u32 = 0x01000000;
+    pub const PALETTE: u32 = 0x04000000;
+    pub const TEXTURE: u32 = 0x05000000;
+    pub const ICON: u32 = 0x06000000;
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HearSpeech {
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub message: String,
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub sender: String,
+    pub sender_id: u32,
+    pub chat_type: u32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoulEmote {
+    pub sender_id: u32,
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub sender_name: String,
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub text: String,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharacterList {
+    pub unknown: u32,
+    #[br(temp)]
+    #[bw(calc = characters.len() as u32)]
+    count: u32,
+    #[br(count = count as usize)]
+    pub characters: Vec<CharacterIdentity>,
+    /// Left off by some emulators.
+    #[br(try)]
+    pub trailer: Option<CharacterListTrailer>,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharacterIdentity {
+    pub id: u32,
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub name: String,
+    pub delete_time: u32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharacterListTrailer {
+    pub unknown: u32,
+    pub slots: u32,
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub account: String,
+    pub use_turbine_chat: u32,
+    pub has_throne_of_destiny: u32,
+}
+
+/// The client sends this with no body.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharacterEnterWorldRequest {
+    #[br(try)]
+    pub char_id: Option<u32>,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharacterEnterWorld {
+    pub id: u32,
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub account: String,
+}
+
+/// Bodies that are only a guid: PlayerCreate, ObjectDelete, PickupEvent, PlayEffect and
+/// CharacterError (whose "guid" is the error code).
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Guid {
+    pub guid: u32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParentEvent {
+    pub parent_guid: u32,
+    pub child_guid: u32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetState {
+    pub guid: u32,
+    pub state: u32,
+    pub instance_sequence: u16,
+    pub state_sequence: u16,
+}
+
+/// Sent to the player as they enter portal space.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerTeleport {
+    #[brw(align_after = 4)]
+    pub teleport_sequence: u16,
+}
+
+/// A property update about the player, which carries a sequence byte instead of a guid.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivateUpdateProperty<V>
+where
+    V: for<'a> BinRead<Args<'a> = ()> + for<'a> BinWrite<Args<'a> = ()>,
+{
+    pub sequence: u8,
+    pub property: u32,
+    pub value: V,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicUpdateProperty<V>
+where
+    V: for<'a> BinRead<Args<'a> = ()> + for<'a> BinWrite<Args<'a> = ()>,
+{
+    pub guid: u32,
+    pub property: u32,
+    pub value: V,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivateUpdatePropertyString {
+    pub sequence: u8,
+    pub property: u32,
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub value: String,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicUpdatePropertyString {
+    pub guid: u32,
+    pub property: u32,
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub value: String,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateSkill {
+    pub sequence: u32,
+    pub skill: Skill,
+}
+
+/// A skill, as sent in skill updates and the PlayerDescription skill table.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Skill {
+    pub skill: u32,
+    pub ranks: u16,
+    pub status: u16,
+    pub sac: u32,
+    pub xp: u32,
+    pub init: u32,
+    pub resistance: u32,
+    pub last_used: f64,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateAttribute {
+    pub sequence: u8,
+    pub attribute: u32,
+    pub value: Attribute,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateVital {
+    pub sequence: u8,
+    pub vital: u32,
+    pub value: Vital,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateVitalCurrent {
+    pub sequence: u8,
+    pub vital: u32,
+    pub current: u32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Attribute {
+    pub ranks: u32,
+    pub start: u32,
+    pub xp: u32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vital {
+    pub ranks: u32,
+    pub start: u32,
+    pub xp: u32,
+    pub current: u32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorUpdate {
+    pub guid: u32,
+    pub velocity: Vector3,
+    pub omega: Vector3,
+    pub instance_sequence: u16,
+    pub vector_sequence: u16,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdatePosition {
+    pub guid: u32,
+    pub pos: PositionPack,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateMotion {
+    pub guid: u32,
+    pub instance_sequence: u16,
+    pub movement_sequence: u16,
+    pub server_control_sequence: u16,
+    #[br(map = |x: u8| x != 0)]
+    #[bw(map = |x: &bool| *x as u8)]
+    #[brw(align_after = 4)]
+    pub autonomous: bool,
+    pub movement: MovementData,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameAction {
+    pub sequence: u32,
+    pub action: GameActionKind,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerMessage {
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub message: String,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BootAccount {
+    /// Left off when there is no reason.
+    #[br(try, parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub reason: Option<String>,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerName {
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub name: String,
+    /// Online and maximum session counts, which are sometimes left off.
+    #[br(try)]
+    pub counts: Option<(u32, u32)>,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DddInterrogationResponse {
+    pub language: u32,
+    /// numElements of the CAllIterationList.
+    pub iteration_count: u32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameEventHeader {
+    pub guid: u32,
+    pub sequence: u32,
+    pub event_type: u32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateHealth {
+    pub target: u32,
+    pub health: f32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EnchantmentList {
+    #[br(temp)]
+    #[bw(calc = enchantments.len() as u32)]
+    count: u32,
+    #[br(count = count as usize)]
+    pub enchantments: Vec<Enchantment>,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayeredSpellList {
+    #[br(temp)]
+    #[bw(calc = spells.len() as u32)]
+    count: u32,
+    #[br(count = count as usize)]
+    pub spells: Vec<LayeredSpell>,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelBroadcast {
+    pub channel: u32,
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub sender: String,
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub message: String,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tell {
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub message: String,
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub sender: String,
+    pub sender_id: u32,
+    pub target_id: u32,
+    pub chat_type: u32,
+    pub secret_flags: u32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewContents {
+    pub container: u32,
+    #[br(temp)]
+    #[bw(calc = items.len() as u32)]
+    count: u32,
+    #[br(count = count as usize)]
+    pub items: Vec<ContentsItem>,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentsItem {
+    pub guid: u32,
+    /// 0 for an item, 1 for a container and 2 for a foci.
+    pub container_type: u32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeenieError {
+    pub error: u32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeenieErrorWithString {
+    pub error: u32,
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub text: String,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharacterTitle {
+    /// Always 1.
+    pub unknown: u32,
+    pub current: u32,
+    #[br(temp)]
+    #[bw(calc = titles.len() as u32)]
+    count: u32,
+    #[br(count = count as usize)]
+    pub titles: Vec<u32>,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FriendsListUpdate {
+    #[br(temp)]
+    #[bw(calc = friends.len() as u32)]
+    count: u32,
+    #[br(count = count as usize)]
+    pub friends: Vec<Friend>,
+    pub update_type: u32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Friend {
+    pub guid: u32,
+    #[br(map = |x: u32| x != 0)]
+    #[bw(map = |x: &bool| *x as u32)]
+    pub online: bool,
+    #[br(map = |x: u32| x != 0)]
+    #[bw(map = |x: &bool| *x as u32)]
+    pub appear_offline: bool,
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub name: String,
+    #[br(temp)]
+    #[bw(calc = friends.len() as u32)]
+    friend_count: u32,
+    /// Who this friend has befriended.
+    #[br(count = friend_count as usize)]
+    pub friends: Vec<u32>,
+    #[br(temp)]
+    #[bw(calc = friend_of.len() as u32)]
+    friend_of_count: u32,
+    /// Who has befriended this friend.
+    #[br(count = friend_of_count as usize)]
+    pub friend_of: Vec<u32>,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FellowshipUpdateFellow {
+    pub guid: u32,
+    pub fellow: Fellow,
+    pub update_type: u32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Fellow {
+    pub cp_cached: u32,
+    pub lum_cached: u32,
+    pub level: u32,
+    pub max_health: u32,
+    pub max_stamina: u32,
+    pub max_mana: u32,
+    pub current_health: u32,
+    pub current_stamina: u32,
+    pub current_mana: u32,
+    #[br(map = |x: u32| x != 0)]
+    #[bw(map = |x: &bool| *x as u32)]
+    pub share_loot: bool,
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub name: String,
+}
+
+/// A hash table as serialised by ACE: entry count, bucket count, then the entries.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropertyTable<E>
+where
+    E: for<'a> BinRead<Args<'a> = ()> + for<'a> BinWrite<Args<'a> = ()> + 'static,
+{
+    #[br(temp)]
+    #[bw(calc = entries.len() as u16)]
+    count: u16,
+    pub buckets: u16,
+    #[br(count = count as usize)]
+    pub entries: Vec<E>,
+}
+
+impl<E> PropertyTable<E>
+where
+    E: for<'a> BinRead<Args<'a> = ()> + for<'a> BinWrite<Args<'a> = ()> + 'static,
+{
+    pub fn new(buckets: u16, entries: Vec<E>) -> Self {
+        Self { buckets, entries }
+    }
+}
+
+/// Property strings inside tables are not padded.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringProperty {
+    pub key: u32,
+    #[br(parse_with = string16_unpadded::read)]
+    #[bw(write_with = string16_unpadded::write)]
+    pub value: String,
+}
+
+/// The PlayerDescription game event. Each table is present when its flag is set.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerDescription {
+    pub property_flags: u32,
+    pub wee_type: u32,
+    #[br(if(property_flags & 0x0001 != 0))]
+    pub int_properties: Option<PropertyTable<(u32, i32)>>,
+    #[br(if(property_flags & 0x0080 != 0))]
+    pub int64_properties: Option<PropertyTable<(u32, i64)>>,
+    #[br(if(property_flags & 0x0002 != 0))]
+    pub bool_properties: Option<PropertyTable<(u32, u32)>>,
+    #[br(if(property_flags & 0x0004 != 0))]
+    pub float_properties: Option<PropertyTable<(u32, f64)>>,
+    #[br(if(property_flags & 0x0010 != 0))]
+    pub string_properties: Option<PropertyTable<StringProperty>>,
+    #[br(if(property_flags & 0x0008 != 0))]
+    pub did_properties: Option<PropertyTable<(u32, u32)>>,
+    #[br(if(property_flags & 0x0040 != 0))]
+    pub iid_properties: Option<PropertyTable<(u32, u32)>>,
+    #[br(if(property_flags & 0x0020 != 0))]
+    pub positions: Option<PropertyTable<(u32, WorldPosition)>>,
+    pub vector_flags: u32,
+    /// Convert.ToUInt32(Session.Player.Health != null)
+    pub has_health_stats: u32,
+    #[br(if(vector_flags & 0x0001 != 0))]
+    pub attributes: Option<AttributeCache>,
+    #[br(if(vector_flags & 0x0002 != 0))]
+    pub skills: Option<PropertyTable<Skill>>,
+    #[br(if(vector_flags & 0x0100 != 0))]
+    pub spells: Option<PropertyTable<(u32, f32)>>,
+    #[br(if(vector_flags & 0x0200 != 0))]
+    pub enchantments: Option<EnchantmentRegistry>,
+}
+
+/// Primary attributes (bits 0-5 of `cache`) then vitals (bits 6-8), in bit order.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeCache {
+    pub cache: u32,
+    #[br(count = (cache & 0x3F).count_ones() as usize)]
+    pub primaries: Vec<Attribute>,
+    #[br(count = ((cache >> 6) & 0x7).count_ones() as usize)]
+    pub vitals: Vec<Vital>,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnchantmentRegistry {
+    pub mask: u32,
+    #[br(if(mask & 0x01 != 0))]
+    pub multiplicative: Option<EnchantmentList>,
+    #[br(if(mask & 0x02 != 0))]
+    pub additive: Option<EnchantmentList>,
+    #[br(if(mask & 0x08 != 0))]
+    pub cooldown: Option<EnchantmentList>,
+    #[br(if(mask & 0x04 != 0))]
+    pub vitae: Option<Enchantment>,
+}
+
+/// ObjectCreate and UpdateObject.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectCreate {
+    pub guid: u32,
+    #[brw(align_after = 4)]
+    pub model: ModelData,
+    #[brw(align_after = 4)]
+    pub physics: PhysicsDesc,
+    pub weenie: WeenieHeader,
+}
+
+/// Marker 0x11 introduces an ObjDesc; anything else is followed by a display model.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelData {
+    pub marker: u8,
+    #[br(if(marker == 0x11))]
+    pub obj_desc: Option<ObjDesc>,
+    /// DisplayModelId(4), DisplayModelType(1) and DisplayModelFlags(4) after the marker.
+    #[br(if(marker != 0x11))]
+    pub display_model: Option<[u8; 8]>,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ObjDesc {
+    #[br(temp)]
+    #[bw(calc = subpalettes.len() as u8)]
+    palette_count: u8,
+    #[br(temp)]
+    #[bw(calc = texture_changes.len() as u8)]
+    texture_count: u8,
+    #[br(temp)]
+    #[bw(calc = model_changes.len() as u8)]
+    model_count: u8,
+    /// Present when there are subpalettes.
+    #[brw(args(known_types::PALETTE))]
+    #[br(if(palette_count > 0), parse_with = packed_did::read)]
+    #[bw(write_with = packed_did::write)]
+    pub base_palette: Option<u32>,
+    #[br(count = palette_count as usize)]
+    pub subpalettes: Vec<Subpalette>,
+    #[br(count = texture_count as usize)]
+    pub texture_changes: Vec<TextureChange>,
+    #[br(count = model_count as usize)]
+    pub model_changes: Vec<ModelChange>,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subpalette {
+    #[brw(args(known_types::PALETTE))]
+    #[br(parse_with = packed_did::read)]
+    #[bw(write_with = packed_did::write)]
+    pub palette: u32,
+    pub offset: u8,
+    pub length: u8,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureChange {
+    pub part: u8,
+    #[brw(args(known_types::TEXTURE))]
+    #[br(parse_with = packed_did::read)]
+    #[bw(write_with = packed_did::write)]
+    pub old_texture: u32,
+    #[brw(args(known_types::TEXTURE))]
+    #[br(parse_with = packed_did::read)]
+    #[bw(write_with = packed_did::write)]
+    pub new_texture: u32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelChange {
+    pub part: u8,
+    #[brw(args(known_types::MODEL))]
+    #[br(parse_with = packed_did::read)]
+    #[bw(write_with = packed_did::write)]
+    pub model: u32,
+}
+
+/// Physics description. Each optional field is present when its flag is set.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhysicsDesc {
+    #[br(map = PhysicsDescriptionFlag::from_bits_retain)]
+    #[bw(map = |f: &PhysicsDescriptionFlag| f.bits())]
+    pub flags: PhysicsDescriptionFlag,
+    #[br(map = PhysicsState::from_bits_retain)]
+    #[bw(map = |s: &PhysicsState| s.bits())]
+    pub state: PhysicsState,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::MOVEMENT)))]
+    pub movement: Option<MovementBuffer>,
+    #[br(if(!flags.contains(PhysicsDescriptionFlag::MOVEMENT)
+        && flags.contains(PhysicsDescriptionFlag::ANIMATION_FRAME)))]
+    pub animation_frame: Option<u32>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::POSITION)))]
+    pub position: Option<WorldPosition>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::MTABLE)))]
+    pub motion_table: Option<u32>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::STABLE)))]
+    pub sound_table: Option<u32>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::PETABLE)))]
+    pub physics_script_table: Option<u32>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::CSETUP)))]
+    pub setup: Option<u32>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::PARENT)))]
+    pub parent_id: Option<u32>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::PARENT)))]
+    pub parent_location: Option<u32>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::CHILDREN)))]
+    pub children: Option<ChildList>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::OBJSCALE)))]
+    pub object_scale: Option<f32>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::FRICTION)))]
+    pub friction: Option<f32>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::ELASTICITY)))]
+    pub elasticity: Option<f32>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::TRANSLUCENCY)))]
+    pub translucency: Option<f32>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::VELOCITY)))]
+    pub velocity: Option<Vector3>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::ACCELERATION)))]
+    pub acceleration: Option<Vector3>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::OMEGA)))]
+    pub omega: Option<Vector3>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::DEFAULT_SCRIPT)))]
+    pub default_script: Option<u32>,
+    #[br(if(flags.contains(PhysicsDescriptionFlag::DEFAULT_SCRIPT_INTENSITY)))]
+    pub default_script_intensity: Option<f32>,
+    /// Position, movement, state, vector, teleport, server-controlled move, force position,
+    /// objdesc and instance sequences. Always present.
+    pub sequences: [u16; 9],
+}
+
+/// A length-prefixed MovementData blob, followed by the autonomous flag when non-empty.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MovementBuffer {
+    #[br(temp)]
+    #[bw(calc = data.len() as u32)]
+    len: u32,
+    #[br(args(len as usize), parse_with = bytes::read)]
+    #[bw(write_with = bytes::write)]
+    pub data: Vec<u8>,
+    #[br(if(len > 0))]
+    pub autonomous: Option<u32>,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChildList {
+    #[br(temp)]
+    #[bw(calc = children.len() as u32)]
+    count: u32,
+    /// (guid, location) pairs.
+    #[br(count = count as usize)]
+    pub children: Vec<(u32, u32)>,
+}
+
+/// The weenie header. Each optional field is present when its flag is set, and the second
+/// set of flags when the description flags include a second header.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeenieHeader {
+    #[br(map = WeenieHeaderFlag::from_bits_retain)]
+    #[bw(map = |f: &WeenieHeaderFlag| f.bits())]
+    pub flags: WeenieHeaderFlag,
+    #[br(parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub name: String,
+    #[br(parse_with = packed_u32::read)]
+    #[bw(write_with = packed_u32::write)]
+    pub wcid: u32,
+    #[brw(args(known_types::ICON))]
+    #[br(parse_with = packed_did::read)]
+    #[bw(write_with = packed_did::write)]
+    pub icon: u32,
+    #[br(map = ItemType::from_bits_retain)]
+    #[bw(map = |t: &ItemType| t.bits())]
+    pub item_type: ItemType,
+    #[brw(align_after = 4)]
+    #[br(map = ObjectDescriptionFlag::from_bits_retain)]
+    #[bw(map = |f: &ObjectDescriptionFlag| f.bits())]
+    pub description_flags: ObjectDescriptionFlag,
+    #[br(
+        if(description_flags.contains(ObjectDescriptionFlag::INCLUDES_SECOND_HEADER)),
+        map = |f: u32| Some(WeenieHeaderFlag2::from_bits_retain(f))
+    )]
+    #[bw(map = |f: &Option<WeenieHeaderFlag2>| f.map(|f| f.bits()))]
+    pub flags2: Option<WeenieHeaderFlag2>,
+    #[br(if(flags.contains(WeenieHeaderFlag::PLURAL_NAME)), parse_with = string16::read)]
+    #[bw(write_with = string16::write)]
+    pub plural_name: Option<String>,
+    #[br(if(flags.contains(WeenieHeaderFlag::ITEMS_CAPACITY)))]
+    pub items_capacity: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::CONTAINERS_CAPACITY)))]
+    pub containers_capacity: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::AMMO_TYPE)))]
+    pub ammo_type: Option<u16>,
+    #[br(if(flags.contains(WeenieHeaderFlag::VALUE)))]
+    pub value: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::USABLE)))]
+    pub usable: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::USE_RADIUS)))]
+    pub use_radius: Option<f32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::TARGET_TYPE)))]
+    pub target_type: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::UI_EFFECTS)))]
+    pub ui_effects: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::COMBAT_USE)))]
+    pub combat_use: Option<u8>,
+    #[br(if(flags.contains(WeenieHeaderFlag::STRUCTURE)))]
+    pub structure: Option<u16>,
+    #[br(if(flags.contains(WeenieHeaderFlag::MAX_STRUCTURE)))]
+    pub max_structure: Option<u16>,
+    #[br(if(flags.contains(WeenieHeaderFlag::STACK_SIZE)))]
+    pub stack_size: Option<u16>,
+    #[br(if(flags.contains(WeenieHeaderFlag::MAX_STACK_SIZE)))]
+    pub max_stack_size: Option<u16>,
+    #[br(if(flags.contains(WeenieHeaderFlag::CONTAINER)))]
+    pub container_id: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::WIELDER)))]
+    pub wielder_id: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::VALID_LOCATIONS)))]
+    pub valid_locations: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::CURRENTLY_WIELDED_LOCATION)))]
+    pub currently_wielded_location: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::PRIORITY)))]
+    pub priority: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::RADAR_BLIP_COLOR)))]
+    pub radar_blip_color: Option<u8>,
+    #[br(if(flags.contains(WeenieHeaderFlag::RADAR_BEHAVIOR)))]
+    pub radar_behavior: Option<u8>,
+    #[br(if(flags.contains(WeenieHeaderFlag::PSCRIPT)))]
+    pub pscript: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::WORKMANSHIP)))]
+    pub workmanship: Option<f32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::BURDEN)))]
+    pub burden: Option<u16>,
+    #[br(if(flags.contains(WeenieHeaderFlag::SPELL)))]
+    pub spell: Option<u16>,
+    #[br(if(flags.contains(WeenieHeaderFlag::HOUSE_OWNER)))]
+    pub house_owner: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::HOUSE_RESTRICTIONS)))]
+    pub house_restrictions: Option<HouseRestrictions>,
+    #[br(if(flags.contains(WeenieHeaderFlag::HOOK_ITEM_TYPES)))]
+    pub hook_item_types: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::MONARCH)))]
+    pub monarch: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::HOOK_TYPE)))]
+    pub hook_type: Option<u32>,
+    #[brw(args(known_types::ICON))]
+    #[br(if(flags.contains(WeenieHeaderFlag::ICON_OVERLAY)), parse_with = packed_did::read)]
+    #[bw(write_with = packed_did::write)]
+    pub icon_overlay: Option<u32>,
+    #[br(if(flags.contains(WeenieHeaderFlag::MATERIAL_TYPE)))]
+    pub material_type: Option<u32>,
+    #[brw(args(known_types::ICON))]
+    #[br(
+        if(flags2.is_some_and(|f| f.contains(WeenieHeaderFlag2::ICON_UNDERLAY))),
+        parse_with = packed_did::read
+    )]
+    #[bw(write_with = packed_did::write)]
+    pub icon_underlay: Option<u32>,
+    #[br(if(flags2.is_some_and(|f| f.contains(WeenieHeaderFlag2::COOLDOWN))))]
+    pub cooldown: Option<u32>,
+    #[br(if(flags2.is_some_and(|f| f.contains(WeenieHeaderFlag2::COOLDOWN_DURATION))))]
+    pub cooldown_duration: Option<f64>,
+    #[br(if(flags2.is_some_and(|f| f.contains(WeenieHeaderFlag2::PET_OWNER))))]
+    pub pet_owner: Option<u32>,
+}
+
+/// RestrictionDB: version, open status and monarch, then (guid, value) entries.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HouseRestrictions {
+    pub version: u32,
+    pub open_status: u32,
+    pub monarch_id: u32,
+    pub table: PropertyTable<(u32, u32)>,
+}
@@ -0,0 +1,273 @@
+//! binrw field helpers for the encodings message bodies share.
+//!
+//! Each module pairs a `read` for `#[br(parse_with = ...)]` with a `write` for
+//! `#[bw(write_with = ...)]`, so a field is read and written from one definition. The
+//! string and packed helpers also serve `Option` fields behind `#[br(if(...))]`: `read`
+//! produces anything the value converts into, and `write` skips a `None`.
+
+use crate::protocol::parse::ensure_remaining;
+use binrw::io::{Read, Seek, SeekFrom, Write};
+use binrw::{BinRead, BinResult, BinWrite, Endian};
+
+/// A field value that may be absent: the value itself, or an `Option` of it.
+pub trait MaybeField<T> {
+    fn get(&self) -> Option<&T>;
+}
+
+impl<T> MaybeField<T> for T {
+    fn get(&self) -> Option<&T> {
+        Some(self)
+    }
+}
+
+impl<T> MaybeField<T> for Option<T> {
+    fn get(&self) -> Option<&T> {
+        self.as_ref()
+    }
+}
+
+fn string16_padding(len: usize) -> usize {
+    (4 - (2 + len) % 4) % 4
+}
+
+/// A u16-length string padded to 4 bytes including the length.
+pub mod string16 {
+    use super::*;
+
+    pub fn read<R: Read + Seek, T: From<String>>(
+        reader: &mut R,
+        endian: Endian,
+        _: (),
+    ) -> BinResult<T> {
+        let s: String = super::string16_unpadded::read(reader, endian, ())?;
+        // Padding left off the end of a message is not an error
+        reader.seek(SeekFrom::Current(string16_padding(s.len()) as i64))?;
+        Ok(s.into())
+    }
+
+    pub fn write<W: Write + Seek, V>(
+        value: &V,
+        writer: &mut W,
+        endian: Endian,
+        _: (),
+    ) -> BinResult<()>
+    where
+        V: MaybeField<String>,
+    {
+        if let Some(s) = value.get() {
+            super::string16_unpadded::write::<_, String>(s, writer, endian, ())?;
+            writer.write_all(&[0u8; 3][..string16_padding(s.len())])?;
+        }
+        Ok(())
+    }
+}
+
+/// A u16-length string with no padding, as found inside property tables.
+pub mod string16_unpadded {
+    use super::*;
+
+    pub fn read<R: Read + Seek, T: From<String>>(
+        reader: &mut R,
+        endian: Endian,
+        _: (),
+    ) -> BinResult<T> {
+        let len = u16::read_options(reader, endian, ())? as usize;
+        let mut bytes = vec![0u8; len];
+        reader.read_exact(&mut bytes)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned().into())
+    }
+
+    pub fn write<W: Write + Seek, V>(
+        value: &V,
+        writer: &mut W,
+        endian: Endian,
+        _: (),
+    ) -> BinResult<()>
+    where
+        V: MaybeField<String>,
+    {
+        if let Some(s) = value.get() {
+            (s.len() as u16).write_options(writer, endian, ())?;
+            writer.write_all(s.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// A u16, or a u32 with the top bit of its high word set.
+pub mod packed_u32 {
+    use super::*;
+
+    pub fn read<R: Read + Seek, T: From<u32>>(
+        reader: &mut R,
+        endian: Endian,
+        _: (),
+    ) -> BinResult<T> {
+        let a = u16::read_options(reader, endian, ())?;
+        if a & 0x8000 == 0 {
+            return Ok((a as u32).into());
+        }
+        let b = u16::read_options(reader, endian, ())?;
+        Ok(((((a & 0x7FFF) as u32) << 16) | b as u32).into())
+    }
+
+    pub fn write<W: Write + Seek, V>(
+        value: &V,
+        writer: &mut W,
+        endian: Endian,
+        _: (),
+    ) -> BinResult<()>
+    where
+        V: MaybeField<u32>,
+    {
+        let Some(&value) = value.get() else {
+            return Ok(());
+        };
+        if value <= 0x7FFF {
+            (value as u16).write_options(writer, endian, ())
+        } else {
+            (((value >> 16) as u16) | 0x8000).write_options(writer, endian, ())?;
+            (value as u16).write_options(writer, endian, ())
+        }
+    }
+}
+
+/// A packed data ID, which leaves off its `known_type` when the rest fits in a u16.
+pub mod packed_did {
+    use super::*;
+
+    pub fn read<R: Read + Seek, T: From<u32>>(
+        reader: &mut R,
+        endian: Endian,
+        (known_type,): (u32,),
+    ) -> BinResult<T> {
+        let a = u16::read_options(reader, endian, ())?;
+        if a & 0x8000 == 0 {
+            return Ok((a as u32 | known_type).into());
+        }
+        let b = u16::read_options(reader, endian, ())?;
+        Ok(((((a & 0x7FFF) as u32) << 16) | b as u32).into())
+    }
+
+    pub fn write<W: Write + Seek, V>(
+        value: &V,
+        writer: &mut W,
+        endian: Endian,
+        (known_type,): (u32,),
+    ) -> BinResult<()>
+    where
+        V: MaybeField<u32>,
+    {
+        let Some(&value) = value.get() else {
+            return Ok(());
+        };
+        if value & 0xFFFF_0000 == known_type && value & 0xFFFF <= 0x7FFF {
+            super::packed_u32::write::<_, u32>(&(value & 0xFFFF), writer, endian, ())
+        } else {
+            super::packed_u32::write::<_, u32>(&value, writer, endian, ())
+        }
+    }
+}
+
+/// `len` raw bytes, with the length checked against the data before it is allocated.
+pub mod bytes {
+    use super::*;
+
+    pub fn read<R: Read + Seek>(reader: &mut R, _: Endian, (len,): (usize,)) -> BinResult<Vec<u8>> {
+        ensure_remaining(reader, len)?;
+        let mut bytes = vec![0u8; len];
+        reader.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    pub fn write<W: Write + Seek, V: AsRef<[u8]>>(
+        bytes: &V,
+        writer: &mut W,
+        _: Endian,
+        _: (),
+    ) -> BinResult<()> {
+        writer.write_all(bytes.as_ref())?;
+        Ok(())
+    }
+}
+
+/// Everything up to the end of the message.
+pub mod rest {
+    use super::*;
+
+    pub fn read<R: Read + Seek>(reader: &mut R, _: Endian, _: ()) -> BinResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    pub use super::bytes::write;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::parse::Stream;
+    use binrw::binrw;
+    use binrw::io::Cursor;
+
+    #[binrw]
+    #[brw(little)]
+    #[derive(Debug, PartialEq)]
+    struct Fields {
+        #[br(parse_with = string16::read)]
+        #[bw(write_with = string16::write)]
+        name: String,
+        #[br(parse_with = packed_u32::read)]
+        #[bw(write_with = packed_u32::write)]
+        small: u32,
+        #[br(parse_with = packed_u32::read)]
+        #[bw(write_with = packed_u32::write)]
+        large: u32,
+        #[brw(args(0x06000000))]
+        #[br(parse_with = packed_did::read)]
+        #[bw(write_with = packed_did::write)]
+        icon: u32,
+        #[brw(args(0x06000000))]
+        #[br(if(small == 0), parse_with = packed_did::read)]
+        #[bw(write_with = packed_did::write)]
+        overlay: Option<u32>,
+    }
+
+    #[test]
+    fn test_fields_roundtrip() {
+        let fields = Fields {
+            name: "abc".to_string(),
+            small: 0x1234,
+            large: 0x00015678,
+            icon: 0x06001234,
+            overlay: None,
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        fields.write_le(&mut cursor).unwrap();
+        let data = cursor.into_inner();
+        assert_eq!(
+            data,
+            [
+                3, 0, b'a', b'b', b'c', 0, 0, 0, 0x34, 0x12, 0x01, 0x80, 0x78, 0x56, 0x34, 0x12
+            ]
+        );
+        assert_eq!(Stream::new(&data, 0).read::<Fields>().unwrap(), fields);
+    }
+
+    #[test]
+    fn test_optional_field_follows_condition() {
+        let fields = Fields {
+            name: String::new(),
+            small: 0,
+            large: 0,
+            icon: 0x06000001,
+            overlay: Some(0x01005678),
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        fields.write_le(&mut cursor).unwrap();
+        let data = cursor.into_inner();
+        assert_eq!(&data[data.len() - 4..], [0x00, 0x81, 0x78, 0x56]);
+        assert_eq!(Stream::new(&data, 0).read::<Fields>().unwrap(), fields);
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::VecDeque;
+
 #[allow(dead_code)]
 pub struct Hash32;
 
@@ -150,6 +152,76 @@ impl Isaac {
     }
 }
 
+/// How far past the newest matched key inbound verification will search the keystream.
+pub const ISAAC_LOOKAHEAD: u64 = 256;
+
+/// The peer's ISAAC keystream, used to verify inbound packets.
+///
+/// Packets can arrive out of order, so keys are generated ahead on demand and each one may
+/// be consumed only once. Keys that fall too far behind the newest match are forgotten.
+pub struct IsaacVerifier {
+    isaac: Isaac,
+    /// Generated but not yet matched keys, tagged with their position in the stream.
+    outstanding: VecDeque<(u64, u32)>,
+    /// Recently matched keys, so retransmissions of packets we already have still verify.
+    consumed: VecDeque<u32>,
+    generated: u64,
+    newest_match: Option<u64>,
+}
+
+impl IsaacVerifier {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            isaac: Isaac::new(seed),
+            outstanding: VecDeque::new(),
+            consumed: VecDeque::new(),
+            generated: 0,
+            newest_match: None,
+        }
+    }
+
+    /// Consumes `key` if it is one of the upcoming keys in the stream.
+    pub fn try_consume(&mut self, key: u32) -> bool {
+        if let Some(pos) = self.outstanding.iter().position(|&(_, k)| k == key) {
+            let (index, _) = self.outstanding.remove(pos).unwrap();
+            self.matched(index, key);
+            return true;
+        }
+
+        let limit = self.newest_match.map_or(0, |n| n + 1) + ISAAC_LOOKAHEAD;
+        while self.generated < limit {
+            let index = self.generated;
+            let candidate = self.isaac.current_key;
+            self.isaac.consume_key();
+            self.generated += 1;
+
+            if candidate == key {
+                self.matched(index, key);
+                return true;
+            }
+            self.outstanding.push_back((index, candidate));
+        }
+        false
+    }
+
+    /// Whether `key` was matched recently (a retransmission reuses its original key).
+    pub fn was_consumed(&self, key: u32) -> bool {
+        self.consumed.contains(&key)
+    }
+
+    fn matched(&mut self, index: u64, key: u32) {
+        let newest = self.newest_match.map_or(index, |n| n.max(index));
+        self.newest_match = Some(newest);
+        self.outstanding
+            .retain(|&(i, _)| i + ISAAC_LOOKAHEAD > newest);
+
+        self.consumed.push_back(key);
+        if self.consumed.len() > ISAAC_LOOKAHEAD as usize {
+            self.consumed.pop_front();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -226,4 +298,18 @@ mod tests {
         zero.consume_key();
         assert_eq!(zero.current_key, 0x300B4A8D);
     }
+
+    #[test]
+    fn test_verifier_accepts_out_of_order_keys_once() {
+        // First keys for seed 0xC83824AB
+        let (k0, k1, k2) = (0x1518CF56, 0x5F2E0E56, 0x73D586BF);
+        let mut verifier = IsaacVerifier::new(0xC83824AB);
+
+        assert!(verifier.try_consume(k1));
+        assert!(verifier.try_consume(k0));
+        assert!(!verifier.try_consume(k0));
+        assert!(verifier.was_consumed(k0));
+        assert!(!verifier.try_consume(0xDEADBEEF));
+        assert!(verifier.try_consume(k2));
+    }
 }
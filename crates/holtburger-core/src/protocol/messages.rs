@@ -1,12 +1,24 @@
-use crate::world::position::WorldPosition;
+use crate::math::Vector3;
+use crate::protocol::bodies;
+use crate::protocol::codec::{rest, string16};
+use crate::protocol::movement::{MovementData, MovementSequences, RawMotionState};
+use crate::protocol::parse::{ParseError, ParseErrorKind, Stream, pack_body};
+use crate::world::entity::{PhysicsDescription, PhysicsSequences, WeenieDescription};
+use crate::world::position::{PositionPack, WorldPosition};
 use crate::world::properties::{
-    ItemType, ObjectDescriptionFlag, WeenieHeaderFlag, WeenieHeaderFlag2,
+    ItemType, ObjectDescriptionFlag, PhysicsDescriptionFlag, PhysicsState, UpdatePositionFlag,
+    WeenieHeaderFlag, WeenieHeaderFlag2,
 };
+use binrw::{BinRead, BinWrite, binrw};
 use byteorder::{ByteOrder, LittleEndian};
+use std::io::{Seek, SeekFrom};
+use std::net::{Ipv4Addr, SocketAddrV4};
 
 pub const HEADER_SIZE: usize = 20;
 pub const FRAGMENT_HEADER_SIZE: usize = 16;
 pub const MAX_PACKET_SIZE: usize = 1024;
+/// Largest chunk of message data carried by a single fragment (ACE `MaxFragementDataSize`).
+pub const MAX_FRAGMENT_DATA_SIZE: usize = 448;
 
 // Protocol Magic Numbers
 pub const CHECKSUM_SEED: u32 = 0xBADD70DD;
@@ -69,6 +81,192 @@ impl PacketHeader {
     }
 }
 
+/// Transport-level optional headers that sit between the packet header and any fragments.
+/// Only the sections the session acts on are decoded; the rest are skipped.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OptionalHeaders {
+    pub server_switch: Option<ServerSwitchHeader>,
+    pub referral: Option<ReferralHeader>,
+    pub request_retransmit: Vec<u32>,
+    pub reject_retransmit: Vec<u32>,
+    pub ack_sequence: Option<u32>,
+    /// Connection cookie presented to a world server after a referral.
+    pub world_login: Option<u64>,
+    pub time_sync: Option<f64>,
+    /// Sender's local time in seconds, to be echoed back.
+    pub echo_request: Option<f32>,
+    /// Our echoed local time and how long the peer held the request, in seconds.
+    pub echo_response: Option<(f32, f32)>,
+    pub flow: Option<FlowHeader>,
+}
+
+/// SERVER_SWITCH optional header: the sequence at which the sender switched servers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ServerSwitchHeader {
+    pub sequence: u32,
+    pub kind: u32,
+}
+
+impl ServerSwitchHeader {
+    pub fn unpack(data: &[u8]) -> Self {
+        ServerSwitchHeader {
+            sequence: LittleEndian::read_u32(&data[0..4]),
+            kind: LittleEndian::read_u32(&data[4..8]),
+        }
+    }
+
+    pub fn pack(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.sequence.to_le_bytes());
+        buf.extend_from_slice(&self.kind.to_le_bytes());
+    }
+}
+
+pub const REFERRAL_HEADER_SIZE: usize = 32;
+
+/// REFERRAL optional header: directs the client to another (world) server.
+/// Layout is the connection cookie, a `sockaddr_in` (port and address in network order),
+/// the server id and padding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReferralHeader {
+    pub cookie: u64,
+    pub address: SocketAddrV4,
+    pub server_id: u16,
+}
+
+impl ReferralHeader {
+    pub fn unpack(data: &[u8]) -> Self {
+        let port = u16::from_be_bytes([data[10], data[11]]);
+        let ip = Ipv4Addr::new(data[12], data[13], data[14], data[15]);
+        ReferralHeader {
+            cookie: LittleEndian::read_u64(&data[0..8]),
+            address: SocketAddrV4::new(ip, port),
+            server_id: LittleEndian::read_u16(&data[24..26]),
+        }
+    }
+
+    pub fn pack(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.cookie.to_le_bytes());
+        buf.extend_from_slice(&2u16.to_le_bytes()); // AF_INET
+        buf.extend_from_slice(&self.address.port().to_be_bytes());
+        buf.extend_from_slice(&self.address.ip().octets());
+        buf.extend_from_slice(&[0u8; 8]);
+        buf.extend_from_slice(&self.server_id.to_le_bytes());
+        buf.extend_from_slice(&[0u8; 6]);
+    }
+}
+
+/// FLOW optional header: bytes the sender received over its last reporting interval.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlowHeader {
+    pub bytes: u32,
+    pub interval: u16,
+}
+
+impl OptionalHeaders {
+    pub fn unpack(flags: u32, data: &[u8]) -> Self {
+        let mut headers = OptionalHeaders::default();
+        let mut offset = 0;
+
+        if flags & flags::SERVER_SWITCH != 0 {
+            if offset + 8 <= data.len() {
+                headers.server_switch = Some(ServerSwitchHeader::unpack(&data[offset..]));
+            }
+            offset += 8;
+        }
+        if flags & flags::REFERRAL != 0 {
+            if offset + REFERRAL_HEADER_SIZE <= data.len() {
+                headers.referral = Some(ReferralHeader::unpack(&data[offset..]));
+            }
+            offset += REFERRAL_HEADER_SIZE;
+        }
+        if flags & flags::REQUEST_RETRANSMIT != 0 {
+            headers.request_retransmit = read_sequence_list(data, &mut offset);
+        }
+        if flags & flags::REJECT_RETRANSMIT != 0 {
+            headers.reject_retransmit = read_sequence_list(data, &mut offset);
+        }
+        if flags & flags::ACK_SEQUENCE != 0 {
+            if offset + 4 <= data.len() {
+                headers.ack_sequence = Some(LittleEndian::read_u32(&data[offset..offset + 4]));
+            }
+            offset += 4;
+        }
+        if flags & flags::CONNECT_REQUEST != 0 {
+            offset += 32;
+        }
+        if flags & flags::LOGIN_REQUEST != 0 {
+            offset = data.len();
+        }
+        if flags & flags::WORLD_LOGIN_REQUEST != 0 {
+            if offset + 8 <= data.len() {
+                headers.world_login = Some(LittleEndian::read_u64(&data[offset..offset + 8]));
+            }
+            offset += 8;
+        }
+        if flags & flags::CONNECT_RESPONSE != 0 {
+            offset += 8;
+        }
+        if flags & flags::CICMD != 0 {
+            offset += 8;
+        }
+        if flags & flags::TIME_SYNC != 0 {
+            if offset + 8 <= data.len() {
+                headers.time_sync = Some(LittleEndian::read_f64(&data[offset..offset + 8]));
+            }
+            offset += 8;
+        }
+        if flags & flags::ECHO_REQUEST != 0 {
+            if offset + 4 <= data.len() {
+                headers.echo_request = Some(LittleEndian::read_f32(&data[offset..offset + 4]));
+            }
+            offset += 4;
+        }
+        if flags & flags::ECHO_RESPONSE != 0 {
+            if offset + 8 <= data.len() {
+                headers.echo_response = Some((
+                    LittleEndian::read_f32(&data[offset..offset + 4]),
+                    LittleEndian::read_f32(&data[offset + 4..offset + 8]),
+                ));
+            }
+            offset += 8;
+        }
+        if flags & flags::FLOW != 0 && offset + 6 <= data.len() {
+            headers.flow = Some(FlowHeader {
+                bytes: LittleEndian::read_u32(&data[offset..offset + 4]),
+                interval: LittleEndian::read_u16(&data[offset + 4..offset + 6]),
+            });
+        }
+
+        headers
+    }
+}
+
+/// Reads a `u32` count followed by that many `u32` packet sequences.
+pub fn read_sequence_list(data: &[u8], offset: &mut usize) -> Vec<u32> {
+    if *offset + 4 > data.len() {
+        return Vec::new();
+    }
+    let count = LittleEndian::read_u32(&data[*offset..*offset + 4]) as usize;
+    *offset += 4;
+
+    let mut sequences = Vec::new();
+    for _ in 0..count {
+        if *offset + 4 > data.len() {
+            break;
+        }
+        sequences.push(LittleEndian::read_u32(&data[*offset..*offset + 4]));
+        *offset += 4;
+    }
+    sequences
+}
+
+pub fn write_sequence_list(buf: &mut Vec<u8>, sequences: &[u32]) {
+    buf.extend_from_slice(&(sequences.len() as u32).to_le_bytes());
+    for sequence in sequences {
+        buf.extend_from_slice(&sequence.to_le_bytes());
+    }
+}
+
 #[derive(Debug)]
 pub struct ConnectRequestData {
     pub cookie: u64,
@@ -133,6 +331,7 @@ pub mod flags {
     pub const ENCRYPTED_CHECKSUM: u32 = 0x00000002;
     pub const BLOB_FRAGMENTS: u32 = 0x00000004;
     pub const SERVER_SWITCH: u32 = 0x00000100;
+    pub const REFERRAL: u32 = 0x00000800;
     pub const REQUEST_RETRANSMIT: u32 = 0x00001000;
     pub const REJECT_RETRANSMIT: u32 = 0x00002000;
     pub const ACK_SEQUENCE: u32 = 0x00004000;
@@ -146,6 +345,34 @@ pub mod flags {
     pub const ECHO_REQUEST: u32 = 0x02000000;
     pub const ECHO_RESPONSE: u32 = 0x04000000;
     pub const FLOW: u32 = 0x08000000;
+
+    /// Names of the flags set in `flags`, lowest bit first.
+    pub fn names(flags: u32) -> Vec<&'static str> {
+        [
+            (RETRANSMISSION, "RETRANSMISSION"),
+            (ENCRYPTED_CHECKSUM, "ENCRYPTED_CHECKSUM"),
+            (BLOB_FRAGMENTS, "BLOB_FRAGMENTS"),
+            (SERVER_SWITCH, "SERVER_SWITCH"),
+            (REFERRAL, "REFERRAL"),
+            (REQUEST_RETRANSMIT, "REQUEST_RETRANSMIT"),
+            (REJECT_RETRANSMIT, "REJECT_RETRANSMIT"),
+            (ACK_SEQUENCE, "ACK_SEQUENCE"),
+            (DISCONNECT, "DISCONNECT"),
+            (LOGIN_REQUEST, "LOGIN_REQUEST"),
+            (WORLD_LOGIN_REQUEST, "WORLD_LOGIN_REQUEST"),
+            (CONNECT_REQUEST, "CONNECT_REQUEST"),
+            (CONNECT_RESPONSE, "CONNECT_RESPONSE"),
+            (CICMD, "CICMD"),
+            (TIME_SYNC, "TIME_SYNC"),
+            (ECHO_REQUEST, "ECHO_REQUEST"),
+            (ECHO_RESPONSE, "ECHO_RESPONSE"),
+            (FLOW, "FLOW"),
+        ]
+        .into_iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, name)| name)
+        .collect()
+    }
 }
 
 pub mod queues {
@@ -163,6 +390,7 @@ pub mod opcodes {
     pub const PARENT_EVENT: u32 = 0xF749;
     pub const PICKUP_EVENT: u32 = 0xF74A;
     pub const SET_STATE: u32 = 0xF74B;
+    pub const PLAYER_TELEPORT: u32 = 0xF751;
     pub const UPDATE_OBJECT: u32 = 0xF7DB;
     pub const PLAY_EFFECT: u32 = 0xF755;
     pub const GAME_EVENT: u32 = 0xF7B0;
@@ -196,8 +424,60 @@ pub mod opcodes {
     pub const UPDATE_MOTION: u32 = 0xF74C;
     pub const UPDATE_POSITION: u32 = 0xF748;
     pub const VECTOR_UPDATE: u32 = 0xF74E;
+
+    pub fn name(opcode: u32) -> Option<&'static str> {
+        Some(match opcode {
+            CHARACTER_LIST => "CHARACTER_LIST",
+            CHARACTER_ENTER_WORLD_REQUEST => "CHARACTER_ENTER_WORLD_REQUEST",
+            CHARACTER_ENTER_WORLD_SERVER_READY => "CHARACTER_ENTER_WORLD_SERVER_READY",
+            CHARACTER_ENTER_WORLD => "CHARACTER_ENTER_WORLD",
+            OBJECT_CREATE => "OBJECT_CREATE",
+            PLAYER_CREATE => "PLAYER_CREATE",
+            OBJECT_DELETE => "OBJECT_DELETE",
+            PARENT_EVENT => "PARENT_EVENT",
+            PICKUP_EVENT => "PICKUP_EVENT",
+            SET_STATE => "SET_STATE",
+            PLAYER_TELEPORT => "PLAYER_TELEPORT",
+            UPDATE_OBJECT => "UPDATE_OBJECT",
+            PLAY_EFFECT => "PLAY_EFFECT",
+            GAME_EVENT => "GAME_EVENT",
+            GAME_ACTION => "GAME_ACTION",
+            SERVER_MESSAGE => "SERVER_MESSAGE",
+            HEAR_SPEECH => "HEAR_SPEECH",
+            SOUL_EMOTE => "SOUL_EMOTE",
+            CHARACTER_ERROR => "CHARACTER_ERROR",
+            SERVER_NAME => "SERVER_NAME",
+            BOOT_ACCOUNT => "BOOT_ACCOUNT",
+            DDD_INTERROGATION => "DDD_INTERROGATION",
+            DDD_INTERROGATION_RESPONSE => "DDD_INTERROGATION_RESPONSE",
+            PRIVATE_UPDATE_PROPERTY_INT => "PRIVATE_UPDATE_PROPERTY_INT",
+            PUBLIC_UPDATE_PROPERTY_INT => "PUBLIC_UPDATE_PROPERTY_INT",
+            PRIVATE_UPDATE_PROPERTY_INT64 => "PRIVATE_UPDATE_PROPERTY_INT64",
+            PUBLIC_UPDATE_PROPERTY_INT64 => "PUBLIC_UPDATE_PROPERTY_INT64",
+            PRIVATE_UPDATE_PROPERTY_BOOL => "PRIVATE_UPDATE_PROPERTY_BOOL",
+            PUBLIC_UPDATE_PROPERTY_BOOL => "PUBLIC_UPDATE_PROPERTY_BOOL",
+            PRIVATE_UPDATE_PROPERTY_FLOAT => "PRIVATE_UPDATE_PROPERTY_FLOAT",
+            PUBLIC_UPDATE_PROPERTY_FLOAT => "PUBLIC_UPDATE_PROPERTY_FLOAT",
+            PRIVATE_UPDATE_PROPERTY_STRING => "PRIVATE_UPDATE_PROPERTY_STRING",
+            PUBLIC_UPDATE_PROPERTY_STRING => "PUBLIC_UPDATE_PROPERTY_STRING",
+            PRIVATE_UPDATE_PROPERTY_DID => "PRIVATE_UPDATE_PROPERTY_DID",
+            PUBLIC_UPDATE_PROPERTY_DID => "PUBLIC_UPDATE_PROPERTY_DID",
+            PRIVATE_UPDATE_PROPERTY_IID => "PRIVATE_UPDATE_PROPERTY_IID",
+            PUBLIC_UPDATE_PROPERTY_IID => "PUBLIC_UPDATE_PROPERTY_IID",
+            PRIVATE_UPDATE_SKILL => "PRIVATE_UPDATE_SKILL",
+            PRIVATE_UPDATE_ATTRIBUTE => "PRIVATE_UPDATE_ATTRIBUTE",
+            PRIVATE_UPDATE_VITAL => "PRIVATE_UPDATE_VITAL",
+            PRIVATE_UPDATE_VITAL_CURRENT => "PRIVATE_UPDATE_VITAL_CURRENT",
+            UPDATE_MOTION => "UPDATE_MOTION",
+            UPDATE_POSITION => "UPDATE_POSITION",
+            VECTOR_UPDATE => "VECTOR_UPDATE",
+            _ => return None,
+        })
+    }
 }
 
+#[binrw]
+#[brw(little)]
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct Enchantment {
     pub spell_id: u16,
@@ -214,6 +494,7 @@ pub struct Enchantment {
     pub stat_mod_type: u32,
     pub stat_mod_key: u32,
     pub stat_mod_value: f32,
+    #[br(if(has_spell_set_id != 0))]
     pub spell_set_id: Option<u32>,
 }
 
@@ -234,83 +515,17 @@ impl Enchantment {
             .partial_cmp(&other.start_time)
             .unwrap_or(std::cmp::Ordering::Equal)
     }
-
-    pub fn read(data: &[u8], offset: &mut usize) -> Option<Self> {
-        if *offset + 60 > data.len() {
-            return None;
-        }
-        let spell_id = LittleEndian::read_u16(&data[*offset..*offset + 2]);
-        let layer = LittleEndian::read_u16(&data[*offset + 2..*offset + 4]);
-        let spell_category = LittleEndian::read_u16(&data[*offset + 4..*offset + 6]);
-        let has_spell_set_id = LittleEndian::read_u16(&data[*offset + 6..*offset + 8]);
-        let power_level = LittleEndian::read_u32(&data[*offset + 8..*offset + 12]);
-        let start_time = LittleEndian::read_f64(&data[*offset + 12..*offset + 20]);
-        let duration = LittleEndian::read_f64(&data[*offset + 20..*offset + 28]);
-        let caster_guid = LittleEndian::read_u32(&data[*offset + 28..*offset + 32]);
-        let degrade_modifier = LittleEndian::read_f32(&data[*offset + 32..*offset + 36]);
-        let degrade_limit = LittleEndian::read_f32(&data[*offset + 36..*offset + 40]);
-        let last_time_degraded = LittleEndian::read_f64(&data[*offset + 40..*offset + 48]);
-        let stat_mod_type = LittleEndian::read_u32(&data[*offset + 48..*offset + 52]);
-        let stat_mod_key = LittleEndian::read_u32(&data[*offset + 52..*offset + 56]);
-        let stat_mod_value = LittleEndian::read_f32(&data[*offset + 56..*offset + 60]);
-        *offset += 60;
-
-        let spell_set_id = if has_spell_set_id != 0 {
-            if *offset + 4 > data.len() {
-                return None;
-            }
-            let id = LittleEndian::read_u32(&data[*offset..*offset + 4]);
-            *offset += 4;
-            Some(id)
-        } else {
-            None
-        };
-
-        Some(Enchantment {
-            spell_id,
-            layer,
-            spell_category,
-            has_spell_set_id,
-            power_level,
-            start_time,
-            duration,
-            caster_guid,
-            degrade_modifier,
-            degrade_limit,
-            last_time_degraded,
-            stat_mod_type,
-            stat_mod_key,
-            stat_mod_value,
-            spell_set_id,
-        })
-    }
 }
 
+#[binrw]
+#[brw(little)]
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct LayeredSpell {
     pub spell_id: u16,
     pub layer: u16,
 }
 
-impl LayeredSpell {
-    pub fn read(data: &[u8], offset: &mut usize) -> Option<Self> {
-        if *offset + 4 > data.len() {
-            return None;
-        }
-        let spell_id = LittleEndian::read_u16(&data[*offset..*offset + 2]);
-        let layer = LittleEndian::read_u16(&data[*offset + 2..*offset + 4]);
-        *offset += 4;
-        Some(LayeredSpell { spell_id, layer })
-    }
-}
-
-pub mod actions {
-    pub const PICKUP: u32 = 0x0033;
-    pub const USE_ITEM: u32 = 0x0036;
-    pub const IDENTIFY_OBJECT: u32 = 0x0197;
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum GameMessage {
     CharacterList {
         characters: Vec<(u32, String)>,
@@ -338,6 +553,16 @@ pub enum GameMessage {
         weenie_flags: WeenieHeaderFlag,
         weenie_flags2: WeenieHeaderFlag2,
         flags: ObjectDescriptionFlag,
+        sequences: PhysicsSequences,
+        /// How the object is moving, when it was created mid-motion.
+        movement: Option<MovementData>,
+        /// Palette, texture and model swaps. Display models are not kept.
+        obj_desc: Box<bodies::ObjDesc>,
+        physics_state: PhysicsState,
+        velocity: Option<Vector3>,
+        omega: Option<Vector3>,
+        physics: Box<PhysicsDescription>,
+        weenie: Box<WeenieDescription>,
     },
     ObjectDelete {
         guid: u32,
@@ -352,6 +577,12 @@ pub enum GameMessage {
     SetState {
         guid: u32,
         state: u32,
+        instance_sequence: u16,
+        state_sequence: u16,
+    },
+    /// The player is entering portal space; the position they arrive at follows.
+    PlayerTeleport {
+        teleport_sequence: u16,
     },
     UpdatePropertyInt {
         guid: u32,
@@ -412,79 +643,56 @@ pub enum GameMessage {
         vital: u32,
         current: u32,
     },
-    MagicUpdateEnchantment {
-        target: u64,
-        enchantment: Enchantment,
-    },
-    MagicUpdateMultipleEnchantments {
-        target: u64,
-        enchantments: Vec<Enchantment>,
-    },
-    MagicRemoveEnchantment {
-        target: u64,
-        spell_id: u16,
-        layer: u16,
-    },
-    MagicRemoveMultipleEnchantments {
-        target: u64,
-        spells: Vec<LayeredSpell>,
-    },
-    MagicPurgeEnchantments {
-        target: u64,
-    },
-    MagicPurgeBadEnchantments {
-        target: u64,
-    },
-    MagicDispelEnchantment {
-        target: u64,
-        spell_id: u16,
-        layer: u16,
-    },
-    MagicDispelMultipleEnchantments {
-        target: u64,
-        spells: Vec<LayeredSpell>,
-    },
-    UpdateHealth {
-        target: u32,
-        health: f32,
-    },
     UpdateMotion {
         guid: u32,
-        data: Vec<u8>,
+        instance_sequence: u16,
+        movement_sequence: u16,
+        server_control_sequence: u16,
+        /// Whether the motion was started by the object's own client.
+        autonomous: bool,
+        movement: MovementData,
     },
     UpdatePosition {
         guid: u32,
         pos: WorldPosition,
+        /// Sent while the object is moving; otherwise it is at rest.
+        velocity: Option<Vector3>,
+        instance_sequence: u16,
+        position_sequence: u16,
+        teleport_sequence: u16,
+        force_position_sequence: u16,
     },
+    /// A new velocity and spin, such as from a jump or a knockback.
     VectorUpdate {
         guid: u32,
-        data: Vec<u8>,
+        velocity: Vector3,
+        /// Angular velocity, in radians per second about each axis.
+        omega: Vector3,
+        instance_sequence: u16,
+        vector_sequence: u16,
     },
     PlayEffect {
         guid: u32,
     },
+    /// A 0xF7B0 game event, addressed to `guid` (normally the player).
     GameEvent {
-        guid: u64,
-        sequence: u32,
-        event_type: u32,
-        data: Vec<u8>,
-    },
-    PlayerDescription {
         guid: u32,
-        name: String,
-        wee_type: u32,
-        pos: Option<WorldPosition>,
-        attributes: Vec<(u32, u32, u32, u32, u32)>, // (type, ranks, start, xp, current)
-        skills: Vec<(u32, u32, u32, u32, u32)>,     // (type, ranks, status, xp, init)
-        enchantments: Vec<Enchantment>,
+        /// Counts up by one with every game event in a session.
+        sequence: u32,
+        event: GameEventKind,
     },
+    /// A 0xF7B1 game action from the client.
     GameAction {
-        action: u32,
-        data: Vec<u8>,
+        /// Counts up by one with every action in a session; see `Session::send_action`.
+        sequence: u32,
+        action: GameActionKind,
     },
     ServerMessage {
         message: String,
     },
+    BootAccount {
+        reason: String,
+    },
     HearSpeech {
         message: String,
         sender: String,
@@ -512,13 +720,390 @@ pub enum GameMessage {
     },
 }
 
+/// The payload of a game event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEventKind {
+    PlayerDescription {
+        name: String,
+        wee_type: u32,
+        pos: Option<WorldPosition>,
+        attributes: Vec<(u32, u32, u32, u32, u32)>, // (type, ranks, start, xp, current)
+        skills: Vec<(u32, u32, u32, u32, u32)>,     // (type, ranks, status, xp, init)
+        enchantments: Vec<Enchantment>,
+    },
+    StartGame,
+    UpdateHealth {
+        target: u32,
+        health: f32,
+    },
+    ChannelBroadcast {
+        channel: u32,
+        /// Empty for the player's own messages.
+        sender: String,
+        message: String,
+    },
+    Tell {
+        message: String,
+        sender: String,
+        sender_id: u32,
+        target_id: u32,
+        chat_type: u32,
+    },
+    ViewContents {
+        container: u32,
+        items: Vec<bodies::ContentsItem>,
+    },
+    WeenieError {
+        error: u32,
+    },
+    WeenieErrorWithString {
+        error: u32,
+        text: String,
+    },
+    CharacterTitle {
+        current: u32,
+        titles: Vec<u32>,
+    },
+    FriendsListUpdate {
+        friends: Vec<bodies::Friend>,
+        update_type: u32,
+    },
+    FellowshipUpdateFellow {
+        guid: u32,
+        fellow: bodies::Fellow,
+        update_type: u32,
+    },
+    MagicUpdateEnchantment {
+        enchantment: Enchantment,
+    },
+    MagicUpdateMultipleEnchantments {
+        enchantments: Vec<Enchantment>,
+    },
+    MagicRemoveEnchantment {
+        spell_id: u16,
+        layer: u16,
+    },
+    MagicRemoveMultipleEnchantments {
+        spells: Vec<LayeredSpell>,
+    },
+    MagicPurgeEnchantments,
+    MagicPurgeBadEnchantments,
+    MagicDispelEnchantment {
+        spell_id: u16,
+        layer: u16,
+    },
+    MagicDispelMultipleEnchantments {
+        spells: Vec<LayeredSpell>,
+    },
+    /// An event type without a parser, with its body.
+    Unknown {
+        event_type: u32,
+        data: Vec<u8>,
+    },
+}
+
+impl GameEventKind {
+    pub fn event_type(&self) -> u32 {
+        use game_event_opcodes::*;
+        match self {
+            GameEventKind::PlayerDescription { .. } => PLAYER_DESCRIPTION,
+            GameEventKind::StartGame => START_GAME,
+            GameEventKind::UpdateHealth { .. } => UPDATE_HEALTH,
+            GameEventKind::ChannelBroadcast { .. } => CHANNEL_BROADCAST,
+            GameEventKind::Tell { .. } => TELL,
+            GameEventKind::ViewContents { .. } => VIEW_CONTENTS,
+            GameEventKind::WeenieError { .. } => WEENIE_ERROR,
+            GameEventKind::WeenieErrorWithString { .. } => WEENIE_ERROR_WITH_STRING,
+            GameEventKind::CharacterTitle { .. } => CHARACTER_TITLE,
+            GameEventKind::FriendsListUpdate { .. } => FRIENDS_LIST_UPDATE,
+            GameEventKind::FellowshipUpdateFellow { .. } => FELLOWSHIP_UPDATE_FELLOW,
+            GameEventKind::MagicUpdateEnchantment { .. } => MAGIC_UPDATE_ENCHANTMENT,
+            GameEventKind::MagicUpdateMultipleEnchantments { .. } => {
+                MAGIC_UPDATE_MULTIPLE_ENCHANTMENTS
+            }
+            GameEventKind::MagicRemoveEnchantment { .. } => MAGIC_REMOVE_ENCHANTMENT,
+            GameEventKind::MagicRemoveMultipleEnchantments { .. } => {
+                MAGIC_REMOVE_MULTIPLE_ENCHANTMENTS
+            }
+            GameEventKind::MagicPurgeEnchantments => MAGIC_PURGE_ENCHANTMENTS,
+            GameEventKind::MagicPurgeBadEnchantments => MAGIC_PURGE_BAD_ENCHANTMENTS,
+            GameEventKind::MagicDispelEnchantment { .. } => MAGIC_DISPEL_ENCHANTMENT,
+            GameEventKind::MagicDispelMultipleEnchantments { .. } => {
+                MAGIC_DISPEL_MULTIPLE_ENCHANTMENTS
+            }
+            GameEventKind::Unknown { event_type, .. } => *event_type,
+        }
+    }
+}
+
+/// The payload of a game action, each variant laid out as the server reads it.
+///
+/// Variants are tried in order by their action type, so an action of a known type whose
+/// body does not fit its layout reads as `Unknown`.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameActionKind {
+    #[brw(magic = 0x0008u32)]
+    TargetedMeleeAttack {
+        target: u32,
+        /// One of `attack_heights`.
+        height: u32,
+        /// How far the power bar was charged, from 0 to 1.
+        power: f32,
+    },
+    #[brw(magic = 0x000Au32)]
+    TargetedMissileAttack {
+        target: u32,
+        height: u32,
+        accuracy: f32,
+    },
+    #[brw(magic = 0x0015u32)]
+    Talk {
+        #[br(parse_with = string16::read)]
+        #[bw(write_with = string16::write)]
+        message: String,
+    },
+    #[brw(magic = 0x0017u32)]
+    RemoveFriend { guid: u32 },
+    #[brw(magic = 0x0018u32)]
+    AddFriend {
+        #[br(parse_with = string16::read)]
+        #[bw(write_with = string16::write)]
+        name: String,
+    },
+    #[brw(magic = 0x0019u32)]
+    PutItemInContainer {
+        item: u32,
+        container: u32,
+        /// Slot in the container, 0 for the first.
+        placement: u32,
+    },
+    #[brw(magic = 0x001Au32)]
+    GetAndWieldItem {
+        item: u32,
+        /// The equip mask of the slot to wield into.
+        location: u32,
+    },
+    #[brw(magic = 0x001Bu32)]
+    DropItem { item: u32 },
+    #[brw(magic = 0x002Cu32)]
+    SetTitle { title: u32 },
+    #[brw(magic = 0x0032u32)]
+    TellById {
+        #[br(parse_with = string16::read)]
+        #[bw(write_with = string16::write)]
+        message: String,
+        target: u32,
+    },
+    #[brw(magic = 0x0035u32)]
+    UseWithTarget { source: u32, target: u32 },
+    #[brw(magic = 0x0036u32)]
+    UseItem { item: u32 },
+    #[brw(magic = 0x0044u32)]
+    RaiseVital { vital: u32, xp: u32 },
+    #[brw(magic = 0x0045u32)]
+    RaiseAttribute { attribute: u32, xp: u32 },
+    #[brw(magic = 0x0046u32)]
+    RaiseSkill { skill: u32, xp: u32 },
+    #[brw(magic = 0x0048u32)]
+    CastUntargetedSpell { spell: u32 },
+    #[brw(magic = 0x004Au32)]
+    CastTargetedSpell { target: u32, spell: u32 },
+    #[brw(magic = 0x0053u32)]
+    ChangeCombatMode {
+        /// One of `combat_modes`.
+        mode: u32,
+    },
+    #[brw(magic = 0x0054u32)]
+    StackableMerge {
+        source: u32,
+        target: u32,
+        amount: u32,
+    },
+    #[brw(magic = 0x0055u32)]
+    StackableSplitToContainer {
+        stack: u32,
+        container: u32,
+        placement: u32,
+        amount: u32,
+    },
+    #[brw(magic = 0x005Du32)]
+    Tell {
+        #[br(parse_with = string16::read)]
+        #[bw(write_with = string16::write)]
+        message: String,
+        #[br(parse_with = string16::read)]
+        #[bw(write_with = string16::write)]
+        target: String,
+    },
+    #[brw(magic = 0x0063u32)]
+    TeleToLifestone,
+    #[brw(magic = 0x00A1u32)]
+    LoginComplete,
+    #[brw(magic = 0x00A2u32)]
+    FellowshipCreate {
+        #[br(parse_with = string16::read)]
+        #[bw(write_with = string16::write)]
+        name: String,
+        #[br(map = |x: u32| x != 0)]
+        #[bw(map = |x: &bool| *x as u32)]
+        share_xp: bool,
+    },
+    #[brw(magic = 0x00A3u32)]
+    FellowshipQuit {
+        #[br(map = |x: u32| x != 0)]
+        #[bw(map = |x: &bool| *x as u32)]
+        disband: bool,
+    },
+    #[brw(magic = 0x00A4u32)]
+    FellowshipDismiss { guid: u32 },
+    #[brw(magic = 0x00A5u32)]
+    FellowshipRecruit { guid: u32 },
+    #[brw(magic = 0x00C8u32)]
+    IdentifyObject { target: u32 },
+    #[brw(magic = 0x00CDu32)]
+    GiveObjectRequest { target: u32, item: u32, amount: u32 },
+    #[brw(magic = 0x0147u32)]
+    ChatChannel {
+        channel: u32,
+        #[br(parse_with = string16::read)]
+        #[bw(write_with = string16::write)]
+        message: String,
+    },
+    #[brw(magic = 0x0195u32)]
+    NoLongerViewingContents { container: u32 },
+    #[brw(magic = 0x01B7u32)]
+    CancelAttack,
+    #[brw(magic = 0x01BFu32)]
+    QueryHealth { target: u32 },
+    #[brw(magic = 0x01DFu32)]
+    Emote {
+        #[br(parse_with = string16::read)]
+        #[bw(write_with = string16::write)]
+        message: String,
+    },
+    #[brw(magic = 0x01E1u32)]
+    SoulEmote {
+        #[br(parse_with = string16::read)]
+        #[bw(write_with = string16::write)]
+        message: String,
+    },
+    #[brw(magic = 0x01E9u32)]
+    Ping,
+    #[brw(magic = 0x028Du32)]
+    TeleToMarketplace,
+    #[brw(magic = 0xF61Bu32)]
+    Jump {
+        /// How far the jump bar was charged, from 0 to 1.
+        extent: f32,
+        velocity: Vector3,
+        sequences: MovementSequences,
+    },
+    /// The player's motion changed, sent with where the client has them.
+    #[brw(magic = 0xF61Cu32)]
+    MoveToState {
+        motion: RawMotionState,
+        position: WorldPosition,
+        sequences: MovementSequences,
+        /// 1 when on the ground, plus 2 for a standing long jump.
+        #[brw(align_after = 4)]
+        contact: u8,
+    },
+    /// Where the client has the player, sent periodically while moving.
+    #[brw(magic = 0xF753u32)]
+    AutonomousPosition {
+        position: WorldPosition,
+        sequences: MovementSequences,
+        /// 1 when on the ground.
+        #[brw(align_after = 4)]
+        contact: u8,
+    },
+    /// An action type without a layout, with its body. Only produced by parsing; the
+    /// session refuses to send it.
+    Unknown {
+        action: u32,
+        #[br(parse_with = rest::read)]
+        #[bw(write_with = rest::write)]
+        data: Vec<u8>,
+    },
+}
+
+impl GameActionKind {
+    pub fn action_type(&self) -> u32 {
+        use action_opcodes::*;
+        match self {
+            GameActionKind::TargetedMeleeAttack { .. } => TARGETED_MELEE_ATTACK,
+            GameActionKind::TargetedMissileAttack { .. } => TARGETED_MISSILE_ATTACK,
+            GameActionKind::Talk { .. } => TALK,
+            GameActionKind::RemoveFriend { .. } => REMOVE_FRIEND,
+            GameActionKind::AddFriend { .. } => ADD_FRIEND,
+            GameActionKind::PutItemInContainer { .. } => PUT_ITEM_IN_CONTAINER,
+            GameActionKind::GetAndWieldItem { .. } => GET_AND_WIELD_ITEM,
+            GameActionKind::DropItem { .. } => DROP_ITEM,
+            GameActionKind::SetTitle { .. } => SET_TITLE,
+            GameActionKind::TellById { .. } => TELL_BY_ID,
+            GameActionKind::UseWithTarget { .. } => USE_WITH_TARGET,
+            GameActionKind::UseItem { .. } => USE_ITEM,
+            GameActionKind::RaiseVital { .. } => RAISE_VITAL,
+            GameActionKind::RaiseAttribute { .. } => RAISE_ATTRIBUTE,
+            GameActionKind::RaiseSkill { .. } => RAISE_SKILL,
+            GameActionKind::CastUntargetedSpell { .. } => CAST_UNTARGETED_SPELL,
+            GameActionKind::CastTargetedSpell { .. } => CAST_TARGETED_SPELL,
+            GameActionKind::ChangeCombatMode { .. } => CHANGE_COMBAT_MODE,
+            GameActionKind::StackableMerge { .. } => STACKABLE_MERGE,
+            GameActionKind::StackableSplitToContainer { .. } => STACKABLE_SPLIT_TO_CONTAINER,
+            GameActionKind::Tell { .. } => TELL,
+            GameActionKind::TeleToLifestone => TELE_TO_LIFESTONE,
+            GameActionKind::LoginComplete => LOGIN_COMPLETE,
+            GameActionKind::FellowshipCreate { .. } => FELLOWSHIP_CREATE,
+            GameActionKind::FellowshipQuit { .. } => FELLOWSHIP_QUIT,
+            GameActionKind::FellowshipDismiss { .. } => FELLOWSHIP_DISMISS,
+            GameActionKind::FellowshipRecruit { .. } => FELLOWSHIP_RECRUIT,
+            GameActionKind::IdentifyObject { .. } => IDENTIFY_OBJECT,
+            GameActionKind::GiveObjectRequest { .. } => GIVE_OBJECT_REQUEST,
+            GameActionKind::ChatChannel { .. } => CHAT_CHANNEL,
+            GameActionKind::NoLongerViewingContents { .. } => NO_LONGER_VIEWING_CONTENTS,
+            GameActionKind::CancelAttack => CANCEL_ATTACK,
+            GameActionKind::QueryHealth { .. } => QUERY_HEALTH,
+            GameActionKind::Emote { .. } => EMOTE,
+            GameActionKind::SoulEmote { .. } => SOUL_EMOTE,
+            GameActionKind::Ping => PING,
+            GameActionKind::TeleToMarketplace => TELE_TO_MARKETPLACE,
+            GameActionKind::Jump { .. } => JUMP,
+            GameActionKind::MoveToState { .. } => MOVE_TO_STATE,
+            GameActionKind::AutonomousPosition { .. } => AUTONOMOUS_POSITION,
+            GameActionKind::Unknown { action, .. } => *action,
+        }
+    }
+}
+
 impl GameMessage {
+    /// Parses a message body, falling back to `Unknown` (with the body after the opcode)
+    /// when it cannot be parsed.
     pub fn unpack(data: &[u8]) -> Self {
+        Self::try_unpack(data).unwrap_or_else(|e| {
+            log::warn!("{}", e);
+            GameMessage::Unknown {
+                opcode: e.opcode,
+                data: data.get(4..).unwrap_or(data).to_vec(),
+            }
+        })
+    }
+
+    /// Parses a message body. Opcodes without a parser become `Unknown`; a body that is too
+    /// short for its opcode is an error naming the field that was cut off.
+    pub fn try_unpack(data: &[u8]) -> Result<Self, ParseError> {
         if data.len() < 4 {
-            return GameMessage::Unknown {
+            return Err(ParseError {
                 opcode: 0,
-                data: data.to_vec(),
-            };
+                field: "opcode",
+                offset: 0,
+                kind: ParseErrorKind::Truncated {
+                    needed: 4,
+                    available: data.len(),
+                },
+            });
         }
         let opcode = LittleEndian::read_u32(&data[0..4]);
         log::info!(
@@ -527,442 +1112,247 @@ impl GameMessage {
             data.len(),
             &data[..data.len().min(32)]
         );
+        let mut s = Stream::new(data, opcode);
+        s.seek(SeekFrom::Start(4))
+            .expect("seeking forward cannot fail");
 
-        match opcode {
+        Ok(match opcode {
             opcodes::HEAR_SPEECH => {
-                let mut offset = 4;
-                let message = read_string16(data, &mut offset);
-                let sender = read_string16(data, &mut offset);
-                // Also has senderID (4) and chatMessageType (4)
-                GameMessage::HearSpeech { message, sender }
+                let body: bodies::HearSpeech = s.read()?;
+                GameMessage::HearSpeech {
+                    message: body.message,
+                    sender: body.sender,
+                }
             }
             opcodes::SOUL_EMOTE => {
-                if data.len() >= 8 {
-                    let mut offset = 4;
-                    let sender_id = LittleEndian::read_u32(&data[offset..offset + 4]);
-                    offset += 4;
-                    let sender_name = read_string16(data, &mut offset);
-                    let text = read_string16(data, &mut offset);
-                    GameMessage::SoulEmote {
-                        sender_id,
-                        sender_name,
-                        text,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::SoulEmote = s.read()?;
+                GameMessage::SoulEmote {
+                    sender_id: body.sender_id,
+                    sender_name: body.sender_name,
+                    text: body.text,
                 }
             }
             opcodes::CHARACTER_LIST => {
-                let mut offset = 8; // opcode + 0u
-                if data.len() < offset + 4 {
-                    return GameMessage::Unknown {
-                        opcode,
-                        data: data.to_vec(),
-                    };
-                }
-                let count = LittleEndian::read_u32(&data[offset..offset + 4]);
-                offset += 4;
-
-                let mut characters = Vec::new();
-                for _ in 0..count {
-                    if offset + 4 > data.len() {
-                        break;
-                    }
-                    let id = LittleEndian::read_u32(&data[offset..offset + 4]);
-                    offset += 4;
-                    let name = read_string16(data, &mut offset);
-
-                    if offset + 4 > data.len() {
-                        break;
-                    }
-                    // skip deleteTime
-                    offset += 4;
-                    characters.push((id, name));
+                let body: bodies::CharacterList = s.read()?;
+                GameMessage::CharacterList {
+                    characters: body
+                        .characters
+                        .into_iter()
+                        .map(|c| (c.id, c.name))
+                        .collect(),
                 }
-                GameMessage::CharacterList { characters }
             }
             opcodes::CHARACTER_ENTER_WORLD_SERVER_READY => {
                 GameMessage::CharacterEnterWorldServerReady
             }
             opcodes::CHARACTER_ENTER_WORLD_REQUEST => {
-                if data.len() >= 8 {
-                    GameMessage::CharacterEnterWorldRequest {
-                        char_id: LittleEndian::read_u32(&data[4..8]),
-                    }
-                } else {
-                    GameMessage::CharacterEnterWorldRequest { char_id: 0 }
+                let body: bodies::CharacterEnterWorldRequest = s.read()?;
+                GameMessage::CharacterEnterWorldRequest {
+                    char_id: body.char_id.unwrap_or(0),
                 }
             }
-            opcodes::PLAYER_CREATE => {
-                if data.len() >= 8 {
-                    GameMessage::PlayerCreate {
-                        player_id: LittleEndian::read_u32(&data[4..8]),
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data.to_vec(),
-                    }
+            opcodes::CHARACTER_ENTER_WORLD => {
+                let body: bodies::CharacterEnterWorld = s.read()?;
+                GameMessage::CharacterEnterWorld {
+                    id: body.id,
+                    account: body.account,
                 }
             }
+            opcodes::PLAYER_CREATE => GameMessage::PlayerCreate {
+                player_id: s.read::<bodies::Guid>()?.guid,
+            },
             opcodes::UPDATE_OBJECT | opcodes::OBJECT_CREATE => {
-                let msg = unpack_object_create(data).unwrap_or(GameMessage::Unknown {
-                    opcode,
-                    data: data.to_vec(),
-                });
-                if let GameMessage::ObjectCreate { guid, name, .. } = &msg {
-                    let log_type = if opcode == opcodes::UPDATE_OBJECT {
-                        "UpdateObject"
-                    } else {
-                        "ObjectCreate"
-                    };
-                    log::info!("!!! {} guid={:08X} name={:?}", log_type, guid, name);
-                }
-                msg
+                let body: bodies::ObjectCreate = s.read()?;
+                let log_type = if opcode == opcodes::UPDATE_OBJECT {
+                    "UpdateObject"
+                } else {
+                    "ObjectCreate"
+                };
+                log::info!(
+                    "!!! {} guid={:08X} name={:?}",
+                    log_type,
+                    body.guid,
+                    body.weenie.name
+                );
+                log::debug!(
+                    "guid={:08X} sequences: {:?}",
+                    body.guid,
+                    body.physics.sequences
+                );
+                object_create_message(body)
             }
             opcodes::OBJECT_DELETE => {
-                if data.len() >= 8 {
-                    let guid = LittleEndian::read_u32(&data[4..8]);
-                    log::info!("!!! ObjectDelete guid={:08X}", guid);
-                    GameMessage::ObjectDelete { guid }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data.to_vec(),
-                    }
-                }
+                let guid = s.read::<bodies::Guid>()?.guid;
+                log::info!("!!! ObjectDelete guid={:08X}", guid);
+                GameMessage::ObjectDelete { guid }
             }
             opcodes::PARENT_EVENT => {
-                if data.len() >= 12 {
-                    let parent_guid = LittleEndian::read_u32(&data[4..8]);
-                    let child_guid = LittleEndian::read_u32(&data[8..12]);
-                    log::info!(
-                        "!!! ParentEvent child={:08X} parent={:08X}",
-                        child_guid,
-                        parent_guid
-                    );
-                    GameMessage::ParentEvent {
-                        child_guid,
-                        parent_guid,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data.to_vec(),
-                    }
+                let body: bodies::ParentEvent = s.read()?;
+                log::info!(
+                    "!!! ParentEvent child={:08X} parent={:08X}",
+                    body.child_guid,
+                    body.parent_guid
+                );
+                GameMessage::ParentEvent {
+                    child_guid: body.child_guid,
+                    parent_guid: body.parent_guid,
                 }
             }
             opcodes::PICKUP_EVENT => {
-                if data.len() >= 8 {
-                    let guid = LittleEndian::read_u32(&data[4..8]);
-                    log::info!("!!! PickupEvent guid={:08X}", guid);
-                    GameMessage::PickupEvent { guid }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data.to_vec(),
-                    }
-                }
+                let guid = s.read::<bodies::Guid>()?.guid;
+                log::info!("!!! PickupEvent guid={:08X}", guid);
+                GameMessage::PickupEvent { guid }
             }
             opcodes::SET_STATE => {
-                if data.len() >= 8 {
-                    let guid = LittleEndian::read_u32(&data[4..8]);
-                    let state = if data.len() >= 12 {
-                        LittleEndian::read_u32(&data[8..12])
-                    } else {
-                        0
-                    };
-                    log::info!("!!! SetState guid={:08X} state={:08X}", guid, state);
-                    GameMessage::SetState { guid, state }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data.to_vec(),
-                    }
-                }
-            }
-            opcodes::PLAY_EFFECT => {
-                if data.len() >= 8 {
-                    GameMessage::PlayEffect {
-                        guid: LittleEndian::read_u32(&data[4..8]),
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data.to_vec(),
-                    }
+                let body: bodies::SetState = s.read()?;
+                log::info!(
+                    "!!! SetState guid={:08X} state={:08X}",
+                    body.guid,
+                    body.state
+                );
+                GameMessage::SetState {
+                    guid: body.guid,
+                    state: body.state,
+                    instance_sequence: body.instance_sequence,
+                    state_sequence: body.state_sequence,
                 }
             }
+            opcodes::PLAYER_TELEPORT => GameMessage::PlayerTeleport {
+                teleport_sequence: s.read::<bodies::PlayerTeleport>()?.teleport_sequence,
+            },
+            opcodes::PLAY_EFFECT => GameMessage::PlayEffect {
+                guid: s.read::<bodies::Guid>()?.guid,
+            },
+            // Private updates are about the player and carry a 1-byte sequence instead of a guid
             opcodes::PRIVATE_UPDATE_PROPERTY_INT => {
-                if data.len() >= 13 {
-                    // Sequence at data[4] (1 byte)
-                    let property = LittleEndian::read_u32(&data[5..9]);
-                    let value = LittleEndian::read_i32(&data[9..13]);
-                    GameMessage::UpdatePropertyInt {
-                        guid: 0,
-                        property,
-                        value,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::PrivateUpdateProperty<i32> = s.read()?;
+                GameMessage::UpdatePropertyInt {
+                    guid: 0,
+                    property: body.property,
+                    value: body.value,
                 }
             }
             opcodes::PUBLIC_UPDATE_PROPERTY_INT => {
-                if data.len() >= 16 {
-                    let guid = LittleEndian::read_u32(&data[4..8]);
-                    let property = LittleEndian::read_u32(&data[8..12]);
-                    let value = LittleEndian::read_i32(&data[12..16]);
-                    GameMessage::UpdatePropertyInt {
-                        guid,
-                        property,
-                        value,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::PublicUpdateProperty<i32> = s.read()?;
+                GameMessage::UpdatePropertyInt {
+                    guid: body.guid,
+                    property: body.property,
+                    value: body.value,
                 }
             }
             opcodes::PRIVATE_UPDATE_PROPERTY_INT64 => {
-                if data.len() >= 17 {
-                    // Sequence at data[4]
-                    let property = LittleEndian::read_u32(&data[5..9]);
-                    let value = LittleEndian::read_i64(&data[9..17]);
-                    GameMessage::UpdatePropertyInt64 {
-                        guid: 0,
-                        property,
-                        value,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::PrivateUpdateProperty<i64> = s.read()?;
+                GameMessage::UpdatePropertyInt64 {
+                    guid: 0,
+                    property: body.property,
+                    value: body.value,
                 }
             }
             opcodes::PUBLIC_UPDATE_PROPERTY_INT64 => {
-                if data.len() >= 20 {
-                    let guid = LittleEndian::read_u32(&data[4..8]);
-                    let property = LittleEndian::read_u32(&data[8..12]);
-                    let value = LittleEndian::read_i64(&data[12..20]);
-                    GameMessage::UpdatePropertyInt64 {
-                        guid,
-                        property,
-                        value,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::PublicUpdateProperty<i64> = s.read()?;
+                GameMessage::UpdatePropertyInt64 {
+                    guid: body.guid,
+                    property: body.property,
+                    value: body.value,
                 }
             }
             opcodes::PRIVATE_UPDATE_PROPERTY_BOOL => {
-                if data.len() >= 13 {
-                    // Sequence at data[4]
-                    let property = LittleEndian::read_u32(&data[5..9]);
-                    let value = LittleEndian::read_u32(&data[9..13]) != 0;
-                    GameMessage::UpdatePropertyBool {
-                        guid: 0,
-                        property,
-                        value,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::PrivateUpdateProperty<u32> = s.read()?;
+                GameMessage::UpdatePropertyBool {
+                    guid: 0,
+                    property: body.property,
+                    value: body.value != 0,
                 }
             }
             opcodes::PUBLIC_UPDATE_PROPERTY_BOOL => {
-                if data.len() >= 16 {
-                    let guid = LittleEndian::read_u32(&data[4..8]);
-                    let property = LittleEndian::read_u32(&data[8..12]);
-                    let value = LittleEndian::read_u32(&data[12..16]) != 0;
-                    GameMessage::UpdatePropertyBool {
-                        guid,
-                        property,
-                        value,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::PublicUpdateProperty<u32> = s.read()?;
+                GameMessage::UpdatePropertyBool {
+                    guid: body.guid,
+                    property: body.property,
+                    value: body.value != 0,
                 }
             }
             opcodes::PRIVATE_UPDATE_PROPERTY_FLOAT => {
-                if data.len() >= 17 {
-                    // Sequence at data[4]
-                    let property = LittleEndian::read_u32(&data[5..9]);
-                    let value = LittleEndian::read_f64(&data[9..17]);
-                    GameMessage::UpdatePropertyFloat {
-                        guid: 0,
-                        property,
-                        value,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::PrivateUpdateProperty<f64> = s.read()?;
+                GameMessage::UpdatePropertyFloat {
+                    guid: 0,
+                    property: body.property,
+                    value: body.value,
                 }
             }
             opcodes::PUBLIC_UPDATE_PROPERTY_FLOAT => {
-                if data.len() >= 20 {
-                    let guid = LittleEndian::read_u32(&data[4..8]);
-                    let property = LittleEndian::read_u32(&data[8..12]);
-                    let value = LittleEndian::read_f64(&data[12..20]);
-                    GameMessage::UpdatePropertyFloat {
-                        guid,
-                        property,
-                        value,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::PublicUpdateProperty<f64> = s.read()?;
+                GameMessage::UpdatePropertyFloat {
+                    guid: body.guid,
+                    property: body.property,
+                    value: body.value,
                 }
             }
             opcodes::PRIVATE_UPDATE_PROPERTY_STRING => {
-                let mut offset = 5; // Skip Opcode(4) + Seq(1)
-                let property = LittleEndian::read_u32(&data[offset..offset + 4]);
-                offset += 4;
-                let value = read_string16(data, &mut offset);
+                let body: bodies::PrivateUpdatePropertyString = s.read()?;
                 GameMessage::UpdatePropertyString {
                     guid: 0,
-                    property,
-                    value,
+                    property: body.property,
+                    value: body.value,
                 }
             }
             opcodes::PUBLIC_UPDATE_PROPERTY_STRING => {
-                let mut offset = 4; // Opcode only
-                let guid = LittleEndian::read_u32(&data[offset..offset + 4]);
-                offset += 4;
-                let property = LittleEndian::read_u32(&data[offset..offset + 4]);
-                offset += 4;
-                let value = read_string16(data, &mut offset);
+                let body: bodies::PublicUpdatePropertyString = s.read()?;
                 GameMessage::UpdatePropertyString {
-                    guid,
-                    property,
-                    value,
+                    guid: body.guid,
+                    property: body.property,
+                    value: body.value,
                 }
             }
             opcodes::PRIVATE_UPDATE_PROPERTY_DID => {
-                if data.len() >= 13 {
-                    let property = LittleEndian::read_u32(&data[5..9]);
-                    let value = LittleEndian::read_u32(&data[9..13]);
-                    GameMessage::UpdatePropertyDataId {
-                        guid: 0,
-                        property,
-                        value,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::PrivateUpdateProperty<u32> = s.read()?;
+                GameMessage::UpdatePropertyDataId {
+                    guid: 0,
+                    property: body.property,
+                    value: body.value,
                 }
             }
             opcodes::PUBLIC_UPDATE_PROPERTY_DID => {
-                if data.len() >= 16 {
-                    let guid = LittleEndian::read_u32(&data[4..8]);
-                    let property = LittleEndian::read_u32(&data[8..12]);
-                    let value = LittleEndian::read_u32(&data[12..16]);
-                    GameMessage::UpdatePropertyDataId {
-                        guid,
-                        property,
-                        value,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::PublicUpdateProperty<u32> = s.read()?;
+                GameMessage::UpdatePropertyDataId {
+                    guid: body.guid,
+                    property: body.property,
+                    value: body.value,
                 }
             }
             opcodes::PRIVATE_UPDATE_PROPERTY_IID => {
-                if data.len() >= 13 {
-                    let property = LittleEndian::read_u32(&data[5..9]);
-                    let value = LittleEndian::read_u32(&data[9..13]);
-                    GameMessage::UpdatePropertyInstanceId {
-                        guid: 0,
-                        property,
-                        value,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::PrivateUpdateProperty<u32> = s.read()?;
+                GameMessage::UpdatePropertyInstanceId {
+                    guid: 0,
+                    property: body.property,
+                    value: body.value,
                 }
             }
             opcodes::PUBLIC_UPDATE_PROPERTY_IID => {
-                if data.len() >= 16 {
-                    let guid = LittleEndian::read_u32(&data[4..8]);
-                    let property = LittleEndian::read_u32(&data[8..12]);
-                    let value = LittleEndian::read_u32(&data[12..16]);
-                    GameMessage::UpdatePropertyInstanceId {
-                        guid,
-                        property,
-                        value,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::PublicUpdateProperty<u32> = s.read()?;
+                GameMessage::UpdatePropertyInstanceId {
+                    guid: body.guid,
+                    property: body.property,
+                    value: body.value,
                 }
             }
             opcodes::PRIVATE_UPDATE_SKILL => {
-                if data.len() >= 40 {
-                    // Header(4) + Seq(4) + SkillID(4) + Ranks(2) + Status(2) + SAC(4) + XP(4) + Init(4) + Resist(4) + Time(8) = 40
-                    let skill = LittleEndian::read_u32(&data[8..12]);
-                    let ranks = LittleEndian::read_u16(&data[12..14]) as u32;
-                    let status = LittleEndian::read_u16(&data[14..16]) as u32;
-                    let _sac = LittleEndian::read_u32(&data[16..20]);
-                    let xp = LittleEndian::read_u32(&data[20..24]);
-                    let init = LittleEndian::read_u32(&data[24..28]);
-
-                    GameMessage::UpdateSkill {
-                        skill,
-                        ranks,
-                        status,
-                        xp,
-                        init,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let skill = s.read::<bodies::UpdateSkill>()?.skill;
+                GameMessage::UpdateSkill {
+                    skill: skill.skill,
+                    ranks: skill.ranks as u32,
+                    status: skill.status as u32,
+                    xp: skill.xp,
+                    init: skill.init,
                 }
             }
             opcodes::PRIVATE_UPDATE_ATTRIBUTE => {
-                if data.len() >= 21 {
-                    let attribute = LittleEndian::read_u32(&data[5..9]);
-                    let ranks = LittleEndian::read_u32(&data[9..13]);
-                    let start = LittleEndian::read_u32(&data[13..17]);
-                    let xp = LittleEndian::read_u32(&data[17..21]);
-                    GameMessage::UpdateAttribute {
-                        attribute,
-                        ranks,
-                        start,
-                        xp,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::UpdateAttribute = s.read()?;
+                GameMessage::UpdateAttribute {
+                    attribute: body.attribute,
+                    ranks: body.value.ranks,
+                    start: body.value.start,
+                    xp: body.value.xp,
                 }
             }
             opcodes::PRIVATE_UPDATE_VITAL => {
@@ -970,310 +1360,546 @@ impl GameMessage {
                     "PRIVATE_UPDATE_VITAL payload (first 25 bytes): {:02X?}",
                     &data[..std::cmp::min(data.len(), 25)]
                 );
-                if data.len() >= 25 {
-                    let vital = LittleEndian::read_u32(&data[5..9]);
-                    let ranks = LittleEndian::read_u32(&data[9..13]);
-                    let start = LittleEndian::read_u32(&data[13..17]);
-                    let xp = LittleEndian::read_u32(&data[17..21]);
-                    let current = LittleEndian::read_u32(&data[21..25]);
-                    log::info!(
-                        "UpdateVital: id={}, ranks={}, start={}, xp={}, current={}",
-                        vital,
-                        ranks,
-                        start,
-                        xp,
-                        current
-                    );
-                    GameMessage::UpdateVital {
-                        vital,
-                        ranks,
-                        start,
-                        xp,
-                        current,
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::UpdateVital = s.read()?;
+                let bodies::Vital {
+                    ranks,
+                    start,
+                    xp,
+                    current,
+                } = body.value;
+                log::info!(
+                    "UpdateVital: id={}, ranks={}, start={}, xp={}, current={}",
+                    body.vital,
+                    ranks,
+                    start,
+                    xp,
+                    current
+                );
+                GameMessage::UpdateVital {
+                    vital: body.vital,
+                    ranks,
+                    start,
+                    xp,
+                    current,
                 }
             }
             opcodes::PRIVATE_UPDATE_VITAL_CURRENT => {
                 log::debug!("PRIVATE_UPDATE_VITAL_CURRENT payload: {:02X?}", &data);
-                if data.len() >= 13 {
-                    let vital = LittleEndian::read_u32(&data[5..9]);
-                    let current = LittleEndian::read_u32(&data[9..13]);
-                    log::info!("UpdateVitalCurrent: id={}, current={}", vital, current);
-                    GameMessage::UpdateVitalCurrent { vital, current }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::UpdateVitalCurrent = s.read()?;
+                log::info!(
+                    "UpdateVitalCurrent: id={}, current={}",
+                    body.vital,
+                    body.current
+                );
+                GameMessage::UpdateVitalCurrent {
+                    vital: body.vital,
+                    current: body.current,
                 }
             }
             opcodes::UPDATE_MOTION => {
-                if data.len() >= 8 {
-                    GameMessage::UpdateMotion {
-                        guid: LittleEndian::read_u32(&data[4..8]),
-                        data: data[8..].to_vec(),
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::UpdateMotion = s.read()?;
+                GameMessage::UpdateMotion {
+                    guid: body.guid,
+                    instance_sequence: body.instance_sequence,
+                    movement_sequence: body.movement_sequence,
+                    server_control_sequence: body.server_control_sequence,
+                    autonomous: body.autonomous,
+                    movement: body.movement,
                 }
             }
             opcodes::UPDATE_POSITION => {
-                if data.len() >= 8 {
-                    let guid = LittleEndian::read_u32(&data[4..8]);
-                    let mut offset = 8;
-                    let pos = WorldPosition::read(data, &mut offset);
-                    GameMessage::UpdatePosition { guid, pos }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
+                let body: bodies::UpdatePosition = s.read()?;
+                GameMessage::UpdatePosition {
+                    guid: body.guid,
+                    pos: body.pos.into(),
+                    velocity: body.pos.velocity,
+                    instance_sequence: body.pos.instance_sequence,
+                    position_sequence: body.pos.position_sequence,
+                    teleport_sequence: body.pos.teleport_sequence,
+                    force_position_sequence: body.pos.force_position_sequence,
                 }
             }
             opcodes::VECTOR_UPDATE => {
-                if data.len() >= 8 {
-                    GameMessage::VectorUpdate {
-                        guid: LittleEndian::read_u32(&data[4..8]),
-                        data: data[8..].to_vec(),
-                    }
-                } else {
-                    GameMessage::Unknown {
-                        opcode,
-                        data: data[4..].to_vec(),
-                    }
-                }
-            }
-            opcodes::GAME_EVENT => {
-                if data.len() < 16 {
-                    return GameMessage::Unknown {
-                        opcode,
-                        data: data.to_vec(),
-                    };
-                }
-                let guid = LittleEndian::read_u32(&data[4..8]) as u64;
-                let sequence = LittleEndian::read_u32(&data[8..12]);
-                let event_type = LittleEndian::read_u32(&data[12..16]);
-
-                #[allow(clippy::collapsible_if)]
-                if event_type == game_event_opcodes::PLAYER_DESCRIPTION {
-                    if let Some(msg) = unpack_player_description(guid as u32, &data[16..]) {
-                        return msg;
-                    }
-                }
-
-                if event_type == game_event_opcodes::UPDATE_HEALTH && data.len() >= 24 {
-                    let target = LittleEndian::read_u32(&data[16..20]);
-                    let health = LittleEndian::read_f32(&data[20..24]);
-                    return GameMessage::UpdateHealth { target, health };
-                }
-
-                if event_type == game_event_opcodes::MAGIC_UPDATE_ENCHANTMENT
-                    && data.len() >= 16 + 60
-                {
-                    let mut offset = 16;
-                    if let Some(enchantment) = Enchantment::read(data, &mut offset) {
-                        return GameMessage::MagicUpdateEnchantment {
-                            target: guid,
-                            enchantment,
-                        };
-                    }
-                }
-
-                if event_type == game_event_opcodes::MAGIC_UPDATE_MULTIPLE_ENCHANTMENTS
-                    && data.len() >= 20
-                {
-                    let mut offset = 16;
-                    let count = LittleEndian::read_u32(&data[offset..offset + 4]);
-                    offset += 4;
-                    let mut enchantments = Vec::with_capacity(count as usize);
-                    for _ in 0..count {
-                        if let Some(e) = Enchantment::read(data, &mut offset) {
-                            enchantments.push(e);
-                        } else {
-                            break;
-                        }
-                    }
-                    return GameMessage::MagicUpdateMultipleEnchantments {
-                        target: guid,
-                        enchantments,
-                    };
-                }
-
-                if event_type == game_event_opcodes::MAGIC_REMOVE_ENCHANTMENT
-                    && data.len() >= 16 + 4
-                {
-                    let spell_id = LittleEndian::read_u16(&data[16..18]);
-                    let layer = LittleEndian::read_u16(&data[18..20]);
-                    return GameMessage::MagicRemoveEnchantment {
-                        target: guid,
-                        spell_id,
-                        layer,
-                    };
-                }
-
-                if event_type == game_event_opcodes::MAGIC_REMOVE_MULTIPLE_ENCHANTMENTS
-                    && data.len() >= 20
-                {
-                    let mut offset = 16;
-                    let count = LittleEndian::read_u32(&data[offset..offset + 4]);
-                    offset += 4;
-                    let mut spells = Vec::with_capacity(count as usize);
-                    for _ in 0..count {
-                        if let Some(s) = LayeredSpell::read(data, &mut offset) {
-                            spells.push(s);
-                        } else {
-                            break;
-                        }
-                    }
-                    return GameMessage::MagicRemoveMultipleEnchantments {
-                        target: guid,
-                        spells,
-                    };
-                }
-
-                if event_type == game_event_opcodes::MAGIC_PURGE_ENCHANTMENTS {
-                    return GameMessage::MagicPurgeEnchantments { target: guid };
-                }
-
-                if event_type == game_event_opcodes::MAGIC_PURGE_BAD_ENCHANTMENTS {
-                    return GameMessage::MagicPurgeBadEnchantments { target: guid };
-                }
-
-                if event_type == game_event_opcodes::MAGIC_DISPEL_ENCHANTMENT
-                    && data.len() >= 16 + 4
-                {
-                    let spell_id = LittleEndian::read_u16(&data[16..18]);
-                    let layer = LittleEndian::read_u16(&data[18..20]);
-                    return GameMessage::MagicDispelEnchantment {
-                        target: guid,
-                        spell_id,
-                        layer,
-                    };
-                }
-
-                if event_type == game_event_opcodes::MAGIC_DISPEL_MULTIPLE_ENCHANTMENTS
-                    && data.len() >= 20
-                {
-                    let mut offset = 16;
-                    let count = LittleEndian::read_u32(&data[offset..offset + 4]);
-                    offset += 4;
-                    let mut spells = Vec::with_capacity(count as usize);
-                    for _ in 0..count {
-                        if let Some(s) = LayeredSpell::read(data, &mut offset) {
-                            spells.push(s);
-                        } else {
-                            break;
-                        }
-                    }
-                    return GameMessage::MagicDispelMultipleEnchantments {
-                        target: guid,
-                        spells,
-                    };
-                }
-
-                GameMessage::GameEvent {
-                    guid,
-                    sequence,
-                    event_type,
-                    data: data[16..].to_vec(),
+                let body: bodies::VectorUpdate = s.read()?;
+                GameMessage::VectorUpdate {
+                    guid: body.guid,
+                    velocity: body.velocity,
+                    omega: body.omega,
+                    instance_sequence: body.instance_sequence,
+                    vector_sequence: body.vector_sequence,
                 }
             }
+            opcodes::GAME_EVENT => read_game_event(&mut s)?,
             opcodes::GAME_ACTION => {
-                if data.len() < 12 {
-                    return GameMessage::Unknown {
-                        opcode,
-                        data: data.to_vec(),
-                    };
-                }
-                let _sequence = LittleEndian::read_u32(&data[4..8]);
-                let action = LittleEndian::read_u32(&data[8..12]);
+                let body: bodies::GameAction = s.read()?;
                 GameMessage::GameAction {
-                    action,
-                    data: data[12..].to_vec(),
-                }
-            }
-            opcodes::SERVER_MESSAGE => {
-                let mut offset = 4;
-                let message = read_string16(data, &mut offset);
-                GameMessage::ServerMessage { message }
-            }
-            opcodes::CHARACTER_ERROR => {
-                if data.len() >= 8 {
-                    GameMessage::CharacterError {
-                        error_code: LittleEndian::read_u32(&data[4..8]),
-                    }
-                } else {
-                    GameMessage::CharacterError { error_code: 0 }
-                }
-            }
-            opcodes::BOOT_ACCOUNT => {
-                let mut offset = 4;
-                let message = read_string16(data, &mut offset);
-                GameMessage::ServerMessage {
-                    message: format!("Terminated: {}", message),
+                    sequence: body.sequence,
+                    action: body.action,
                 }
             }
+            opcodes::SERVER_MESSAGE => GameMessage::ServerMessage {
+                message: s.read::<bodies::ServerMessage>()?.message,
+            },
+            opcodes::CHARACTER_ERROR => GameMessage::CharacterError {
+                error_code: s.read::<bodies::Guid>()?.guid,
+            },
+            opcodes::BOOT_ACCOUNT => GameMessage::BootAccount {
+                reason: s.read::<bodies::BootAccount>()?.reason.unwrap_or_default(),
+            },
             opcodes::DDD_INTERROGATION => GameMessage::DddInterrogation,
             opcodes::SERVER_NAME => {
-                let mut offset = 4;
-                let name = read_string16(data, &mut offset);
-                // online/max are sometimes here too
+                let body: bodies::ServerName = s.read()?;
+                let (online_count, max_sessions) = body.counts.unwrap_or((0, 1000));
                 GameMessage::ServerName {
-                    name,
-                    online_count: 0,
-                    max_sessions: 1000,
+                    name: body.name,
+                    online_count,
+                    max_sessions,
                 }
             }
+            opcodes::DDD_INTERROGATION_RESPONSE => GameMessage::DddInterrogationResponse {
+                language: s.read::<bodies::DddInterrogationResponse>()?.language,
+            },
             _ => {
                 log::warn!("Unhandled GameMessage opcode: 0x{:08X}", opcode);
                 GameMessage::Unknown {
                     opcode,
-                    data: data[4..].to_vec(),
+                    data: s.rest().to_vec(),
                 }
             }
-        }
+        })
     }
 
+    /// Serialises the message body, opcode included, in the layout `try_unpack` reads.
+    ///
+    /// Fields the parser skips are written as zeros, so `unpack(pack(m)) == m` for any
+    /// message that `unpack` itself could have produced.
     pub fn pack(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
         match self {
-            GameMessage::DddInterrogationResponse { language } => {
-                buf.extend_from_slice(&0xF7E6u32.to_le_bytes());
-                buf.extend_from_slice(&language.to_le_bytes());
-                buf.extend_from_slice(&0u32.to_le_bytes()); // iteration count (numElements in CAllIterationList)
+            GameMessage::CharacterList { characters } => pack_body(
+                opcodes::CHARACTER_LIST,
+                &bodies::CharacterList {
+                    unknown: 0,
+                    characters: characters
+                        .iter()
+                        .map(|(id, name)| bodies::CharacterIdentity {
+                            id: *id,
+                            name: name.clone(),
+                            delete_time: 0,
+                        })
+                        .collect(),
+                    trailer: Some(bodies::CharacterListTrailer {
+                        unknown: 0,
+                        slots: 11,
+                        account: String::new(),
+                        use_turbine_chat: 1,
+                        has_throne_of_destiny: 1,
+                    }),
+                },
+            ),
+            GameMessage::CharacterEnterWorldServerReady => {
+                pack_body(opcodes::CHARACTER_ENTER_WORLD_SERVER_READY, &())
             }
-            GameMessage::CharacterEnterWorldRequest { .. } => {
-                buf.extend_from_slice(&opcodes::CHARACTER_ENTER_WORLD_REQUEST.to_le_bytes());
+            GameMessage::CharacterEnterWorldRequest { .. } => pack_body(
+                opcodes::CHARACTER_ENTER_WORLD_REQUEST,
+                &bodies::CharacterEnterWorldRequest { char_id: None },
+            ),
+            GameMessage::CharacterEnterWorld { id, account } => pack_body(
+                opcodes::CHARACTER_ENTER_WORLD,
+                &bodies::CharacterEnterWorld {
+                    id: *id,
+                    account: account.clone(),
+                },
+            ),
+            GameMessage::PlayerCreate { player_id } => {
+                pack_body(opcodes::PLAYER_CREATE, &bodies::Guid { guid: *player_id })
             }
-            GameMessage::CharacterEnterWorld { id, account } => {
-                buf.extend_from_slice(&opcodes::CHARACTER_ENTER_WORLD.to_le_bytes());
-                buf.extend_from_slice(&id.to_le_bytes());
-                write_string16(&mut buf, account);
+            GameMessage::ObjectCreate { .. } => {
+                pack_body(opcodes::OBJECT_CREATE, &object_create_body(self))
+            }
+            GameMessage::ObjectDelete { guid } => {
+                pack_body(opcodes::OBJECT_DELETE, &bodies::Guid { guid: *guid })
+            }
+            GameMessage::ParentEvent {
+                child_guid,
+                parent_guid,
+            } => pack_body(
+                opcodes::PARENT_EVENT,
+                &bodies::ParentEvent {
+                    parent_guid: *parent_guid,
+                    child_guid: *child_guid,
+                },
+            ),
+            GameMessage::PickupEvent { guid } => {
+                pack_body(opcodes::PICKUP_EVENT, &bodies::Guid { guid: *guid })
+            }
+            GameMessage::SetState {
+                guid,
+                state,
+                instance_sequence,
+                state_sequence,
+            } => pack_body(
+                opcodes::SET_STATE,
+                &bodies::SetState {
+                    guid: *guid,
+                    state: *state,
+                    instance_sequence: *instance_sequence,
+                    state_sequence: *state_sequence,
+                },
+            ),
+            GameMessage::PlayerTeleport { teleport_sequence } => pack_body(
+                opcodes::PLAYER_TELEPORT,
+                &bodies::PlayerTeleport {
+                    teleport_sequence: *teleport_sequence,
+                },
+            ),
+            // A guid of 0 means the player, which the server sends as a private update
+            GameMessage::UpdatePropertyInt {
+                guid,
+                property,
+                value,
+            } => pack_property_update(
+                *guid,
+                *property,
+                *value,
+                opcodes::PRIVATE_UPDATE_PROPERTY_INT,
+                opcodes::PUBLIC_UPDATE_PROPERTY_INT,
+            ),
+            GameMessage::UpdatePropertyInt64 {
+                guid,
+                property,
+                value,
+            } => pack_property_update(
+                *guid,
+                *property,
+                *value,
+                opcodes::PRIVATE_UPDATE_PROPERTY_INT64,
+                opcodes::PUBLIC_UPDATE_PROPERTY_INT64,
+            ),
+            GameMessage::UpdatePropertyBool {
+                guid,
+                property,
+                value,
+            } => pack_property_update(
+                *guid,
+                *property,
+                *value as u32,
+                opcodes::PRIVATE_UPDATE_PROPERTY_BOOL,
+                opcodes::PUBLIC_UPDATE_PROPERTY_BOOL,
+            ),
+            GameMessage::UpdatePropertyFloat {
+                guid,
+                property,
+                value,
+            } => pack_property_update(
+                *guid,
+                *property,
+                *value,
+                opcodes::PRIVATE_UPDATE_PROPERTY_FLOAT,
+                opcodes::PUBLIC_UPDATE_PROPERTY_FLOAT,
+            ),
+            GameMessage::UpdatePropertyString {
+                guid: 0,
+                property,
+                value,
+            } => pack_body(
+                opcodes::PRIVATE_UPDATE_PROPERTY_STRING,
+                &bodies::PrivateUpdatePropertyString {
+                    sequence: 0,
+                    property: *property,
+                    value: value.clone(),
+                },
+            ),
+            GameMessage::UpdatePropertyString {
+                guid,
+                property,
+                value,
+            } => pack_body(
+                opcodes::PUBLIC_UPDATE_PROPERTY_STRING,
+                &bodies::PublicUpdatePropertyString {
+                    guid: *guid,
+                    property: *property,
+                    value: value.clone(),
+                },
+            ),
+            GameMessage::UpdatePropertyDataId {
+                guid,
+                property,
+                value,
+            } => pack_property_update(
+                *guid,
+                *property,
+                *value,
+                opcodes::PRIVATE_UPDATE_PROPERTY_DID,
+                opcodes::PUBLIC_UPDATE_PROPERTY_DID,
+            ),
+            GameMessage::UpdatePropertyInstanceId {
+                guid,
+                property,
+                value,
+            } => pack_property_update(
+                *guid,
+                *property,
+                *value,
+                opcodes::PRIVATE_UPDATE_PROPERTY_IID,
+                opcodes::PUBLIC_UPDATE_PROPERTY_IID,
+            ),
+            GameMessage::UpdateSkill {
+                skill,
+                ranks,
+                status,
+                xp,
+                init,
+            } => pack_body(
+                opcodes::PRIVATE_UPDATE_SKILL,
+                &bodies::UpdateSkill {
+                    sequence: 0,
+                    skill: bodies::Skill {
+                        skill: *skill,
+                        ranks: *ranks as u16,
+                        status: *status as u16,
+                        xp: *xp,
+                        init: *init,
+                        ..Default::default()
+                    },
+                },
+            ),
+            GameMessage::UpdateAttribute {
+                attribute,
+                ranks,
+                start,
+                xp,
+            } => pack_body(
+                opcodes::PRIVATE_UPDATE_ATTRIBUTE,
+                &bodies::UpdateAttribute {
+                    sequence: 0,
+                    attribute: *attribute,
+                    value: bodies::Attribute {
+                        ranks: *ranks,
+                        start: *start,
+                        xp: *xp,
+                    },
+                },
+            ),
+            GameMessage::UpdateVital {
+                vital,
+                ranks,
+                start,
+                xp,
+                current,
+            } => pack_body(
+                opcodes::PRIVATE_UPDATE_VITAL,
+                &bodies::UpdateVital {
+                    sequence: 0,
+                    vital: *vital,
+                    value: bodies::Vital {
+                        ranks: *ranks,
+                        start: *start,
+                        xp: *xp,
+                        current: *current,
+                    },
+                },
+            ),
+            GameMessage::UpdateVitalCurrent { vital, current } => pack_body(
+                opcodes::PRIVATE_UPDATE_VITAL_CURRENT,
+                &bodies::UpdateVitalCurrent {
+                    sequence: 0,
+                    vital: *vital,
+                    current: *current,
+                },
+            ),
+            GameMessage::UpdateMotion {
+                guid,
+                instance_sequence,
+                movement_sequence,
+                server_control_sequence,
+                autonomous,
+                movement,
+            } => pack_body(
+                opcodes::UPDATE_MOTION,
+                &bodies::UpdateMotion {
+                    guid: *guid,
+                    instance_sequence: *instance_sequence,
+                    movement_sequence: *movement_sequence,
+                    server_control_sequence: *server_control_sequence,
+                    autonomous: *autonomous,
+                    movement: movement.clone(),
+                },
+            ),
+            GameMessage::UpdatePosition {
+                guid,
+                pos,
+                velocity,
+                instance_sequence,
+                position_sequence,
+                teleport_sequence,
+                force_position_sequence,
+            } => {
+                let mut pack = PositionPack {
+                    velocity: *velocity,
+                    instance_sequence: *instance_sequence,
+                    position_sequence: *position_sequence,
+                    teleport_sequence: *teleport_sequence,
+                    force_position_sequence: *force_position_sequence,
+                    ..(*pos).into()
+                };
+                pack.flags
+                    .set(UpdatePositionFlag::HAS_VELOCITY, velocity.is_some());
+                pack_body(
+                    opcodes::UPDATE_POSITION,
+                    &bodies::UpdatePosition {
+                        guid: *guid,
+                        pos: pack,
+                    },
+                )
             }
-            GameMessage::GameAction { action, data } => {
-                buf.extend_from_slice(&opcodes::GAME_ACTION.to_le_bytes());
-                buf.extend_from_slice(&0u32.to_le_bytes());
-                buf.extend_from_slice(&action.to_le_bytes());
-                buf.extend_from_slice(data);
+            GameMessage::VectorUpdate {
+                guid,
+                velocity,
+                omega,
+                instance_sequence,
+                vector_sequence,
+            } => pack_body(
+                opcodes::VECTOR_UPDATE,
+                &bodies::VectorUpdate {
+                    guid: *guid,
+                    velocity: *velocity,
+                    omega: *omega,
+                    instance_sequence: *instance_sequence,
+                    vector_sequence: *vector_sequence,
+                },
+            ),
+            GameMessage::PlayEffect { guid } => {
+                pack_body(opcodes::PLAY_EFFECT, &bodies::Guid { guid: *guid })
             }
-            _ => unimplemented!("Packing for {:?} not implemented yet", self),
+            GameMessage::GameEvent {
+                guid,
+                sequence,
+                event,
+            } => pack_game_event(*guid, *sequence, event),
+            GameMessage::GameAction { sequence, action } => pack_body(
+                opcodes::GAME_ACTION,
+                &bodies::GameAction {
+                    sequence: *sequence,
+                    action: action.clone(),
+                },
+            ),
+            GameMessage::ServerMessage { message } => pack_body(
+                opcodes::SERVER_MESSAGE,
+                &bodies::ServerMessage {
+                    message: message.clone(),
+                },
+            ),
+            GameMessage::BootAccount { reason } => pack_body(
+                opcodes::BOOT_ACCOUNT,
+                &bodies::BootAccount {
+                    reason: Some(reason.clone()),
+                },
+            ),
+            GameMessage::HearSpeech { message, sender } => pack_body(
+                opcodes::HEAR_SPEECH,
+                &bodies::HearSpeech {
+                    message: message.clone(),
+                    sender: sender.clone(),
+                    sender_id: 0,
+                    chat_type: 0,
+                },
+            ),
+            GameMessage::SoulEmote {
+                sender_id,
+                sender_name,
+                text,
+            } => pack_body(
+                opcodes::SOUL_EMOTE,
+                &bodies::SoulEmote {
+                    sender_id: *sender_id,
+                    sender_name: sender_name.clone(),
+                    text: text.clone(),
+                },
+            ),
+            GameMessage::CharacterError { error_code } => pack_body(
+                opcodes::CHARACTER_ERROR,
+                &bodies::Guid { guid: *error_code },
+            ),
+            GameMessage::ServerName {
+                name,
+                online_count,
+                max_sessions,
+            } => pack_body(
+                opcodes::SERVER_NAME,
+                &bodies::ServerName {
+                    name: name.clone(),
+                    counts: Some((*online_count, *max_sessions)),
+                },
+            ),
+            GameMessage::DddInterrogation => pack_body(opcodes::DDD_INTERROGATION, &()),
+            GameMessage::DddInterrogationResponse { language } => pack_body(
+                opcodes::DDD_INTERROGATION_RESPONSE,
+                &bodies::DddInterrogationResponse {
+                    language: *language,
+                    iteration_count: 0,
+                },
+            ),
+            GameMessage::Unknown { opcode, data } => pack_body(*opcode, data),
         }
-        buf
     }
 }
 
 #[allow(dead_code)]
 pub mod action_opcodes {
+    pub const TARGETED_MELEE_ATTACK: u32 = 0x0008;
+    pub const TARGETED_MISSILE_ATTACK: u32 = 0x000A;
     pub const TALK: u32 = 0x0015; // Client -> Server talk
+    pub const REMOVE_FRIEND: u32 = 0x0017;
+    pub const ADD_FRIEND: u32 = 0x0018;
+    pub const PUT_ITEM_IN_CONTAINER: u32 = 0x0019;
+    pub const GET_AND_WIELD_ITEM: u32 = 0x001A;
+    pub const DROP_ITEM: u32 = 0x001B;
+    pub const SET_TITLE: u32 = 0x002C;
+    pub const TELL_BY_ID: u32 = 0x0032;
+    pub const USE_WITH_TARGET: u32 = 0x0035;
+    pub const USE_ITEM: u32 = 0x0036;
+    pub const RAISE_VITAL: u32 = 0x0044;
+    pub const RAISE_ATTRIBUTE: u32 = 0x0045;
+    pub const RAISE_SKILL: u32 = 0x0046;
+    pub const CAST_UNTARGETED_SPELL: u32 = 0x0048;
+    pub const CAST_TARGETED_SPELL: u32 = 0x004A;
+    pub const CHANGE_COMBAT_MODE: u32 = 0x0053;
+    pub const STACKABLE_MERGE: u32 = 0x0054;
+    pub const STACKABLE_SPLIT_TO_CONTAINER: u32 = 0x0055;
+    pub const TELL: u32 = 0x005D;
+    pub const TELE_TO_LIFESTONE: u32 = 0x0063;
     pub const LOGIN_COMPLETE: u32 = 0x00A1;
+    pub const FELLOWSHIP_CREATE: u32 = 0x00A2;
+    pub const FELLOWSHIP_QUIT: u32 = 0x00A3;
+    pub const FELLOWSHIP_DISMISS: u32 = 0x00A4;
+    pub const FELLOWSHIP_RECRUIT: u32 = 0x00A5;
+    pub const IDENTIFY_OBJECT: u32 = 0x00C8;
+    pub const GIVE_OBJECT_REQUEST: u32 = 0x00CD;
+    pub const CHAT_CHANNEL: u32 = 0x0147;
+    pub const NO_LONGER_VIEWING_CONTENTS: u32 = 0x0195;
+    pub const CANCEL_ATTACK: u32 = 0x01B7;
+    pub const QUERY_HEALTH: u32 = 0x01BF;
+    pub const EMOTE: u32 = 0x01DF;
+    pub const SOUL_EMOTE: u32 = 0x01E1;
+    pub const PING: u32 = 0x01E9;
+    pub const TELE_TO_MARKETPLACE: u32 = 0x028D;
+    pub const JUMP: u32 = 0xF61B;
+    pub const MOVE_TO_STATE: u32 = 0xF61C;
+    pub const AUTONOMOUS_POSITION: u32 = 0xF753;
+}
+
+/// Heights for melee and missile attacks.
+pub mod attack_heights {
+    pub const HIGH: u32 = 1;
+    pub const MEDIUM: u32 = 2;
+    pub const LOW: u32 = 3;
+}
+
+pub mod combat_modes {
+    pub const NON_COMBAT: u32 = 0x1;
+    pub const MELEE: u32 = 0x2;
+    pub const MISSILE: u32 = 0x4;
+    pub const MAGIC: u32 = 0x8;
 }
 
 pub mod game_event_opcodes {
@@ -1285,6 +1911,7 @@ pub mod game_event_opcodes {
     pub const VIEW_CONTENTS: u32 = 0x0196;
     pub const START_GAME: u32 = 0x0282;
     pub const WEENIE_ERROR: u32 = 0x028A;
+    pub const WEENIE_ERROR_WITH_STRING: u32 = 0x028B;
     pub const TELL: u32 = 0x02BD;
     pub const FELLOWSHIP_UPDATE_FELLOW: u32 = 0x02C0;
     pub const MAGIC_UPDATE_SPELL: u32 = 0x02C1;
@@ -1316,23 +1943,6 @@ pub fn write_string16(buf: &mut Vec<u8>, s: &str) {
     }
 }
 
-pub fn read_packed_u32(data: &[u8], offset: &mut usize) -> u32 {
-    if data.len() < *offset + 2 {
-        return 0;
-    }
-    let a = LittleEndian::read_u16(&data[*offset..*offset + 2]);
-    *offset += 2;
-    if a & 0x8000 == 0 {
-        return a as u32;
-    }
-    if data.len() < *offset + 2 {
-        return ((a & 0x7FFF) as u32) << 16;
-    }
-    let b = LittleEndian::read_u16(&data[*offset..*offset + 2]);
-    *offset += 2;
-    (((a & 0x7FFF) as u32) << 16) | (b as u32)
-}
-
 pub fn read_string16(data: &[u8], offset: &mut usize) -> String {
     if data.len() < *offset + 2 {
         return String::new();
@@ -1355,21 +1965,6 @@ pub fn read_string16(data: &[u8], offset: &mut usize) -> String {
     s
 }
 
-pub fn read_string16_unpadded(data: &[u8], offset: &mut usize) -> String {
-    if data.len() < *offset + 2 {
-        return String::new();
-    }
-    let len = LittleEndian::read_u16(&data[*offset..*offset + 2]) as usize;
-    *offset += 2;
-
-    if data.len() < *offset + len {
-        return String::new();
-    }
-    let s = String::from_utf8_lossy(&data[*offset..*offset + len]).to_string();
-    *offset += len;
-    s
-}
-
 #[allow(dead_code)]
 pub fn write_string32(buf: &mut Vec<u8>, s: &str) {
     let s_len = s.len() as u32;
@@ -1386,639 +1981,899 @@ pub fn write_string32(buf: &mut Vec<u8>, s: &str) {
     }
 }
 
-pub fn build_login_payload(account: &str, password: &str, sequence: u32) -> Vec<u8> {
-    let mut payload = Vec::new();
-    write_string16(&mut payload, "1802"); // ClientVersion
+/// Client build string sent in the login request.
+pub const CLIENT_VERSION: &str = "1802";
+
+/// The body of a LOGIN_REQUEST packet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoginRequest {
+    pub client_version: String,
+    pub auth_type: u32,
+    pub auth_flags: u32,
+    pub timestamp: u32,
+    pub account: String,
+    pub admin_override: String,
+    pub password: String,
+}
+
+impl LoginRequest {
+    pub fn new(account: &str, password: &str, timestamp: u32) -> Self {
+        Self {
+            client_version: CLIENT_VERSION.to_string(),
+            auth_type: 0x02,  // NetAuthType: AccountPassword
+            auth_flags: 0x01, // AuthFlags: EnableCrypto
+            timestamp,
+            account: account.to_string(),
+            admin_override: String::new(),
+            password: password.to_string(),
+        }
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        write_string16(&mut payload, &self.client_version);
+
+        // Placeholder for data_len
+        let len_pos = payload.len();
+        payload.extend_from_slice(&[0u8; 4]);
 
-    // Placeholder for data_len
-    let len_pos = payload.len();
-    payload.extend_from_slice(&[0u8; 4]);
+        let start_of_data = payload.len();
 
-    let start_of_data = payload.len();
+        payload.extend_from_slice(&self.auth_type.to_le_bytes());
+        payload.extend_from_slice(&self.auth_flags.to_le_bytes());
+        payload.extend_from_slice(&self.timestamp.to_le_bytes());
+        write_string16(&mut payload, &self.account);
+        write_string16(&mut payload, &self.admin_override);
+        write_string32(&mut payload, &self.password);
+
+        let data_len = (payload.len() - start_of_data) as u32;
+        LittleEndian::write_u32(&mut payload[len_pos..len_pos + 4], data_len);
+
+        payload
+    }
 
-    payload.extend_from_slice(&0x02u32.to_le_bytes()); // NetAuthType: AccountPassword
-    payload.extend_from_slice(&0x01u32.to_le_bytes()); // AuthFlags: EnableCrypto
-    payload.extend_from_slice(&sequence.to_le_bytes()); // Timestamp
-    write_string16(&mut payload, account);
-    write_string16(&mut payload, ""); // AdminOverride
-    write_string32(&mut payload, password);
+    pub fn unpack(data: &[u8]) -> Option<Self> {
+        let mut offset = 0;
+        let client_version = read_string16(data, &mut offset);
+        if data.len() < offset + 16 {
+            return None;
+        }
+        let read_u32 = |at: usize| LittleEndian::read_u32(&data[at..at + 4]);
+        let auth_type = read_u32(offset + 4);
+        let auth_flags = read_u32(offset + 8);
+        let timestamp = read_u32(offset + 12);
+        offset += 16;
+        let account = read_string16(data, &mut offset);
+        let admin_override = read_string16(data, &mut offset);
+
+        // String32: total length, a packed length of one or two bytes, then the text
+        let mut len = *data.get(offset + 4)? as usize;
+        offset += 5;
+        if len & 0x80 != 0 {
+            len = ((len & 0x7F) << 8) | *data.get(offset)? as usize;
+            offset += 1;
+        }
+        let password = String::from_utf8_lossy(data.get(offset..offset + len)?).into_owned();
+
+        Some(Self {
+            client_version,
+            auth_type,
+            auth_flags,
+            timestamp,
+            account,
+            admin_override,
+            password,
+        })
+    }
+}
 
-    let data_len = (payload.len() - start_of_data) as u32;
-    LittleEndian::write_u32(&mut payload[len_pos..len_pos + 4], data_len);
+pub fn build_login_payload(account: &str, password: &str, sequence: u32) -> Vec<u8> {
+    LoginRequest::new(account, password, sequence).pack()
+}
 
-    payload
+/// A private property update (about the player) when `guid` is 0, otherwise a public one.
+fn pack_property_update<V>(guid: u32, property: u32, value: V, private: u32, public: u32) -> Vec<u8>
+where
+    V: for<'a> BinRead<Args<'a> = ()> + for<'a> BinWrite<Args<'a> = ()>,
+{
+    if guid == 0 {
+        let body = bodies::PrivateUpdateProperty {
+            sequence: 0,
+            property,
+            value,
+        };
+        pack_body(private, &body)
+    } else {
+        let body = bodies::PublicUpdateProperty {
+            guid,
+            property,
+            value,
+        };
+        pack_body(public, &body)
+    }
 }
 
-pub fn unpack_player_description(guid: u32, data: &[u8]) -> Option<GameMessage> {
-    let mut offset = 0;
-    let mut name = "Unknown".to_string();
-    if data.len() < 8 {
-        return None;
+fn pack_game_event(guid: u32, sequence: u32, event: &GameEventKind) -> Vec<u8> {
+    fn pack<T>(guid: u32, sequence: u32, event_type: u32, body: &T) -> Vec<u8>
+    where
+        T: for<'a> BinWrite<Args<'a> = ()>,
+    {
+        let header = bodies::GameEventHeader {
+            guid,
+            sequence,
+            event_type,
+        };
+        pack_body(opcodes::GAME_EVENT, &(header, body))
+    }
+
+    let event_type = event.event_type();
+    match event {
+        GameEventKind::PlayerDescription { .. } => {
+            pack(guid, sequence, event_type, &player_description_body(event))
+        }
+        GameEventKind::StartGame
+        | GameEventKind::MagicPurgeEnchantments
+        | GameEventKind::MagicPurgeBadEnchantments => pack(guid, sequence, event_type, &()),
+        GameEventKind::UpdateHealth { target, health } => pack(
+            guid,
+            sequence,
+            event_type,
+            &bodies::UpdateHealth {
+                target: *target,
+                health: *health,
+            },
+        ),
+        GameEventKind::ChannelBroadcast {
+            channel,
+            sender,
+            message,
+        } => pack(
+            guid,
+            sequence,
+            event_type,
+            &bodies::ChannelBroadcast {
+                channel: *channel,
+                sender: sender.clone(),
+                message: message.clone(),
+            },
+        ),
+        GameEventKind::Tell {
+            message,
+            sender,
+            sender_id,
+            target_id,
+            chat_type,
+        } => pack(
+            guid,
+            sequence,
+            event_type,
+            &bodies::Tell {
+                message: message.clone(),
+                sender: sender.clone(),
+                sender_id: *sender_id,
+                target_id: *target_id,
+                chat_type: *chat_type,
+                secret_flags: 0,
+            },
+        ),
+        GameEventKind::ViewContents { container, items } => pack(
+            guid,
+            sequence,
+            event_type,
+            &bodies::ViewContents {
+                container: *container,
+                items: items.clone(),
+            },
+        ),
+        GameEventKind::WeenieError { error } => pack(
+            guid,
+            sequence,
+            event_type,
+            &bodies::WeenieError { error: *error },
+        ),
+        GameEventKind::WeenieErrorWithString { error, text } => pack(
+            guid,
+            sequence,
+            event_type,
+            &bodies::WeenieErrorWithString {
+                error: *error,
+                text: text.clone(),
+            },
+        ),
+        GameEventKind::CharacterTitle { current, titles } => pack(
+            guid,
+            sequence,
+            event_type,
+            &bodies::CharacterTitle {
+                unknown: 1,
+                current: *current,
+                titles: titles.clone(),
+            },
+        ),
+        GameEventKind::FriendsListUpdate {
+            friends,
+            update_type,
+        } => pack(
+            guid,
+            sequence,
+            event_type,
+            &bodies::FriendsListUpdate {
+                friends: friends.clone(),
+                update_type: *update_type,
+            },
+        ),
+        GameEventKind::FellowshipUpdateFellow {
+            guid: fellow_guid,
+            fellow,
+            update_type,
+        } => pack(
+            guid,
+            sequence,
+            event_type,
+            &bodies::FellowshipUpdateFellow {
+                guid: *fellow_guid,
+                fellow: fellow.clone(),
+                update_type: *update_type,
+            },
+        ),
+        GameEventKind::MagicUpdateEnchantment { enchantment } => {
+            pack(guid, sequence, event_type, enchantment)
+        }
+        GameEventKind::MagicUpdateMultipleEnchantments { enchantments } => pack(
+            guid,
+            sequence,
+            event_type,
+            &bodies::EnchantmentList {
+                enchantments: enchantments.clone(),
+            },
+        ),
+        GameEventKind::MagicRemoveEnchantment { spell_id, layer }
+        | GameEventKind::MagicDispelEnchantment { spell_id, layer } => pack(
+            guid,
+            sequence,
+            event_type,
+            &LayeredSpell {
+                spell_id: *spell_id,
+                layer: *layer,
+            },
+        ),
+        GameEventKind::MagicRemoveMultipleEnchantments { spells }
+        | GameEventKind::MagicDispelMultipleEnchantments { spells } => pack(
+            guid,
+            sequence,
+            event_type,
+            &bodies::LayeredSpellList {
+                spells: spells.clone(),
+            },
+        ),
+        GameEventKind::Unknown { data, .. } => pack(guid, sequence, event_type, data),
     }
+}
 
-    // [propertyFlags:u32][weenieType:u32]
-    // Note: ACE writes propertyFlags to the same position as the initial placeholder zero.
-    let property_flags = LittleEndian::read_u32(&data[offset..offset + 4]);
-    offset += 4;
-    let wee_type = LittleEndian::read_u32(&data[offset..offset + 4]);
-    offset += 4;
-
-    // Skip property hash tables based on property_flags
-    // Each table starts with (ushort count, ushort numBuckets) = 4 bytes header
+fn read_game_event(s: &mut Stream) -> Result<GameMessage, ParseError> {
+    let header: bodies::GameEventHeader = s.read()?;
+    Ok(GameMessage::GameEvent {
+        guid: header.guid,
+        sequence: header.sequence,
+        event: read_game_event_kind(s, header.event_type)?,
+    })
+}
 
-    // 0x0001: PropertyInt32
-    if property_flags & 0x0001 != 0 {
-        if data.len() < offset + 4 {
-            return None;
+fn read_game_event_kind(s: &mut Stream, event_type: u32) -> Result<GameEventKind, ParseError> {
+    Ok(match event_type {
+        game_event_opcodes::PLAYER_DESCRIPTION => player_description_event(s.read()?),
+        game_event_opcodes::START_GAME => GameEventKind::StartGame,
+        game_event_opcodes::UPDATE_HEALTH => {
+            let body: bodies::UpdateHealth = s.read()?;
+            GameEventKind::UpdateHealth {
+                target: body.target,
+                health: body.health,
+            }
         }
-        let count = LittleEndian::read_u16(&data[offset..offset + 2]) as usize;
-        offset += 4 + (count * 8); // 4 key + 4 val
-    }
-    // 0x0080: PropertyInt64
-    if property_flags & 0x0080 != 0 {
-        if data.len() < offset + 4 {
-            return None;
+        game_event_opcodes::CHANNEL_BROADCAST => {
+            let body: bodies::ChannelBroadcast = s.read()?;
+            GameEventKind::ChannelBroadcast {
+                channel: body.channel,
+                sender: body.sender,
+                message: body.message,
+            }
         }
-        let count = LittleEndian::read_u16(&data[offset..offset + 2]) as usize;
-        offset += 4 + (count * 12); // 4 key + 8 val
-    }
-    // 0x0002: PropertyBool
-    if property_flags & 0x0002 != 0 {
-        if data.len() < offset + 4 {
-            return None;
+        game_event_opcodes::TELL => {
+            let body: bodies::Tell = s.read()?;
+            GameEventKind::Tell {
+                message: body.message,
+                sender: body.sender,
+                sender_id: body.sender_id,
+                target_id: body.target_id,
+                chat_type: body.chat_type,
+            }
         }
-        let count = LittleEndian::read_u16(&data[offset..offset + 2]) as usize;
-        offset += 4 + (count * 8); // 4 key + 4 val
-    }
-    // 0x0004: PropertyDouble
-    if property_flags & 0x0004 != 0 {
-        if data.len() < offset + 4 {
-            return None;
+        game_event_opcodes::VIEW_CONTENTS => {
+            let body: bodies::ViewContents = s.read()?;
+            GameEventKind::ViewContents {
+                container: body.container,
+                items: body.items,
+            }
         }
-        let count = LittleEndian::read_u16(&data[offset..offset + 2]) as usize;
-        offset += 4 + (count * 12); // 4 key + 8 val
-    }
-    // 0x0010: PropertyString
-    if property_flags & 0x0010 != 0 && data.len() >= offset + 4 {
-        let count = LittleEndian::read_u16(&data[offset..offset + 2]) as usize;
-        offset += 4;
-        for _ in 0..count {
-            if data.len() < offset + 6 {
-                break;
+        game_event_opcodes::WEENIE_ERROR => GameEventKind::WeenieError {
+            error: s.read::<bodies::WeenieError>()?.error,
+        },
+        game_event_opcodes::WEENIE_ERROR_WITH_STRING => {
+            let body: bodies::WeenieErrorWithString = s.read()?;
+            GameEventKind::WeenieErrorWithString {
+                error: body.error,
+                text: body.text,
             }
-            let key = LittleEndian::read_u32(&data[offset..offset + 4]);
-            offset += 4;
-            let len = LittleEndian::read_u16(&data[offset..offset + 2]) as usize;
-            offset += 2;
-            if data.len() < offset + len {
-                break;
+        }
+        game_event_opcodes::CHARACTER_TITLE => {
+            let body: bodies::CharacterTitle = s.read()?;
+            GameEventKind::CharacterTitle {
+                current: body.current,
+                titles: body.titles,
             }
-            let val = String::from_utf8_lossy(&data[offset..offset + len]).to_string();
-            offset += len;
-
-            if key == 1 {
-                // PropertyString::Name
-                name = val;
+        }
+        game_event_opcodes::FRIENDS_LIST_UPDATE => {
+            let body: bodies::FriendsListUpdate = s.read()?;
+            GameEventKind::FriendsListUpdate {
+                friends: body.friends,
+                update_type: body.update_type,
             }
-            // ACE property strings in hash tables are NOT padded
         }
-    }
-    // 0x0008: PropertyDid
-    if property_flags & 0x0008 != 0 {
-        if data.len() < offset + 4 {
-            return None;
+        game_event_opcodes::FELLOWSHIP_UPDATE_FELLOW => {
+            let body: bodies::FellowshipUpdateFellow = s.read()?;
+            GameEventKind::FellowshipUpdateFellow {
+                guid: body.guid,
+                fellow: body.fellow,
+                update_type: body.update_type,
+            }
         }
-        let count = LittleEndian::read_u16(&data[offset..offset + 2]) as usize;
-        offset += 4 + (count * 8); // 4 key + 4 val
-    }
-    // 0x0040: PropertyIid
-    if property_flags & 0x0040 != 0 {
-        if data.len() < offset + 4 {
-            return None;
+        game_event_opcodes::MAGIC_UPDATE_ENCHANTMENT => GameEventKind::MagicUpdateEnchantment {
+            enchantment: s.read()?,
+        },
+        game_event_opcodes::MAGIC_UPDATE_MULTIPLE_ENCHANTMENTS => {
+            GameEventKind::MagicUpdateMultipleEnchantments {
+                enchantments: s.read::<bodies::EnchantmentList>()?.enchantments,
+            }
         }
-        let count = LittleEndian::read_u16(&data[offset..offset + 2]) as usize;
-        offset += 4 + (count * 8); // 4 key + 4 val
-    }
-    let mut pos = None;
-    // 0x0020: Position
-    if property_flags & 0x0020 != 0 {
-        if data.len() < offset + 4 {
-            return None;
+        game_event_opcodes::MAGIC_REMOVE_ENCHANTMENT => {
+            let spell: LayeredSpell = s.read()?;
+            GameEventKind::MagicRemoveEnchantment {
+                spell_id: spell.spell_id,
+                layer: spell.layer,
+            }
         }
-        let count = LittleEndian::read_u16(&data[offset..offset + 2]) as usize;
-        offset += 4;
-        for _ in 0..count {
-            if data.len() < offset + 4 {
-                return None;
+        game_event_opcodes::MAGIC_REMOVE_MULTIPLE_ENCHANTMENTS => {
+            GameEventKind::MagicRemoveMultipleEnchantments {
+                spells: s.read::<bodies::LayeredSpellList>()?.spells,
             }
-            let key = LittleEndian::read_u32(&data[offset..offset + 4]);
-            offset += 4;
-
-            let p = WorldPosition::read_raw(data, &mut offset);
-            if key == 1 {
-                pos = Some(p);
+        }
+        game_event_opcodes::MAGIC_PURGE_ENCHANTMENTS => GameEventKind::MagicPurgeEnchantments,
+        game_event_opcodes::MAGIC_PURGE_BAD_ENCHANTMENTS => {
+            GameEventKind::MagicPurgeBadEnchantments
+        }
+        game_event_opcodes::MAGIC_DISPEL_ENCHANTMENT => {
+            let spell: LayeredSpell = s.read()?;
+            GameEventKind::MagicDispelEnchantment {
+                spell_id: spell.spell_id,
+                layer: spell.layer,
             }
         }
-    }
+        game_event_opcodes::MAGIC_DISPEL_MULTIPLE_ENCHANTMENTS => {
+            GameEventKind::MagicDispelMultipleEnchantments {
+                spells: s.read::<bodies::LayeredSpellList>()?.spells,
+            }
+        }
+        _ => GameEventKind::Unknown {
+            event_type,
+            data: s.rest().to_vec(),
+        },
+    })
+}
 
-    if offset + 4 > data.len() {
-        return None;
-    }
-    let vector_flags = LittleEndian::read_u32(&data[offset..offset + 4]);
-    offset += 4;
+/// Parses the body of a PlayerDescription event (everything after the event type).
+pub fn unpack_player_description(data: &[u8]) -> Result<GameEventKind, ParseError> {
+    let body = Stream::new(data, opcodes::GAME_EVENT).read()?;
+    Ok(player_description_event(body))
+}
 
-    // Convert.ToUInt32(Session.Player.Health != null)
-    if data.len() < offset + 4 {
-        return None;
-    }
-    let _has_health_stats = LittleEndian::read_u32(&data[offset..offset + 4]);
-    offset += 4;
+fn player_description_event(body: bodies::PlayerDescription) -> GameEventKind {
+    // PropertyString::Name and PositionType::Location
+    let name = body
+        .string_properties
+        .into_iter()
+        .flat_map(|t| t.entries)
+        .filter(|p| p.key == 1)
+        .map(|p| p.value)
+        .next_back()
+        .unwrap_or_else(|| "Unknown".to_string());
+    let pos = body
+        .positions
+        .into_iter()
+        .flat_map(|t| t.entries)
+        .filter(|(key, _)| *key == 1)
+        .map(|(_, pos)| pos)
+        .next_back();
 
     let mut attributes = Vec::new();
-    // 0x0001: Attribute
-    if vector_flags & 0x0001 != 0 && data.len() >= offset + 4 {
-        let attr_cache = LittleEndian::read_u32(&data[offset..offset + 4]);
-        offset += 4;
-
-        // Primary attributes (Str, End, Qui, Coo, Foc, Self)
-        for i in 1..=6 {
-            if attr_cache & (1 << (i - 1)) != 0 {
-                if data.len() >= offset + 12 {
-                    let ranks = LittleEndian::read_u32(&data[offset..offset + 4]);
-                    let start = LittleEndian::read_u32(&data[offset + 4..offset + 8]);
-                    let xp = LittleEndian::read_u32(&data[offset + 8..offset + 12]);
-                    attributes.push((i as u32, ranks, start, xp, ranks + start));
-                    offset += 12;
-                } else {
-                    break;
-                }
-            }
-        }
-        // Vitals (Health, Stamina, Mana)
-        for i in 1..=3 {
-            if attr_cache & (1 << (i + 5)) != 0 {
-                if data.len() >= offset + 16 {
-                    let ranks = LittleEndian::read_u32(&data[offset..offset + 4]);
-                    let start = LittleEndian::read_u32(&data[offset + 4..offset + 8]);
-                    let xp = LittleEndian::read_u32(&data[offset + 8..offset + 12]);
-                    let current = LittleEndian::read_u32(&data[offset + 12..offset + 16]);
-                    attributes.push(((i + 100) as u32, ranks, start, xp, current));
-                    offset += 16;
-                } else {
-                    break;
-                }
-            }
+    if let Some(cache) = body.attributes {
+        let primaries = (1..=6).filter(|i| cache.cache & (1 << (i - 1)) != 0);
+        for (i, a) in primaries.zip(cache.primaries) {
+            attributes.push((i, a.ranks, a.start, a.xp, a.ranks.wrapping_add(a.start)));
         }
-    }
-
-    let mut skills = Vec::new();
-    // 0x0002: Skill
-    if vector_flags & 0x0002 != 0 && data.len() >= offset + 4 {
-        let count = LittleEndian::read_u16(&data[offset..offset + 2]) as usize;
-        offset += 4; // Skip count + buckets header
-        for _ in 0..count {
-            // Skill struct size is 32 bytes
-            // Format: type(4), ranks(2), status(2), sac(4), xp(4), init(4), resist(4), last_used(8)
-            if data.len() < offset + 32 {
-                log::warn!("Truncated skill vector at offset {}", offset);
-                break;
-            }
-            let sk_type = LittleEndian::read_u32(&data[offset..offset + 4]);
-            let ranks = LittleEndian::read_u16(&data[offset + 4..offset + 6]) as u32;
-            let status = LittleEndian::read_u16(&data[offset + 6..offset + 8]) as u32;
-            let _sac = LittleEndian::read_u32(&data[offset + 8..offset + 12]);
-            let xp = LittleEndian::read_u32(&data[offset + 12..offset + 16]);
-            let init = LittleEndian::read_u32(&data[offset + 16..offset + 20]);
-            // Bytes 20..24: Resistance (4)
-            // Bytes 24..32: LastUsed (8)
-            skills.push((sk_type, ranks, status, xp, init));
-            offset += 32;
+        let vitals = (1..=3).filter(|i| cache.cache & (1 << (i + 5)) != 0);
+        for (i, v) in vitals.zip(cache.vitals) {
+            attributes.push((i + 100, v.ranks, v.start, v.xp, v.current));
         }
     }
 
-    // 0x0100: Spell
-    if vector_flags & 0x0100 != 0 && data.len() >= offset + 4 {
-        let count = LittleEndian::read_u16(&data[offset..offset + 2]) as usize;
-        offset += 4 + (count * 8); // key:u32 + float:f32
-    }
+    let skills = body
+        .skills
+        .map(|t| t.entries)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|s| (s.skill, s.ranks as u32, s.status as u32, s.xp, s.init))
+        .collect();
 
     let mut enchantments = Vec::new();
-    // 0x0200: Enchantment
-    if vector_flags & 0x0200 != 0 && data.len() >= offset + 4 {
-        let mask = LittleEndian::read_u32(&data[offset..offset + 4]);
-        offset += 4;
-
-        // Multiplicative = 0x1
-        if mask & 0x01 != 0 && data.len() >= offset + 4 {
-            let count = LittleEndian::read_u32(&data[offset..offset + 4]) as usize;
-            offset += 4;
-            for _ in 0..count {
-                if let Some(e) = Enchantment::read(data, &mut offset) {
-                    enchantments.push(e);
-                } else {
-                    break;
-                }
-            }
-        }
-        // Additive = 0x2
-        if mask & 0x02 != 0 && data.len() >= offset + 4 {
-            let count = LittleEndian::read_u32(&data[offset..offset + 4]) as usize;
-            offset += 4;
-            for _ in 0..count {
-                if let Some(e) = Enchantment::read(data, &mut offset) {
-                    enchantments.push(e);
-                } else {
-                    break;
-                }
-            }
-        }
-        // Cooldown = 0x08
-        if mask & 0x08 != 0 && data.len() >= offset + 4 {
-            let count = LittleEndian::read_u32(&data[offset..offset + 4]) as usize;
-            offset += 4;
-            for _ in 0..count {
-                if let Some(e) = Enchantment::read(data, &mut offset) {
-                    enchantments.push(e);
-                } else {
-                    break;
-                }
-            }
-        }
-        // Vitae = 0x04
-        if mask & 0x04 != 0
-            && let Some(e) = Enchantment::read(data, &mut offset)
+    if let Some(registry) = body.enchantments {
+        for list in [
+            registry.multiplicative,
+            registry.additive,
+            registry.cooldown,
+        ]
+        .into_iter()
+        .flatten()
         {
-            enchantments.push(e);
+            enchantments.extend(list.enchantments);
         }
+        enchantments.extend(registry.vitae);
     }
 
-    Some(GameMessage::PlayerDescription {
-        guid,
+    GameEventKind::PlayerDescription {
         name,
-        wee_type,
+        wee_type: body.wee_type,
         pos,
         attributes,
         skills,
         enchantments,
-    })
-}
-
-fn unpack_object_create(data: &[u8]) -> Option<GameMessage> {
-    let mut offset = 4; // Skip opcode
-    if data.len() < offset + 4 {
-        return None;
-    }
-    let guid = LittleEndian::read_u32(&data[offset..offset + 4]);
-    offset += 4;
-
-    // 1. ModelData
-    if data.len() < offset + 1 {
-        return None;
     }
-    let marker = data[offset];
-    if marker == 0x11 {
-        offset += 1;
-        let num_p = data[offset];
-        let num_t = data[offset + 1];
-        let num_m = data[offset + 2];
-        offset += 3;
+}
 
-        if num_p > 0 {
-            read_packed_u32_with_known_type(data, &mut offset, 0x04000000); // base palette
-            for _ in 0..num_p {
-                read_packed_u32_with_known_type(data, &mut offset, 0x04000000); // subpal id
-                offset += 2; // offset and length
-            }
-        }
-        for _ in 0..num_t {
-            offset += 1;
-            read_packed_u32_with_known_type(data, &mut offset, 0x05000000); // old
-            read_packed_u32_with_known_type(data, &mut offset, 0x05000000); // new
-        }
-        for _ in 0..num_m {
-            offset += 1;
-            read_packed_u32_with_known_type(data, &mut offset, 0x01000000); // model
-        }
-    } else {
-        if offset + 9 > data.len() {
-            return None;
+fn player_description_body(event: &GameEventKind) -> bodies::PlayerDescription {
+    let GameEventKind::PlayerDescription {
+        name,
+        wee_type,
+        pos,
+        attributes,
+        skills,
+        enchantments,
+    } = event
+    else {
+        unreachable!("not a PlayerDescription: {:?}", event);
+    };
+
+    // Primary attributes are bits 0-5 and vitals bits 6-8, written in bit order
+    let find = |id: u32| attributes.iter().find(|a| a.0 == id);
+    let mut cache = 0u32;
+    for i in 1..=6 {
+        if find(i).is_some() {
+            cache |= 1 << (i - 1);
         }
-        offset += 4; // DisplayModelId
-        offset += 1; // DisplayModelType
-        offset += 4; // DisplayModelFlags
-    }
-    offset = align_to_4(offset);
-
-    // 2. PhysicsData
-    if data.len() < offset + 8 {
-        return None;
     }
-    use crate::world::properties::{PhysicsDescriptionFlag, PhysicsState};
-    let phys_flags_bits = LittleEndian::read_u32(&data[offset..offset + 4]);
-    let phys_flags = PhysicsDescriptionFlag::from_bits_retain(phys_flags_bits);
-    let _phys_state =
-        PhysicsState::from_bits_retain(LittleEndian::read_u32(&data[offset + 4..offset + 8]));
-    offset += 8;
-
-    if phys_flags.intersects(PhysicsDescriptionFlag::MOVEMENT) {
-        if offset + 4 <= data.len() {
-            let len = LittleEndian::read_u32(&data[offset..offset + 4]) as usize;
-            offset += 4 + len;
-            if len > 0 {
-                offset += 4;
-            } // autonomous
+    for i in 1..=3 {
+        if find(i + 100).is_some() {
+            cache |= 1 << (i + 5);
         }
-    } else if phys_flags.intersects(PhysicsDescriptionFlag::ANIMATION_FRAME) {
-        offset += 4;
     }
-
-    let mut pos = None;
-    if phys_flags.intersects(PhysicsDescriptionFlag::POSITION) {
-        pos = Some(WorldPosition::read_raw(data, &mut offset));
+    let primaries = (1..=6)
+        .filter_map(find)
+        .map(|&(_, ranks, start, xp, _)| bodies::Attribute { ranks, start, xp })
+        .collect();
+    let vitals = (101..=103)
+        .filter_map(find)
+        .map(|&(_, ranks, start, xp, current)| bodies::Vital {
+            ranks,
+            start,
+            xp,
+            current,
+        })
+        .collect();
+
+    let skills = skills
+        .iter()
+        .map(|&(skill, ranks, status, xp, init)| bodies::Skill {
+            skill,
+            ranks: ranks as u16,
+            status: status as u16,
+            xp,
+            init,
+            ..Default::default()
+        })
+        .collect();
+
+    // Only the name (PropertyString) and location (Position) tables are kept, and every
+    // enchantment goes in the Multiplicative list
+    let enchantments = (!enchantments.is_empty()).then(|| bodies::EnchantmentRegistry {
+        mask: 0x01,
+        multiplicative: Some(bodies::EnchantmentList {
+            enchantments: enchantments.clone(),
+        }),
+        additive: None,
+        cooldown: None,
+        vitae: None,
+    });
+    bodies::PlayerDescription {
+        property_flags: 0x0010 | if pos.is_some() { 0x0020 } else { 0 },
+        wee_type: *wee_type,
+        int_properties: None,
+        int64_properties: None,
+        bool_properties: None,
+        float_properties: None,
+        string_properties: Some(bodies::PropertyTable::new(
+            1,
+            vec![bodies::StringProperty {
+                key: 1,
+                value: name.clone(),
+            }],
+        )),
+        did_properties: None,
+        iid_properties: None,
+        positions: pos.map(|pos| bodies::PropertyTable::new(1, vec![(1, pos)])),
+        vector_flags: 0x0001 | 0x0002 | if enchantments.is_some() { 0x0200 } else { 0 },
+        has_health_stats: 1,
+        attributes: Some(bodies::AttributeCache {
+            cache,
+            primaries,
+            vitals,
+        }),
+        skills: Some(bodies::PropertyTable::new(32, skills)),
+        spells: None,
+        enchantments,
     }
+}
 
-    if phys_flags.intersects(PhysicsDescriptionFlag::MTABLE) {
-        offset += 4;
-    }
-    if phys_flags.intersects(PhysicsDescriptionFlag::STABLE) {
-        offset += 4;
-    }
-    if phys_flags.intersects(PhysicsDescriptionFlag::PETABLE) {
-        offset += 4;
-    }
-    if phys_flags.intersects(PhysicsDescriptionFlag::CSETUP) {
-        offset += 4;
-    }
-    let mut parent_id = None;
-    if phys_flags.intersects(PhysicsDescriptionFlag::PARENT) {
-        parent_id = Some(LittleEndian::read_u32(&data[offset..offset + 4]));
-        offset += 8;
-    }
-    if phys_flags.intersects(PhysicsDescriptionFlag::CHILDREN) && offset + 4 <= data.len() {
-        let count = LittleEndian::read_u32(&data[offset..offset + 4]);
-        offset += 4 + (count as usize * 8);
-    }
-    if phys_flags.intersects(PhysicsDescriptionFlag::OBJSCALE) {
-        offset += 4;
-    }
-    if phys_flags.intersects(PhysicsDescriptionFlag::FRICTION) {
-        offset += 4;
-    }
-    if phys_flags.intersects(PhysicsDescriptionFlag::ELASTICITY) {
-        offset += 4;
-    }
-    if phys_flags.intersects(PhysicsDescriptionFlag::TRANSLUCENCY) {
-        offset += 4;
-    }
-    if phys_flags.intersects(PhysicsDescriptionFlag::VELOCITY) {
-        offset += 12;
-    }
-    if phys_flags.intersects(PhysicsDescriptionFlag::ACCELERATION) {
-        offset += 12;
-    }
-    if phys_flags.intersects(PhysicsDescriptionFlag::OMEGA) {
-        offset += 12;
-    }
-    if phys_flags.intersects(PhysicsDescriptionFlag::DEFAULT_SCRIPT) {
-        offset += 4;
-    }
-    if phys_flags.intersects(PhysicsDescriptionFlag::DEFAULT_SCRIPT_INTENSITY) {
-        offset += 4;
+fn object_create_message(body: bodies::ObjectCreate) -> GameMessage {
+    let (physics, weenie) = (body.physics, body.weenie);
+    GameMessage::ObjectCreate {
+        guid: body.guid,
+        name: Some(weenie.name),
+        wcid: Some(weenie.wcid),
+        pos: physics.position,
+        parent_id: physics.parent_id,
+        container_id: weenie.container_id,
+        wielder_id: weenie.wielder_id,
+        item_type: weenie.item_type,
+        weenie_flags: weenie.flags,
+        weenie_flags2: weenie.flags2.unwrap_or(WeenieHeaderFlag2::empty()),
+        flags: weenie.description_flags,
+        sequences: physics.sequences.into(),
+        movement: physics.movement.and_then(|buf| {
+            // The blob is aligned from its own start
+            Stream::new(&buf.data, opcodes::OBJECT_CREATE)
+                .read::<MovementData>()
+                .ok()
+        }),
+        obj_desc: Box::new(body.model.obj_desc.unwrap_or_default()),
+        physics_state: physics.state,
+        velocity: physics.velocity,
+        omega: physics.omega,
+        physics: Box::new(PhysicsDescription {
+            setup_id: physics.setup,
+            motion_table_id: physics.motion_table,
+            sound_table_id: physics.sound_table,
+            physics_script_table_id: physics.physics_script_table,
+            animation_frame: physics.animation_frame,
+            parent_location: physics.parent_location,
+            children: physics.children.map(|c| c.children).unwrap_or_default(),
+            object_scale: physics.object_scale,
+            friction: physics.friction,
+            elasticity: physics.elasticity,
+            translucency: physics.translucency,
+            acceleration: physics.acceleration,
+            default_script: physics.default_script,
+            default_script_intensity: physics.default_script_intensity,
+        }),
+        weenie: Box::new(WeenieDescription {
+            icon: weenie.icon,
+            plural_name: weenie.plural_name,
+            items_capacity: weenie.items_capacity,
+            containers_capacity: weenie.containers_capacity,
+            ammo_type: weenie.ammo_type,
+            value: weenie.value,
+            usable: weenie.usable,
+            use_radius: weenie.use_radius,
+            target_type: weenie.target_type,
+            ui_effects: weenie.ui_effects,
+            combat_use: weenie.combat_use,
+            structure: weenie.structure,
+            max_structure: weenie.max_structure,
+            stack_size: weenie.stack_size,
+            max_stack_size: weenie.max_stack_size,
+            valid_locations: weenie.valid_locations,
+            currently_wielded_location: weenie.currently_wielded_location,
+            priority: weenie.priority,
+            radar_blip_color: weenie.radar_blip_color,
+            radar_behavior: weenie.radar_behavior,
+            pscript: weenie.pscript,
+            workmanship: weenie.workmanship,
+            burden: weenie.burden,
+            spell: weenie.spell,
+            house_owner: weenie.house_owner,
+            house_restrictions: weenie.house_restrictions,
+            hook_item_types: weenie.hook_item_types,
+            monarch: weenie.monarch,
+            hook_type: weenie.hook_type,
+            icon_overlay: weenie.icon_overlay,
+            material_type: weenie.material_type,
+            icon_underlay: weenie.icon_underlay,
+            cooldown: weenie.cooldown,
+            cooldown_duration: weenie.cooldown_duration,
+            pet_owner: weenie.pet_owner,
+        }),
     }
+}
 
-    // Sequences (always present at the end of physics)
-    // 9 ushort sequences (18 bytes) + 2 bytes padding = 20 bytes
-    if data.len() >= offset + 20 {
-        let mut sequences = [0u16; 9];
-        for i in 0..9 {
-            sequences[i] = LittleEndian::read_u16(&data[offset + i * 2..offset + i * 2 + 2]);
-        }
-        log::debug!("guid={:08X} sequences: {:?}", guid, sequences);
-        offset += 20;
-    } else if data.len() >= offset + 18 {
-        let mut sequences = [0u16; 9];
-        for i in 0..9 {
-            sequences[i] = LittleEndian::read_u16(&data[offset + i * 2..offset + i * 2 + 2]);
-        }
-        log::debug!("guid={:08X} sequences(18): {:?}", guid, sequences);
-        offset += 18;
-    }
-    offset = align_to_4(offset);
-    log::info!(
-        "guid={:08X} post-physics offset={} data_len={}",
-        guid,
-        offset,
-        data.len()
-    );
-
-    // 3. WeenieHeader
-    if data.len() < offset + 4 {
-        log::warn!(
-            "guid={:08X} failed to read weenie_flags, offset={}, len={}",
-            guid,
-            offset,
-            data.len()
-        );
-        return None;
-    }
-    let weenie_flags = LittleEndian::read_u32(&data[offset..offset + 4]);
-    offset += 4;
-    log::info!(
-        "guid={:08X} weenie_flags={:08X} offset={}",
-        guid,
-        weenie_flags,
-        offset
-    );
-
-    let (name, _) = read_string16_with_len(data, &mut offset);
-    log::info!("guid={:08X} name={:?} offset={}", guid, name, offset);
-    let class_id = read_packed_u32(data, &mut offset);
-    let _icon_id = read_packed_u32_with_known_type(data, &mut offset, 0x06000000);
-    if offset + 8 > data.len() {
-        return None;
-    }
-    let item_type = LittleEndian::read_u32(&data[offset..offset + 4]);
-    let obj_desc_flags = LittleEndian::read_u32(&data[offset + 4..offset + 8]);
-    offset += 8;
-    offset = align_to_4(offset);
-
-    let mut weenie_flags2 = 0;
-    if (obj_desc_flags & 0x04000000) != 0 {
-        // IncludesSecondHeader
-        weenie_flags2 = LittleEndian::read_u32(&data[offset..offset + 4]);
-        offset += 4;
-    }
-
-    // Optional Fields (WeenieHeaderFlag)
-    if (weenie_flags & 0x00000001) != 0 {
-        read_string16_with_len(data, &mut offset);
-    } // PluralName
-    if (weenie_flags & 0x00000002) != 0 {
-        offset += 4;
-    } // ItemsCapacity
-    if (weenie_flags & 0x00000004) != 0 {
-        offset += 4;
-    } // ContainersCapacity
-    if (weenie_flags & 0x00000100) != 0 {
-        offset += 2;
-    } // AmmoType
-    if (weenie_flags & 0x00000008) != 0 {
-        offset += 4;
-    } // Value
-    if (weenie_flags & 0x00000010) != 0 {
-        offset += 4;
-    } // Usable
-    if (weenie_flags & 0x00000020) != 0 {
-        offset += 4;
-    } // UseRadius
-    if (weenie_flags & 0x00080000) != 0 {
-        offset += 4;
-    } // TargetType
-    if (weenie_flags & 0x00000080) != 0 {
-        offset += 4;
-    } // UiEffects
-    if (weenie_flags & 0x00000200) != 0 {
-        offset += 1;
-    } // CombatUse
-    if (weenie_flags & 0x00000400) != 0 {
-        offset += 2;
-    } // Structure
-    if (weenie_flags & 0x00000800) != 0 {
-        offset += 2;
-    } // MaxStructure
-    if (weenie_flags & 0x00001000) != 0 {
-        offset += 2;
-    } // StackSize
-    if (weenie_flags & 0x00002000) != 0 {
-        offset += 2;
-    } // MaxStackSize
-    let mut container_id = None;
-    if (weenie_flags & 0x00004000) != 0 {
-        container_id = Some(LittleEndian::read_u32(&data[offset..offset + 4]));
-        offset += 4;
-    } // Container
-    let mut wielder_id = None;
-    if (weenie_flags & 0x00008000) != 0 {
-        wielder_id = Some(LittleEndian::read_u32(&data[offset..offset + 4]));
-        offset += 4;
-    } // Wielder
-    if (weenie_flags & 0x00010000) != 0 {
-        offset += 4;
-    } // ValidLocations
-    if (weenie_flags & 0x00020000) != 0 {
-        offset += 4;
-    } // CurrentlyWieldedLocation
-    if (weenie_flags & 0x00040000) != 0 {
-        offset += 4;
-    } // Priority
-    if (weenie_flags & 0x00100000) != 0 {
-        offset += 1;
-    } // RadarBlipColor
-    if (weenie_flags & 0x00800000) != 0 {
-        offset += 1;
-    } // RadarBehavior
-    if (weenie_flags & 0x08000000) != 0 {
-        offset += 4;
-    } // PScript
-    if (weenie_flags & 0x01000000) != 0 {
-        offset += 4;
-    } // Workmanship
-    if (weenie_flags & 0x00200000) != 0 {
-        offset += 2;
-    } // Burden
-    if (weenie_flags & 0x00400000) != 0 {
-        offset += 2;
-    } // Spell
-    if (weenie_flags & 0x02000000) != 0 {
-        offset += 4;
-    } // HouseOwner
-    if (weenie_flags & 0x04000000) != 0 {
-        // HouseRestrictions (RestrictionDB)
-        if offset + 12 <= data.len() {
-            offset += 4; // Version
-            offset += 4; // OpenStatus
-            offset += 4; // MonarchID
-            if offset + 4 <= data.len() {
-                let count = LittleEndian::read_u16(&data[offset..offset + 2]) as usize;
-                offset += 4; // Hash Header (count + numBuckets)
-                offset += count * 8; // Table entries (GUID + Value)
-            }
-        }
-    }
-    if (weenie_flags & 0x20000000) != 0 {
-        offset += 4;
-    } // HookItemTypes
-    if (weenie_flags & 0x00000040) != 0 {
-        offset += 4;
-    } // Monarch
-    if (weenie_flags & 0x10000000) != 0 {
-        offset += 4;
-    } // HookType
-    if (weenie_flags & 0x40000000) != 0 {
-        read_packed_u32_with_known_type(data, &mut offset, 0x06000000);
-    } // IconOverlay
-    if (weenie_flags & 0x80000000) != 0 {
-        offset += 4;
-    } // MaterialType
-
-    // weenie_flags2
-    if (weenie_flags2 & 0x01) != 0 {
-        read_packed_u32_with_known_type(data, &mut offset, 0x06000000);
-    } // IconUnderlay
-    if (weenie_flags2 & 0x02) != 0 {
-        offset += 4;
-    } // Cooldown
-    if (weenie_flags2 & 0x04) != 0 {
-        offset += 8;
-    } // CooldownDuration (double)
-    if (weenie_flags2 & 0x08) != 0 {
-        let _ = offset + 4;
-    } // PetOwner
-
-    Some(GameMessage::ObjectCreate {
+fn object_create_body(msg: &GameMessage) -> bodies::ObjectCreate {
+    let GameMessage::ObjectCreate {
         guid,
-        name: Some(name),
-        wcid: Some(class_id),
+        name,
+        wcid,
         pos,
         parent_id,
         container_id,
         wielder_id,
-        item_type: ItemType::from_bits_retain(item_type),
-        weenie_flags: WeenieHeaderFlag::from_bits_retain(weenie_flags),
-        weenie_flags2: WeenieHeaderFlag2::from_bits_retain(weenie_flags2),
-        flags: ObjectDescriptionFlag::from_bits_retain(obj_desc_flags),
-    })
-}
-
-fn read_packed_u32_with_known_type(data: &[u8], offset: &mut usize, known_type: u32) -> u32 {
-    let start = *offset;
-    let raw = read_packed_u32(data, offset);
-    if (*offset - start) == 2 {
-        raw | known_type
-    } else {
-        raw
+        item_type,
+        weenie_flags,
+        weenie_flags2,
+        flags,
+        sequences,
+        movement,
+        obj_desc,
+        physics_state,
+        velocity,
+        omega,
+        physics,
+        weenie,
+    } = msg
+    else {
+        unreachable!("not an ObjectCreate: {:?}", msg);
+    };
+
+    let model = bodies::ModelData {
+        marker: 0x11,
+        obj_desc: Some((**obj_desc).clone()),
+        display_model: None,
+    };
+
+    // Presence of each optional physics field follows its Option
+    let movement = movement.as_ref().map(|movement| {
+        let mut cursor = binrw::io::Cursor::new(Vec::new());
+        movement
+            .write_le(&mut cursor)
+            .expect("writing to memory cannot fail");
+        bodies::MovementBuffer {
+            data: cursor.into_inner(),
+            autonomous: Some(0),
+        }
+    });
+    // An animation frame is only sent when there is no movement
+    let animation_frame = physics.animation_frame.filter(|_| movement.is_none());
+    let children = (!physics.children.is_empty()).then(|| bodies::ChildList {
+        children: physics.children.clone(),
+    });
+    let mut phys_flags = PhysicsDescriptionFlag::NONE;
+    for (flag, present) in [
+        (PhysicsDescriptionFlag::MOVEMENT, movement.is_some()),
+        (
+            PhysicsDescriptionFlag::ANIMATION_FRAME,
+            animation_frame.is_some(),
+        ),
+        (PhysicsDescriptionFlag::POSITION, pos.is_some()),
+        (
+            PhysicsDescriptionFlag::MTABLE,
+            physics.motion_table_id.is_some(),
+        ),
+        (
+            PhysicsDescriptionFlag::STABLE,
+            physics.sound_table_id.is_some(),
+        ),
+        (
+            PhysicsDescriptionFlag::PETABLE,
+            physics.physics_script_table_id.is_some(),
+        ),
+        (PhysicsDescriptionFlag::CSETUP, physics.setup_id.is_some()),
+        (PhysicsDescriptionFlag::PARENT, parent_id.is_some()),
+        (PhysicsDescriptionFlag::CHILDREN, children.is_some()),
+        (
+            PhysicsDescriptionFlag::OBJSCALE,
+            physics.object_scale.is_some(),
+        ),
+        (PhysicsDescriptionFlag::FRICTION, physics.friction.is_some()),
+        (
+            PhysicsDescriptionFlag::ELASTICITY,
+            physics.elasticity.is_some(),
+        ),
+        (
+            PhysicsDescriptionFlag::TRANSLUCENCY,
+            physics.translucency.is_some(),
+        ),
+        (PhysicsDescriptionFlag::VELOCITY, velocity.is_some()),
+        (
+            PhysicsDescriptionFlag::ACCELERATION,
+            physics.acceleration.is_some(),
+        ),
+        (PhysicsDescriptionFlag::OMEGA, omega.is_some()),
+        (
+            PhysicsDescriptionFlag::DEFAULT_SCRIPT,
+            physics.default_script.is_some(),
+        ),
+        (
+            PhysicsDescriptionFlag::DEFAULT_SCRIPT_INTENSITY,
+            physics.default_script_intensity.is_some(),
+        ),
+    ] {
+        phys_flags.set(flag, present);
     }
-}
-
-pub fn read_string16_with_len(data: &[u8], offset: &mut usize) -> (String, usize) {
-    if data.len() < *offset + 2 {
-        return (String::new(), 0);
+    let physics = bodies::PhysicsDesc {
+        flags: phys_flags,
+        state: *physics_state,
+        movement,
+        animation_frame,
+        position: *pos,
+        motion_table: physics.motion_table_id,
+        sound_table: physics.sound_table_id,
+        physics_script_table: physics.physics_script_table_id,
+        setup: physics.setup_id,
+        parent_id: *parent_id,
+        parent_location: parent_id.map(|_| physics.parent_location.unwrap_or(0)),
+        children,
+        object_scale: physics.object_scale,
+        friction: physics.friction,
+        elasticity: physics.elasticity,
+        translucency: physics.translucency,
+        velocity: *velocity,
+        acceleration: physics.acceleration,
+        omega: *omega,
+        default_script: physics.default_script,
+        default_script_intensity: physics.default_script_intensity,
+        sequences: (*sequences).into(),
+    };
+
+    // Likewise for the weenie header
+    let w = weenie;
+    let mut weenie_flags = *weenie_flags;
+    for (flag, present) in [
+        (WeenieHeaderFlag::PLURAL_NAME, w.plural_name.is_some()),
+        (WeenieHeaderFlag::ITEMS_CAPACITY, w.items_capacity.is_some()),
+        (
+            WeenieHeaderFlag::CONTAINERS_CAPACITY,
+            w.containers_capacity.is_some(),
+        ),
+        (WeenieHeaderFlag::AMMO_TYPE, w.ammo_type.is_some()),
+        (WeenieHeaderFlag::VALUE, w.value.is_some()),
+        (WeenieHeaderFlag::USABLE, w.usable.is_some()),
+        (WeenieHeaderFlag::USE_RADIUS, w.use_radius.is_some()),
+        (WeenieHeaderFlag::TARGET_TYPE, w.target_type.is_some()),
+        (WeenieHeaderFlag::UI_EFFECTS, w.ui_effects.is_some()),
+        (WeenieHeaderFlag::COMBAT_USE, w.combat_use.is_some()),
+        (WeenieHeaderFlag::STRUCTURE, w.structure.is_some()),
+        (WeenieHeaderFlag::MAX_STRUCTURE, w.max_structure.is_some()),
+        (WeenieHeaderFlag::STACK_SIZE, w.stack_size.is_some()),
+        (WeenieHeaderFlag::MAX_STACK_SIZE, w.max_stack_size.is_some()),
+        (WeenieHeaderFlag::CONTAINER, container_id.is_some()),
+        (WeenieHeaderFlag::WIELDER, wielder_id.is_some()),
+        (
+            WeenieHeaderFlag::VALID_LOCATIONS,
+            w.valid_locations.is_some(),
+        ),
+        (
+            WeenieHeaderFlag::CURRENTLY_WIELDED_LOCATION,
+            w.currently_wielded_location.is_some(),
+        ),
+        (WeenieHeaderFlag::PRIORITY, w.priority.is_some()),
+        (
+            WeenieHeaderFlag::RADAR_BLIP_COLOR,
+            w.radar_blip_color.is_some(),
+        ),
+        (WeenieHeaderFlag::RADAR_BEHAVIOR, w.radar_behavior.is_some()),
+        (WeenieHeaderFlag::PSCRIPT, w.pscript.is_some()),
+        (WeenieHeaderFlag::WORKMANSHIP, w.workmanship.is_some()),
+        (WeenieHeaderFlag::BURDEN, w.burden.is_some()),
+        (WeenieHeaderFlag::SPELL, w.spell.is_some()),
+        (WeenieHeaderFlag::HOUSE_OWNER, w.house_owner.is_some()),
+        (
+            WeenieHeaderFlag::HOUSE_RESTRICTIONS,
+            w.house_restrictions.is_some(),
+        ),
+        (
+            WeenieHeaderFlag::HOOK_ITEM_TYPES,
+            w.hook_item_types.is_some(),
+        ),
+        (WeenieHeaderFlag::MONARCH, w.monarch.is_some()),
+        (WeenieHeaderFlag::HOOK_TYPE, w.hook_type.is_some()),
+        (WeenieHeaderFlag::ICON_OVERLAY, w.icon_overlay.is_some()),
+        (WeenieHeaderFlag::MATERIAL_TYPE, w.material_type.is_some()),
+    ] {
+        weenie_flags.set(flag, present);
     }
-    let len = LittleEndian::read_u16(&data[*offset..*offset + 2]) as usize;
-    *offset += 2;
-    if data.len() < *offset + len {
-        return (String::new(), 0);
+    let mut weenie_flags2 = *weenie_flags2;
+    for (flag, present) in [
+        (WeenieHeaderFlag2::ICON_UNDERLAY, w.icon_underlay.is_some()),
+        (WeenieHeaderFlag2::COOLDOWN, w.cooldown.is_some()),
+        (
+            WeenieHeaderFlag2::COOLDOWN_DURATION,
+            w.cooldown_duration.is_some(),
+        ),
+        (WeenieHeaderFlag2::PET_OWNER, w.pet_owner.is_some()),
+    ] {
+        weenie_flags2.set(flag, present);
+    }
+    let mut flags = *flags;
+    if !weenie_flags2.is_empty() {
+        flags |= ObjectDescriptionFlag::INCLUDES_SECOND_HEADER;
+    }
+    let flags2 = flags
+        .contains(ObjectDescriptionFlag::INCLUDES_SECOND_HEADER)
+        .then_some(weenie_flags2);
+
+    let weenie = bodies::WeenieHeader {
+        flags: weenie_flags,
+        name: name.clone().unwrap_or_default(),
+        wcid: wcid.unwrap_or(0),
+        icon: w.icon,
+        item_type: *item_type,
+        description_flags: flags,
+        flags2,
+        plural_name: w.plural_name.clone(),
+        items_capacity: w.items_capacity,
+        containers_capacity: w.containers_capacity,
+        ammo_type: w.ammo_type,
+        value: w.value,
+        usable: w.usable,
+        use_radius: w.use_radius,
+        target_type: w.target_type,
+        ui_effects: w.ui_effects,
+        combat_use: w.combat_use,
+        structure: w.structure,
+        max_structure: w.max_structure,
+        stack_size: w.stack_size,
+        max_stack_size: w.max_stack_size,
+        container_id: *container_id,
+        wielder_id: *wielder_id,
+        valid_locations: w.valid_locations,
+        currently_wielded_location: w.currently_wielded_location,
+        priority: w.priority,
+        radar_blip_color: w.radar_blip_color,
+        radar_behavior: w.radar_behavior,
+        pscript: w.pscript,
+        workmanship: w.workmanship,
+        burden: w.burden,
+        spell: w.spell,
+        house_owner: w.house_owner,
+        house_restrictions: w.house_restrictions.clone(),
+        hook_item_types: w.hook_item_types,
+        monarch: w.monarch,
+        hook_type: w.hook_type,
+        icon_overlay: w.icon_overlay,
+        material_type: w.material_type,
+        icon_underlay: w.icon_underlay,
+        cooldown: w.cooldown,
+        cooldown_duration: w.cooldown_duration,
+        pet_owner: w.pet_owner,
+    };
+
+    bodies::ObjectCreate {
+        guid: *guid,
+        model,
+        physics,
+        weenie,
     }
-    let s = String::from_utf8_lossy(&data[*offset..*offset + len]).to_string();
-    *offset += len;
-    let padding = (4 - ((2 + len) % 4)) % 4;
-    *offset += padding;
-    (s, len)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::protocol::movement::{Motion, hold_keys, motion_commands};
 
     #[test]
     fn test_packet_header_roundtrip() {
@@ -2058,25 +2913,284 @@ mod tests {
         assert_eq!(header.count, unpacked.count);
     }
 
+    #[test]
+    fn test_optional_headers_unpack() {
+        let mut data = Vec::new();
+        write_sequence_list(&mut data, &[7, 8]);
+        data.extend_from_slice(&42u32.to_le_bytes());
+
+        let headers =
+            OptionalHeaders::unpack(flags::REQUEST_RETRANSMIT | flags::ACK_SEQUENCE, &data);
+        assert_eq!(headers.request_retransmit, vec![7, 8]);
+        assert!(headers.reject_retransmit.is_empty());
+        assert_eq!(headers.ack_sequence, Some(42));
+    }
+
+    #[test]
+    fn test_character_enter_world_roundtrip() {
+        let msg = GameMessage::CharacterEnterWorld {
+            id: 0x50000001,
+            account: "tester".to_string(),
+        };
+        match GameMessage::unpack(&msg.pack()) {
+            GameMessage::CharacterEnterWorld { id, account } => {
+                assert_eq!(id, 0x50000001);
+                assert_eq!(account, "tester");
+            }
+            other => panic!("Unexpected message {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_referral_header_roundtrip() {
+        let referral = ReferralHeader {
+            cookie: 0xDEADBEEFCAFEF00D,
+            address: "192.168.1.20:9050".parse().unwrap(),
+            server_id: 2,
+        };
+        let mut data = Vec::new();
+        ServerSwitchHeader {
+            sequence: 3,
+            kind: 1,
+        }
+        .pack(&mut data);
+        referral.pack(&mut data);
+        assert_eq!(data.len(), 8 + REFERRAL_HEADER_SIZE);
+        // Port is in network byte order inside the sockaddr
+        assert_eq!(&data[18..20], &9050u16.to_be_bytes());
+
+        let headers = OptionalHeaders::unpack(flags::SERVER_SWITCH | flags::REFERRAL, &data);
+        assert_eq!(
+            headers.server_switch,
+            Some(ServerSwitchHeader {
+                sequence: 3,
+                kind: 1
+            })
+        );
+        assert_eq!(headers.referral, Some(referral));
+    }
+
+    #[test]
+    fn test_optional_headers_echo_and_flow() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&9u32.to_le_bytes()); // ack
+        data.extend_from_slice(&1.5f64.to_le_bytes()); // time sync
+        data.extend_from_slice(&2.25f32.to_le_bytes()); // echo request
+        data.extend_from_slice(&1024u32.to_le_bytes());
+        data.extend_from_slice(&3u16.to_le_bytes());
+
+        let headers = OptionalHeaders::unpack(
+            flags::ACK_SEQUENCE | flags::TIME_SYNC | flags::ECHO_REQUEST | flags::FLOW,
+            &data,
+        );
+        assert_eq!(headers.ack_sequence, Some(9));
+        assert_eq!(headers.time_sync, Some(1.5));
+        assert_eq!(headers.echo_request, Some(2.25));
+        assert_eq!(
+            headers.flow,
+            Some(FlowHeader {
+                bytes: 1024,
+                interval: 3
+            })
+        );
+    }
+
     #[test]
     fn test_game_action_unpack() {
         let mut data = Vec::new();
         data.extend_from_slice(&opcodes::GAME_ACTION.to_le_bytes());
-        data.extend_from_slice(&0u32.to_le_bytes()); // sequence
-        data.extend_from_slice(&action_opcodes::LOGIN_COMPLETE.to_le_bytes());
-        data.extend_from_slice(&[1, 2, 3, 4]); // payload
+        data.extend_from_slice(&7u32.to_le_bytes()); // sequence
+        data.extend_from_slice(&action_opcodes::USE_ITEM.to_le_bytes());
+        data.extend_from_slice(&0x80000158u32.to_le_bytes()); // item
+
+        assert_eq!(
+            GameMessage::unpack(&data),
+            GameMessage::GameAction {
+                sequence: 7,
+                action: GameActionKind::UseItem { item: 0x80000158 },
+            }
+        );
 
-        let msg = GameMessage::unpack(&data);
-        if let GameMessage::GameAction {
+        // A body too short for its layout is kept as Unknown
+        data.truncate(14);
+        assert_eq!(
+            GameMessage::unpack(&data),
+            GameMessage::GameAction {
+                sequence: 7,
+                action: GameActionKind::Unknown {
+                    action: action_opcodes::USE_ITEM,
+                    data: vec![0x58, 0x01],
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_game_action_types_match_layouts() {
+        let actions = vec![
+            GameActionKind::TargetedMeleeAttack {
+                target: 0x80000158,
+                height: attack_heights::MEDIUM,
+                power: 0.5,
+            },
+            GameActionKind::TargetedMissileAttack {
+                target: 0x80000158,
+                height: attack_heights::HIGH,
+                accuracy: 1.0,
+            },
+            GameActionKind::Talk {
+                message: "hello".to_string(),
+            },
+            GameActionKind::RemoveFriend { guid: 0x50000002 },
+            GameActionKind::AddFriend {
+                name: "Pal".to_string(),
+            },
+            GameActionKind::PutItemInContainer {
+                item: 0x80000158,
+                container: 0x50000001,
+                placement: 0,
+            },
+            GameActionKind::GetAndWieldItem {
+                item: 0x80000158,
+                location: 0x00100000,
+            },
+            GameActionKind::DropItem { item: 0x80000158 },
+            GameActionKind::SetTitle { title: 3 },
+            GameActionKind::TellById {
+                message: "hi".to_string(),
+                target: 0x50000002,
+            },
+            GameActionKind::UseWithTarget {
+                source: 0x80000158,
+                target: 0x80000159,
+            },
+            GameActionKind::UseItem { item: 0x80000158 },
+            GameActionKind::RaiseVital { vital: 1, xp: 100 },
+            GameActionKind::RaiseAttribute {
+                attribute: 1,
+                xp: 100,
+            },
+            GameActionKind::RaiseSkill { skill: 6, xp: 100 },
+            GameActionKind::CastUntargetedSpell { spell: 2 },
+            GameActionKind::CastTargetedSpell {
+                target: 0x80000158,
+                spell: 27,
+            },
+            GameActionKind::ChangeCombatMode {
+                mode: combat_modes::MELEE,
+            },
+            GameActionKind::StackableMerge {
+                source: 0x80000158,
+                target: 0x80000159,
+                amount: 5,
+            },
+            GameActionKind::StackableSplitToContainer {
+                stack: 0x80000158,
+                container: 0x50000001,
+                placement: 2,
+                amount: 5,
+            },
+            GameActionKind::Tell {
+                message: "hi".to_string(),
+                target: "Pal".to_string(),
+            },
+            GameActionKind::TeleToLifestone,
+            GameActionKind::LoginComplete,
+            GameActionKind::FellowshipCreate {
+                name: "Band".to_string(),
+                share_xp: true,
+            },
+            GameActionKind::FellowshipQuit { disband: false },
+            GameActionKind::FellowshipDismiss { guid: 0x50000002 },
+            GameActionKind::FellowshipRecruit { guid: 0x50000002 },
+            GameActionKind::IdentifyObject { target: 0x80000158 },
+            GameActionKind::GiveObjectRequest {
+                target: 0x50000002,
+                item: 0x80000158,
+                amount: 1,
+            },
+            GameActionKind::ChatChannel {
+                channel: 2,
+                message: "lfg".to_string(),
+            },
+            GameActionKind::NoLongerViewingContents {
+                container: 0x80000158,
+            },
+            GameActionKind::CancelAttack,
+            GameActionKind::QueryHealth { target: 0x80000158 },
+            GameActionKind::Emote {
+                message: "waves".to_string(),
+            },
+            GameActionKind::SoulEmote {
+                message: "waves".to_string(),
+            },
+            GameActionKind::Ping,
+            GameActionKind::TeleToMarketplace,
+            GameActionKind::Jump {
+                extent: 0.5,
+                velocity: Vector3::new(0.0, 4.0, 6.0),
+                sequences: MovementSequences::default(),
+            },
+            GameActionKind::MoveToState {
+                motion: RawMotionState::default(),
+                position: WorldPosition::default(),
+                sequences: MovementSequences::default(),
+                contact: 1,
+            },
+            GameActionKind::AutonomousPosition {
+                position: WorldPosition::default(),
+                sequences: MovementSequences::default(),
+                contact: 1,
+            },
+            GameActionKind::Unknown {
+                action: 0xFFFF,
+                data: vec![1, 2, 3],
+            },
+        ];
+
+        for action in actions {
+            let msg = GameMessage::GameAction {
+                sequence: 3,
+                action: action.clone(),
+            };
+            let data = msg.pack();
+            assert_eq!(LittleEndian::read_u32(&data[8..12]), action.action_type());
+            assert_eq!(GameMessage::try_unpack(&data).unwrap(), msg);
+        }
+    }
+
+    #[test]
+    fn test_move_to_state_layout() {
+        let mut motion = RawMotionState::default();
+        motion.set_forward(motion_commands::WALK_FORWARD, hold_keys::NONE);
+        let action = GameActionKind::MoveToState {
+            motion,
+            position: WorldPosition {
+                landblock_id: 0xA9B40019,
+                coords: Vector3::new(84.0, 7.1, 94.0),
+                rotation: crate::math::Quaternion::identity(),
+            },
+            sequences: MovementSequences {
+                instance: 1,
+                server_control: 2,
+                teleport: 3,
+                force_position: 4,
+            },
+            contact: 1,
+        };
+        let data = GameMessage::GameAction {
+            sequence: 9,
             action,
-            data: payload,
-        } = msg
-        {
-            assert_eq!(action, action_opcodes::LOGIN_COMPLETE);
-            assert_eq!(payload, vec![1, 2, 3, 4]);
-        } else {
-            panic!("Failed to unpack GameAction");
         }
+        .pack();
+
+        // Header, three-field motion state, position, sequences and the padded contact byte
+        assert_eq!(data.len(), 12 + 12 + 32 + 8 + 4);
+        assert_eq!(LittleEndian::read_u32(&data[12..16]), 0x0C);
+        assert_eq!(LittleEndian::read_u32(&data[24..28]), 0xA9B40019);
+        assert_eq!(LittleEndian::read_u16(&data[56..58]), 1);
+        assert_eq!(LittleEndian::read_u16(&data[62..64]), 4);
+        assert_eq!(&data[64..68], &[1, 0, 0, 0]);
     }
 
     #[test]
@@ -2105,27 +3219,53 @@ mod tests {
             0xB0, 0xF7, 0x00, 0x00, // Opcode
             0x01, 0x00, 0x00, 0x50, // GUID
             0x0E, 0x00, 0x00, 0x00, // Seq
-            0xBD, 0x02, 0x00, 0x00, // Type
+            0xBD, 0x02, 0x00, 0x00, // Type: Tell
             0x02, 0x00, 0x68, 0x69, // "hi"
-            0x00, 0x00, // padding
+            0x03, 0x00, 0x42, 0x6F, 0x62, 0x00, 0x00, 0x00, // "Bob" + padding
+            0x02, 0x00, 0x00, 0x50, // sender
+            0x01, 0x00, 0x00, 0x50, // target
+            0x03, 0x00, 0x00, 0x00, // chat type
+            0x00, 0x00, 0x00, 0x00, // secret flags
         ];
 
         let msg = GameMessage::unpack(&data);
-        if let GameMessage::GameEvent {
-            guid,
-            sequence,
-            event_type,
-            data: event_data,
-        } = msg
-        {
-            assert_eq!(guid, 0x50000001);
-            assert_eq!(sequence, 14);
-            assert_eq!(event_type, 0x02BD);
-            assert_eq!(event_data.len(), 6);
-            assert_eq!(event_data[0], 0x02);
-        } else {
-            panic!("Expected GameEvent, got {:?}", msg);
+        assert_eq!(
+            msg,
+            GameMessage::GameEvent {
+                guid: 0x50000001,
+                sequence: 14,
+                event: GameEventKind::Tell {
+                    message: "hi".to_string(),
+                    sender: "Bob".to_string(),
+                    sender_id: 0x50000002,
+                    target_id: 0x50000001,
+                    chat_type: 3,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_unknown_game_event_keeps_body() {
+        let mut data = Vec::new();
+        for word in [opcodes::GAME_EVENT, 0x50000001, 3, 0x0999] {
+            data.extend_from_slice(&word.to_le_bytes());
         }
+        data.extend_from_slice(&[1, 2, 3]);
+
+        let msg = GameMessage::unpack(&data);
+        let GameMessage::GameEvent { event, .. } = &msg else {
+            panic!("Expected GameEvent, got {:?}", msg);
+        };
+        assert_eq!(
+            *event,
+            GameEventKind::Unknown {
+                event_type: 0x0999,
+                data: vec![1, 2, 3],
+            }
+        );
+        assert_eq!(event.event_type(), 0x0999);
+        assert_eq!(msg.pack(), data);
     }
 
     #[test]
@@ -2133,7 +3273,7 @@ mod tests {
         use crate::world::properties::ItemType;
         let hex = "45f700003950a57d110000000d80020018040100650000001f0055da8885af4283c013437dbfba41f704353f0000000000000000f70435bf8a04000200000000000000007889a8bf0000000000000000cdcc1cc100000000000000000000000000000000000000001800200011005370697265206f6620536572656e697479005403d31280000000140000007d000000010000002823000061000000000000800100a80000000a00";
         let data = hex::decode(hex).unwrap();
-        let msg = unpack_object_create(&data).unwrap();
+        let msg = GameMessage::try_unpack(&data).unwrap();
         if let GameMessage::ObjectCreate {
             guid,
             name,
@@ -2158,7 +3298,7 @@ mod tests {
         use crate::world::properties::ItemType;
         let hex = "45f700005650a57d11000000038001000c0c00000c00000000000000020000000300000000000000e70155da364bc04254030b43c0ca6b418e926bbf0000000000000000906ac8be03000009b301000200000000000000000000000000000000000000003000800017004d616e6e696b696e20466f756e64727920506f7274616c000000054d6b10000001001400040020000000cdccccbd0400000071000000000000800100c80000000a00";
         let data = hex::decode(hex).unwrap();
-        let msg = unpack_object_create(&data).unwrap();
+        let msg = GameMessage::try_unpack(&data).unwrap();
         if let GameMessage::ObjectCreate {
             guid,
             name,
@@ -2183,12 +3323,17 @@ mod tests {
         use crate::world::properties::ItemType;
         let hex = "45f7000058010080110706017e008710500c8710600c8710740c8710d8189310480893106c089310ae0c00d503fe1a00d403fc1a00b00bf91a00be0cfd1a00c402fa1a00cc02fb1a00740400011802001404000065000000140000202b000034d40000020000000000000000000000000000000000000000184025000c0041636164656d7920436f617400009d33151f0200000012000000960000000100000001000050001e0000003c000058020000210000000000008001008c0000000a00";
         let data = hex::decode(hex).unwrap();
-        let msg = unpack_object_create(&data).unwrap();
+        let msg = GameMessage::try_unpack(&data).unwrap();
+        // Every field is kept, so it packs back the same (the capture has trailing bytes)
+        assert!(data.starts_with(&msg.pack()));
         if let GameMessage::ObjectCreate {
             guid,
             name,
             item_type,
             weenie_flags,
+            obj_desc,
+            physics,
+            weenie,
             ..
         } = msg
         {
@@ -2196,6 +3341,12 @@ mod tests {
             assert_eq!(name.unwrap(), "Academy Coat");
             assert_eq!(item_type, ItemType::ARMOR);
             assert!(weenie_flags.contains(WeenieHeaderFlag::CONTAINER));
+            assert_eq!(obj_desc.model_changes[0].model, 0x01000474);
+            assert_eq!(physics.setup_id, Some(0x020000D4));
+            assert_eq!(physics.sound_table_id, Some(0x20000014));
+            assert_eq!(weenie.value, Some(150));
+            assert_eq!(weenie.burden, Some(600));
+            assert_eq!(weenie.valid_locations, Some(0x1E00));
         } else {
             panic!("Expected ObjectCreate, got {:?}", msg);
         }
@@ -2206,12 +3357,13 @@ mod tests {
         use crate::world::properties::ItemType;
         let hex = "45f7000001000050110814237e00df1f1808a8040018af042008b105400872044808c705281897065c04b705a008109800fd11104c02571010f5027710105c02981000b00bb00b00be0cbe0c05d803a10001d803a10009de03bd0209d6035e0200b00b5d0200be0cea0c0acc02be020dcc02be020bc4020d140ec4020d1403c00cce0307c00cce0304dc03ce0308dc03ce03101748007704053b12013e1209b9040033120a4a120d49120b31120e321203790407780404ba0408bc0402c40406c5040cb7040fc30411ec0112ec0113ec0114ec0115ec0116ec0117ec0118ec0119ec011aec011bec011cec011dec011eec011fec0120ec0121ec010003980100104440000c00000000003d00030000003d000300000000001d0055da9a99a9426874c4423d0aa0410000803f0000000000000000000000000100000902000020040000344e00000200000000000000000000000000000000370000003600800006002b427564647904003610100000001c0010006607010000000000003f040085000000000000800200240001000a001000000004000000ffff2000000000004040080401afe04d169c0100000e003132372e302e302e313a393030301c00000000000000024000001cf399bc00000000080000000e0000000e00000001afe04d169c0100000e003132372e302e302e313a3930303034000000060000000640";
         let data = hex::decode(hex).unwrap();
-        let msg = unpack_object_create(&data).unwrap();
+        let msg = GameMessage::try_unpack(&data).unwrap();
         if let GameMessage::ObjectCreate {
             guid,
             name,
             pos,
             item_type,
+            movement,
             ..
         } = msg
         {
@@ -2221,6 +3373,13 @@ mod tests {
             let pos = pos.expect("Should have position");
             assert_eq!(pos.landblock_id, 0xDA55001D);
             assert!(pos.coords.x > 84.0 && pos.coords.x < 85.0);
+            // Standing at ease
+            let movement = movement.expect("Should have movement");
+            assert_eq!(movement.stance, 0x3D);
+            assert!(matches!(
+                movement.motion,
+                Motion::Interpreted { ref state, sticky_target: None } if !state.is_moving()
+            ));
         } else {
             panic!("Expected ObjectCreate, got {:?}", msg);
         }
@@ -2231,17 +3390,24 @@ mod tests {
         use crate::world::properties::ItemType;
         let hex = "45f70000c100008011000000811802001404000065000000140000202b0000340e0a00021f852b3f00000000000000000000000000000000000000001070210010005061746877617264656e20546f6b656e000000804d83956480000000100000000000010000000100640001000050000000000a00000016000000000000800100800000000a00";
         let data = hex::decode(hex).unwrap();
-        let msg = unpack_object_create(&data).unwrap();
+        let msg = GameMessage::try_unpack(&data).unwrap();
+        assert!(data.starts_with(&msg.pack()));
         if let GameMessage::ObjectCreate {
             guid,
             name,
             item_type,
+            physics,
+            weenie,
             ..
         } = msg
         {
             assert_eq!(guid, 0x800000C1);
             assert_eq!(name.unwrap(), "Pathwarden Token");
             assert_eq!(item_type, ItemType::MISC);
+            assert_eq!(physics.object_scale, Some(0.67));
+            assert_eq!(weenie.stack_size, Some(1));
+            assert_eq!(weenie.max_stack_size, Some(100));
+            assert_eq!(weenie.burden, Some(10));
         } else {
             panic!("Expected ObjectCreate, got {:?}", msg);
         }
@@ -2277,9 +3443,10 @@ mod tests {
         let data = hex::decode(hex).unwrap();
         let msg = GameMessage::unpack(&data);
 
-        if let GameMessage::MagicUpdateEnchantment {
-            target,
-            enchantment,
+        if let GameMessage::GameEvent {
+            guid: target,
+            event: GameEventKind::MagicUpdateEnchantment { enchantment },
+            ..
         } = msg
         {
             assert_eq!(target, 0x50000001);
@@ -2300,10 +3467,10 @@ mod tests {
         let data = hex::decode(hex).unwrap();
         let msg = GameMessage::unpack(&data);
 
-        if let GameMessage::MagicRemoveEnchantment {
-            target,
-            spell_id,
-            layer,
+        if let GameMessage::GameEvent {
+            guid: target,
+            event: GameEventKind::MagicRemoveEnchantment { spell_id, layer },
+            ..
         } = msg
         {
             assert_eq!(target, 0x50000001);
@@ -2322,7 +3489,12 @@ mod tests {
         let data = hex::decode(hex).unwrap();
         let msg = GameMessage::unpack(&data);
 
-        if let GameMessage::MagicDispelMultipleEnchantments { target, spells } = msg {
+        if let GameMessage::GameEvent {
+            guid: target,
+            event: GameEventKind::MagicDispelMultipleEnchantments { spells },
+            ..
+        } = msg
+        {
             assert_eq!(target, 0x50000001);
             assert_eq!(spells.len(), 2);
             assert_eq!(spells[0].spell_id, 1);
@@ -2384,8 +3556,10 @@ mod tests {
         data.extend_from_slice(&5.0f32.to_le_bytes()); // stat_mod_value
 
         let msg = GameMessage::unpack(&data);
-        if let GameMessage::PlayerDescription {
-            guid, enchantments, ..
+        if let GameMessage::GameEvent {
+            guid,
+            event: GameEventKind::PlayerDescription { enchantments, .. },
+            ..
         } = msg
         {
             assert_eq!(guid, 0x50000001);
@@ -2415,9 +3589,8 @@ mod tests {
         data.extend_from_slice(&0u32.to_le_bytes()); // has_health
         data.extend_from_slice(&0x0000_0000u32.to_le_bytes()); // attr_cache: empty
 
-        let msg = unpack_player_description(0, &data);
-        assert!(msg.is_some());
-        if let Some(GameMessage::PlayerDescription { name, .. }) = msg {
+        let msg = unpack_player_description(&data);
+        if let Ok(GameEventKind::PlayerDescription { name, .. }) = msg {
             assert_eq!(name, "Foo");
         } else {
             panic!("Expected PlayerDescription");
@@ -2447,9 +3620,8 @@ mod tests {
         data.extend_from_slice(&0u32.to_le_bytes()); // resistance
         data.extend_from_slice(&0.0f64.to_le_bytes()); // last used
 
-        let msg = unpack_player_description(0, &data);
-        assert!(msg.is_some());
-        if let Some(GameMessage::PlayerDescription { skills, .. }) = msg {
+        let msg = unpack_player_description(&data);
+        if let Ok(GameEventKind::PlayerDescription { skills, .. }) = msg {
             assert_eq!(skills.len(), 1);
             assert_eq!(skills[0].0, 32); // id
             assert_eq!(skills[0].1, 100); // ranks
@@ -2458,4 +3630,475 @@ mod tests {
             panic!("Expected PlayerDescription");
         }
     }
+
+    fn test_vectors() -> Vec<Vec<u8>> {
+        include_str!("../../testdata/messages.hex")
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| hex::decode(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_try_unpack_truncated_and_corrupted_vectors_never_panic() {
+        for data in test_vectors() {
+            for len in 0..data.len() {
+                let _ = GameMessage::try_unpack(&data[..len]);
+            }
+            for i in 4..data.len() {
+                for value in [0x00, 0x7F, 0x80, 0xFF] {
+                    let mut corrupted = data.clone();
+                    corrupted[i] = value;
+                    let _ = GameMessage::try_unpack(&corrupted);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_unpack_reports_truncated_field() {
+        // ObjectCreate with the PARENT physics flag but no parent id
+        let mut data = Vec::new();
+        data.extend_from_slice(&opcodes::OBJECT_CREATE.to_le_bytes());
+        data.extend_from_slice(&0x80000001u32.to_le_bytes()); // guid
+        data.extend_from_slice(&[0x11, 0, 0, 0]); // empty model data
+        data.extend_from_slice(&0x0020u32.to_le_bytes()); // physics flags: PARENT
+        data.extend_from_slice(&0u32.to_le_bytes()); // physics state
+
+        let err = GameMessage::try_unpack(&data).unwrap_err();
+        assert_eq!(err.opcode, opcodes::OBJECT_CREATE);
+        assert_eq!(err.field, "parent_id");
+        assert_eq!(err.offset, 20);
+
+        match GameMessage::unpack(&data) {
+            GameMessage::Unknown { opcode, data: body } => {
+                assert_eq!(opcode, opcodes::OBJECT_CREATE);
+                assert_eq!(body, data[4..]);
+            }
+            msg => panic!("Expected Unknown, got {:?}", msg),
+        }
+    }
+
+    #[test]
+    fn test_pack_roundtrip_every_variant() {
+        use crate::math::{Quaternion, Vector3};
+        let pos = WorldPosition {
+            landblock_id: 0xDA55001D,
+            coords: Vector3::new(84.8, 98.2, 20.0),
+            rotation: Quaternion {
+                w: 0.7,
+                x: 0.0,
+                y: 0.0,
+                z: -0.7,
+            },
+        };
+        let enchantment = Enchantment {
+            spell_id: 400,
+            layer: 1,
+            power_level: 100,
+            start_time: -10.0,
+            duration: 3600.0,
+            caster_guid: 0x50000001,
+            stat_mod_type: 0x02000001,
+            stat_mod_key: 1,
+            stat_mod_value: 10.0,
+            ..Default::default()
+        };
+        let with_set = Enchantment {
+            has_spell_set_id: 1,
+            spell_set_id: Some(7),
+            ..enchantment.clone()
+        };
+        let spells = vec![
+            LayeredSpell {
+                spell_id: 1,
+                layer: 1,
+            },
+            LayeredSpell {
+                spell_id: 2,
+                layer: 3,
+            },
+        ];
+
+        let messages = vec![
+            GameMessage::CharacterList {
+                characters: vec![
+                    (0x50000001, "Buddy".to_string()),
+                    (0x50000002, "Pal".to_string()),
+                ],
+            },
+            GameMessage::CharacterEnterWorldServerReady,
+            GameMessage::CharacterEnterWorldRequest { char_id: 0 },
+            GameMessage::CharacterEnterWorld {
+                id: 0x50000001,
+                account: "acct".to_string(),
+            },
+            GameMessage::PlayerCreate {
+                player_id: 0x50000001,
+            },
+            GameMessage::ObjectCreate {
+                guid: 0x80000158,
+                name: Some("Academy Coat".to_string()),
+                wcid: Some(0x8000),
+                pos: Some(pos),
+                parent_id: Some(0x50000001),
+                container_id: Some(0x50000001),
+                wielder_id: None,
+                item_type: ItemType::ARMOR,
+                weenie_flags: WeenieHeaderFlag::CONTAINER
+                    | WeenieHeaderFlag::PLURAL_NAME
+                    | WeenieHeaderFlag::VALUE
+                    | WeenieHeaderFlag::HOUSE_RESTRICTIONS
+                    | WeenieHeaderFlag::ICON_OVERLAY,
+                weenie_flags2: WeenieHeaderFlag2::ICON_UNDERLAY | WeenieHeaderFlag2::PET_OWNER,
+                flags: ObjectDescriptionFlag::INCLUDES_SECOND_HEADER,
+                sequences: [1, 2, 3, 4, 5, 6, 7, 8, 9].into(),
+                movement: Some(MovementData::default()),
+                obj_desc: Box::new(bodies::ObjDesc {
+                    base_palette: Some(0x0400007E),
+                    subpalettes: vec![bodies::Subpalette {
+                        palette: 0x04001087,
+                        offset: 0x0C,
+                        length: 0x50,
+                    }],
+                    texture_changes: vec![bodies::TextureChange {
+                        part: 0,
+                        old_texture: 0x05000CAE,
+                        new_texture: 0x05001AFE,
+                    }],
+                    model_changes: vec![bodies::ModelChange {
+                        part: 0,
+                        model: 0x01000474,
+                    }],
+                }),
+                physics_state: PhysicsState::ETHEREAL | PhysicsState::GRAVITY,
+                velocity: Some(Vector3::new(0.0, 1.0, 0.0)),
+                omega: None,
+                physics: Box::new(PhysicsDescription {
+                    setup_id: Some(0x020000D4),
+                    sound_table_id: Some(0x20000014),
+                    parent_location: Some(1),
+                    children: vec![(0x80000159, 2)],
+                    object_scale: Some(1.5),
+                    translucency: Some(0.25),
+                    ..PhysicsDescription::default()
+                }),
+                weenie: Box::new(WeenieDescription {
+                    icon: 0x06001F15,
+                    plural_name: Some("Academy Coats".to_string()),
+                    value: Some(150),
+                    house_restrictions: Some(bodies::HouseRestrictions {
+                        version: 0x10000002,
+                        open_status: 0,
+                        monarch_id: 0,
+                        table: bodies::PropertyTable::new(0, Vec::new()),
+                    }),
+                    icon_overlay: Some(0x06001B5D),
+                    icon_underlay: Some(0x06001B5E),
+                    pet_owner: Some(0x50000001),
+                    ..WeenieDescription::default()
+                }),
+            },
+            GameMessage::ObjectDelete { guid: 0x80000158 },
+            GameMessage::ParentEvent {
+                child_guid: 0x80000158,
+                parent_guid: 0x50000001,
+            },
+            GameMessage::PickupEvent { guid: 0x80000158 },
+            GameMessage::SetState {
+                guid: 0x80000158,
+                state: 0x408,
+                instance_sequence: 1,
+                state_sequence: 3,
+            },
+            GameMessage::PlayerTeleport {
+                teleport_sequence: 4,
+            },
+            GameMessage::UpdatePropertyInt {
+                guid: 0,
+                property: 25,
+                value: -3,
+            },
+            GameMessage::UpdatePropertyInt {
+                guid: 0x80000158,
+                property: 25,
+                value: 3,
+            },
+            GameMessage::UpdatePropertyInt64 {
+                guid: 0,
+                property: 1,
+                value: 1 << 40,
+            },
+            GameMessage::UpdatePropertyInt64 {
+                guid: 0x80000158,
+                property: 1,
+                value: -1,
+            },
+            GameMessage::UpdatePropertyBool {
+                guid: 0,
+                property: 2,
+                value: true,
+            },
+            GameMessage::UpdatePropertyBool {
+                guid: 0x80000158,
+                property: 2,
+                value: false,
+            },
+            GameMessage::UpdatePropertyFloat {
+                guid: 0,
+                property: 3,
+                value: 0.5,
+            },
+            GameMessage::UpdatePropertyFloat {
+                guid: 0x80000158,
+                property: 3,
+                value: -1.25,
+            },
+            GameMessage::UpdatePropertyString {
+                guid: 0,
+                property: 1,
+                value: "Buddy".to_string(),
+            },
+            GameMessage::UpdatePropertyString {
+                guid: 0x80000158,
+                property: 16,
+                value: "A coat".to_string(),
+            },
+            GameMessage::UpdatePropertyDataId {
+                guid: 0,
+                property: 1,
+                value: 0x02000001,
+            },
+            GameMessage::UpdatePropertyDataId {
+                guid: 0x80000158,
+                property: 1,
+                value: 0x02000001,
+            },
+            GameMessage::UpdatePropertyInstanceId {
+                guid: 0,
+                property: 2,
+                value: 0x50000001,
+            },
+            GameMessage::UpdatePropertyInstanceId {
+                guid: 0x80000158,
+                property: 2,
+                value: 0x50000001,
+            },
+            GameMessage::UpdateSkill {
+                skill: 6,
+                ranks: 10,
+                status: 2,
+                xp: 1000,
+                init: 5,
+            },
+            GameMessage::UpdateAttribute {
+                attribute: 1,
+                ranks: 10,
+                start: 90,
+                xp: 500,
+            },
+            GameMessage::UpdateVital {
+                vital: 2,
+                ranks: 10,
+                start: 90,
+                xp: 500,
+                current: 75,
+            },
+            GameMessage::UpdateVitalCurrent {
+                vital: 2,
+                current: 75,
+            },
+            GameMessage::UpdateMotion {
+                guid: 0x80000158,
+                instance_sequence: 1,
+                movement_sequence: 2,
+                server_control_sequence: 3,
+                autonomous: true,
+                movement: MovementData {
+                    motion: Motion::TurnToObject {
+                        target: 0x50000001,
+                        desired_heading: 90.0,
+                        params: Default::default(),
+                    },
+                    ..Default::default()
+                },
+            },
+            GameMessage::UpdatePosition {
+                guid: 0x80000158,
+                pos,
+                velocity: Some(Vector3::new(1.0, 0.0, 0.0)),
+                instance_sequence: 1,
+                position_sequence: 2,
+                teleport_sequence: 3,
+                force_position_sequence: 4,
+            },
+            GameMessage::VectorUpdate {
+                guid: 0x80000158,
+                velocity: Vector3::new(0.0, 1.5, 6.0),
+                omega: Vector3::new(0.0, 0.0, 3.0),
+                instance_sequence: 1,
+                vector_sequence: 2,
+            },
+            GameMessage::PlayEffect { guid: 0x80000158 },
+            GameMessage::GameAction {
+                sequence: 3,
+                action: GameActionKind::UseItem { item: 0x80000158 },
+            },
+            GameMessage::ServerMessage {
+                message: "Welcome".to_string(),
+            },
+            GameMessage::BootAccount {
+                reason: "Bye".to_string(),
+            },
+            GameMessage::HearSpeech {
+                message: "hello".to_string(),
+                sender: "Buddy".to_string(),
+            },
+            GameMessage::SoulEmote {
+                sender_id: 0x50000001,
+                sender_name: "Buddy".to_string(),
+                text: "waves".to_string(),
+            },
+            GameMessage::CharacterError {
+                error_code: character_error_codes::CHARACTER_LIMIT_REACHED,
+            },
+            GameMessage::ServerName {
+                name: "Holtburger".to_string(),
+                online_count: 3,
+                max_sessions: 100,
+            },
+            GameMessage::DddInterrogation,
+            GameMessage::DddInterrogationResponse { language: 1 },
+            GameMessage::Unknown {
+                opcode: 0xFFFF,
+                data: vec![1, 2, 3],
+            },
+        ];
+
+        let events = vec![
+            GameEventKind::PlayerDescription {
+                name: "Buddy".to_string(),
+                wee_type: 1,
+                pos: Some(pos),
+                attributes: vec![(1, 10, 90, 0, 100), (3, 5, 50, 0, 55), (101, 1, 2, 3, 4)],
+                skills: vec![(32, 100, 1, 1000, 10)],
+                enchantments: vec![enchantment.clone(), with_set.clone()],
+            },
+            GameEventKind::PlayerDescription {
+                name: "Unknown".to_string(),
+                wee_type: 0,
+                pos: None,
+                attributes: vec![],
+                skills: vec![],
+                enchantments: vec![],
+            },
+            GameEventKind::StartGame,
+            GameEventKind::UpdateHealth {
+                target: 0x80000158,
+                health: 0.5,
+            },
+            GameEventKind::ChannelBroadcast {
+                channel: 2,
+                sender: String::new(),
+                message: "hello".to_string(),
+            },
+            GameEventKind::Tell {
+                message: "hi".to_string(),
+                sender: "Buddy".to_string(),
+                sender_id: 0x50000002,
+                target_id: 0x50000001,
+                chat_type: 3,
+            },
+            GameEventKind::ViewContents {
+                container: 0x80000158,
+                items: vec![
+                    bodies::ContentsItem {
+                        guid: 0x80000159,
+                        container_type: 0,
+                    },
+                    bodies::ContentsItem {
+                        guid: 0x8000015A,
+                        container_type: 1,
+                    },
+                ],
+            },
+            GameEventKind::WeenieError { error: 0x0402 },
+            GameEventKind::WeenieErrorWithString {
+                error: 0x051D,
+                text: "Buddy".to_string(),
+            },
+            GameEventKind::CharacterTitle {
+                current: 3,
+                titles: vec![1, 3, 7],
+            },
+            GameEventKind::FriendsListUpdate {
+                friends: vec![bodies::Friend {
+                    guid: 0x50000002,
+                    online: true,
+                    appear_offline: false,
+                    name: "Pal".to_string(),
+                    friends: vec![0x50000001],
+                    friend_of: vec![],
+                }],
+                update_type: 1,
+            },
+            GameEventKind::FellowshipUpdateFellow {
+                guid: 0x50000002,
+                fellow: bodies::Fellow {
+                    level: 12,
+                    max_health: 100,
+                    current_health: 80,
+                    share_loot: true,
+                    name: "Pal".to_string(),
+                    ..Default::default()
+                },
+                update_type: 1,
+            },
+            GameEventKind::MagicUpdateEnchantment {
+                enchantment: with_set.clone(),
+            },
+            GameEventKind::MagicUpdateMultipleEnchantments {
+                enchantments: vec![enchantment, with_set],
+            },
+            GameEventKind::MagicRemoveEnchantment {
+                spell_id: 400,
+                layer: 1,
+            },
+            GameEventKind::MagicRemoveMultipleEnchantments {
+                spells: spells.clone(),
+            },
+            GameEventKind::MagicPurgeEnchantments,
+            GameEventKind::MagicPurgeBadEnchantments,
+            GameEventKind::MagicDispelEnchantment {
+                spell_id: 400,
+                layer: 1,
+            },
+            GameEventKind::MagicDispelMultipleEnchantments { spells },
+            GameEventKind::Unknown {
+                event_type: 0x0999,
+                data: vec![0x02, 0x00, b'h', b'i'],
+            },
+        ];
+        let messages =
+            messages
+                .into_iter()
+                .chain(events.into_iter().map(|event| GameMessage::GameEvent {
+                    guid: 0x50000001,
+                    sequence: 14,
+                    event,
+                }));
+
+        for msg in messages {
+            assert_eq!(GameMessage::try_unpack(&msg.pack()).unwrap(), msg);
+        }
+    }
+
+    #[test]
+    fn test_pack_roundtrip_test_vectors() {
+        for data in test_vectors() {
+            if let Ok(msg) = GameMessage::try_unpack(&data) {
+                assert_eq!(GameMessage::try_unpack(&msg.pack()).unwrap(), msg);
+            }
+        }
+    }
 }
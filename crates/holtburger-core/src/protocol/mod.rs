@@ -1,3 +1,7 @@
+pub mod bodies;
+pub mod codec;
 pub mod crypto;
 pub mod messages;
+pub mod movement;
+pub mod parse;
 pub mod properties;
@@ -0,0 +1,411 @@
+//! Wire layouts of movement: the motion the client asks for in its movement actions, and
+//! the motion the server describes for objects in UpdateMotion and ObjectCreate.
+
+use crate::math::Vector3;
+use crate::world::properties::{InterpretedMotionFlags, RawMotionFlags};
+use binrw::binrw;
+
+/// Motion commands, as sent in motion states.
+pub mod motion_commands {
+    pub const READY: u32 = 0x41000003;
+    pub const WALK_FORWARD: u32 = 0x45000005;
+    pub const WALK_BACKWARDS: u32 = 0x45000006;
+    pub const RUN_FORWARD: u32 = 0x44000007;
+    pub const TURN_RIGHT: u32 = 0x6500000D;
+    pub const TURN_LEFT: u32 = 0x6500000E;
+    pub const SIDESTEP_RIGHT: u32 = 0x6500000F;
+    pub const SIDESTEP_LEFT: u32 = 0x65000010;
+}
+
+/// Stances, the motion style an object is in.
+pub mod stances {
+    pub const HAND_COMBAT: u32 = 0x8000003C;
+    pub const NON_COMBAT: u32 = 0x8000003D;
+}
+
+/// How a MovementData moves its object.
+pub mod movement_types {
+    /// Moving under an interpreted motion state.
+    pub const INTERPRETED: u8 = 0;
+    pub const MOVE_TO_OBJECT: u8 = 6;
+    pub const MOVE_TO_POSITION: u8 = 7;
+    pub const TURN_TO_OBJECT: u8 = 8;
+    pub const TURN_TO_HEADING: u8 = 9;
+}
+
+pub mod motion_flags {
+    /// The object is stuck to a target, following it.
+    pub const STICK_TO_OBJECT: u8 = 0x1;
+    pub const STANDING_LONG_JUMP: u8 = 0x2;
+}
+
+/// The low 16 bits of a motion command or stance, as motion states from the server carry
+/// them.
+pub fn short_command(command: u32) -> u16 {
+    command as u16
+}
+
+/// Keys held alongside a motion command. Holding run turns walking into running.
+pub mod hold_keys {
+    pub const INVALID: u32 = 0;
+    pub const NONE: u32 = 1;
+    pub const RUN: u32 = 2;
+}
+
+/// A discrete motion queued in a motion state, such as an emote.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotionItem {
+    /// The low 16 bits of the motion command.
+    pub command: u16,
+    /// The sequence in the low 15 bits, with the top bit set when autonomous.
+    pub packed_sequence: u16,
+    pub speed: f32,
+}
+
+/// The motion the client asks for, as sent in MoveToState. Each field is only sent when
+/// its flag is set and is left at its default otherwise.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RawMotionState {
+    #[br(temp)]
+    #[bw(calc = flags.bits() | (commands.len() as u32) << 11)]
+    packed_flags: u32,
+    #[br(calc = RawMotionFlags::from_bits_truncate(packed_flags))]
+    #[bw(ignore)]
+    pub flags: RawMotionFlags,
+    /// One of `hold_keys`.
+    #[br(if(flags.contains(RawMotionFlags::CURRENT_HOLD_KEY)))]
+    pub current_hold_key: Option<u32>,
+    #[br(if(flags.contains(RawMotionFlags::CURRENT_STYLE)))]
+    pub current_style: Option<u32>,
+    /// One of `motion_commands`.
+    #[br(if(flags.contains(RawMotionFlags::FORWARD_COMMAND)))]
+    pub forward_command: Option<u32>,
+    #[br(if(flags.contains(RawMotionFlags::FORWARD_HOLD_KEY)))]
+    pub forward_hold_key: Option<u32>,
+    #[br(if(flags.contains(RawMotionFlags::FORWARD_SPEED)))]
+    pub forward_speed: Option<f32>,
+    #[br(if(flags.contains(RawMotionFlags::SIDESTEP_COMMAND)))]
+    pub sidestep_command: Option<u32>,
+    #[br(if(flags.contains(RawMotionFlags::SIDESTEP_HOLD_KEY)))]
+    pub sidestep_hold_key: Option<u32>,
+    #[br(if(flags.contains(RawMotionFlags::SIDESTEP_SPEED)))]
+    pub sidestep_speed: Option<f32>,
+    #[br(if(flags.contains(RawMotionFlags::TURN_COMMAND)))]
+    pub turn_command: Option<u32>,
+    #[br(if(flags.contains(RawMotionFlags::TURN_HOLD_KEY)))]
+    pub turn_hold_key: Option<u32>,
+    #[br(if(flags.contains(RawMotionFlags::TURN_SPEED)))]
+    pub turn_speed: Option<f32>,
+    #[br(count = packed_flags >> 11)]
+    pub commands: Vec<MotionItem>,
+}
+
+impl RawMotionState {
+    /// Sets the held key, flagging it as sent.
+    pub fn set_current_hold_key(&mut self, key: u32) {
+        self.flags |= RawMotionFlags::CURRENT_HOLD_KEY;
+        self.current_hold_key = Some(key);
+    }
+
+    /// Sets the forward (or backward) command and its held key.
+    pub fn set_forward(&mut self, command: u32, hold_key: u32) {
+        self.flags |= RawMotionFlags::FORWARD_COMMAND | RawMotionFlags::FORWARD_HOLD_KEY;
+        self.forward_command = Some(command);
+        self.forward_hold_key = Some(hold_key);
+    }
+
+    /// Sets the sidestep command and its held key.
+    pub fn set_sidestep(&mut self, command: u32, hold_key: u32) {
+        self.flags |= RawMotionFlags::SIDESTEP_COMMAND | RawMotionFlags::SIDESTEP_HOLD_KEY;
+        self.sidestep_command = Some(command);
+        self.sidestep_hold_key = Some(hold_key);
+    }
+
+    /// Sets the turn command and its held key.
+    pub fn set_turn(&mut self, command: u32, hold_key: u32) {
+        self.flags |= RawMotionFlags::TURN_COMMAND | RawMotionFlags::TURN_HOLD_KEY;
+        self.turn_command = Some(command);
+        self.turn_hold_key = Some(hold_key);
+    }
+}
+
+/// The sequences a movement action is checked against. Each echoes the latest value the
+/// server sent for the player, so the server can tell which of its updates the client had
+/// seen.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MovementSequences {
+    pub instance: u16,
+    pub server_control: u16,
+    pub teleport: u16,
+    pub force_position: u16,
+}
+
+/// How an object is moving, as the server describes it. Commands and stances are sent as
+/// their low 16 bits; compare them with `short_command`.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InterpretedMotionState {
+    #[br(temp)]
+    #[bw(calc = flags.bits() | (commands.len() as u32) << 7)]
+    packed_flags: u32,
+    #[br(calc = InterpretedMotionFlags::from_bits_truncate(packed_flags))]
+    #[bw(ignore)]
+    pub flags: InterpretedMotionFlags,
+    #[br(if(flags.contains(InterpretedMotionFlags::CURRENT_STYLE)))]
+    pub current_style: Option<u16>,
+    #[br(if(flags.contains(InterpretedMotionFlags::FORWARD_COMMAND)))]
+    pub forward_command: Option<u16>,
+    #[br(if(flags.contains(InterpretedMotionFlags::SIDESTEP_COMMAND)))]
+    pub sidestep_command: Option<u16>,
+    #[br(if(flags.contains(InterpretedMotionFlags::TURN_COMMAND)))]
+    pub turn_command: Option<u16>,
+    #[br(if(flags.contains(InterpretedMotionFlags::FORWARD_SPEED)))]
+    pub forward_speed: Option<f32>,
+    #[br(if(flags.contains(InterpretedMotionFlags::SIDESTEP_SPEED)))]
+    pub sidestep_speed: Option<f32>,
+    #[br(if(flags.contains(InterpretedMotionFlags::TURN_SPEED)))]
+    pub turn_speed: Option<f32>,
+    /// Discrete motions such as attacks and emotes, in the order they play.
+    #[br(count = packed_flags >> 7)]
+    #[brw(align_after = 4)]
+    pub commands: Vec<MotionItem>,
+}
+
+impl InterpretedMotionState {
+    /// The forward command, which is Ready when not sent.
+    pub fn forward(&self) -> u16 {
+        self.forward_command
+            .unwrap_or(short_command(motion_commands::READY))
+    }
+
+    /// Whether the object is walking, running or turning rather than standing.
+    pub fn is_moving(&self) -> bool {
+        self.forward() != short_command(motion_commands::READY)
+            || self.sidestep_command.is_some()
+            || self.turn_command.is_some()
+    }
+}
+
+/// A cell and a point in it, where a move-to started.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Origin {
+    pub cell: u32,
+    pub position: Vector3,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MoveToParameters {
+    pub flags: u32,
+    pub distance_to_object: f32,
+    pub min_distance: f32,
+    pub fail_distance: f32,
+    pub speed: f32,
+    /// Beyond this distance the object runs rather than walks.
+    pub walk_run_threshold: f32,
+    pub desired_heading: f32,
+}
+
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TurnToParameters {
+    pub flags: u32,
+    pub speed: f32,
+    pub desired_heading: f32,
+}
+
+/// The motion in a MovementData, by movement type.
+#[binrw]
+#[brw(little)]
+#[br(import(movement_type: u8, motion_flags: u8))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Motion {
+    #[br(pre_assert(movement_type == movement_types::INTERPRETED))]
+    Interpreted {
+        state: InterpretedMotionState,
+        /// The object being followed, with `motion_flags::STICK_TO_OBJECT`.
+        #[br(if(motion_flags & motion_flags::STICK_TO_OBJECT != 0))]
+        sticky_target: Option<u32>,
+    },
+    #[br(pre_assert(movement_type == movement_types::MOVE_TO_OBJECT))]
+    MoveToObject {
+        target: u32,
+        origin: Origin,
+        params: MoveToParameters,
+        run_rate: f32,
+    },
+    #[br(pre_assert(movement_type == movement_types::MOVE_TO_POSITION))]
+    MoveToPosition {
+        origin: Origin,
+        params: MoveToParameters,
+        run_rate: f32,
+    },
+    #[br(pre_assert(movement_type == movement_types::TURN_TO_OBJECT))]
+    TurnToObject {
+        target: u32,
+        desired_heading: f32,
+        params: TurnToParameters,
+    },
+    #[br(pre_assert(movement_type == movement_types::TURN_TO_HEADING))]
+    TurnToHeading { params: TurnToParameters },
+}
+
+impl Motion {
+    pub fn movement_type(&self) -> u8 {
+        match self {
+            Motion::Interpreted { .. } => movement_types::INTERPRETED,
+            Motion::MoveToObject { .. } => movement_types::MOVE_TO_OBJECT,
+            Motion::MoveToPosition { .. } => movement_types::MOVE_TO_POSITION,
+            Motion::TurnToObject { .. } => movement_types::TURN_TO_OBJECT,
+            Motion::TurnToHeading { .. } => movement_types::TURN_TO_HEADING,
+        }
+    }
+
+    /// The object this motion is heading for, following or facing.
+    pub fn target(&self) -> Option<u32> {
+        match self {
+            Motion::Interpreted { sticky_target, .. } => *sticky_target,
+            Motion::MoveToObject { target, .. } | Motion::TurnToObject { target, .. } => {
+                Some(*target)
+            }
+            Motion::MoveToPosition { .. } | Motion::TurnToHeading { .. } => None,
+        }
+    }
+}
+
+/// How an object moves: its stance and either an interpreted motion state or a move or
+/// turn towards something.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MovementData {
+    #[br(temp)]
+    #[bw(calc = motion.movement_type())]
+    movement_type: u8,
+    /// `motion_flags` bits. The sticky target is only sent with `STICK_TO_OBJECT`.
+    pub motion_flags: u8,
+    /// The stance, as the low 16 bits of one of `stances`.
+    pub stance: u16,
+    #[br(args(movement_type, motion_flags))]
+    pub motion: Motion,
+}
+
+impl Default for MovementData {
+    fn default() -> Self {
+        Self {
+            motion_flags: 0,
+            stance: short_command(stances::NON_COMBAT),
+            motion: Motion::Interpreted {
+                state: InterpretedMotionState::default(),
+                sticky_target: None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use binrw::{BinRead, BinWrite, io::Cursor};
+
+    #[test]
+    fn test_raw_motion_state_round_trip() {
+        let mut state = RawMotionState::default();
+        state.set_current_hold_key(hold_keys::RUN);
+        state.set_forward(motion_commands::WALK_FORWARD, hold_keys::RUN);
+        state.set_turn(motion_commands::TURN_LEFT, hold_keys::RUN);
+        state.commands.push(MotionItem {
+            command: 0x0087,
+            packed_sequence: 0x8001,
+            speed: 1.0,
+        });
+
+        let mut cursor = Cursor::new(Vec::new());
+        state.write(&mut cursor).unwrap();
+        let bytes = cursor.into_inner();
+        // Flags, five u32 fields and one motion item
+        assert_eq!(bytes.len(), 4 + 5 * 4 + 8);
+        assert_eq!(&bytes[0..4], &(0x30Du32 | 1 << 11).to_le_bytes());
+        assert_eq!(&bytes[8..12], &motion_commands::WALK_FORWARD.to_le_bytes());
+
+        let read = RawMotionState::read(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(read, state);
+
+        // No flags and no commands is an empty motion state, meaning stand still
+        let mut cursor = Cursor::new(Vec::new());
+        RawMotionState::default().write(&mut cursor).unwrap();
+        assert_eq!(cursor.into_inner(), vec![0; 4]);
+    }
+
+    #[test]
+    fn test_movement_data_interpreted() {
+        let data = [
+            0x00, 0x01, 0x3C, 0x00, // Interpreted, sticky, hand combat
+            0x85, 0x00, 0x00, 0x00, // Style and forward speed, one command
+            0x3C, 0x00, // Style
+            0x00, 0x00, 0x40, 0x40, // Forward speed 3.0
+            0x62, 0x00, 0x05, 0x80, // AttackHigh1, autonomous sequence 5
+            0x00, 0x00, 0x80, 0x3F, // Speed 1.0
+            0x00, 0x00, // Padding
+            0x01, 0x00, 0x00, 0x50, // Sticky target
+        ];
+        let movement = MovementData::read(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(movement.stance, short_command(stances::HAND_COMBAT));
+        assert_eq!(movement.motion.target(), Some(0x50000001));
+        let Motion::Interpreted { state, .. } = &movement.motion else {
+            panic!("Expected an interpreted motion, got {:?}", movement.motion);
+        };
+        assert_eq!(state.current_style, Some(0x3C));
+        assert_eq!(state.forward_speed, Some(3.0));
+        assert_eq!(state.commands[0].command, 0x62);
+        assert!(!state.is_moving());
+
+        let mut cursor = Cursor::new(Vec::new());
+        movement.write(&mut cursor).unwrap();
+        assert_eq!(cursor.into_inner(), data);
+    }
+
+    #[test]
+    fn test_movement_data_move_to_object() {
+        let movement = MovementData {
+            motion: Motion::MoveToObject {
+                target: 0x50000001,
+                origin: Origin {
+                    cell: 0xA9B40019,
+                    position: Vector3::new(84.0, 7.1, 94.0),
+                },
+                params: MoveToParameters {
+                    walk_run_threshold: 15.0,
+                    ..Default::default()
+                },
+                run_rate: 2.5,
+            },
+            ..Default::default()
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        movement.write(&mut cursor).unwrap();
+        let bytes = cursor.into_inner();
+        assert_eq!(bytes[0], movement_types::MOVE_TO_OBJECT);
+        assert_eq!(bytes.len(), 4 + 4 + 16 + 28 + 4);
+        assert_eq!(
+            MovementData::read(&mut Cursor::new(&bytes)).unwrap(),
+            movement
+        );
+
+        // An unknown movement type does not parse
+        let mut bytes = bytes;
+        bytes[0] = 3;
+        assert!(MovementData::read(&mut Cursor::new(&bytes)).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+//! Bounds-checked reading of message bodies.
+//!
+//! Message bodies are binrw structs (see `bodies`), read from a `Stream` over the whole
+//! message. The stream remembers where its last read started and how much it asked for, so
+//! that a binrw error can be reported as a `ParseError` naming the opcode, field and offset
+//! that was cut short. Truncated or hostile input can never index out of bounds.
+
+use binrw::{BinRead, BinWrite, io::Cursor};
+use std::borrow::Cow;
+use std::io::{self, Read, Seek, SeekFrom};
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseErrorKind {
+    #[error("needs {needed} bytes but only {available} remain")]
+    Truncated { needed: usize, available: usize },
+    #[error("{message}")]
+    Invalid { message: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Failed to parse {field} of opcode 0x{opcode:04X} at offset {offset}: {kind}")]
+pub struct ParseError {
+    pub opcode: u32,
+    pub field: &'static str,
+    /// Offset into the message body (opcode included) of the read that ran short.
+    pub offset: usize,
+    pub kind: ParseErrorKind,
+}
+
+/// A cursor over a message body that binrw reads from.
+#[derive(Debug, Clone)]
+pub struct Stream<'a> {
+    data: &'a [u8],
+    pos: u64,
+    opcode: u32,
+    /// Start and length of the most recent read.
+    last_read: (u64, usize),
+}
+
+impl<'a> Stream<'a> {
+    /// A stream over `data`, reporting errors against `opcode`.
+    pub fn new(data: &'a [u8], opcode: u32) -> Self {
+        Self {
+            data,
+            pos: 0,
+            opcode,
+            last_read: (0, 0),
+        }
+    }
+
+    pub fn offset(&self) -> usize {
+        self.pos as usize
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.offset())
+    }
+
+    /// Reads a little-endian `T` at the cursor.
+    pub fn read<T>(&mut self) -> Result<T, ParseError>
+    where
+        T: for<'b> BinRead<Args<'b> = ()>,
+    {
+        T::read_le(self).map_err(|e| self.error(e))
+    }
+
+    /// Everything from the cursor to the end of the data.
+    pub fn rest(&mut self) -> &'a [u8] {
+        let rest = self.data.get(self.offset()..).unwrap_or_default();
+        self.pos = self.data.len() as u64;
+        rest
+    }
+
+    fn remaining_at(&self, pos: u64) -> usize {
+        self.data.len().saturating_sub(pos as usize)
+    }
+
+    fn error(&self, err: binrw::Error) -> ParseError {
+        let (field, err) = match err {
+            binrw::Error::Backtrace(backtrace) => (
+                backtrace.frames.iter().find_map(frame_field),
+                *backtrace.error,
+            ),
+            err => (None, err),
+        };
+        let field = field.unwrap_or("body");
+
+        let (offset, kind) = match err {
+            binrw::Error::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                let (pos, needed) = self.last_read;
+                (
+                    pos,
+                    ParseErrorKind::Truncated {
+                        needed,
+                        available: self.remaining_at(pos),
+                    },
+                )
+            }
+            binrw::Error::Custom { pos, err } => match err.downcast::<ParseErrorKind>() {
+                Ok(kind) => (pos, *kind),
+                Err(err) => (
+                    pos,
+                    ParseErrorKind::Invalid {
+                        message: format!("{:?}", err),
+                    },
+                ),
+            },
+            err => (
+                self.pos,
+                ParseErrorKind::Invalid {
+                    message: err.to_string(),
+                },
+            ),
+        };
+        ParseError {
+            opcode: self.opcode,
+            field,
+            offset: offset as usize,
+            kind,
+        }
+    }
+}
+
+/// The field named by a binrw "While parsing field 'x' in Y" frame.
+fn frame_field(frame: &binrw::error::BacktraceFrame) -> Option<&'static str> {
+    let message = match frame {
+        binrw::error::BacktraceFrame::Full { message, .. } => message,
+        binrw::error::BacktraceFrame::Message(message) => message,
+        _ => return None,
+    };
+    let Cow::Borrowed(message) = message else {
+        return None;
+    };
+    let rest = message.strip_prefix("While parsing field '")?;
+    Some(&rest[..rest.find('\'')?])
+}
+
+/// Fails with a truncation error unless `len` more bytes can be read, so that lengths taken
+/// from the data are checked before anything is allocated for them.
+pub fn ensure_remaining<R: Read + Seek>(reader: &mut R, len: usize) -> binrw::BinResult<()> {
+    let pos = reader.stream_position()?;
+    let end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(pos))?;
+    let available = end.saturating_sub(pos) as usize;
+    if len > available {
+        return Err(binrw::Error::Custom {
+            pos,
+            err: Box::new(ParseErrorKind::Truncated {
+                needed: len,
+                available,
+            }),
+        });
+    }
+    Ok(())
+}
+
+/// Writes `opcode` followed by `body`. Alignment inside the body is relative to the opcode,
+/// as it is when reading.
+pub fn pack_body<T>(opcode: u32, body: &T) -> Vec<u8>
+where
+    T: for<'b> BinWrite<Args<'b> = ()>,
+{
+    let mut cursor = Cursor::new(opcode.to_le_bytes().to_vec());
+    cursor.set_position(4);
+    body.write_le(&mut cursor)
+        .expect("writing to memory cannot fail");
+    cursor.into_inner()
+}
+
+impl Read for Stream<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.last_read = (self.pos, buf.len());
+        let rest = self.data.get(self.offset()..).unwrap_or_default();
+        let n = rest.len().min(buf.len());
+        buf[..n].copy_from_slice(&rest[..n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.last_read = (self.pos, buf.len());
+        if buf.len() > self.remaining() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let start = self.offset();
+        buf.copy_from_slice(&self.data[start..start + buf.len()]);
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+}
+
+impl Seek for Stream<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => (self.data.len() as u64).checked_add_signed(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+        };
+        self.pos = target.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before start of data")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use binrw::binrw;
+
+    #[binrw]
+    #[brw(little)]
+    #[derive(Debug, PartialEq)]
+    struct Sample {
+        value: u16,
+        packed: u32,
+    }
+
+    #[test]
+    fn test_reads_and_reports_truncation() {
+        let data = [0xB0, 0xF7, 0, 0, 0x34, 0x12, 0x01, 0x80];
+        let mut s = Stream::new(&data, 0xF7B0);
+        s.seek(SeekFrom::Start(4)).unwrap();
+
+        let err = s.read::<Sample>().unwrap_err();
+        assert_eq!(
+            err,
+            ParseError {
+                opcode: 0xF7B0,
+                field: "packed",
+                offset: 6,
+                kind: ParseErrorKind::Truncated {
+                    needed: 4,
+                    available: 2
+                },
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Failed to parse packed of opcode 0xF7B0 at offset 6: needs 4 bytes but only 2 remain"
+        );
+    }
+
+    #[test]
+    fn test_ensure_remaining_checks_before_allocating() {
+        let data = [0u8; 6];
+        let mut s = Stream::new(&data, 0);
+        s.seek(SeekFrom::Start(4)).unwrap();
+        let err = ensure_remaining(&mut s, usize::MAX).unwrap_err();
+        assert_eq!(
+            s.error(err).kind,
+            ParseErrorKind::Truncated {
+                needed: usize::MAX,
+                available: 2
+            }
+        );
+        assert_eq!(s.offset(), 4);
+    }
+
+    #[test]
+    fn test_pack_body_follows_opcode() {
+        let body = Sample {
+            value: 0x1234,
+            packed: 7,
+        };
+        let data = pack_body(0xF7B0, &body);
+        assert_eq!(data, [0xB0, 0xF7, 0, 0, 0x34, 0x12, 7, 0, 0, 0]);
+        let mut s = Stream::new(&data, 0xF7B0);
+        s.seek(SeekFrom::Start(4)).unwrap();
+        assert_eq!(s.read::<Sample>().unwrap(), body);
+    }
+}
@@ -1,31 +1,265 @@
+//! Packet capture files.
+//!
+//! v2 files start with a header (`MAGIC`, version, flags, start time, account hash, server
+//! address, client version) followed by records, zlib-compressed when `FLAG_COMPRESSED` is
+//! set. Each record is a type byte and a microsecond timestamp, then either a packet
+//! (address, length-prefixed datagram) or the ISAAC seeds from a ConnectRequest.
+//!
+//! v1 files have no header and hold bare packet records with millisecond timestamps and a
+//! textual address; `CaptureReader` still opens them.
+
+use crate::session::pcap;
+use crate::session::scrub::{self, ScrubLevel};
 use anyhow::{Result, anyhow};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use std::collections::VecDeque;
 use std::fs::File;
-use std::io::{Read, Write};
-use std::net::SocketAddr;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const MAGIC: [u8; 4] = *b"HBCP";
+pub const CAPTURE_VERSION: u16 = 2;
+pub const FLAG_COMPRESSED: u16 = 0x0001;
+
+const RECORD_INBOUND: u8 = 0;
+const RECORD_OUTBOUND: u8 = 1;
+const RECORD_SESSION_KEYS: u8 = 2;
+
+/// A compressed capture is flushed after this many records or this long since the last
+/// flush, whichever comes first. Every flush ends a deflate block, so flushing each packet
+/// would give up most of the compression.
+const COMPRESSED_FLUSH_RECORDS: usize = 256;
+const COMPRESSED_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Longest datagram a record may hold: any UDP payload, since pcap imports keep packets the
+/// client would never send. Lengths read from a file are checked against these before
+/// allocating, so a corrupt capture cannot ask for gigabytes.
+const MAX_RECORD_DATA_LEN: usize = 65_535;
+/// Longest textual address in a v1 record.
+const MAX_V1_ADDR_LEN: usize = 64;
+const MAX_CLIENT_VERSION_LEN: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Direction {
     Inbound = 0,
     Outbound = 1,
 }
 
+#[derive(Debug, Clone)]
 pub struct CaptureEntry {
     pub direction: Direction,
-    pub timestamp_ms: u64,
+    /// Microseconds since the Unix epoch.
+    pub timestamp_us: u64,
     pub addr: SocketAddr,
     pub data: Vec<u8>,
 }
 
+/// ISAAC seeds handed out in a ConnectRequest, recorded so checksums can be verified
+/// without re-parsing the handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionKeys {
+    pub timestamp_us: u64,
+    pub server_seed: u32,
+    pub client_seed: u32,
+}
+
+#[derive(Debug, Clone)]
+pub enum CaptureRecord {
+    Packet(CaptureEntry),
+    SessionKeys(SessionKeys),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureHeader {
+    pub version: u16,
+    pub flags: u16,
+    /// Microseconds since the Unix epoch.
+    pub started_at_us: u64,
+    /// `account_hash` of the account name, so captures can be grouped without naming it.
+    pub account_hash: u64,
+    pub server: SocketAddr,
+    pub client_version: String,
+}
+
+impl CaptureHeader {
+    pub fn new(server: SocketAddr, account: &str) -> Self {
+        Self {
+            version: CAPTURE_VERSION,
+            flags: 0,
+            started_at_us: now_us(),
+            account_hash: account_hash(account),
+            server,
+            client_version: crate::protocol::messages::CLIENT_VERSION.to_string(),
+        }
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.flags & FLAG_COMPRESSED != 0
+    }
+
+    fn write(&self, w: &mut impl Write) -> Result<()> {
+        w.write_all(&MAGIC)?;
+        w.write_u16::<LittleEndian>(self.version)?;
+        w.write_u16::<LittleEndian>(self.flags)?;
+        w.write_u64::<LittleEndian>(self.started_at_us)?;
+        w.write_u64::<LittleEndian>(self.account_hash)?;
+        write_addr(w, &self.server)?;
+        w.write_u16::<LittleEndian>(self.client_version.len() as u16)?;
+        w.write_all(self.client_version.as_bytes())?;
+        Ok(())
+    }
+
+    /// Reads the header following `MAGIC`.
+    fn read(r: &mut impl Read) -> Result<Self> {
+        let version = r.read_u16::<LittleEndian>()?;
+        if version != CAPTURE_VERSION {
+            return Err(anyhow!("Unsupported capture version {}", version));
+        }
+        let flags = r.read_u16::<LittleEndian>()?;
+        let started_at_us = r.read_u64::<LittleEndian>()?;
+        let account_hash = r.read_u64::<LittleEndian>()?;
+        let server = read_addr(r)?;
+        let len = r.read_u16::<LittleEndian>()? as usize;
+        let client_version = read_bytes(r, len, MAX_CLIENT_VERSION_LEN, "client version")?;
+        Ok(Self {
+            version,
+            flags,
+            started_at_us,
+            account_hash,
+            server,
+            client_version: String::from_utf8_lossy(&client_version).into_owned(),
+        })
+    }
+}
+
+/// Stable 64-bit FNV-1a hash of a lower-cased account name.
+pub fn account_hash(account: &str) -> u64 {
+    account
+        .to_lowercase()
+        .bytes()
+        .fold(0xcbf29ce484222325, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+fn now_us() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64
+}
+
+/// Reads `len` bytes, refusing lengths above `max` before allocating anything.
+fn read_bytes(r: &mut impl Read, len: usize, max: usize, what: &str) -> Result<Vec<u8>> {
+    if len > max {
+        return Err(anyhow!(
+            "Capture {} length {} exceeds {} bytes",
+            what,
+            len,
+            max
+        ));
+    }
+    let mut bytes = vec![0u8; len];
+    r.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn write_addr(w: &mut impl Write, addr: &SocketAddr) -> Result<()> {
+    match addr.ip() {
+        IpAddr::V4(ip) => {
+            w.write_u8(4)?;
+            w.write_all(&ip.octets())?;
+        }
+        IpAddr::V6(ip) => {
+            w.write_u8(6)?;
+            w.write_all(&ip.octets())?;
+        }
+    }
+    w.write_u16::<LittleEndian>(addr.port())?;
+    Ok(())
+}
+
+fn read_addr(r: &mut impl Read) -> Result<SocketAddr> {
+    let ip = match r.read_u8()? {
+        4 => {
+            let mut octets = [0u8; 4];
+            r.read_exact(&mut octets)?;
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        6 => {
+            let mut octets = [0u8; 16];
+            r.read_exact(&mut octets)?;
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        family => return Err(anyhow!("Invalid address family {}", family)),
+    };
+    let port = r.read_u16::<LittleEndian>()?;
+    Ok(SocketAddr::new(ip, port))
+}
+
+enum CaptureOutput {
+    Plain(File),
+    Compressed(ZlibEncoder<File>),
+}
+
+impl Write for CaptureOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            CaptureOutput::Plain(file) => file.write(buf),
+            CaptureOutput::Compressed(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            CaptureOutput::Plain(file) => file.flush(),
+            CaptureOutput::Compressed(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Writes a capture file. A compressed stream is finished by `finish`, or when the writer
+/// is dropped (ignoring errors).
 pub struct CaptureWriter {
-    file: File,
+    out: CaptureOutput,
+    scrub: ScrubLevel,
+    /// Account name from the last LOGIN_REQUEST, masked in later messages.
+    account: String,
+    /// Records written since the last flush of a compressed capture.
+    unflushed: usize,
+    last_flush: Instant,
 }
 
 impl CaptureWriter {
-    pub fn create(path: &str) -> Result<Self> {
-        let file = File::create(path)?;
-        Ok(Self { file })
+    pub fn create(path: &str, mut header: CaptureHeader, compress: bool) -> Result<Self> {
+        let mut file = File::create(path)?;
+        if compress {
+            header.flags |= FLAG_COMPRESSED;
+        }
+        header.write(&mut file)?;
+        file.flush()?;
+        let out = if compress {
+            CaptureOutput::Compressed(ZlibEncoder::new(file, Compression::default()))
+        } else {
+            CaptureOutput::Plain(file)
+        };
+        Ok(Self {
+            out,
+            scrub: ScrubLevel::default(),
+            account: String::new(),
+            unflushed: 0,
+            last_flush: Instant::now(),
+        })
+    }
+
+    /// What to redact from written packets. Defaults to the login password; `Account` also
+    /// masks the account name in the LOGIN_REQUEST and every message after it.
+    pub fn set_scrub(&mut self, scrub: ScrubLevel) {
+        self.scrub = scrub;
     }
 
     pub fn write_entry(
@@ -34,91 +268,389 @@ impl CaptureWriter {
         addr: SocketAddr,
         data: &[u8],
     ) -> Result<()> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis() as u64;
+        self.write_record(&CaptureRecord::Packet(CaptureEntry {
+            direction,
+            timestamp_us: now_us(),
+            addr,
+            data: data.to_vec(),
+        }))
+    }
 
-        self.file.write_u8(direction as u8)?;
-        self.file.write_u64::<LittleEndian>(now)?;
+    pub fn write_session_keys(&mut self, server_seed: u32, client_seed: u32) -> Result<()> {
+        self.write_record(&CaptureRecord::SessionKeys(SessionKeys {
+            timestamp_us: now_us(),
+            server_seed,
+            client_seed,
+        }))
+    }
 
-        let addr_str = addr.to_string();
-        self.file.write_u16::<LittleEndian>(addr_str.len() as u16)?;
-        self.file.write_all(addr_str.as_bytes())?;
+    /// Writes a record with its own timestamp, e.g. when converting from another format.
+    pub fn write_record(&mut self, record: &CaptureRecord) -> Result<()> {
+        match record {
+            CaptureRecord::Packet(entry) => {
+                let kind = match entry.direction {
+                    Direction::Inbound => RECORD_INBOUND,
+                    Direction::Outbound => RECORD_OUTBOUND,
+                };
+                let scrubbed = self.scrub_packet(entry);
+                let data = scrubbed.as_deref().unwrap_or(&entry.data);
+                self.out.write_u8(kind)?;
+                self.out.write_u64::<LittleEndian>(entry.timestamp_us)?;
+                write_addr(&mut self.out, &entry.addr)?;
+                self.out.write_u32::<LittleEndian>(data.len() as u32)?;
+                self.out.write_all(data)?;
+            }
+            CaptureRecord::SessionKeys(keys) => {
+                self.out.write_u8(RECORD_SESSION_KEYS)?;
+                self.out.write_u64::<LittleEndian>(keys.timestamp_us)?;
+                self.out.write_u32::<LittleEndian>(keys.server_seed)?;
+                self.out.write_u32::<LittleEndian>(keys.client_seed)?;
+            }
+        }
 
-        self.file.write_u32::<LittleEndian>(data.len() as u32)?;
-        self.file.write_all(data)?;
+        // Uncompressed records go straight to the file, so it stays readable if we crash
+        if let CaptureOutput::Compressed(encoder) = &mut self.out {
+            self.unflushed += 1;
+            if self.unflushed >= COMPRESSED_FLUSH_RECORDS
+                || self.last_flush.elapsed() >= COMPRESSED_FLUSH_INTERVAL
+            {
+                encoder.flush()?;
+                self.unflushed = 0;
+                self.last_flush = Instant::now();
+            }
+        }
+        Ok(())
+    }
 
-        self.file.flush()?;
+    /// Ends the capture, finishing the compressed stream.
+    pub fn finish(self) -> Result<()> {
+        match self.out {
+            CaptureOutput::Plain(mut file) => file.flush()?,
+            CaptureOutput::Compressed(encoder) => {
+                encoder.finish()?;
+            }
+        }
         Ok(())
     }
-}
 
-pub struct CaptureReader {
-    file: File,
+    fn scrub_packet(&mut self, entry: &CaptureEntry) -> Option<Vec<u8>> {
+        if entry.direction == Direction::Outbound
+            && let Some(login) = scrub::login_request(&entry.data)
+        {
+            self.account = login.account;
+            return scrub::scrub_login_packet(&entry.data, self.scrub);
+        }
+        scrub::scrub_packet_messages(&entry.data, &self.account, self.scrub)
+    }
 }
 
-pub struct ReplayTransport {
-    pub reader: std::sync::Arc<std::sync::Mutex<CaptureReader>>,
+pub struct CaptureReader {
+    input: Box<dyn Read + Send>,
+    header: Option<CaptureHeader>,
+    /// Records converted up front from a pcap/pcapng file.
+    imported: VecDeque<CaptureRecord>,
 }
 
-#[crate::session::async_trait]
-impl crate::session::Transport for ReplayTransport {
-    async fn send_to(&self, _buf: &[u8], _addr: SocketAddr) -> anyhow::Result<usize> {
-        Ok(_buf.len())
-    }
-
-    async fn recv_from(&self, buf: &mut [u8]) -> anyhow::Result<(usize, SocketAddr)> {
-        let mut reader = self
-            .reader
-            .lock()
-            .map_err(|_| anyhow::anyhow!("Lock poisoned"))?;
-        while let Some(entry) = reader.read_next()? {
-            if entry.direction == Direction::Inbound {
-                let len = entry.data.len().min(buf.len());
-                buf[..len].copy_from_slice(&entry.data[..len]);
-                return Ok((len, entry.addr));
-            }
+/// Address of the first inbound packet in a capture, i.e. the server the client started with.
+pub fn first_inbound_addr(path: &str) -> Result<Option<SocketAddr>> {
+    let mut reader = CaptureReader::open(path)?;
+    while let Some(entry) = reader.read_next()? {
+        if entry.direction == Direction::Inbound {
+            return Ok(Some(entry.addr));
         }
-        Err(anyhow::anyhow!("End of capture"))
     }
+    Ok(None)
 }
 
 impl CaptureReader {
+    /// Opens a v2 capture, a headerless v1 capture, or a pcap/pcapng file (which is
+    /// converted on the fly, see `pcap::import`).
     pub fn open(path: &str) -> Result<Self> {
-        let file = File::open(path)?;
-        Ok(Self { file })
+        let mut file = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; 4];
+        let has_magic = match file.read_exact(&mut magic) {
+            Ok(()) => true,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => false,
+            Err(e) => return Err(e.into()),
+        };
+        if has_magic && pcap::is_pcap(&magic) {
+            let mut data = magic.to_vec();
+            file.read_to_end(&mut data)?;
+            let (header, records) = pcap::import(&data)?;
+            return Ok(Self {
+                input: Box::new(std::io::empty()),
+                header: Some(header),
+                imported: records.into(),
+            });
+        }
+        if !has_magic || magic != MAGIC {
+            file.seek(SeekFrom::Start(0))?;
+            return Ok(Self {
+                input: Box::new(file),
+                header: None,
+                imported: VecDeque::new(),
+            });
+        }
+
+        let header = CaptureHeader::read(&mut file)?;
+        let input: Box<dyn Read + Send> = if header.is_compressed() {
+            Box::new(ZlibDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+        Ok(Self {
+            input,
+            header: Some(header),
+            imported: VecDeque::new(),
+        })
+    }
+
+    /// The file header, or `None` for a v1 capture.
+    pub fn header(&self) -> Option<&CaptureHeader> {
+        self.header.as_ref()
+    }
+
+    pub fn version(&self) -> u16 {
+        self.header.as_ref().map_or(1, |h| h.version)
     }
 
+    /// Reads the next packet, skipping any other records.
     pub fn read_next(&mut self) -> Result<Option<CaptureEntry>> {
-        let direction_u8 = match self.file.read_u8() {
+        while let Some(record) = self.read_record()? {
+            if let CaptureRecord::Packet(entry) = record {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn read_record(&mut self) -> Result<Option<CaptureRecord>> {
+        if let Some(record) = self.imported.pop_front() {
+            return Ok(Some(record));
+        }
+        let kind = match self.input.read_u8() {
             Ok(b) => b,
             Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
             Err(e) => return Err(e.into()),
         };
+        if self.header.is_none() {
+            return self
+                .read_v1_entry(kind)
+                .map(|e| Some(CaptureRecord::Packet(e)));
+        }
 
+        let timestamp_us = self.input.read_u64::<LittleEndian>()?;
+        match kind {
+            RECORD_INBOUND | RECORD_OUTBOUND => {
+                let addr = read_addr(&mut self.input)?;
+                let data_len = self.input.read_u32::<LittleEndian>()? as usize;
+                let data = read_bytes(&mut self.input, data_len, MAX_RECORD_DATA_LEN, "packet")?;
+                let direction = if kind == RECORD_INBOUND {
+                    Direction::Inbound
+                } else {
+                    Direction::Outbound
+                };
+                Ok(Some(CaptureRecord::Packet(CaptureEntry {
+                    direction,
+                    timestamp_us,
+                    addr,
+                    data,
+                })))
+            }
+            RECORD_SESSION_KEYS => Ok(Some(CaptureRecord::SessionKeys(SessionKeys {
+                timestamp_us,
+                server_seed: self.input.read_u32::<LittleEndian>()?,
+                client_seed: self.input.read_u32::<LittleEndian>()?,
+            }))),
+            kind => Err(anyhow!("Unknown capture record type {}", kind)),
+        }
+    }
+
+    fn read_v1_entry(&mut self, direction_u8: u8) -> Result<CaptureEntry> {
         let direction = if direction_u8 == 0 {
             Direction::Inbound
         } else {
             Direction::Outbound
         };
-        let timestamp_ms = self.file.read_u64::<LittleEndian>()?;
+        let timestamp_ms = self.input.read_u64::<LittleEndian>()?;
 
-        let addr_len = self.file.read_u16::<LittleEndian>()? as usize;
-        let mut addr_buf = vec![0u8; addr_len];
-        self.file.read_exact(&mut addr_buf)?;
+        let addr_len = self.input.read_u16::<LittleEndian>()? as usize;
+        let addr_buf = read_bytes(&mut self.input, addr_len, MAX_V1_ADDR_LEN, "address")?;
         let addr_str = String::from_utf8_lossy(&addr_buf);
         let addr: SocketAddr = addr_str.parse().map_err(|_| anyhow!("Invalid address"))?;
 
-        let data_len = self.file.read_u32::<LittleEndian>()? as usize;
-        let mut data = vec![0u8; data_len];
-        self.file.read_exact(&mut data)?;
+        let data_len = self.input.read_u32::<LittleEndian>()? as usize;
+        let data = read_bytes(&mut self.input, data_len, MAX_RECORD_DATA_LEN, "packet")?;
 
-        Ok(Some(CaptureEntry {
+        Ok(CaptureEntry {
             direction,
-            timestamp_ms,
+            timestamp_us: timestamp_ms * 1000,
             addr,
             data,
-        }))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("holtburger-{}-{}.cap", name, std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn write_sample(path: &str, compress: bool) -> CaptureHeader {
+        let server: SocketAddr = "10.0.0.1:9000".parse().unwrap();
+        let header = CaptureHeader::new(server, "Tester");
+        let mut writer = CaptureWriter::create(path, header.clone(), compress).unwrap();
+        writer
+            .write_entry(Direction::Outbound, server, &[1, 2, 3])
+            .unwrap();
+        writer.write_session_keys(0xC83824AB, 0xFBD52C87).unwrap();
+        writer
+            .write_entry(Direction::Inbound, "[::1]:9001".parse().unwrap(), &[4, 5])
+            .unwrap();
+        header
+    }
+
+    fn read_all(path: &str) -> (CaptureReader, Vec<CaptureRecord>) {
+        let mut reader = CaptureReader::open(path).unwrap();
+        let records = std::iter::from_fn(|| reader.read_record().unwrap()).collect();
+        (reader, records)
+    }
+
+    #[test]
+    fn test_v2_roundtrip() {
+        for compress in [false, true] {
+            let path = temp_path(&format!("v2-{}", compress));
+            let written = write_sample(&path, compress);
+            let (reader, records) = read_all(&path);
+            std::fs::remove_file(&path).unwrap();
+
+            let header = reader.header().unwrap();
+            assert_eq!(header.is_compressed(), compress);
+            assert_eq!(header.started_at_us, written.started_at_us);
+            assert_eq!(header.account_hash, account_hash("tester"));
+            assert_eq!(header.server, written.server);
+            assert_eq!(header.client_version, "1802");
+
+            assert_eq!(records.len(), 3);
+            let CaptureRecord::Packet(first) = &records[0] else {
+                panic!("Expected a packet");
+            };
+            assert_eq!(first.direction, Direction::Outbound);
+            assert_eq!(first.data, vec![1, 2, 3]);
+            assert!(first.timestamp_us >= header.started_at_us);
+            let CaptureRecord::SessionKeys(keys) = &records[1] else {
+                panic!("Expected session keys");
+            };
+            assert_eq!(
+                (keys.server_seed, keys.client_seed),
+                (0xC83824AB, 0xFBD52C87)
+            );
+            let CaptureRecord::Packet(last) = &records[2] else {
+                panic!("Expected a packet");
+            };
+            assert_eq!(last.addr, "[::1]:9001".parse().unwrap());
+            assert_eq!(last.data, vec![4, 5]);
+        }
+    }
+
+    #[test]
+    fn test_compressed_capture_flushes_in_batches() {
+        let path = temp_path("batched");
+        let server: SocketAddr = "10.0.0.1:9000".parse().unwrap();
+        let header = CaptureHeader::new(server, "Tester");
+        let mut header_bytes = Vec::new();
+        let mut compressed = header.clone();
+        compressed.flags |= FLAG_COMPRESSED;
+        compressed.write(&mut header_bytes).unwrap();
+
+        let mut writer = CaptureWriter::create(&path, header, true).unwrap();
+        let packet = [0x42u8; 100];
+        for _ in 0..COMPRESSED_FLUSH_RECORDS - 1 {
+            writer
+                .write_entry(Direction::Inbound, server, &packet)
+                .unwrap();
+        }
+        // Nothing past the header reaches the file until a batch is complete
+        let written = std::fs::metadata(&path).unwrap().len() as usize;
+        assert!(written <= header_bytes.len() + 2, "{}", written);
+
+        writer
+            .write_entry(Direction::Inbound, server, &packet)
+            .unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() as usize > written);
+        writer.finish().unwrap();
+
+        let (_, records) = read_all(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(records.len(), COMPRESSED_FLUSH_RECORDS);
+    }
+
+    #[test]
+    fn test_rejects_oversized_lengths() {
+        let path = temp_path("oversized");
+        let server: SocketAddr = "10.0.0.1:9000".parse().unwrap();
+        let mut header = CaptureHeader::new(server, "Tester");
+        let mut file = Vec::new();
+        header.write(&mut file).unwrap();
+        file.write_u8(RECORD_INBOUND).unwrap();
+        file.write_u64::<LittleEndian>(0).unwrap();
+        write_addr(&mut file, &server).unwrap();
+        file.write_u32::<LittleEndian>(u32::MAX).unwrap();
+        std::fs::write(&path, &file).unwrap();
+        let mut reader = CaptureReader::open(&path).unwrap();
+        assert!(reader.read_record().is_err());
+
+        header.client_version = "x".repeat(MAX_CLIENT_VERSION_LEN + 1);
+        let mut file = Vec::new();
+        header.write(&mut file).unwrap();
+        std::fs::write(&path, &file).unwrap();
+        assert!(CaptureReader::open(&path).is_err());
+
+        // A v1 record with a garbage address length
+        let mut v1 = vec![0u8];
+        v1.write_u64::<LittleEndian>(0).unwrap();
+        v1.write_u16::<LittleEndian>(u16::MAX).unwrap();
+        std::fs::write(&path, &v1).unwrap();
+        let mut reader = CaptureReader::open(&path).unwrap();
+        assert!(reader.read_record().is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_reads_v1_captures() {
+        let path = temp_path("v1");
+        let mut v1 = Vec::new();
+        for (direction, data) in [(1u8, &[9u8, 9][..]), (0, &[7])] {
+            let addr = "127.0.0.1:9000";
+            v1.write_u8(direction).unwrap();
+            v1.write_u64::<LittleEndian>(1_700_000_000_000).unwrap();
+            v1.write_u16::<LittleEndian>(addr.len() as u16).unwrap();
+            v1.write_all(addr.as_bytes()).unwrap();
+            v1.write_u32::<LittleEndian>(data.len() as u32).unwrap();
+            v1.write_all(data).unwrap();
+        }
+        std::fs::write(&path, v1).unwrap();
+
+        let mut reader = CaptureReader::open(&path).unwrap();
+        assert!(reader.header().is_none());
+        assert_eq!(reader.version(), 1);
+        let first = reader.read_next().unwrap().unwrap();
+        assert_eq!(first.direction, Direction::Outbound);
+        assert_eq!(first.timestamp_us, 1_700_000_000_000_000);
+        assert_eq!(first.data, vec![9, 9]);
+        assert_eq!(reader.read_next().unwrap().unwrap().data, vec![7]);
+        assert!(reader.read_next().unwrap().is_none());
+
+        assert_eq!(
+            first_inbound_addr(&path).unwrap(),
+            Some("127.0.0.1:9000".parse().unwrap())
+        );
+        std::fs::remove_file(&path).unwrap();
     }
 }
@@ -0,0 +1,318 @@
+//! In-memory transports for driving a `Session` without a network.
+//!
+//! `LoopbackTransport::pair` gives two connected ends; `ImpairedTransport` wraps any
+//! transport and degrades what it sends (loss, duplication, reordering, latency and
+//! corruption) using a seeded RNG so that test runs are reproducible.
+
+use crate::session::{Transport, async_trait};
+use anyhow::{Result, anyhow};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+type Datagram = (Vec<u8>, SocketAddr);
+
+/// One end of an in-memory datagram link. Everything sent arrives at the other end,
+/// appearing to come from this end's address; the destination address is ignored.
+pub struct LoopbackTransport {
+    local_addr: SocketAddr,
+    tx: mpsc::UnboundedSender<Datagram>,
+    rx: tokio::sync::Mutex<mpsc::UnboundedReceiver<Datagram>>,
+}
+
+impl LoopbackTransport {
+    pub fn pair(a: SocketAddr, b: SocketAddr) -> (Self, Self) {
+        let (a_tx, b_rx) = mpsc::unbounded_channel();
+        let (b_tx, a_rx) = mpsc::unbounded_channel();
+        (
+            Self {
+                local_addr: a,
+                tx: a_tx,
+                rx: tokio::sync::Mutex::new(a_rx),
+            },
+            Self {
+                local_addr: b,
+                tx: b_tx,
+                rx: tokio::sync::Mutex::new(b_rx),
+            },
+        )
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+#[async_trait]
+impl Transport for LoopbackTransport {
+    async fn send_to(&self, buf: &[u8], _addr: SocketAddr) -> Result<usize> {
+        self.tx
+            .send((buf.to_vec(), self.local_addr))
+            .map_err(|_| anyhow!("Loopback peer closed"))?;
+        Ok(buf.len())
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        let (data, from) = self
+            .rx
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or_else(|| anyhow!("Loopback peer closed"))?;
+        let len = data.len().min(buf.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        Ok((len, from))
+    }
+}
+
+/// Probabilities (0.0 to 1.0) and delays applied to each outbound datagram.
+#[derive(Debug, Clone, Default)]
+pub struct Impairment {
+    pub loss: f64,
+    pub duplicate: f64,
+    /// Chance a datagram is held back and sent after the next one.
+    pub reorder: f64,
+    /// Chance a single bit of the datagram is flipped.
+    pub corrupt: f64,
+    pub latency: Duration,
+    /// Extra random delay of up to this much on top of `latency`.
+    pub jitter: Duration,
+}
+
+/// Counts of what an `ImpairedTransport` did to the traffic passing through it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImpairmentStats {
+    pub sent: u64,
+    pub dropped: u64,
+    pub duplicated: u64,
+    pub reordered: u64,
+    pub corrupted: u64,
+}
+
+struct ImpairmentState {
+    rng: StdRng,
+    held: Vec<Datagram>,
+    stats: ImpairmentStats,
+}
+
+/// Wraps a transport and impairs what it sends. Receiving is passed straight through,
+/// so wrap the sending end of whichever direction should be degraded.
+pub struct ImpairedTransport<T> {
+    inner: Arc<T>,
+    impairment: Impairment,
+    state: Mutex<ImpairmentState>,
+}
+
+impl<T: Transport + 'static> ImpairedTransport<T> {
+    pub fn new(inner: T, impairment: Impairment, seed: u64) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            impairment,
+            state: Mutex::new(ImpairmentState {
+                rng: StdRng::seed_from_u64(seed),
+                held: Vec::new(),
+                stats: ImpairmentStats::default(),
+            }),
+        }
+    }
+
+    pub fn stats(&self) -> ImpairmentStats {
+        self.state.lock().unwrap().stats.clone()
+    }
+
+    /// The wrapped transport, for traffic that should bypass the impairment.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Sends any datagram still being held back for reordering.
+    pub async fn flush(&self) -> Result<()> {
+        let held = std::mem::take(&mut self.state.lock().unwrap().held);
+        for (data, addr) in held {
+            self.inner.send_to(&data, addr).await?;
+        }
+        Ok(())
+    }
+
+    /// Decides the fate of one datagram. Returns the datagrams to deliver now, in order.
+    fn impair(&self, buf: &[u8], addr: SocketAddr) -> Vec<(Datagram, Duration)> {
+        let imp = &self.impairment;
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        state.stats.sent += 1;
+
+        let mut out = Vec::new();
+        if state.rng.gen_bool(imp.loss.clamp(0.0, 1.0)) {
+            state.stats.dropped += 1;
+        } else {
+            let mut data = buf.to_vec();
+            if !data.is_empty() && state.rng.gen_bool(imp.corrupt.clamp(0.0, 1.0)) {
+                let bit = state.rng.gen_range(0..data.len() * 8);
+                data[bit / 8] ^= 1 << (bit % 8);
+                state.stats.corrupted += 1;
+            }
+            if state.rng.gen_bool(imp.duplicate.clamp(0.0, 1.0)) {
+                state.stats.duplicated += 1;
+                out.push((data.clone(), addr));
+            }
+            out.push((data, addr));
+
+            if state.held.is_empty() && state.rng.gen_bool(imp.reorder.clamp(0.0, 1.0)) {
+                state.stats.reordered += 1;
+                state.held = out;
+                return Vec::new();
+            }
+        }
+
+        // A held-back datagram goes out behind whatever was sent after it
+        out.append(&mut state.held);
+        out.into_iter()
+            .map(|datagram| {
+                let jitter = if imp.jitter.is_zero() {
+                    Duration::ZERO
+                } else {
+                    imp.jitter.mul_f64(state.rng.r#gen::<f64>())
+                };
+                (datagram, imp.latency + jitter)
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<T: Transport + 'static> Transport for ImpairedTransport<T> {
+    async fn send_to(&self, buf: &[u8], addr: SocketAddr) -> Result<usize> {
+        for ((data, addr), delay) in self.impair(buf, addr) {
+            if delay.is_zero() {
+                self.inner.send_to(&data, addr).await?;
+            } else {
+                let inner = self.inner.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    let _ = inner.send_to(&data, addr).await;
+                });
+            }
+        }
+        Ok(buf.len())
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        self.inner.recv_from(buf).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs() -> (SocketAddr, SocketAddr) {
+        (
+            "127.0.0.1:50000".parse().unwrap(),
+            "127.0.0.1:9000".parse().unwrap(),
+        )
+    }
+
+    async fn drain(end: &LoopbackTransport) -> Vec<Vec<u8>> {
+        let mut received = Vec::new();
+        let mut buf = [0u8; 64];
+        while let Ok(Ok((len, _))) =
+            tokio::time::timeout(Duration::from_millis(10), end.recv_from(&mut buf)).await
+        {
+            received.push(buf[..len].to_vec());
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn test_loopback_pair_delivers_both_ways() {
+        let (client_addr, server_addr) = addrs();
+        let (client, server) = LoopbackTransport::pair(client_addr, server_addr);
+        let mut buf = [0u8; 16];
+
+        client.send_to(&[1, 2, 3], server_addr).await.unwrap();
+        let (len, from) = server.recv_from(&mut buf).await.unwrap();
+        assert_eq!((&buf[..len], from), (&[1u8, 2, 3][..], client_addr));
+
+        server.send_to(&[4], client_addr).await.unwrap();
+        let (len, from) = client.recv_from(&mut buf).await.unwrap();
+        assert_eq!((&buf[..len], from), (&[4u8][..], server_addr));
+    }
+
+    async fn run_impaired(impairment: Impairment, seed: u64) -> (Vec<Vec<u8>>, ImpairmentStats) {
+        let (client_addr, server_addr) = addrs();
+        let (client, server) = LoopbackTransport::pair(client_addr, server_addr);
+        let client = ImpairedTransport::new(client, impairment, seed);
+        for i in 0..32u8 {
+            client.send_to(&[i; 4], server_addr).await.unwrap();
+        }
+        client.flush().await.unwrap();
+        (drain(&server).await, client.stats())
+    }
+
+    #[tokio::test]
+    async fn test_impairment_is_deterministic_per_seed() {
+        let impairment = Impairment {
+            loss: 0.2,
+            duplicate: 0.2,
+            reorder: 0.2,
+            corrupt: 0.1,
+            ..Default::default()
+        };
+        let (first, stats) = run_impaired(impairment.clone(), 7).await;
+        let (second, _) = run_impaired(impairment.clone(), 7).await;
+        let (other, _) = run_impaired(impairment, 8).await;
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+
+        assert_eq!(stats.sent, 32);
+        assert!(stats.dropped > 0 && stats.duplicated > 0 && stats.reordered > 0);
+        assert_eq!(
+            first.len() as u64,
+            stats.sent - stats.dropped + stats.duplicated
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reorder_swaps_with_next_datagram() {
+        let (client_addr, server_addr) = addrs();
+        let (client, server) = LoopbackTransport::pair(client_addr, server_addr);
+        let client = ImpairedTransport::new(
+            client,
+            Impairment {
+                reorder: 1.0,
+                ..Default::default()
+            },
+            1,
+        );
+        for i in 0..4u8 {
+            client.send_to(&[i], server_addr).await.unwrap();
+        }
+        assert_eq!(
+            drain(&server).await,
+            vec![vec![1], vec![0], vec![3], vec![2]]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_latency_delays_delivery() {
+        let (client_addr, server_addr) = addrs();
+        let (client, server) = LoopbackTransport::pair(client_addr, server_addr);
+        let client = ImpairedTransport::new(
+            client,
+            Impairment {
+                latency: Duration::from_millis(200),
+                ..Default::default()
+            },
+            1,
+        );
+        let started = tokio::time::Instant::now();
+        client.send_to(&[9], server_addr).await.unwrap();
+        let mut buf = [0u8; 4];
+        server.recv_from(&mut buf).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+}
@@ -1,13 +1,25 @@
 pub mod capture;
-
-use crate::protocol::crypto::Isaac;
+pub mod decode;
+pub mod loopback;
+pub mod pcap;
+pub mod reliability;
+pub mod replay;
+pub mod scrub;
+pub mod stats;
+
+use crate::protocol::crypto::{Isaac, IsaacVerifier};
 use crate::protocol::messages::*;
-use crate::session::capture::{CaptureWriter, Direction};
+use crate::session::capture::{CaptureHeader, CaptureWriter, Direction};
+use crate::session::reliability::{ReorderBuffer, RetransmitWindow, SentPacket, is_sequenced};
+use crate::session::replay::{ReplayControl, ReplayTransport};
+use crate::session::scrub::ScrubLevel;
+use crate::session::stats::{ConnectionStats, MAX_PENDING_ECHOES};
 use anyhow::{Result, anyhow};
 pub use async_trait::async_trait;
 use byteorder::{ByteOrder, LittleEndian};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::net::SocketAddr;
+use std::time::{Duration, Instant};
 use tokio::net::UdpSocket;
 
 #[async_trait]
@@ -37,6 +49,163 @@ impl Transport for MockTransport {
     }
 }
 
+/// Calculates the payload checksum used by ACE: Sum of hashes for each component.
+pub fn calculate_payload_hash(flags: u32, payload: &[u8]) -> u32 {
+    let mut total_payload_checksum: u32 = 0;
+    let mut offset = 0;
+
+    // 1. Optional Headers Section (Follows ACE PacketHeaderOptional sequence)
+    // Sections are clamped to the payload so malformed inbound packets hash (and fail) rather than panic.
+    let mut header_optional_bytes = Vec::new();
+    let mut take = |len: usize, offset: &mut usize| {
+        let start = (*offset).min(payload.len());
+        let end = offset.saturating_add(len).min(payload.len());
+        header_optional_bytes.extend_from_slice(&payload[start..end]);
+        *offset = end;
+        &payload[start..end]
+    };
+
+    if flags & flags::SERVER_SWITCH != 0 {
+        take(8, &mut offset);
+    }
+    if flags & flags::REFERRAL != 0 {
+        take(REFERRAL_HEADER_SIZE, &mut offset);
+    }
+    if flags & flags::REQUEST_RETRANSMIT != 0 {
+        let count = take(4, &mut offset);
+        let count = if count.len() == 4 {
+            LittleEndian::read_u32(count) as usize
+        } else {
+            0
+        };
+        take(count.saturating_mul(4), &mut offset);
+    }
+    if flags & flags::REJECT_RETRANSMIT != 0 {
+        let count = take(4, &mut offset);
+        let count = if count.len() == 4 {
+            LittleEndian::read_u32(count) as usize
+        } else {
+            0
+        };
+        take(count.saturating_mul(4), &mut offset);
+    }
+    if flags & flags::ACK_SEQUENCE != 0 {
+        take(4, &mut offset);
+    }
+    if flags & flags::CONNECT_REQUEST != 0 {
+        take(32, &mut offset);
+    }
+    if flags & flags::LOGIN_REQUEST != 0 {
+        take(payload.len(), &mut offset);
+    }
+    if flags & flags::WORLD_LOGIN_REQUEST != 0 {
+        take(8, &mut offset);
+    }
+    if flags & flags::CONNECT_RESPONSE != 0 {
+        take(8, &mut offset);
+    }
+    if flags & flags::CICMD != 0 {
+        take(8, &mut offset);
+    }
+    if flags & flags::TIME_SYNC != 0 {
+        take(8, &mut offset);
+    }
+    if flags & flags::ECHO_REQUEST != 0 {
+        take(4, &mut offset);
+    }
+    if flags & flags::ECHO_RESPONSE != 0 {
+        take(8, &mut offset);
+    }
+    if flags & flags::FLOW != 0 {
+        take(6, &mut offset);
+    }
+
+    if !header_optional_bytes.is_empty() {
+        let h = crate::protocol::crypto::Hash32::compute(&header_optional_bytes);
+        total_payload_checksum = total_payload_checksum.wrapping_add(h);
+    }
+
+    // 2. Fragments Section
+    if flags & flags::BLOB_FRAGMENTS != 0 {
+        while offset < payload.len() {
+            if offset + FRAGMENT_HEADER_SIZE > payload.len() {
+                break;
+            }
+            // Fragment Header
+            let hh = crate::protocol::crypto::Hash32::compute(
+                &payload[offset..offset + FRAGMENT_HEADER_SIZE],
+            );
+            total_payload_checksum = total_payload_checksum.wrapping_add(hh);
+
+            let frag_header =
+                FragmentHeader::unpack(&payload[offset..offset + FRAGMENT_HEADER_SIZE]);
+            let frag_data_size = (frag_header.size as usize).saturating_sub(FRAGMENT_HEADER_SIZE);
+            offset += FRAGMENT_HEADER_SIZE;
+
+            // Fragment Data
+            if frag_data_size > 0 {
+                if offset + frag_data_size > payload.len() {
+                    break;
+                }
+                let dh = crate::protocol::crypto::Hash32::compute(
+                    &payload[offset..offset + frag_data_size],
+                );
+                total_payload_checksum = total_payload_checksum.wrapping_add(dh);
+                offset += frag_data_size;
+            }
+
+            let aligned_offset = (offset + 3) & !3;
+            offset = aligned_offset;
+        }
+    }
+
+    total_payload_checksum
+}
+
+/// Offset of the first fragment in a packet payload, past the optional headers.
+pub fn get_payload_offset(flags: u32, data: &[u8]) -> usize {
+    let mut offset = 0;
+    if flags & flags::SERVER_SWITCH != 0 {
+        offset += 8;
+    }
+    if flags & flags::REFERRAL != 0 {
+        offset += REFERRAL_HEADER_SIZE;
+    }
+    if flags & flags::REQUEST_RETRANSMIT != 0 && offset + 4 <= data.len() {
+        let count = LittleEndian::read_u32(&data[offset..offset + 4]);
+        offset += 4 + (count as usize * 4);
+    }
+    if flags & flags::REJECT_RETRANSMIT != 0 && offset + 4 <= data.len() {
+        let count = LittleEndian::read_u32(&data[offset..offset + 4]);
+        offset += 4 + (count as usize * 4);
+    }
+    if flags & flags::ACK_SEQUENCE != 0 {
+        offset += 4;
+    }
+    if flags & flags::WORLD_LOGIN_REQUEST != 0 {
+        offset += 8;
+    }
+    if flags & flags::CONNECT_RESPONSE != 0 {
+        offset += 8;
+    }
+    if flags & flags::CICMD != 0 {
+        offset += 8;
+    }
+    if flags & flags::TIME_SYNC != 0 {
+        offset += 8;
+    }
+    if flags & flags::ECHO_REQUEST != 0 {
+        offset += 4;
+    }
+    if flags & flags::ECHO_RESPONSE != 0 {
+        offset += 8;
+    }
+    if flags & flags::FLOW != 0 {
+        offset += 6;
+    }
+    offset
+}
+
 #[derive(Debug)]
 pub struct PendingMessage {
     pub count: u16,
@@ -44,188 +213,222 @@ pub struct PendingMessage {
     pub received_count: u16,
 }
 
+/// Splits the blob payload of a packet into its fragments. A fragment that overruns the
+/// packet ends the list.
+pub fn split_fragments(flags: u32, data: &[u8]) -> Vec<(FragmentHeader, &[u8])> {
+    let mut fragments = Vec::new();
+    if flags & flags::BLOB_FRAGMENTS == 0 {
+        return fragments;
+    }
+    let mut offset = get_payload_offset(flags, data);
+    while offset + FRAGMENT_HEADER_SIZE <= data.len() {
+        let header = FragmentHeader::unpack(&data[offset..offset + FRAGMENT_HEADER_SIZE]);
+        let size = (header.size as usize).saturating_sub(FRAGMENT_HEADER_SIZE);
+        offset += FRAGMENT_HEADER_SIZE;
+        if offset + size > data.len() {
+            break;
+        }
+        fragments.push((header, &data[offset..offset + size]));
+        offset += size;
+    }
+    fragments
+}
+
+/// Adds a fragment to the messages being reassembled, returning the message once all of
+/// its fragments have arrived.
+pub fn reassemble_fragment(
+    pending: &mut HashMap<u32, PendingMessage>,
+    header: &FragmentHeader,
+    data: &[u8],
+) -> Option<Vec<u8>> {
+    if header.count == 1 {
+        return Some(data.to_vec());
+    }
+
+    let entry = pending
+        .entry(header.sequence)
+        .or_insert_with(|| PendingMessage {
+            count: header.count,
+            fragments: vec![None; header.count as usize],
+            received_count: 0,
+        });
+
+    // SAFETY: Handle server restart or ID reuse with different fragment count
+    if header.count != entry.count {
+        log::warn!(
+            "Fragment count mismatch for Seq {}: expected {}, got {}. Resetting reassembler.",
+            header.sequence,
+            entry.count,
+            header.count
+        );
+        entry.count = header.count;
+        entry.fragments = vec![None; header.count as usize];
+        entry.received_count = 0;
+    }
+
+    if header.index >= entry.count {
+        return None;
+    }
+
+    if entry.fragments[header.index as usize].is_none() {
+        entry.fragments[header.index as usize] = Some(data.to_vec());
+        entry.received_count += 1;
+    }
+
+    if entry.received_count == entry.count {
+        let mut full_message = Vec::new();
+        let pending = pending.remove(&header.sequence)?;
+        for f in pending.fragments.into_iter().flatten() {
+            full_message.extend_from_slice(&f);
+        }
+        Some(full_message)
+    } else {
+        None
+    }
+}
+
 #[derive(Debug)]
 pub enum SessionEvent {
     Message(Vec<u8>),
     HandshakeRequest(ConnectRequestData),
-    HandshakeResponse { cookie: u64, client_id: u16 },
+    HandshakeResponse {
+        cookie: u64,
+        client_id: u16,
+    },
     TimeSync(f64),
+    /// The server is referring us to another endpoint (e.g. login to world).
+    ServerSwitch(ReferralHeader),
+    /// The server closed the connection.
+    Disconnect,
 }
 
 pub struct Session {
     transport: Box<dyn Transport>,
     pub server_addr: SocketAddr,
     pub isaac_c2s: Option<Isaac>,
-    pub isaac_s2c: Option<Isaac>,
+    pub isaac_s2c: Option<IsaacVerifier>,
     pub packet_sequence: u32,
     pub fragment_sequence: u32,
+    /// Sequence of the last game action sent.
+    pub action_sequence: u32,
     fragment_id: u32,
     // NetID/ClientID assigned by server
     pub client_id: u16,
     pub last_server_seq: u32,
     pub fragment_reassembler: HashMap<u32, PendingMessage>,
     pub capture: Option<CaptureWriter>,
+    /// Steers playback when the session is replaying a capture.
+    pub replay: Option<ReplayControl>,
+    /// Sent packets awaiting acknowledgement, kept for server retransmit requests.
+    pub retransmit_window: RetransmitWindow,
+    /// Inbound packets held back until they can be processed in sequence order.
+    pub reorder_buffer: ReorderBuffer,
+    /// Messages waiting for the next `flush`, so small ones can share packets.
+    outbound: Vec<GameMessage>,
+    pub stats: ConnectionStats,
+    started_at: Instant,
+    /// Echo requests awaiting a response: the f32 time we sent, as bits, and when. The
+    /// round trip is timed from the `Instant`, as the f32 loses precision over long sessions.
+    echoes_sent: VecDeque<(u32, Instant)>,
+    /// Bytes received since we last reported FLOW to the server.
+    flow_bytes: u32,
+    flow_started: Instant,
 }
 
 impl Session {
     pub async fn new(server_addr: SocketAddr) -> Result<Self> {
         let socket = UdpSocket::bind("0.0.0.0:0").await?;
-        Ok(Self {
-            transport: Box::new(socket),
-            server_addr,
-            isaac_c2s: None,
-            isaac_s2c: None,
-            packet_sequence: 0,
-            fragment_sequence: 1,
-            fragment_id: 1,
-            client_id: 0,
-            last_server_seq: 0,
-            fragment_reassembler: HashMap::new(),
-            capture: None,
-        })
+        Ok(Self::from_transport(Box::new(socket), server_addr))
     }
 
     pub fn new_replay(path: &str, server_addr: SocketAddr) -> Result<Self> {
-        let reader = capture::CaptureReader::open(path)?;
-        let transport = capture::ReplayTransport {
-            reader: std::sync::Arc::new(std::sync::Mutex::new(reader)),
+        let transport = ReplayTransport::open(path)?;
+        let control = transport.control();
+        let mut session = Self::from_transport(Box::new(transport), server_addr);
+        session.replay = Some(control);
+        Ok(session)
+    }
+
+    /// Points the session at a new server and drops all per-connection state, ready for a
+    /// fresh handshake. Capture and stats carry over.
+    pub fn switch_server(&mut self, server_addr: SocketAddr) {
+        log::info!("Switching server {} -> {}", self.server_addr, server_addr);
+        self.server_addr = server_addr;
+        self.isaac_c2s = None;
+        self.isaac_s2c = None;
+        self.packet_sequence = 0;
+        self.fragment_sequence = 1;
+        self.action_sequence = 0;
+        self.fragment_id = 1;
+        self.client_id = 0;
+        self.last_server_seq = 0;
+        self.fragment_reassembler.clear();
+        self.retransmit_window.clear();
+        self.reorder_buffer.clear();
+        self.outbound.clear();
+        self.echoes_sent.clear();
+    }
+
+    /// Presents the connection cookie from a referral to the world server.
+    pub async fn send_world_login_request(&mut self, cookie: u64) -> Result<()> {
+        let header = PacketHeader {
+            flags: flags::WORLD_LOGIN_REQUEST,
+            sequence: self.packet_sequence,
+            ..Default::default()
         };
-        Ok(Self {
-            transport: Box::new(transport),
+        self.packet_sequence += 1;
+        self.send_packet(header, &cookie.to_le_bytes()).await
+    }
+
+    pub fn from_transport(transport: Box<dyn Transport>, server_addr: SocketAddr) -> Self {
+        Self {
+            transport,
             server_addr,
             isaac_c2s: None,
             isaac_s2c: None,
             packet_sequence: 0,
             fragment_sequence: 1,
+            action_sequence: 0,
             fragment_id: 1,
             client_id: 0,
             last_server_seq: 0,
             fragment_reassembler: HashMap::new(),
             capture: None,
-        })
+            replay: None,
+            retransmit_window: RetransmitWindow::default(),
+            reorder_buffer: ReorderBuffer::default(),
+            outbound: Vec::new(),
+            stats: ConnectionStats::default(),
+            started_at: Instant::now(),
+            echoes_sent: VecDeque::new(),
+            flow_bytes: 0,
+            flow_started: Instant::now(),
+        }
     }
 
-    pub fn set_capture(&mut self, path: &str) -> Result<()> {
-        self.capture = Some(CaptureWriter::create(path)?);
+    pub fn set_capture(
+        &mut self,
+        path: &str,
+        account: &str,
+        compress: bool,
+        scrub: ScrubLevel,
+    ) -> Result<()> {
+        let header = CaptureHeader::new(self.server_addr, account);
+        let mut capture = CaptureWriter::create(path, header, compress)?;
+        capture.set_scrub(scrub);
+        self.capture = Some(capture);
         Ok(())
     }
 
     pub fn new_test() -> Self {
-        Session {
-            transport: Box::new(MockTransport),
-            server_addr: "127.0.0.1:9000".parse().unwrap(),
-            isaac_c2s: None,
-            isaac_s2c: None,
-            packet_sequence: 1,
-            fragment_sequence: 1,
-            fragment_id: 1,
-            fragment_reassembler: HashMap::new(),
-            client_id: 0,
-            last_server_seq: 0,
-            capture: None,
-        }
+        let mut session =
+            Self::from_transport(Box::new(MockTransport), "127.0.0.1:9000".parse().unwrap());
+        session.packet_sequence = 1;
+        session
     }
 
-    /// Calculates the payload checksum used by ACE: Sum of hashes for each component.
     fn calculate_payload_hash(&self, flags: u32, payload: &[u8]) -> u32 {
-        let mut total_payload_checksum: u32 = 0;
-        let mut offset = 0;
-
-        // 1. Optional Headers Section (Follows ACE PacketHeaderOptional sequence)
-        let mut header_optional_bytes = Vec::new();
-
-        if flags & flags::SERVER_SWITCH != 0 {
-            header_optional_bytes.extend_from_slice(&payload[offset..offset + 8]);
-            offset += 8;
-        }
-        if flags & flags::REQUEST_RETRANSMIT != 0 {
-            let count = LittleEndian::read_u32(&payload[offset..offset + 4]) as usize;
-            header_optional_bytes.extend_from_slice(&payload[offset..offset + 4 + (count * 4)]);
-            offset += 4 + (count * 4);
-        }
-        if flags & flags::REJECT_RETRANSMIT != 0 {
-            let count = LittleEndian::read_u32(&payload[offset..offset + 4]) as usize;
-            header_optional_bytes.extend_from_slice(&payload[offset..offset + 4 + (count * 4)]);
-            offset += 4 + (count * 4);
-        }
-        if flags & flags::ACK_SEQUENCE != 0 {
-            header_optional_bytes.extend_from_slice(&payload[offset..offset + 4]);
-            offset += 4;
-        }
-        if flags & flags::CONNECT_REQUEST != 0 {
-            header_optional_bytes.extend_from_slice(&payload[offset..offset + 32]);
-            offset += 32;
-        }
-        if flags & flags::LOGIN_REQUEST != 0 {
-            header_optional_bytes.extend_from_slice(&payload[offset..]);
-            offset = payload.len();
-        }
-        if flags & flags::CONNECT_RESPONSE != 0 {
-            header_optional_bytes.extend_from_slice(&payload[offset..offset + 8]);
-            offset += 8;
-        }
-        if flags & flags::CICMD != 0 {
-            header_optional_bytes.extend_from_slice(&payload[offset..offset + 8]);
-            offset += 8;
-        }
-        if flags & flags::TIME_SYNC != 0 {
-            header_optional_bytes.extend_from_slice(&payload[offset..offset + 8]);
-            offset += 8;
-        }
-        if flags & flags::ECHO_REQUEST != 0 {
-            header_optional_bytes.extend_from_slice(&payload[offset..offset + 4]);
-            offset += 4;
-        }
-        if flags & flags::ECHO_RESPONSE != 0 {
-            header_optional_bytes.extend_from_slice(&payload[offset..offset + 8]);
-            offset += 8;
-        }
-        if flags & flags::FLOW != 0 {
-            header_optional_bytes.extend_from_slice(&payload[offset..offset + 6]);
-            offset += 6;
-        }
-
-        if !header_optional_bytes.is_empty() {
-            let h = crate::protocol::crypto::Hash32::compute(&header_optional_bytes);
-            total_payload_checksum = total_payload_checksum.wrapping_add(h);
-        }
-
-        // 2. Fragments Section
-        if flags & flags::BLOB_FRAGMENTS != 0 {
-            while offset < payload.len() {
-                if offset + FRAGMENT_HEADER_SIZE > payload.len() {
-                    break;
-                }
-                // Fragment Header
-                let hh = crate::protocol::crypto::Hash32::compute(
-                    &payload[offset..offset + FRAGMENT_HEADER_SIZE],
-                );
-                total_payload_checksum = total_payload_checksum.wrapping_add(hh);
-
-                let frag_header =
-                    FragmentHeader::unpack(&payload[offset..offset + FRAGMENT_HEADER_SIZE]);
-                let frag_data_size =
-                    (frag_header.size as usize).saturating_sub(FRAGMENT_HEADER_SIZE);
-                offset += FRAGMENT_HEADER_SIZE;
-
-                // Fragment Data
-                if frag_data_size > 0 {
-                    if offset + frag_data_size > payload.len() {
-                        break;
-                    }
-                    let dh = crate::protocol::crypto::Hash32::compute(
-                        &payload[offset..offset + frag_data_size],
-                    );
-                    total_payload_checksum = total_payload_checksum.wrapping_add(dh);
-                    offset += frag_data_size;
-                }
-
-                let aligned_offset = (offset + 3) & !3;
-                offset = aligned_offset;
-            }
-        }
-
-        total_payload_checksum
+        calculate_payload_hash(flags, payload)
     }
 
     pub async fn send_packet(&mut self, header: PacketHeader, payload: &[u8]) -> Result<()> {
@@ -241,36 +444,70 @@ impl Session {
     ) -> Result<()> {
         let mut full_payload = Vec::new();
 
-        if self.last_server_seq > 0
+        // Callers that set ACK_SEQUENCE themselves supply the ack value in the payload.
+        let explicit_ack = header.flags & flags::ACK_SEQUENCE != 0;
+        if !explicit_ack
+            && self.last_server_seq > 0
             && (header.flags & flags::CONNECT_REQUEST == 0)
             && (header.flags & flags::CONNECT_RESPONSE == 0)
             && (header.flags & flags::LOGIN_REQUEST == 0)
+            && (header.flags & flags::WORLD_LOGIN_REQUEST == 0)
         {
             header.flags |= flags::ACK_SEQUENCE;
         }
 
-        if (header.flags & flags::ACK_SEQUENCE) != 0 {
+        // ACK_SEQUENCE sits after the server switch and retransmit lists in the optional headers.
+        let pre_ack_len = Self::pre_ack_headers_len(header.flags, payload);
+        full_payload.extend_from_slice(&payload[..pre_ack_len]);
+        if !explicit_ack && (header.flags & flags::ACK_SEQUENCE) != 0 {
             full_payload.extend_from_slice(&self.last_server_seq.to_le_bytes());
         }
-
-        full_payload.extend_from_slice(payload);
+        full_payload.extend_from_slice(&payload[pre_ack_len..]);
         header.size = full_payload.len() as u16;
 
         let is_handshake = (header.flags
-            & (flags::LOGIN_REQUEST | flags::CONNECT_REQUEST | flags::CONNECT_RESPONSE))
+            & (flags::LOGIN_REQUEST
+                | flags::WORLD_LOGIN_REQUEST
+                | flags::CONNECT_REQUEST
+                | flags::CONNECT_RESPONSE))
             != 0;
 
-        if let (Some(_), false) = (&mut self.isaac_c2s, is_handshake) {
-            header.flags |= flags::ENCRYPTED_CHECKSUM;
+        let isaac_key = match (&mut self.isaac_c2s, is_handshake) {
+            (Some(isaac), false) => {
+                header.flags |= flags::ENCRYPTED_CHECKSUM;
+                let key = isaac.current_key;
+                isaac.consume_key();
+                Some(key)
+            }
+            _ => None,
+        };
+
+        // Only packets carrying fragments occupy a reliable sequence slot.
+        if header.flags & flags::BLOB_FRAGMENTS != 0 {
+            self.retransmit_window.insert(SentPacket {
+                header: header.clone(),
+                payload: full_payload.clone(),
+                isaac_key,
+                sent_at: Instant::now(),
+                retransmits: 0,
+            });
         }
 
-        let header_hash = header.calculate_checksum();
-        let payload_hash = self.calculate_payload_hash(header.flags, &full_payload);
+        self.transmit(header, &full_payload, isaac_key, addr).await
+    }
 
-        if let (Some(isaac), false) = (&mut self.isaac_c2s, is_handshake) {
-            let key = isaac.current_key;
-            isaac.consume_key();
+    /// Checksums and sends a fully assembled packet. Does not touch any sequence or ISAAC state.
+    async fn transmit(
+        &mut self,
+        mut header: PacketHeader,
+        payload: &[u8],
+        isaac_key: Option<u32>,
+        addr: SocketAddr,
+    ) -> Result<()> {
+        let header_hash = header.calculate_checksum();
+        let payload_hash = self.calculate_payload_hash(header.flags, payload);
 
+        if let Some(key) = isaac_key {
             header.checksum = header_hash.wrapping_add(payload_hash ^ key);
             log::debug!(
                 ">>> Encrypted Send to {}: Seq={} ID={} Flags={:08X} FinalCRC={:08X}",
@@ -294,13 +531,76 @@ impl Session {
 
         let mut packet = vec![0u8; HEADER_SIZE];
         header.pack(&mut packet);
-        packet.extend_from_slice(&full_payload);
+        packet.extend_from_slice(payload);
 
         if let Some(ref mut capture) = self.capture {
             let _ = capture.write_entry(Direction::Outbound, addr, &packet);
         }
 
         self.transport.send_to(&packet, addr).await?;
+        self.stats.packets_sent += 1;
+        self.stats.bytes_sent += packet.len() as u64;
+        Ok(())
+    }
+
+    /// Length of the optional headers that precede ACK_SEQUENCE in a payload.
+    fn pre_ack_headers_len(flags: u32, payload: &[u8]) -> usize {
+        let mut offset = 0;
+        if flags & flags::SERVER_SWITCH != 0 {
+            offset += 8;
+        }
+        if flags & flags::REFERRAL != 0 {
+            offset += REFERRAL_HEADER_SIZE;
+        }
+        if flags & flags::REQUEST_RETRANSMIT != 0 {
+            read_sequence_list(payload, &mut offset);
+        }
+        if flags & flags::REJECT_RETRANSMIT != 0 {
+            read_sequence_list(payload, &mut offset);
+        }
+        offset.min(payload.len())
+    }
+
+    /// Resends the requested packets from the retransmit window with RETRANSMISSION set.
+    /// Sequences we no longer hold are reported back to the server as rejected.
+    pub async fn handle_retransmit_request(&mut self, sequences: &[u32]) -> Result<()> {
+        let mut rejected = Vec::new();
+
+        for &sequence in sequences {
+            let Some(sent) = self.retransmit_window.get_mut(sequence) else {
+                rejected.push(sequence);
+                continue;
+            };
+            sent.retransmits += 1;
+            sent.sent_at = Instant::now();
+
+            let mut header = sent.header.clone();
+            header.flags |= flags::RETRANSMISSION;
+            let payload = sent.payload.clone();
+            let isaac_key = sent.isaac_key;
+
+            log::debug!("Retransmitting Seq={} on server request", sequence);
+            self.stats.retransmits_sent += 1;
+            self.transmit(header, &payload, isaac_key, self.server_addr)
+                .await?;
+        }
+
+        if !rejected.is_empty() {
+            log::warn!(
+                "Rejecting retransmit request for unknown sequences {:?}",
+                rejected
+            );
+            let header = PacketHeader {
+                flags: flags::REJECT_RETRANSMIT,
+                sequence: 0,
+                id: self.client_id,
+                ..Default::default()
+            };
+            let mut payload = Vec::new();
+            write_sequence_list(&mut payload, &rejected);
+            self.send_packet(header, &payload).await?;
+        }
+
         Ok(())
     }
 
@@ -312,72 +612,112 @@ impl Session {
             header.count,
             data.len()
         );
-        if header.count == 1 {
-            return Some(data.to_vec());
-        }
+        reassemble_fragment(&mut self.fragment_reassembler, header, data)
+    }
 
-        let entry = self
-            .fragment_reassembler
-            .entry(header.sequence)
-            .or_insert_with(|| PendingMessage {
-                count: header.count,
-                fragments: vec![None; header.count as usize],
-                received_count: 0,
-            });
+    /// Sends a message straight away, after anything already queued.
+    pub async fn send_message(&mut self, message: &GameMessage) -> Result<()> {
+        self.queue_message(message.clone());
+        self.flush().await
+    }
 
-        // SAFETY: Handle server restart or ID reuse with different fragment count
-        if header.count != entry.count {
-            log::warn!(
-                "Fragment count mismatch for Seq {}: expected {}, got {}. Resetting reassembler.",
-                header.sequence,
-                entry.count,
-                header.count
-            );
-            entry.count = header.count;
-            entry.fragments = vec![None; header.count as usize];
-            entry.received_count = 0;
+    /// Sends a game action with the next action sequence, after anything already queued.
+    pub async fn send_action(&mut self, action: GameActionKind) -> Result<()> {
+        self.queue_action(action)?;
+        self.flush().await
+    }
+
+    /// Queues a message to go out with the next `flush`.
+    pub fn queue_message(&mut self, message: GameMessage) {
+        self.outbound.push(message);
+    }
+
+    /// Queues a game action with the next action sequence. `Unknown` actions only come from
+    /// parsing and are refused, so nothing malformed is sent under a real action type.
+    pub fn queue_action(&mut self, action: GameActionKind) -> Result<()> {
+        if let GameActionKind::Unknown { action, .. } = action {
+            return Err(anyhow!("Refusing to send unparsed action 0x{:04X}", action));
         }
+        self.action_sequence = self.action_sequence.wrapping_add(1);
+        self.queue_message(GameMessage::GameAction {
+            sequence: self.action_sequence,
+            action,
+        });
+        Ok(())
+    }
 
-        if header.index >= entry.count {
-            return None;
+    /// Sends every queued message, packing them into as few packets as possible.
+    pub async fn flush(&mut self) -> Result<()> {
+        if self.outbound.is_empty() {
+            return Ok(());
         }
+        let messages = std::mem::take(&mut self.outbound);
+        self.send_messages(&messages).await
+    }
 
-        if entry.fragments[header.index as usize].is_none() {
-            entry.fragments[header.index as usize] = Some(data.to_vec());
-            entry.received_count += 1;
+    /// Sends several messages, splitting large ones into fragments and packing small
+    /// fragments together so they share packets.
+    pub async fn send_messages(&mut self, messages: &[GameMessage]) -> Result<()> {
+        let mut fragments = Vec::new();
+        for message in messages {
+            log::debug!(">>> Outgoing Message: {:?}", message);
+            fragments.extend(self.fragment_message(&message.pack()));
         }
 
-        if entry.received_count == entry.count {
-            let mut full_message = Vec::new();
-            let pending = self.fragment_reassembler.remove(&header.sequence)?;
-            for f in pending.fragments.into_iter().flatten() {
-                full_message.extend_from_slice(&f);
+        // Leave room for the ACK_SEQUENCE header send_packet may insert.
+        let budget = MAX_PACKET_SIZE - HEADER_SIZE - 4;
+        let mut body: Vec<u8> = Vec::new();
+        let mut aligned = true;
+        for fragment in fragments {
+            // Only append after 4-byte aligned fragments so the layout is unambiguous
+            // whether or not the reader pads between fragments.
+            if !body.is_empty() && (!aligned || body.len() + fragment.len() > budget) {
+                self.send_fragment_packet(&body).await?;
+                body.clear();
             }
-            Some(full_message)
-        } else {
-            None
+            aligned = fragment.len() % 4 == 0;
+            body.extend_from_slice(&fragment);
         }
+        if !body.is_empty() {
+            self.send_fragment_packet(&body).await?;
+        }
+        Ok(())
     }
 
-    pub async fn send_message(&mut self, message: &GameMessage) -> Result<()> {
-        log::debug!(">>> Outgoing Message: {:?}", message);
-        let payload = message.pack();
-
-        let frag_header = FragmentHeader {
-            sequence: self.fragment_sequence,
-            id: self.fragment_id,
-            count: 1,
-            index: 0,
-            size: (payload.len() + FRAGMENT_HEADER_SIZE) as u16,
-            queue: queues::GENERAL,
+    /// Splits a packed message into fragments (header plus data) sharing one fragment sequence.
+    fn fragment_message(&mut self, payload: &[u8]) -> Vec<Vec<u8>> {
+        let chunks: Vec<&[u8]> = if payload.is_empty() {
+            vec![payload]
+        } else {
+            payload.chunks(MAX_FRAGMENT_DATA_SIZE).collect()
         };
+        let count = chunks.len() as u16;
+
+        let fragments = chunks
+            .into_iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let frag_header = FragmentHeader {
+                    sequence: self.fragment_sequence,
+                    id: self.fragment_id,
+                    count,
+                    index: index as u16,
+                    size: (chunk.len() + FRAGMENT_HEADER_SIZE) as u16,
+                    queue: queues::GENERAL,
+                };
+                let mut fragment = vec![0u8; FRAGMENT_HEADER_SIZE];
+                frag_header.pack(&mut fragment);
+                fragment.extend_from_slice(chunk);
+                fragment
+            })
+            .collect();
+
         self.fragment_sequence += 1;
         self.fragment_id += 1;
+        fragments
+    }
 
-        let mut body = vec![0u8; FRAGMENT_HEADER_SIZE];
-        frag_header.pack(&mut body);
-        body.extend_from_slice(&payload);
-
+    async fn send_fragment_packet(&mut self, body: &[u8]) -> Result<()> {
         let header = PacketHeader {
             flags: flags::BLOB_FRAGMENTS,
             sequence: self.packet_sequence,
@@ -386,7 +726,7 @@ impl Session {
         };
         self.packet_sequence += 1;
 
-        self.send_packet(header, &body).await
+        self.send_packet(header, body).await
     }
 
     pub async fn send_ack(&mut self, sequence: u32) -> Result<()> {
@@ -403,18 +743,96 @@ impl Session {
         self.send_packet(header, &payload).await
     }
 
-    pub async fn recv_packet(&mut self, buf: &mut [u8]) -> Result<(PacketHeader, Vec<u8>)> {
-        let (len, addr) = self.transport.recv_from(buf).await?;
-        if len < HEADER_SIZE {
-            return Err(anyhow!("Packet too short"));
+    /// Asks the server to resend packets we never received.
+    pub async fn send_retransmit_request(&mut self, sequences: &[u32]) -> Result<()> {
+        log::debug!("Requesting retransmit of {:?}", sequences);
+        let header = PacketHeader {
+            flags: flags::REQUEST_RETRANSMIT,
+            sequence: 0,
+            id: self.client_id,
+            ..Default::default()
+        };
+
+        let mut payload = Vec::new();
+        write_sequence_list(&mut payload, sequences);
+
+        self.send_packet(header, &payload).await
+    }
+
+    /// Checks an inbound packet's checksum. For encrypted packets this consumes the matching
+    /// server ISAAC key, wherever it falls in the keystream.
+    fn verify_checksum(&mut self, header: &PacketHeader, data: &[u8]) -> bool {
+        let header_hash = header.calculate_checksum();
+        let payload_hash = self.calculate_payload_hash(header.flags, data);
+
+        if header.flags & flags::ENCRYPTED_CHECKSUM == 0 {
+            return header.checksum == header_hash.wrapping_add(payload_hash);
         }
 
-        if let Some(ref mut capture) = self.capture {
-            let _ = capture.write_entry(Direction::Inbound, addr, &buf[..len]);
+        let Some(isaac) = self.isaac_s2c.as_mut() else {
+            return false;
+        };
+        let key = header.checksum.wrapping_sub(header_hash) ^ payload_hash;
+        isaac.try_consume(key)
+            || (header.flags & flags::RETRANSMISSION != 0 && isaac.was_consumed(key))
+    }
+
+    /// Sends an echo request to measure round-trip time, reporting FLOW alongside it.
+    pub async fn send_echo_request(&mut self) -> Result<()> {
+        let header = PacketHeader {
+            flags: flags::ECHO_REQUEST | flags::FLOW,
+            sequence: 0,
+            id: self.client_id,
+            ..Default::default()
+        };
+
+        let interval = self.flow_started.elapsed().as_secs().min(u16::MAX as u64) as u16;
+        let client_time = self.started_at.elapsed().as_secs_f64() as f32;
+        self.echoes_sent
+            .push_back((client_time.to_bits(), Instant::now()));
+        while self.echoes_sent.len() > MAX_PENDING_ECHOES {
+            self.echoes_sent.pop_front();
         }
+        let mut payload = Vec::with_capacity(10);
+        payload.extend_from_slice(&client_time.to_le_bytes());
+        payload.extend_from_slice(&self.flow_bytes.to_le_bytes());
+        payload.extend_from_slice(&interval.to_le_bytes());
+        self.flow_bytes = 0;
+        self.flow_started = Instant::now();
+
+        self.send_packet(header, &payload).await
+    }
+
+    pub async fn recv_packet(&mut self, buf: &mut [u8]) -> Result<(PacketHeader, Vec<u8>)> {
+        let (header, data, addr) = loop {
+            let (len, addr) = self.transport.recv_from(buf).await?;
+            if len < HEADER_SIZE {
+                return Err(anyhow!("Packet too short"));
+            }
 
-        let header = PacketHeader::unpack(&buf[..HEADER_SIZE]);
-        let data = buf[HEADER_SIZE..len].to_vec();
+            if let Some(ref mut capture) = self.capture {
+                let _ = capture.write_entry(Direction::Inbound, addr, &buf[..len]);
+            }
+
+            self.stats.packets_received += 1;
+            self.stats.bytes_received += len as u64;
+            self.flow_bytes = self.flow_bytes.wrapping_add(len as u32);
+
+            let header = PacketHeader::unpack(&buf[..HEADER_SIZE]);
+            let data = buf[HEADER_SIZE..len].to_vec();
+
+            if self.verify_checksum(&header, &data) {
+                break (header, data, addr);
+            }
+            self.stats.packets_rejected += 1;
+            log::warn!(
+                "Dropping packet from {} with bad checksum: Seq={} Flags={:08X} CRC={:08X}",
+                addr,
+                header.sequence,
+                header.flags,
+                header.checksum
+            );
+        };
 
         log::debug!(
             "<<< Inbound Packet from {}: Seq={} ID={} Flags={:08X} Size={}",
@@ -422,69 +840,97 @@ impl Session {
             header.sequence,
             header.id,
             header.flags,
-            len
+            HEADER_SIZE + data.len()
         );
 
-        if header.sequence > self.last_server_seq {
-            self.last_server_seq = header.sequence;
+        let optional = OptionalHeaders::unpack(header.flags, &data);
+        if let Some(acked) = optional.ack_sequence {
+            let released = self.retransmit_window.acknowledge(acked);
+            if released > 0 {
+                log::debug!("Server acked up to Seq={} ({} released)", acked, released);
+            }
+        }
+        if !optional.request_retransmit.is_empty()
+            && let Err(e) = self
+                .handle_retransmit_request(&optional.request_retransmit)
+                .await
+        {
+            log::warn!("Failed to resend requested packets: {}", e);
+        }
+
+        // Handle Transport-layer housekeeping (ordering, ACKs, retransmit requests)
+        let previous = self.reorder_buffer.last_contiguous();
+        if header.flags & flags::CONNECT_REQUEST != 0 {
+            // The ConnectRequest takes the sequence just before the server's first data packet
+            self.reorder_buffer
+                .start_at(header.sequence.wrapping_add(1));
+        }
+        if !optional.reject_retransmit.is_empty() {
+            self.reorder_buffer.reject(&optional.reject_retransmit);
+        }
+        let fresh = self.reorder_buffer.accept(header.clone(), data.clone());
+        if !fresh {
+            self.stats.duplicates_dropped += 1;
+        }
+        let contiguous = self.reorder_buffer.last_contiguous();
+        if let Some(sequence) = contiguous {
+            self.last_server_seq = sequence;
         }
 
-        if header.flags & flags::ENCRYPTED_CHECKSUM != 0
-            && let Some(isaac) = self.isaac_s2c.as_mut()
+        // Only ever ack the contiguous sequence; re-ack duplicates in case our ack was lost.
+        if let Some(sequence) = contiguous
+            && (contiguous != previous || (!fresh && is_sequenced(&header)))
         {
-            isaac.consume_key();
+            let _ = self.send_ack(sequence).await;
         }
 
-        // Handle Transport-layer housekeeping (ACKs)
-        if header.sequence > 0 && (header.flags & flags::ACK_SEQUENCE == 0) {
-            let _ = self.send_ack(header.sequence).await;
+        let missing = self.reorder_buffer.take_missing(Instant::now());
+        if !missing.is_empty() {
+            self.stats.retransmits_requested += missing.len() as u64;
+            let _ = self.send_retransmit_request(&missing).await;
+        }
+
+        if let Some(flow) = optional.flow {
+            self.stats.server_flow = Some(flow);
+        }
+
+        if let Some((client_time, holding)) = optional.echo_response {
+            let sent = self
+                .echoes_sent
+                .iter()
+                .position(|&(bits, _)| bits == client_time.to_bits());
+            if let Some(index) = sent {
+                let (_, sent_at) = self.echoes_sent[index];
+                // Earlier requests still waiting were lost or overtaken
+                self.echoes_sent.drain(..=index);
+                let holding = Duration::try_from_secs_f32(holding).unwrap_or_default();
+                let rtt = sent_at.elapsed().saturating_sub(holding);
+                log::debug!("Echo round trip {:.1}ms", rtt.as_secs_f64() * 1000.0);
+                self.stats.record_rtt(rtt);
+            } else {
+                log::debug!("Ignoring echo response for unknown time {}", client_time);
+            }
         }
 
         // ECHO_REQUEST Handling
-        if header.flags & flags::ECHO_REQUEST != 0 {
-            let mut resp = header.clone();
-            resp.flags = flags::ECHO_RESPONSE;
-            let _ = self.send_packet_to_addr(resp, &[], addr).await;
+        if let Some(client_time) = optional.echo_request {
+            let resp = PacketHeader {
+                flags: flags::ECHO_RESPONSE,
+                sequence: 0,
+                id: self.client_id,
+                ..Default::default()
+            };
+            let mut payload = Vec::with_capacity(8);
+            payload.extend_from_slice(&client_time.to_le_bytes());
+            payload.extend_from_slice(&0f32.to_le_bytes());
+            let _ = self.send_packet_to_addr(resp, &payload, addr).await;
         }
 
         Ok((header, data))
     }
 
     pub fn get_payload_offset(&self, flags: u32, data: &[u8]) -> usize {
-        let mut offset = 0;
-        if flags & flags::SERVER_SWITCH != 0 {
-            offset += 8;
-        }
-        if flags & flags::REQUEST_RETRANSMIT != 0 && offset + 4 <= data.len() {
-            let count = LittleEndian::read_u32(&data[offset..offset + 4]);
-            offset += 4 + (count as usize * 4);
-        }
-        if flags & flags::REJECT_RETRANSMIT != 0 && offset + 4 <= data.len() {
-            let count = LittleEndian::read_u32(&data[offset..offset + 4]);
-            offset += 4 + (count as usize * 4);
-        }
-        if flags & flags::ACK_SEQUENCE != 0 {
-            offset += 4;
-        }
-        if flags & flags::CONNECT_RESPONSE != 0 {
-            offset += 8;
-        }
-        if flags & flags::CICMD != 0 {
-            offset += 8;
-        }
-        if flags & flags::TIME_SYNC != 0 {
-            offset += 8;
-        }
-        if flags & flags::ECHO_REQUEST != 0 {
-            offset += 4;
-        }
-        if flags & flags::ECHO_RESPONSE != 0 {
-            offset += 8;
-        }
-        if flags & flags::FLOW != 0 {
-            offset += 6;
-        }
-        offset
+        get_payload_offset(flags, data)
     }
 
     /// Higher-level receiver that handles fragmentation and returns complete message payloads or handshake events.
@@ -498,6 +944,9 @@ impl Session {
             let offset = self.get_payload_offset(header.flags, &data);
             if offset + 32 <= data.len() {
                 let crd = ConnectRequestData::unpack(&data[offset..offset + 32]);
+                if let Some(ref mut capture) = self.capture {
+                    let _ = capture.write_session_keys(crd.server_seed, crd.client_seed);
+                }
                 events.push(SessionEvent::HandshakeRequest(crd));
             }
         }
@@ -514,57 +963,33 @@ impl Session {
             }
         }
 
-        // 3. Check for TimeSync
-        if header.flags & flags::TIME_SYNC != 0 {
-            let mut offset = 0;
-            // TimeSync is an optional header. We need to find its specific offset.
-            // PacketHeaderOptional sequence:
-            // SERVER_SWITCH (8), REQUEST_RETRANSMIT (4+4*n), REJECT_RETRANSMIT (4+4*n), ACK_SEQUENCE (4), CONNECT_RESPONSE (8), CICMD (8), TIME_SYNC (8)
-            if header.flags & flags::SERVER_SWITCH != 0 {
-                offset += 8;
-            }
-            if header.flags & flags::REQUEST_RETRANSMIT != 0 && offset + 4 <= data.len() {
-                let count = LittleEndian::read_u32(&data[offset..offset + 4]);
-                offset += 4 + (count as usize * 4);
-            }
-            if header.flags & flags::REJECT_RETRANSMIT != 0 && offset + 4 <= data.len() {
-                let count = LittleEndian::read_u32(&data[offset..offset + 4]);
-                offset += 4 + (count as usize * 4);
-            }
-            if header.flags & flags::ACK_SEQUENCE != 0 {
-                offset += 4;
-            }
-            if header.flags & flags::CONNECT_RESPONSE != 0 {
-                offset += 8;
-            }
-            if header.flags & flags::CICMD != 0 {
-                offset += 8;
-            }
-            if header.flags & flags::TIME_SYNC != 0 && offset + 8 <= data.len() {
-                let server_time = LittleEndian::read_f64(&data[offset..offset + 8]);
-                events.push(SessionEvent::TimeSync(server_time));
-            }
+        if header.flags & flags::DISCONNECT != 0 {
+            events.push(SessionEvent::Disconnect);
         }
 
-        // 4. Check for Blobs
-        if header.flags & flags::BLOB_FRAGMENTS != 0 {
-            let mut offset = self.get_payload_offset(header.flags, &data);
-            while offset + FRAGMENT_HEADER_SIZE <= data.len() {
-                let frag_header =
-                    FragmentHeader::unpack(&data[offset..offset + FRAGMENT_HEADER_SIZE]);
-                let frag_data_size =
-                    (frag_header.size as usize).saturating_sub(FRAGMENT_HEADER_SIZE);
-                offset += FRAGMENT_HEADER_SIZE;
-
-                if offset + frag_data_size > data.len() {
-                    break;
-                }
-                let frag_data = &data[offset..offset + frag_data_size];
+        // 3. Check for TimeSync and server switches
+        let optional = OptionalHeaders::unpack(header.flags, &data);
+        if let Some(server_time) = optional.time_sync {
+            events.push(SessionEvent::TimeSync(server_time));
+        }
+        if let Some(switch) = optional.server_switch {
+            log::debug!(
+                "<<< Server switch at Seq={} Type={}",
+                switch.sequence,
+                switch.kind
+            );
+        }
+        if let Some(referral) = optional.referral {
+            events.push(SessionEvent::ServerSwitch(referral));
+        }
 
+        // 4. Check for Blobs, in server sequence order
+        while let Some(packet) = self.reorder_buffer.pop_ready() {
+            let (header, data) = (packet.header, packet.data);
+            for (frag_header, frag_data) in split_fragments(header.flags, &data) {
                 if let Some(full) = self.process_fragment(&frag_header, frag_data) {
                     events.push(SessionEvent::Message(full));
                 }
-                offset += frag_data_size;
             }
         }
 
@@ -576,6 +1001,40 @@ impl Session {
 mod tests {
     use super::*;
     use crate::protocol::messages::flags;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct RecordingTransport {
+        sent: Arc<Mutex<Vec<Vec<u8>>>>,
+        inbound: Arc<Mutex<VecDeque<Vec<u8>>>>,
+    }
+
+    #[async_trait]
+    impl Transport for RecordingTransport {
+        async fn send_to(&self, buf: &[u8], _addr: SocketAddr) -> Result<usize> {
+            self.sent.lock().unwrap().push(buf.to_vec());
+            Ok(buf.len())
+        }
+        async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+            let packet = self
+                .inbound
+                .lock()
+                .unwrap()
+                .pop_front()
+                .ok_or_else(|| anyhow!("Recording transport"))?;
+            buf[..packet.len()].copy_from_slice(&packet);
+            Ok((packet.len(), "127.0.0.1:9000".parse().unwrap()))
+        }
+    }
+
+    fn recording_session() -> (Session, Arc<Mutex<Vec<Vec<u8>>>>) {
+        let transport = RecordingTransport::default();
+        let sent = transport.sent.clone();
+        let session =
+            Session::from_transport(Box::new(transport), "127.0.0.1:9000".parse().unwrap());
+        (session, sent)
+    }
 
     #[tokio::test]
     async fn test_payload_offset_handshake() {
@@ -691,4 +1150,609 @@ mod tests {
             final_checksum
         );
     }
+
+    #[tokio::test]
+    async fn test_retransmit_reuses_original_isaac_key() {
+        let (mut session, sent) = recording_session();
+        session.isaac_c2s = Some(crate::protocol::crypto::Isaac::new(0x99E77855));
+        session.packet_sequence = 2;
+        session.last_server_seq = 5;
+
+        let msg = GameMessage::DddInterrogationResponse { language: 1 };
+        session.send_message(&msg).await.unwrap();
+        assert!(session.retransmit_window.contains(2));
+
+        // Unrelated traffic advances the ISAAC stream before the retransmit request
+        session.send_ack(5).await.unwrap();
+
+        session.handle_retransmit_request(&[2, 77]).await.unwrap();
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 4);
+
+        let original = PacketHeader::unpack(&sent[0][..HEADER_SIZE]);
+        let resent = PacketHeader::unpack(&sent[2][..HEADER_SIZE]);
+        assert_eq!(resent.sequence, 2);
+        assert_eq!(resent.flags, original.flags | flags::RETRANSMISSION);
+        assert_eq!(&sent[2][HEADER_SIZE..], &sent[0][HEADER_SIZE..]);
+
+        // First key for this seed is 0xAD497DF3
+        let payload = &sent[2][HEADER_SIZE..];
+        let expected = resent
+            .calculate_checksum()
+            .wrapping_add(session.calculate_payload_hash(resent.flags, payload) ^ 0xAD497DF3);
+        assert_eq!(resent.checksum, expected);
+
+        // Unknown sequences are rejected, with the list ahead of the ack
+        let reject = PacketHeader::unpack(&sent[3][..HEADER_SIZE]);
+        assert_ne!(reject.flags & flags::REJECT_RETRANSMIT, 0);
+        let optional = OptionalHeaders::unpack(reject.flags, &sent[3][HEADER_SIZE..]);
+        assert_eq!(optional.reject_retransmit, vec![77]);
+        assert_eq!(optional.ack_sequence, Some(5));
+    }
+
+    #[tokio::test]
+    async fn test_only_fragment_packets_enter_retransmit_window() {
+        let (mut session, _sent) = recording_session();
+        session.packet_sequence = 1;
+        session.last_server_seq = 3;
+
+        session.send_ack(3).await.unwrap();
+        assert!(session.retransmit_window.is_empty());
+
+        let msg = GameMessage::DddInterrogationResponse { language: 1 };
+        session.send_message(&msg).await.unwrap();
+        session.send_message(&msg).await.unwrap();
+        assert_eq!(session.retransmit_window.len(), 2);
+
+        session.retransmit_window.acknowledge(1);
+        assert!(!session.retransmit_window.contains(1));
+        assert!(session.retransmit_window.contains(2));
+    }
+
+    fn server_packet(
+        session: &Session,
+        sequence: u32,
+        message: &[u8],
+        key: Option<u32>,
+    ) -> Vec<u8> {
+        let frag = FragmentHeader {
+            sequence,
+            id: sequence,
+            count: 1,
+            index: 0,
+            size: (message.len() + FRAGMENT_HEADER_SIZE) as u16,
+            queue: queues::GENERAL,
+        };
+        server_fragment_packet(session, sequence, frag, message, key)
+    }
+
+    fn server_fragment_packet(
+        session: &Session,
+        sequence: u32,
+        frag: FragmentHeader,
+        chunk: &[u8],
+        key: Option<u32>,
+    ) -> Vec<u8> {
+        let mut body = vec![0u8; FRAGMENT_HEADER_SIZE];
+        frag.pack(&mut body);
+        body.extend_from_slice(chunk);
+
+        let mut header = PacketHeader {
+            sequence,
+            flags: flags::BLOB_FRAGMENTS,
+            size: body.len() as u16,
+            ..Default::default()
+        };
+        if key.is_some() {
+            header.flags |= flags::ENCRYPTED_CHECKSUM;
+        }
+        let payload_hash = session.calculate_payload_hash(header.flags, &body);
+        header.checksum = header
+            .calculate_checksum()
+            .wrapping_add(payload_hash ^ key.unwrap_or(0));
+        let mut packet = vec![0u8; HEADER_SIZE];
+        header.pack(&mut packet);
+        packet.extend_from_slice(&body);
+        packet
+    }
+
+    fn server_connect_request(session: &Session, sequence: u32) -> Vec<u8> {
+        let body = [0u8; 32];
+        let mut header = PacketHeader {
+            sequence,
+            flags: flags::CONNECT_REQUEST,
+            size: body.len() as u16,
+            ..Default::default()
+        };
+        header.checksum = header
+            .calculate_checksum()
+            .wrapping_add(session.calculate_payload_hash(header.flags, &body));
+        let mut packet = vec![0u8; HEADER_SIZE];
+        header.pack(&mut packet);
+        packet.extend_from_slice(&body);
+        packet
+    }
+
+    #[tokio::test]
+    async fn test_first_packet_out_of_order_after_handshake() {
+        let transport = RecordingTransport::default();
+        let inbound = transport.inbound.clone();
+        let mut session =
+            Session::from_transport(Box::new(transport), "127.0.0.1:9000".parse().unwrap());
+        {
+            let mut inbound = inbound.lock().unwrap();
+            inbound.push_back(server_connect_request(&session, 0));
+            inbound.push_back(server_packet(&session, 2, &[0xA2], None));
+            inbound.push_back(server_packet(&session, 1, &[0xA1], None));
+        }
+
+        let mut delivered = Vec::new();
+        for _ in 0..3 {
+            for event in session.recv_message().await.unwrap() {
+                if let SessionEvent::Message(data) = event {
+                    delivered.push(data[0]);
+                }
+            }
+        }
+        // Seq 2 overtook the first data packet but did not become the start of the order
+        assert_eq!(delivered, vec![0xA1, 0xA2]);
+        assert_eq!(session.last_server_seq, 2);
+        assert_eq!(session.stats.duplicates_dropped, 0);
+    }
+
+    #[tokio::test]
+    async fn test_out_of_order_packets_are_delivered_in_sequence() {
+        let transport = RecordingTransport::default();
+        let (sent, inbound) = (transport.sent.clone(), transport.inbound.clone());
+        let mut session =
+            Session::from_transport(Box::new(transport), "127.0.0.1:9000".parse().unwrap());
+        {
+            let mut inbound = inbound.lock().unwrap();
+            inbound.push_back(server_packet(&session, 1, &[0xA1], None));
+            inbound.push_back(server_packet(&session, 3, &[0xA3], None));
+            inbound.push_back(server_packet(&session, 2, &[0xA2], None));
+        }
+
+        let mut delivered = Vec::new();
+        for _ in 0..3 {
+            for event in session.recv_message().await.unwrap() {
+                if let SessionEvent::Message(data) = event {
+                    delivered.push(data[0]);
+                }
+            }
+        }
+        assert_eq!(delivered, vec![0xA1, 0xA2, 0xA3]);
+        assert_eq!(session.last_server_seq, 3);
+
+        let sent = sent.lock().unwrap();
+        let mut acks = Vec::new();
+        let mut requested = Vec::new();
+        for packet in sent.iter() {
+            let header = PacketHeader::unpack(&packet[..HEADER_SIZE]);
+            let optional = OptionalHeaders::unpack(header.flags, &packet[HEADER_SIZE..]);
+            requested.extend(optional.request_retransmit);
+            if header.flags & flags::REQUEST_RETRANSMIT == 0 {
+                acks.extend(optional.ack_sequence);
+            }
+        }
+        // Seq 3 is never acked ahead of the gap at 2
+        assert_eq!(acks, vec![1, 3]);
+        assert_eq!(requested, vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_inbound_checksums_are_verified() {
+        let transport = RecordingTransport::default();
+        let inbound = transport.inbound.clone();
+        let mut session =
+            Session::from_transport(Box::new(transport), "127.0.0.1:9000".parse().unwrap());
+        session.isaac_s2c = Some(IsaacVerifier::new(0xC83824AB));
+
+        // First keys for seed 0xC83824AB, delivered out of order
+        let (k0, k1) = (0x1518CF56, 0x5F2E0E56);
+        let mut corrupt = server_packet(&session, 3, &[0xEE], Some(k1));
+        corrupt[HEADER_SIZE + FRAGMENT_HEADER_SIZE] ^= 0xFF;
+        {
+            let mut inbound = inbound.lock().unwrap();
+            inbound.push_back(server_packet(&session, 1, &[0xA1], Some(k0)));
+            inbound.push_back(corrupt);
+            inbound.push_back(server_packet(&session, 3, &[0xA3], Some(0xDEADBEEF)));
+            inbound.push_back(server_packet(&session, 2, &[0xA2], Some(k1)));
+            inbound.push_back(server_packet(&session, 2, &[0xA2], Some(k1)));
+        }
+
+        let mut delivered = Vec::new();
+        while let Ok(events) = session.recv_message().await {
+            for event in events {
+                if let SessionEvent::Message(data) = event {
+                    delivered.push(data[0]);
+                }
+            }
+        }
+
+        assert_eq!(delivered, vec![0xA1, 0xA2]);
+        // Corrupt payload, unknown key and a replayed key without RETRANSMISSION
+        assert_eq!(session.stats.packets_rejected, 3);
+    }
+
+    fn sent_fragments(sent: &[Vec<u8>]) -> Vec<(u32, FragmentHeader)> {
+        let mut fragments = Vec::new();
+        for packet in sent {
+            let header = PacketHeader::unpack(&packet[..HEADER_SIZE]);
+            let data = &packet[HEADER_SIZE..];
+            let mut offset = Session::new_test().get_payload_offset(header.flags, data);
+            while offset + FRAGMENT_HEADER_SIZE <= data.len() {
+                let frag = FragmentHeader::unpack(&data[offset..]);
+                offset += frag.size as usize;
+                fragments.push((header.sequence, frag));
+            }
+        }
+        fragments
+    }
+
+    #[tokio::test]
+    async fn test_large_message_is_split_into_fragments() {
+        let (mut session, sent) = recording_session();
+        session.packet_sequence = 1;
+        session.last_server_seq = 4;
+
+        let msg = GameMessage::GameAction {
+            sequence: 1,
+            action: GameActionKind::Talk {
+                message: "Z".repeat(1200),
+            },
+        };
+        let packed = msg.pack();
+        session.send_message(&msg).await.unwrap();
+
+        let sent = sent.lock().unwrap();
+        for packet in sent.iter() {
+            assert!(packet.len() <= MAX_PACKET_SIZE);
+        }
+
+        let fragments = sent_fragments(&sent);
+        assert_eq!(fragments.len(), 3);
+        for (i, (_, frag)) in fragments.iter().enumerate() {
+            assert_eq!(frag.index, i as u16);
+            assert_eq!(frag.count, 3);
+            assert_eq!(frag.sequence, fragments[0].1.sequence);
+        }
+        let total: usize = fragments
+            .iter()
+            .map(|(_, f)| f.size as usize - FRAGMENT_HEADER_SIZE)
+            .sum();
+        assert_eq!(total, packed.len());
+
+        // The pieces reassemble into the original message
+        let mut receiver = Session::new_test();
+        let mut reassembled = None;
+        for packet in sent.iter() {
+            let data = &packet[HEADER_SIZE + 4..];
+            let mut offset = 0;
+            while offset < data.len() {
+                let frag = FragmentHeader::unpack(&data[offset..]);
+                let end = offset + frag.size as usize;
+                reassembled = receiver
+                    .process_fragment(&frag, &data[offset + FRAGMENT_HEADER_SIZE..end])
+                    .or(reassembled);
+                offset = end;
+            }
+        }
+        assert_eq!(reassembled.unwrap(), packed);
+    }
+
+    #[tokio::test]
+    async fn test_small_messages_share_a_packet() {
+        let (mut session, sent) = recording_session();
+        session.packet_sequence = 1;
+
+        let msgs: Vec<GameMessage> = (0..3)
+            .map(|i| GameMessage::GameAction {
+                sequence: i,
+                action: GameActionKind::UseItem { item: 0x80000158 },
+            })
+            .collect();
+        session.send_messages(&msgs).await.unwrap();
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        let fragments = sent_fragments(&sent);
+        assert_eq!(fragments.len(), 3);
+        assert_eq!(session.packet_sequence, 2);
+        let sequences: Vec<u32> = fragments.iter().map(|(_, f)| f.sequence).collect();
+        assert_eq!(sequences, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_actions_are_numbered_in_order() {
+        let (mut session, sent) = recording_session();
+        session.packet_sequence = 1;
+
+        session
+            .send_action(GameActionKind::LoginComplete)
+            .await
+            .unwrap();
+        session
+            .send_action(GameActionKind::UseItem { item: 0x80000158 })
+            .await
+            .unwrap();
+
+        let sent = sent.lock().unwrap();
+        let actions: Vec<GameMessage> = sent
+            .iter()
+            .map(|packet| {
+                let data = &packet[HEADER_SIZE..];
+                let frag = FragmentHeader::unpack(data);
+                GameMessage::unpack(&data[FRAGMENT_HEADER_SIZE..frag.size as usize])
+            })
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                GameMessage::GameAction {
+                    sequence: 1,
+                    action: GameActionKind::LoginComplete,
+                },
+                GameMessage::GameAction {
+                    sequence: 2,
+                    action: GameActionKind::UseItem { item: 0x80000158 },
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_echo_round_trip_updates_stats() {
+        let transport = RecordingTransport::default();
+        let (sent, inbound) = (transport.sent.clone(), transport.inbound.clone());
+        let mut session =
+            Session::from_transport(Box::new(transport), "127.0.0.1:9000".parse().unwrap());
+
+        session.send_echo_request().await.unwrap();
+        let request = sent.lock().unwrap()[0].clone();
+        let header = PacketHeader::unpack(&request[..HEADER_SIZE]);
+        let optional = OptionalHeaders::unpack(header.flags, &request[HEADER_SIZE..]);
+        let client_time = optional.echo_request.unwrap();
+        assert!(optional.flow.is_some());
+
+        // Server echoes our time back alongside its own FLOW report
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&client_time.to_le_bytes());
+        payload.extend_from_slice(&0f32.to_le_bytes());
+        payload.extend_from_slice(&512u32.to_le_bytes());
+        payload.extend_from_slice(&5u16.to_le_bytes());
+        let mut reply = PacketHeader {
+            flags: flags::ECHO_RESPONSE | flags::FLOW,
+            size: payload.len() as u16,
+            ..Default::default()
+        };
+        reply.checksum = reply
+            .calculate_checksum()
+            .wrapping_add(session.calculate_payload_hash(reply.flags, &payload));
+        let mut packet = vec![0u8; HEADER_SIZE];
+        reply.pack(&mut packet);
+        packet.extend_from_slice(&payload);
+        inbound.lock().unwrap().push_back(packet.clone());
+
+        session.recv_message().await.unwrap();
+        let rtt = session.stats.rtt.unwrap();
+        assert!(rtt < Duration::from_secs(1));
+
+        let stats = &session.stats;
+        assert_eq!(stats.packets_sent, 1);
+        assert_eq!(stats.bytes_sent, request.len() as u64);
+        assert_eq!(stats.packets_received, 1);
+        assert_eq!(stats.bytes_received, packet.len() as u64);
+        assert_eq!(
+            stats.server_flow,
+            Some(FlowHeader {
+                bytes: 512,
+                interval: 5
+            })
+        );
+
+        // An echo of a time we never sent, or one already answered, is ignored
+        let mut stale = payload.clone();
+        stale[..4].copy_from_slice(&(client_time + 1.0).to_le_bytes());
+        for body in [stale, payload] {
+            let mut header = PacketHeader {
+                flags: flags::ECHO_RESPONSE | flags::FLOW,
+                size: body.len() as u16,
+                ..Default::default()
+            };
+            header.checksum = header
+                .calculate_checksum()
+                .wrapping_add(session.calculate_payload_hash(header.flags, &body));
+            let mut echo = vec![0u8; HEADER_SIZE];
+            header.pack(&mut echo);
+            echo.extend_from_slice(&body);
+            inbound.lock().unwrap().push_back(echo);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            session.recv_message().await.unwrap();
+        }
+        assert_eq!(session.stats.rtt, Some(rtt));
+    }
+
+    #[tokio::test]
+    async fn test_referral_switches_server() {
+        let transport = RecordingTransport::default();
+        let (sent, inbound) = (transport.sent.clone(), transport.inbound.clone());
+        let mut session =
+            Session::from_transport(Box::new(transport), "127.0.0.1:9000".parse().unwrap());
+        session.isaac_c2s = Some(crate::protocol::crypto::Isaac::new(0x99E77855));
+        session.packet_sequence = 7;
+        session.last_server_seq = 4;
+
+        let referral = ReferralHeader {
+            cookie: 0x1122334455667788,
+            address: "10.0.0.2:9050".parse().unwrap(),
+            server_id: 3,
+        };
+        let mut payload = Vec::new();
+        ServerSwitchHeader {
+            sequence: 5,
+            kind: 1,
+        }
+        .pack(&mut payload);
+        referral.pack(&mut payload);
+        let mut header = PacketHeader {
+            flags: flags::SERVER_SWITCH | flags::REFERRAL,
+            size: payload.len() as u16,
+            ..Default::default()
+        };
+        header.checksum = header
+            .calculate_checksum()
+            .wrapping_add(session.calculate_payload_hash(header.flags, &payload));
+        let mut packet = vec![0u8; HEADER_SIZE];
+        header.pack(&mut packet);
+        packet.extend_from_slice(&payload);
+        inbound.lock().unwrap().push_back(packet);
+
+        let events = session.recv_message().await.unwrap();
+        let Some(SessionEvent::ServerSwitch(received)) = events.into_iter().next() else {
+            panic!("Expected a server switch");
+        };
+        assert_eq!(received, referral);
+
+        session.switch_server(std::net::SocketAddr::V4(received.address));
+        assert!(session.isaac_c2s.is_none());
+        assert_eq!(session.last_server_seq, 0);
+        session
+            .send_world_login_request(received.cookie)
+            .await
+            .unwrap();
+
+        let sent = sent.lock().unwrap();
+        let login = sent.last().unwrap();
+        let login_header = PacketHeader::unpack(&login[..HEADER_SIZE]);
+        // Handshake packets carry no ack and are not ISAAC keyed
+        assert_eq!(login_header.flags, flags::WORLD_LOGIN_REQUEST);
+        assert_eq!(login_header.sequence, 0);
+        let optional = OptionalHeaders::unpack(login_header.flags, &login[HEADER_SIZE..]);
+        assert_eq!(optional.world_login, Some(0x1122334455667788));
+    }
+
+    fn loopback_session(
+        impairment: loopback::Impairment,
+        seed: u64,
+    ) -> (
+        Session,
+        loopback::ImpairedTransport<loopback::LoopbackTransport>,
+    ) {
+        let server_addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let (client, server) =
+            loopback::LoopbackTransport::pair("127.0.0.1:50000".parse().unwrap(), server_addr);
+        let session = Session::from_transport(Box::new(client), server_addr);
+        (
+            session,
+            loopback::ImpairedTransport::new(server, impairment, seed),
+        )
+    }
+
+    /// Receives until the session has been quiet for a short while.
+    async fn recv_messages(session: &mut Session) -> Vec<Vec<u8>> {
+        let mut messages = Vec::new();
+        while let Ok(Ok(events)) =
+            tokio::time::timeout(Duration::from_millis(20), session.recv_message()).await
+        {
+            for event in events {
+                if let SessionEvent::Message(data) = event {
+                    messages.push(data);
+                }
+            }
+        }
+        messages
+    }
+
+    #[tokio::test]
+    async fn test_fragments_survive_reordering_and_duplication() {
+        let (mut session, server) = loopback_session(
+            loopback::Impairment {
+                duplicate: 0.5,
+                reorder: 0.5,
+                ..Default::default()
+            },
+            3,
+        );
+        let client_addr: SocketAddr = "127.0.0.1:50000".parse().unwrap();
+        let connect = server_connect_request(&session, 0);
+        server.inner().send_to(&connect, client_addr).await.unwrap();
+
+        let message: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+        let chunks: Vec<&[u8]> = message.chunks(MAX_FRAGMENT_DATA_SIZE).collect();
+        for (index, chunk) in chunks.iter().enumerate() {
+            let frag = FragmentHeader {
+                sequence: 1,
+                id: 1,
+                count: chunks.len() as u16,
+                index: index as u16,
+                size: (chunk.len() + FRAGMENT_HEADER_SIZE) as u16,
+                queue: queues::GENERAL,
+            };
+            let sequence = index as u32 + 1;
+            let packet = server_fragment_packet(&session, sequence, frag, chunk, None);
+            server.send_to(&packet, client_addr).await.unwrap();
+        }
+        let tail = server_packet(&session, 4, &[0xB4], None);
+        server.send_to(&tail, client_addr).await.unwrap();
+        server.flush().await.unwrap();
+
+        let delivered = recv_messages(&mut session).await;
+        assert_eq!(delivered, vec![message, vec![0xB4]]);
+        assert_eq!(session.last_server_seq, 4);
+
+        let impaired = server.stats();
+        assert!(impaired.duplicated > 0 && impaired.reordered > 0);
+        assert_eq!(session.stats.duplicates_dropped, impaired.duplicated);
+    }
+
+    #[tokio::test]
+    async fn test_lost_packets_are_recovered_by_retransmit_requests() {
+        let (mut session, server) = loopback_session(
+            loopback::Impairment {
+                loss: 0.3,
+                ..Default::default()
+            },
+            11,
+        );
+        let client_addr: SocketAddr = "127.0.0.1:50000".parse().unwrap();
+        let connect = server_connect_request(&session, 0);
+        server.inner().send_to(&connect, client_addr).await.unwrap();
+        let packets: Vec<Vec<u8>> = (1..=12u32)
+            .map(|seq| server_packet(&session, seq, &[seq as u8], None))
+            .collect();
+        // The last packet lets the session see every gap before it, so it is never put at risk
+        for packet in &packets[..11] {
+            server.send_to(packet, client_addr).await.unwrap();
+        }
+        server
+            .inner()
+            .send_to(&packets[11], client_addr)
+            .await
+            .unwrap();
+
+        let mut delivered = Vec::new();
+        let mut buf = [0u8; 1024];
+        for _ in 0..10 {
+            delivered.extend(recv_messages(&mut session).await.into_iter().map(|m| m[0]));
+            while let Ok(Ok((len, _))) =
+                tokio::time::timeout(Duration::from_millis(5), server.recv_from(&mut buf)).await
+            {
+                let header = PacketHeader::unpack(&buf[..HEADER_SIZE]);
+                let optional = OptionalHeaders::unpack(header.flags, &buf[HEADER_SIZE..len]);
+                for seq in optional.request_retransmit {
+                    let packet = &packets[seq as usize - 1];
+                    server.inner().send_to(packet, client_addr).await.unwrap();
+                }
+            }
+            if delivered.len() == packets.len() {
+                break;
+            }
+        }
+
+        assert_eq!(delivered, (1..=12u8).collect::<Vec<_>>());
+        assert!(server.stats().dropped > 0);
+        assert_eq!(session.stats.retransmits_requested, server.stats().dropped);
+    }
 }
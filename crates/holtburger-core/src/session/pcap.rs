@@ -0,0 +1,657 @@
+//! Conversion between our captures and pcap/pcapng.
+//!
+//! Export writes pcapng with raw IP frames, synthesising IPv4/IPv6 and UDP headers from the
+//! recorded server address and a caller-chosen client address, so the result opens in
+//! Wireshark with any AC dissector. Import reads classic pcap (as written by tcpdump) and
+//! pcapng, pulls out the UDP datagrams of the AC client and turns them back into capture
+//! records.
+
+use crate::protocol::messages::{
+    ConnectRequestData, HEADER_SIZE, LoginRequest, OFF_CONNECT_CLIENT_SEED, PacketHeader, flags,
+};
+use crate::session::capture::{
+    CaptureEntry, CaptureHeader, CaptureReader, CaptureRecord, CaptureWriter, Direction,
+    SessionKeys,
+};
+use anyhow::{Result, anyhow};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Client address used in exports when the caller has none in mind.
+pub const DEFAULT_CLIENT_ADDR: &str = "10.0.0.2:50000";
+
+const PCAPNG_SHB: u32 = 0x0A0D0D0A;
+const PCAPNG_IDB: u32 = 0x0000_0001;
+const PCAPNG_SPB: u32 = 0x0000_0003;
+const PCAPNG_EPB: u32 = 0x0000_0006;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B3C4D;
+
+const PCAP_MAGIC_US: u32 = 0xA1B2C3D4;
+const PCAP_MAGIC_NS: u32 = 0xA1B23C4D;
+
+const LINKTYPE_NULL: u16 = 0;
+const LINKTYPE_ETHERNET: u16 = 1;
+const LINKTYPE_RAW_OPENBSD: u16 = 12;
+const LINKTYPE_RAW: u16 = 101;
+const LINKTYPE_LINUX_SLL: u16 = 113;
+const LINKTYPE_IPV4: u16 = 228;
+const LINKTYPE_IPV6: u16 = 229;
+const LINKTYPE_LINUX_SLL2: u16 = 276;
+
+const IPPROTO_UDP: u8 = 17;
+/// Port range ACE listens on by default, used to spot the server when no login is captured.
+const SERVER_PORTS: std::ops::RangeInclusive<u16> = 9000..=9013;
+
+/// Whether a file starting with `magic` is pcap or pcapng.
+pub fn is_pcap(magic: &[u8; 4]) -> bool {
+    let le = LittleEndian::read_u32(magic);
+    let be = BigEndian::read_u32(magic);
+    le == PCAPNG_SHB
+        || [PCAP_MAGIC_US, PCAP_MAGIC_NS]
+            .iter()
+            .any(|&m| m == le || m == be)
+}
+
+/// Converts a capture file to pcapng. Returns the number of packets written.
+pub fn export_file(capture_path: &str, pcapng_path: &str, client: SocketAddr) -> Result<usize> {
+    let mut reader = CaptureReader::open(capture_path)?;
+    let mut out = std::io::BufWriter::new(std::fs::File::create(pcapng_path)?);
+    let count = export_pcapng(&mut reader, &mut out, client)?;
+    out.flush()?;
+    Ok(count)
+}
+
+/// Converts a pcap/pcapng file to a capture file. Returns the number of packets written.
+pub fn import_file(pcap_path: &str, capture_path: &str, compress: bool) -> Result<usize> {
+    let (header, records) = import(&std::fs::read(pcap_path)?)?;
+    let mut writer = CaptureWriter::create(capture_path, header, compress)?;
+    let mut count = 0;
+    for record in &records {
+        writer.write_record(record)?;
+        if matches!(record, CaptureRecord::Packet(_)) {
+            count += 1;
+        }
+    }
+    writer.finish()?;
+    Ok(count)
+}
+
+/// Writes every packet in `reader` to `out` as pcapng, as seen from `client`.
+pub fn export_pcapng(
+    reader: &mut CaptureReader,
+    out: &mut impl Write,
+    client: SocketAddr,
+) -> Result<usize> {
+    write_block(out, PCAPNG_SHB, |body| {
+        body.write_u32::<LittleEndian>(PCAPNG_BYTE_ORDER_MAGIC)?;
+        body.write_u16::<LittleEndian>(1)?; // major version
+        body.write_u16::<LittleEndian>(0)?; // minor version
+        body.write_i64::<LittleEndian>(-1)?; // section length unknown
+        Ok(())
+    })?;
+    write_block(out, PCAPNG_IDB, |body| {
+        body.write_u16::<LittleEndian>(LINKTYPE_RAW)?;
+        body.write_u16::<LittleEndian>(0)?;
+        body.write_u32::<LittleEndian>(0)?; // no snap length limit
+        // if_tsresol = 6 (microseconds), then opt_endofopt
+        body.write_all(&[9, 0, 1, 0, 6, 0, 0, 0, 0, 0, 0, 0])?;
+        Ok(())
+    })?;
+
+    let mut count = 0;
+    while let Some(entry) = reader.read_next()? {
+        let client = match_family(client, entry.addr.ip());
+        let (src, dst) = match entry.direction {
+            Direction::Inbound => (entry.addr, client),
+            Direction::Outbound => (client, entry.addr),
+        };
+        let frame = build_ip_udp(src, dst, &entry.data);
+        write_block(out, PCAPNG_EPB, |body| {
+            body.write_u32::<LittleEndian>(0)?; // interface
+            body.write_u32::<LittleEndian>((entry.timestamp_us >> 32) as u32)?;
+            body.write_u32::<LittleEndian>(entry.timestamp_us as u32)?;
+            body.write_u32::<LittleEndian>(frame.len() as u32)?;
+            body.write_u32::<LittleEndian>(frame.len() as u32)?;
+            body.write_all(&frame)?;
+            Ok(())
+        })?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Writes a pcapng block, padding the body to 32 bits and framing it with its length.
+fn write_block(
+    out: &mut impl Write,
+    block_type: u32,
+    fill: impl FnOnce(&mut Vec<u8>) -> std::io::Result<()>,
+) -> Result<()> {
+    let mut body = Vec::new();
+    fill(&mut body)?;
+    body.resize(body.len().next_multiple_of(4), 0);
+    let total = body.len() as u32 + 12;
+    out.write_u32::<LittleEndian>(block_type)?;
+    out.write_u32::<LittleEndian>(total)?;
+    out.write_all(&body)?;
+    out.write_u32::<LittleEndian>(total)?;
+    Ok(())
+}
+
+fn match_family(client: SocketAddr, server: IpAddr) -> SocketAddr {
+    let ip = match (client.ip(), server) {
+        (IpAddr::V4(ip), IpAddr::V6(_)) => IpAddr::V6(ip.to_ipv6_mapped()),
+        (IpAddr::V6(ip), IpAddr::V4(_)) => {
+            IpAddr::V4(ip.to_ipv4_mapped().unwrap_or(Ipv4Addr::UNSPECIFIED))
+        }
+        (ip, _) => ip,
+    };
+    SocketAddr::new(ip, client.port())
+}
+
+fn checksum_add(mut sum: u32, data: &[u8]) -> u32 {
+    let mut chunks = data.chunks_exact(2);
+    for chunk in &mut chunks {
+        sum += BigEndian::read_u16(chunk) as u32;
+    }
+    if let [last] = chunks.remainder() {
+        sum += (*last as u32) << 8;
+    }
+    sum
+}
+
+fn checksum_finish(mut sum: u32) -> u16 {
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn build_ip_udp(src: SocketAddr, dst: SocketAddr, payload: &[u8]) -> Vec<u8> {
+    let udp_len = (8 + payload.len()) as u16;
+    let mut udp = Vec::with_capacity(udp_len as usize);
+    udp.write_u16::<BigEndian>(src.port()).unwrap();
+    udp.write_u16::<BigEndian>(dst.port()).unwrap();
+    udp.write_u16::<BigEndian>(udp_len).unwrap();
+    udp.write_u16::<BigEndian>(0).unwrap();
+    udp.extend_from_slice(payload);
+
+    let mut frame = Vec::new();
+    let pseudo_sum = match (src.ip(), dst.ip()) {
+        (IpAddr::V4(s), IpAddr::V4(d)) => {
+            let mut ip = vec![0x45, 0];
+            ip.write_u16::<BigEndian>(20 + udp_len).unwrap();
+            ip.extend_from_slice(&[0, 0, 0x40, 0, 64, IPPROTO_UDP, 0, 0]); // DF, TTL 64
+            ip.extend_from_slice(&s.octets());
+            ip.extend_from_slice(&d.octets());
+            let header_checksum = checksum_finish(checksum_add(0, &ip));
+            BigEndian::write_u16(&mut ip[10..12], header_checksum);
+            frame.extend_from_slice(&ip);
+
+            let sum = checksum_add(0, &s.octets());
+            checksum_add(sum, &d.octets()) + IPPROTO_UDP as u32 + udp_len as u32
+        }
+        (s, d) => {
+            let to_v6 = |ip: IpAddr| match ip {
+                IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+                IpAddr::V6(ip) => ip,
+            };
+            let (s, d) = (to_v6(s), to_v6(d));
+            frame.extend_from_slice(&[0x60, 0, 0, 0]);
+            frame.write_u16::<BigEndian>(udp_len).unwrap();
+            frame.extend_from_slice(&[IPPROTO_UDP, 64]);
+            frame.extend_from_slice(&s.octets());
+            frame.extend_from_slice(&d.octets());
+
+            let sum = checksum_add(0, &s.octets());
+            checksum_add(sum, &d.octets()) + IPPROTO_UDP as u32 + udp_len as u32
+        }
+    };
+    let udp_checksum = match checksum_finish(checksum_add(pseudo_sum, &udp)) {
+        0 => 0xFFFF,
+        c => c,
+    };
+    BigEndian::write_u16(&mut udp[6..8], udp_checksum);
+    frame.extend_from_slice(&udp);
+    frame
+}
+
+/// A UDP datagram pulled out of a captured frame.
+struct Datagram {
+    timestamp_us: u64,
+    src: SocketAddr,
+    dst: SocketAddr,
+    payload: Vec<u8>,
+}
+
+/// Reads a pcap or pcapng file and converts the AC client's traffic into capture records.
+///
+/// The client is whoever sent the first LoginRequest; failing that, whoever talked to a
+/// port in ACE's default range. Everything the client sent is outbound and everything it
+/// received is inbound; unrelated traffic is dropped.
+pub fn import(data: &[u8]) -> Result<(CaptureHeader, Vec<CaptureRecord>)> {
+    if data.len() < 4 {
+        return Err(anyhow!("Not a pcap file"));
+    }
+    let datagrams = if LittleEndian::read_u32(data) == PCAPNG_SHB {
+        read_pcapng(data)?
+    } else {
+        read_pcap(data)?
+    };
+
+    let is_login = |d: &Datagram| {
+        d.payload.len() >= HEADER_SIZE
+            && PacketHeader::unpack(&d.payload[..HEADER_SIZE]).flags & flags::LOGIN_REQUEST != 0
+    };
+    let client = datagrams
+        .iter()
+        .find(|d| is_login(d))
+        .or_else(|| {
+            datagrams
+                .iter()
+                .find(|d| SERVER_PORTS.contains(&d.dst.port()))
+        })
+        .map(|d| d.src)
+        .ok_or_else(|| anyhow!("No AC client traffic found"))?;
+
+    let mut header: Option<CaptureHeader> = None;
+    let mut records = Vec::new();
+    for datagram in datagrams {
+        let (direction, addr) = if datagram.src == client {
+            (Direction::Outbound, datagram.dst)
+        } else if datagram.dst == client {
+            (Direction::Inbound, datagram.src)
+        } else {
+            continue;
+        };
+
+        let header = header.get_or_insert_with(|| {
+            let mut header = CaptureHeader::new(addr, "");
+            header.started_at_us = datagram.timestamp_us;
+            header
+        });
+        if direction == Direction::Outbound
+            && is_login(&datagram)
+            && let Some(login) = LoginRequest::unpack(&datagram.payload[HEADER_SIZE..])
+        {
+            header.client_version = login.client_version;
+            header.account_hash = crate::session::capture::account_hash(&login.account);
+        }
+
+        let packet = &datagram.payload;
+        if direction == Direction::Inbound
+            && packet.len() >= HEADER_SIZE + OFF_CONNECT_CLIENT_SEED + 4
+            && PacketHeader::unpack(&packet[..HEADER_SIZE]).flags & flags::CONNECT_REQUEST != 0
+        {
+            let crd = ConnectRequestData::unpack(&packet[HEADER_SIZE..]);
+            records.push(CaptureRecord::SessionKeys(SessionKeys {
+                timestamp_us: datagram.timestamp_us,
+                server_seed: crd.server_seed,
+                client_seed: crd.client_seed,
+            }));
+        }
+        records.push(CaptureRecord::Packet(CaptureEntry {
+            direction,
+            timestamp_us: datagram.timestamp_us,
+            addr,
+            data: datagram.payload,
+        }));
+    }
+
+    let header = header.ok_or_else(|| anyhow!("No AC client traffic found"))?;
+    Ok((header, records))
+}
+
+fn read_pcap(data: &[u8]) -> Result<Vec<Datagram>> {
+    if data.len() < 24 {
+        return Err(anyhow!("Truncated pcap header"));
+    }
+    let (big_endian, nanos) = match (LittleEndian::read_u32(data), BigEndian::read_u32(data)) {
+        (PCAP_MAGIC_US, _) => (false, false),
+        (PCAP_MAGIC_NS, _) => (false, true),
+        (_, PCAP_MAGIC_US) => (true, false),
+        (_, PCAP_MAGIC_NS) => (true, true),
+        _ => return Err(anyhow!("Not a pcap file")),
+    };
+    let read_u32 = |b: &[u8]| {
+        if big_endian {
+            BigEndian::read_u32(b)
+        } else {
+            LittleEndian::read_u32(b)
+        }
+    };
+    let linktype = (read_u32(&data[20..24]) & 0xFFFF) as u16;
+
+    let mut datagrams = Vec::new();
+    let mut offset = 24;
+    while offset + 16 <= data.len() {
+        let seconds = read_u32(&data[offset..]) as u64;
+        let fraction = read_u32(&data[offset + 4..]) as u64;
+        let captured = read_u32(&data[offset + 8..]) as usize;
+        offset += 16;
+        let Some(frame) = data.get(offset..offset + captured) else {
+            break;
+        };
+        offset += captured;
+
+        let timestamp_us = seconds * 1_000_000 + if nanos { fraction / 1000 } else { fraction };
+        datagrams.extend(parse_frame(linktype, frame, timestamp_us));
+    }
+    Ok(datagrams)
+}
+
+fn read_pcapng(data: &[u8]) -> Result<Vec<Datagram>> {
+    struct Interface {
+        linktype: u16,
+        /// Timestamp units per second.
+        resolution: u64,
+    }
+
+    let mut datagrams = Vec::new();
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut big_endian = false;
+    let mut offset = 0;
+    while offset + 12 <= data.len() {
+        // The section header's type reads the same in either byte order
+        if LittleEndian::read_u32(&data[offset..]) == PCAPNG_SHB {
+            // Each section declares its own byte order
+            big_endian = BigEndian::read_u32(&data[offset + 8..]) == PCAPNG_BYTE_ORDER_MAGIC;
+            interfaces.clear();
+        }
+        let read_u16 = |b: &[u8]| {
+            if big_endian {
+                BigEndian::read_u16(b)
+            } else {
+                LittleEndian::read_u16(b)
+            }
+        };
+        let read_u32 = |b: &[u8]| {
+            if big_endian {
+                BigEndian::read_u32(b)
+            } else {
+                LittleEndian::read_u32(b)
+            }
+        };
+        let block_type = read_u32(&data[offset..]);
+        let length = read_u32(&data[offset + 4..]) as usize;
+        if length < 12 || offset + length > data.len() {
+            break;
+        }
+        let body = &data[offset + 8..offset + length - 4];
+        offset += length;
+
+        match block_type {
+            PCAPNG_IDB if body.len() >= 8 => {
+                let mut resolution = 1_000_000;
+                let mut options = &body[8..];
+                while options.len() >= 4 {
+                    let code = read_u16(options);
+                    let len = read_u16(&options[2..]) as usize;
+                    let value = options.get(4..4 + len).unwrap_or_default();
+                    if code == 0 {
+                        break;
+                    }
+                    if code == 9
+                        && let [tsresol] = value
+                    {
+                        let exponent = (tsresol & 0x7F) as u32;
+                        resolution = if tsresol & 0x80 == 0 {
+                            10u64.saturating_pow(exponent)
+                        } else {
+                            2u64.saturating_pow(exponent)
+                        };
+                    }
+                    options = options
+                        .get(4 + len.next_multiple_of(4)..)
+                        .unwrap_or_default();
+                }
+                interfaces.push(Interface {
+                    linktype: read_u16(body),
+                    resolution,
+                });
+            }
+            PCAPNG_EPB if body.len() >= 20 => {
+                let Some(interface) = interfaces.get(read_u32(body) as usize) else {
+                    continue;
+                };
+                let ticks = ((read_u32(&body[4..]) as u64) << 32) | read_u32(&body[8..]) as u64;
+                let captured = read_u32(&body[12..]) as usize;
+                let Some(frame) = body.get(20..20 + captured) else {
+                    continue;
+                };
+                let timestamp_us =
+                    (ticks as u128 * 1_000_000 / interface.resolution.max(1) as u128) as u64;
+                datagrams.extend(parse_frame(interface.linktype, frame, timestamp_us));
+            }
+            PCAPNG_SPB if body.len() >= 4 => {
+                // Simple packets carry no timestamp
+                if let Some(interface) = interfaces.first() {
+                    datagrams.extend(parse_frame(interface.linktype, &body[4..], 0));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(datagrams)
+}
+
+/// Strips the link layer and returns the UDP datagram in `frame`, if there is one.
+fn parse_frame(linktype: u16, frame: &[u8], timestamp_us: u64) -> Option<Datagram> {
+    let ip = match linktype {
+        LINKTYPE_RAW | LINKTYPE_RAW_OPENBSD | LINKTYPE_IPV4 | LINKTYPE_IPV6 => frame,
+        LINKTYPE_NULL => frame.get(4..)?,
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            // Skip any 802.1Q / 802.1ad VLAN tags
+            while matches!(
+                BigEndian::read_u16(frame.get(offset..offset + 2)?),
+                0x8100 | 0x88A8
+            ) {
+                offset += 4;
+            }
+            frame.get(offset + 2..)?
+        }
+        LINKTYPE_LINUX_SLL => frame.get(16..)?,
+        LINKTYPE_LINUX_SLL2 => frame.get(20..)?,
+        _ => return None,
+    };
+    let (src, dst, udp) = match ip.first()? >> 4 {
+        4 => {
+            let header_len = ((ip[0] & 0x0F) as usize) * 4;
+            let total_len = BigEndian::read_u16(ip.get(2..4)?) as usize;
+            let fragment = BigEndian::read_u16(ip.get(6..8)?);
+            // Reassembling IP fragments is out of scope; AC packets never need it
+            if ip.get(9)? != &IPPROTO_UDP || fragment & 0x3FFF != 0 {
+                return None;
+            }
+            let src = Ipv4Addr::from(<[u8; 4]>::try_from(ip.get(12..16)?).ok()?);
+            let dst = Ipv4Addr::from(<[u8; 4]>::try_from(ip.get(16..20)?).ok()?);
+            let end = total_len.min(ip.len());
+            (IpAddr::V4(src), IpAddr::V4(dst), ip.get(header_len..end)?)
+        }
+        6 => {
+            if ip.get(6)? != &IPPROTO_UDP {
+                return None;
+            }
+            let payload_len = BigEndian::read_u16(ip.get(4..6)?) as usize;
+            let src = Ipv6Addr::from(<[u8; 16]>::try_from(ip.get(8..24)?).ok()?);
+            let dst = Ipv6Addr::from(<[u8; 16]>::try_from(ip.get(24..40)?).ok()?);
+            let end = (40 + payload_len).min(ip.len());
+            (IpAddr::V6(src), IpAddr::V6(dst), ip.get(40..end)?)
+        }
+        _ => return None,
+    };
+
+    let src_port = BigEndian::read_u16(udp.get(0..2)?);
+    let dst_port = BigEndian::read_u16(udp.get(2..4)?);
+    let udp_len = BigEndian::read_u16(udp.get(4..6)?) as usize;
+    let payload = udp.get(8..udp_len.clamp(8, udp.len()))?;
+    Some(Datagram {
+        timestamp_us,
+        src: SocketAddr::new(src, src_port),
+        dst: SocketAddr::new(dst, dst_port),
+        payload: payload.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::messages::{OFF_CONNECT_SERVER_SEED, build_login_payload};
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("holtburger-pcap-{}-{}", name, std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn packet(flags: u32, payload: &[u8]) -> Vec<u8> {
+        let header = PacketHeader {
+            flags,
+            size: payload.len() as u16,
+            ..Default::default()
+        };
+        let mut packet = vec![0u8; HEADER_SIZE];
+        header.pack(&mut packet);
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn sample_capture(path: &str) -> SocketAddr {
+        let server: SocketAddr = "192.168.1.10:9000".parse().unwrap();
+        let mut writer =
+            CaptureWriter::create(path, CaptureHeader::new(server, "x"), false).unwrap();
+        let login = packet(
+            flags::LOGIN_REQUEST,
+            &build_login_payload("Tester", "pw", 0),
+        );
+        let mut connect = vec![0u8; 32];
+        LittleEndian::write_u32(&mut connect[OFF_CONNECT_SERVER_SEED..], 0x11111111);
+        LittleEndian::write_u32(&mut connect[OFF_CONNECT_CLIENT_SEED..], 0x22222222);
+        let connect = packet(flags::CONNECT_REQUEST, &connect);
+        for (i, (direction, data)) in [(Direction::Outbound, login), (Direction::Inbound, connect)]
+            .into_iter()
+            .enumerate()
+        {
+            writer
+                .write_record(&CaptureRecord::Packet(CaptureEntry {
+                    direction,
+                    timestamp_us: 1_700_000_000_000_000 + i as u64 * 1500,
+                    addr: server,
+                    data,
+                }))
+                .unwrap();
+        }
+        server
+    }
+
+    #[test]
+    fn test_pcapng_export_import_roundtrip() {
+        let (cap, pcapng) = (temp_path("export.cap"), temp_path("export.pcapng"));
+        let server = sample_capture(&cap);
+        let client: SocketAddr = DEFAULT_CLIENT_ADDR.parse().unwrap();
+        assert_eq!(export_file(&cap, &pcapng, client).unwrap(), 2);
+
+        let mut reader = CaptureReader::open(&pcapng).unwrap();
+        let header = reader.header().unwrap().clone();
+        assert_eq!(header.server, server);
+        assert_eq!(header.started_at_us, 1_700_000_000_000_000);
+        assert_eq!(header.client_version, "1802");
+        assert_eq!(
+            header.account_hash,
+            crate::session::capture::account_hash("tester")
+        );
+
+        let records: Vec<CaptureRecord> =
+            std::iter::from_fn(|| reader.read_record().unwrap()).collect();
+        let mut original = CaptureReader::open(&cap).unwrap();
+        std::fs::remove_file(&cap).unwrap();
+        std::fs::remove_file(&pcapng).unwrap();
+
+        assert!(matches!(
+            records[1],
+            CaptureRecord::SessionKeys(SessionKeys {
+                server_seed: 0x11111111,
+                client_seed: 0x22222222,
+                ..
+            })
+        ));
+        let packets: Vec<&CaptureEntry> = records
+            .iter()
+            .filter_map(|r| match r {
+                CaptureRecord::Packet(entry) => Some(entry),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(packets.len(), 2);
+        for imported in packets {
+            let expected = original.read_next().unwrap().unwrap();
+            assert_eq!(imported.direction, expected.direction);
+            assert_eq!(imported.timestamp_us, expected.timestamp_us);
+            assert_eq!(imported.addr, expected.addr);
+            assert_eq!(imported.data, expected.data);
+        }
+    }
+
+    #[test]
+    fn test_synthesised_checksums_are_valid() {
+        let src: SocketAddr = "10.0.0.2:50000".parse().unwrap();
+        let dst: SocketAddr = "192.168.1.10:9000".parse().unwrap();
+        let frame = build_ip_udp(src, dst, &[1, 2, 3]);
+        assert_eq!(checksum_finish(checksum_add(0, &frame[..20])), 0);
+
+        let mut sum = checksum_add(0, &frame[12..20]);
+        sum += IPPROTO_UDP as u32 + (frame.len() - 20) as u32;
+        assert_eq!(checksum_finish(checksum_add(sum, &frame[20..])), 0);
+    }
+
+    #[test]
+    fn test_imports_tcpdump_ethernet_pcap() {
+        let client: SocketAddr = "10.0.0.2:50000".parse().unwrap();
+        let server: SocketAddr = "10.0.0.1:9000".parse().unwrap();
+        let other: SocketAddr = "10.0.0.9:53".parse().unwrap();
+        let resolver: SocketAddr = "10.0.0.2:40000".parse().unwrap();
+
+        let mut pcap = Vec::new();
+        pcap.write_u32::<LittleEndian>(PCAP_MAGIC_NS).unwrap();
+        pcap.extend_from_slice(&[2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        pcap.write_u32::<LittleEndian>(65535).unwrap();
+        pcap.write_u32::<LittleEndian>(LINKTYPE_ETHERNET as u32)
+            .unwrap();
+        let frames = [
+            (other, resolver, vec![0xDD]),
+            (server, client, packet(flags::ACK_SEQUENCE, &[1, 0, 0, 0])),
+            (client, server, packet(flags::ACK_SEQUENCE, &[2, 0, 0, 0])),
+        ];
+        for (i, (src, dst, payload)) in frames.iter().enumerate() {
+            let mut frame = vec![0u8; 12];
+            frame.extend_from_slice(&[0x81, 0x00, 0, 1, 0x08, 0x00]); // VLAN-tagged IPv4
+            frame.extend_from_slice(&build_ip_udp(*src, *dst, payload));
+            pcap.write_u32::<LittleEndian>(1_700_000_000).unwrap();
+            pcap.write_u32::<LittleEndian>(i as u32 * 1000).unwrap();
+            pcap.write_u32::<LittleEndian>(frame.len() as u32).unwrap();
+            pcap.write_u32::<LittleEndian>(frame.len() as u32).unwrap();
+            pcap.extend_from_slice(&frame);
+        }
+
+        let (header, records) = import(&pcap).unwrap();
+        assert_eq!(header.server, server);
+        let entries: Vec<(Direction, u64, SocketAddr, u8)> = records
+            .into_iter()
+            .filter_map(|r| match r {
+                CaptureRecord::Packet(e) => {
+                    Some((e.direction, e.timestamp_us, e.addr, e.data[HEADER_SIZE]))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (Direction::Inbound, 1_700_000_000_000_001, server, 1),
+                (Direction::Outbound, 1_700_000_000_000_002, server, 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,430 @@
+use crate::protocol::messages::{PacketHeader, flags};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Maximum number of un-acked outbound packets we keep around for retransmission.
+pub const MAX_RETRANSMIT_WINDOW: usize = 256;
+/// Maximum number of out-of-order inbound packets held while waiting for a gap to fill.
+pub const MAX_REORDER_BUFFER: usize = 256;
+/// Minimum delay before asking the server again for the same missing sequence.
+pub const RETRANSMIT_REQUEST_INTERVAL: Duration = Duration::from_millis(500);
+
+/// An outbound packet retained until the server acknowledges it.
+#[derive(Debug, Clone)]
+pub struct SentPacket {
+    /// Header as originally sent (checksum is recomputed on every transmission).
+    pub header: PacketHeader,
+    /// Payload as originally sent, including any optional headers.
+    pub payload: Vec<u8>,
+    /// The ISAAC key the packet was originally keyed with, if it was encrypted.
+    /// Retransmissions must reuse this key rather than consuming a new one.
+    pub isaac_key: Option<u32>,
+    pub sent_at: Instant,
+    pub retransmits: u32,
+}
+
+/// Bounded window of sent, un-acked packets keyed by packet sequence.
+#[derive(Debug)]
+pub struct RetransmitWindow {
+    packets: BTreeMap<u32, SentPacket>,
+    capacity: usize,
+}
+
+impl Default for RetransmitWindow {
+    fn default() -> Self {
+        Self::new(MAX_RETRANSMIT_WINDOW)
+    }
+}
+
+impl RetransmitWindow {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            packets: BTreeMap::new(),
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    pub fn contains(&self, sequence: u32) -> bool {
+        self.packets.contains_key(&sequence)
+    }
+
+    pub fn insert(&mut self, packet: SentPacket) {
+        self.packets.insert(packet.header.sequence, packet);
+        while self.packets.len() > self.capacity {
+            if let Some((sequence, _)) = self.packets.pop_first() {
+                log::warn!(
+                    "Retransmit window full, dropping un-acked packet Seq={}",
+                    sequence
+                );
+            }
+        }
+    }
+
+    pub fn get_mut(&mut self, sequence: u32) -> Option<&mut SentPacket> {
+        self.packets.get_mut(&sequence)
+    }
+
+    /// Drops every packet with a sequence at or below `sequence`.
+    /// Returns the number of packets released.
+    pub fn acknowledge(&mut self, sequence: u32) -> usize {
+        let remaining = match sequence.checked_add(1) {
+            Some(next) => self.packets.split_off(&next),
+            None => BTreeMap::new(),
+        };
+        let released = self.packets.len();
+        self.packets = remaining;
+        released
+    }
+
+    pub fn clear(&mut self) {
+        self.packets.clear();
+    }
+}
+
+/// Whether a server packet occupies a slot in the server's packet sequence.
+/// Pure acks and retransmit requests repeat the current sequence instead of consuming one,
+/// and handshake packets precede sequencing entirely.
+pub fn is_sequenced(header: &PacketHeader) -> bool {
+    let significant = header.flags & !(flags::ENCRYPTED_CHECKSUM | flags::RETRANSMISSION);
+    header.sequence != 0
+        && significant != flags::ACK_SEQUENCE
+        && significant & (flags::REQUEST_RETRANSMIT | flags::CONNECT_REQUEST) == 0
+}
+
+#[derive(Debug, Clone)]
+pub struct InboundPacket {
+    pub header: PacketHeader,
+    pub data: Vec<u8>,
+}
+
+/// Reorders inbound server packets so they are released strictly in sequence order.
+///
+/// The session starts the buffer at the first sequence the server will send; without that
+/// it anchors on the first sequenced packet it sees. From then on any gap holds back later
+/// packets until the missing ones arrive or the server rejects them. Packets more than
+/// `capacity` ahead of the gap are dropped, so one bogus sequence cannot open a huge gap.
+#[derive(Debug)]
+pub struct ReorderBuffer {
+    next_expected: Option<u32>,
+    pending: BTreeMap<u32, InboundPacket>,
+    requested: HashMap<u32, Instant>,
+    ready: VecDeque<InboundPacket>,
+    capacity: usize,
+}
+
+impl Default for ReorderBuffer {
+    fn default() -> Self {
+        Self::new(MAX_REORDER_BUFFER)
+    }
+}
+
+impl ReorderBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            next_expected: None,
+            pending: BTreeMap::new(),
+            requested: HashMap::new(),
+            ready: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Expects `sequence` to be the first sequenced packet, so one that overtakes it is held
+    /// back rather than taken as the start. Does nothing once packets have been accepted.
+    pub fn start_at(&mut self, sequence: u32) {
+        if self.next_expected.is_none() {
+            self.next_expected = Some(sequence);
+        }
+    }
+
+    /// Highest sequence received with no gaps before it, i.e. the value safe to ack.
+    pub fn last_contiguous(&self) -> Option<u32> {
+        self.next_expected.map(|next| next.wrapping_sub(1))
+    }
+
+    /// Number of out-of-order packets being held back.
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Offers a packet to the buffer. Returns `false` if it was dropped, either as a
+    /// duplicate or for being too far ahead of the next expected sequence.
+    pub fn accept(&mut self, header: PacketHeader, data: Vec<u8>) -> bool {
+        if !is_sequenced(&header) {
+            self.ready.push_back(InboundPacket { header, data });
+            return true;
+        }
+
+        let sequence = header.sequence;
+        let next = *self.next_expected.get_or_insert(sequence);
+
+        if sequence < next || self.pending.contains_key(&sequence) {
+            log::debug!("Dropping duplicate server packet Seq={}", sequence);
+            return false;
+        }
+        if !self.in_window(next, sequence) {
+            log::warn!(
+                "Dropping server packet Seq={} too far ahead of Seq={}",
+                sequence,
+                next
+            );
+            return false;
+        }
+
+        self.requested.remove(&sequence);
+        self.pending
+            .insert(sequence, InboundPacket { header, data });
+        self.release_contiguous();
+
+        // Everything held back lies within `capacity` past the gap at `next_expected`, which
+        // `in_window` enforces, so the buffer cannot grow beyond that
+        debug_assert!(self.pending.len() <= self.capacity);
+
+        true
+    }
+
+    /// The server has told us it cannot resend these sequences; stop waiting for them.
+    pub fn reject(&mut self, sequences: &[u32]) {
+        let Some(next) = self.next_expected else {
+            return;
+        };
+
+        let mut lost: Vec<u32> = sequences
+            .iter()
+            .copied()
+            .filter(|&s| s >= next && self.in_window(next, s))
+            .collect();
+        lost.sort_unstable();
+        for sequence in lost {
+            self.requested.remove(&sequence);
+            if Some(sequence) == self.next_expected {
+                log::warn!("Server rejected retransmit of Seq={}, skipping", sequence);
+                self.skip_to(sequence.wrapping_add(1));
+            } else {
+                // Remember the hole so it is skipped once the buffer reaches it
+                self.pending.entry(sequence).or_insert(InboundPacket {
+                    header: PacketHeader {
+                        sequence,
+                        ..Default::default()
+                    },
+                    data: Vec::new(),
+                });
+            }
+        }
+    }
+
+    /// Missing sequences that have not been requested recently. Marks them as requested.
+    pub fn take_missing(&mut self, now: Instant) -> Vec<u32> {
+        let (Some(next), Some(&highest)) = (self.next_expected, self.pending.keys().next_back())
+        else {
+            return Vec::new();
+        };
+
+        let mut missing = Vec::new();
+        for sequence in next..highest {
+            if self.pending.contains_key(&sequence) {
+                continue;
+            }
+            let due = self
+                .requested
+                .get(&sequence)
+                .is_none_or(|&at| now.duration_since(at) >= RETRANSMIT_REQUEST_INTERVAL);
+            if due {
+                self.requested.insert(sequence, now);
+                missing.push(sequence);
+            }
+        }
+        missing
+    }
+
+    /// Pops the next packet that is ready for fragment processing.
+    pub fn pop_ready(&mut self) -> Option<InboundPacket> {
+        self.ready.pop_front()
+    }
+
+    pub fn clear(&mut self) {
+        self.next_expected = None;
+        self.pending.clear();
+        self.requested.clear();
+        self.ready.clear();
+    }
+
+    /// Whether `sequence` is close enough past `next` to be held while waiting for the gap.
+    fn in_window(&self, next: u32, sequence: u32) -> bool {
+        sequence - next <= self.capacity as u32
+    }
+
+    fn skip_to(&mut self, sequence: u32) {
+        self.next_expected = Some(sequence);
+        self.requested.retain(|&s, _| s >= sequence);
+        self.release_contiguous();
+    }
+
+    fn release_contiguous(&mut self) {
+        while let Some(next) = self.next_expected
+            && let Some(packet) = self.pending.remove(&next)
+        {
+            // Rejected placeholders carry no payload and are never delivered
+            if packet.header.flags != 0 {
+                self.ready.push_back(packet);
+            }
+            self.next_expected = Some(next.wrapping_add(1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sent(sequence: u32) -> SentPacket {
+        SentPacket {
+            header: PacketHeader {
+                sequence,
+                ..Default::default()
+            },
+            payload: Vec::new(),
+            isaac_key: None,
+            sent_at: Instant::now(),
+            retransmits: 0,
+        }
+    }
+
+    #[test]
+    fn test_acknowledge_releases_up_to_sequence() {
+        let mut window = RetransmitWindow::default();
+        for seq in 2..=6 {
+            window.insert(sent(seq));
+        }
+
+        assert_eq!(window.acknowledge(4), 3);
+        assert_eq!(window.len(), 2);
+        assert!(!window.contains(4));
+        assert!(window.contains(5));
+        assert!(window.contains(6));
+
+        // Stale acks are harmless
+        assert_eq!(window.acknowledge(3), 0);
+        assert_eq!(window.len(), 2);
+    }
+
+    #[test]
+    fn test_window_is_bounded() {
+        let mut window = RetransmitWindow::new(4);
+        for seq in 1..=10 {
+            window.insert(sent(seq));
+        }
+
+        assert_eq!(window.len(), 4);
+        assert!(!window.contains(6));
+        assert!(window.contains(7));
+        assert!(window.contains(10));
+    }
+
+    fn inbound(sequence: u32) -> PacketHeader {
+        PacketHeader {
+            sequence,
+            flags: flags::BLOB_FRAGMENTS | flags::ENCRYPTED_CHECKSUM,
+            ..Default::default()
+        }
+    }
+
+    fn drain(buffer: &mut ReorderBuffer) -> Vec<u32> {
+        std::iter::from_fn(|| buffer.pop_ready())
+            .map(|p| p.header.sequence)
+            .collect()
+    }
+
+    #[test]
+    fn test_reorder_releases_in_sequence() {
+        let mut buffer = ReorderBuffer::default();
+        assert!(buffer.accept(inbound(2), Vec::new()));
+        assert!(buffer.accept(inbound(4), Vec::new()));
+        assert_eq!(drain(&mut buffer), vec![2]);
+        assert_eq!(buffer.last_contiguous(), Some(2));
+
+        assert_eq!(buffer.take_missing(Instant::now()), vec![3]);
+        // Not asked again straight away
+        assert!(buffer.take_missing(Instant::now()).is_empty());
+
+        assert!(buffer.accept(inbound(3), Vec::new()));
+        assert_eq!(drain(&mut buffer), vec![3, 4]);
+        assert_eq!(buffer.last_contiguous(), Some(4));
+
+        // Duplicates are dropped
+        assert!(!buffer.accept(inbound(3), Vec::new()));
+        assert!(drain(&mut buffer).is_empty());
+    }
+
+    #[test]
+    fn test_unsequenced_packets_bypass_ordering() {
+        let mut buffer = ReorderBuffer::default();
+        buffer.accept(inbound(5), Vec::new());
+        buffer.accept(inbound(7), Vec::new());
+        let ack = PacketHeader {
+            sequence: 5,
+            flags: flags::ACK_SEQUENCE | flags::ENCRYPTED_CHECKSUM,
+            ..Default::default()
+        };
+        buffer.accept(ack, Vec::new());
+        assert_eq!(drain(&mut buffer), vec![5, 5]);
+        assert_eq!(buffer.last_contiguous(), Some(5));
+    }
+
+    #[test]
+    fn test_reject_skips_lost_sequences() {
+        let mut buffer = ReorderBuffer::default();
+        buffer.accept(inbound(1), Vec::new());
+        buffer.accept(inbound(4), Vec::new());
+        assert_eq!(drain(&mut buffer), vec![1]);
+
+        buffer.reject(&[3]);
+        assert_eq!(buffer.take_missing(Instant::now()), vec![2]);
+        assert!(drain(&mut buffer).is_empty());
+
+        buffer.reject(&[2]);
+        assert_eq!(drain(&mut buffer), vec![4]);
+        assert_eq!(buffer.last_contiguous(), Some(4));
+
+        // Rejections far past the gap are ignored rather than remembered
+        buffer.reject(&[u32::MAX]);
+        assert_eq!(buffer.pending_len(), 0);
+    }
+
+    #[test]
+    fn test_first_packet_out_of_order() {
+        let mut buffer = ReorderBuffer::default();
+        buffer.start_at(2);
+        assert!(buffer.accept(inbound(3), Vec::new()));
+        assert!(drain(&mut buffer).is_empty());
+        assert_eq!(buffer.take_missing(Instant::now()), vec![2]);
+
+        // The overtaken first packet is delivered, not taken for a duplicate
+        assert!(buffer.accept(inbound(2), Vec::new()));
+        assert_eq!(drain(&mut buffer), vec![2, 3]);
+        assert_eq!(buffer.last_contiguous(), Some(3));
+
+        // Starting again once packets have flowed changes nothing
+        buffer.start_at(1);
+        assert_eq!(buffer.last_contiguous(), Some(3));
+    }
+
+    #[test]
+    fn test_sequences_far_ahead_are_dropped() {
+        let mut buffer = ReorderBuffer::new(8);
+        buffer.start_at(1);
+        assert!(!buffer.accept(inbound(u32::MAX), Vec::new()));
+        assert!(!buffer.accept(inbound(10), Vec::new()));
+        assert!(buffer.accept(inbound(9), Vec::new()));
+        assert_eq!(buffer.pending_len(), 1);
+        assert_eq!(buffer.take_missing(Instant::now()).len(), 8);
+    }
+}
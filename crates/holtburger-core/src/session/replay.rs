@@ -0,0 +1,489 @@
+//! Capture replay with pacing, pausing and seeking.
+//!
+//! `ReplayTransport` serves the inbound packets of a capture to a `Session`; a cloneable
+//! `ReplayControl` steers it from elsewhere (the `Client`, a UI). Seeking only ever moves
+//! forward, by fast-forwarding through the packets in between so that everything built
+//! from them stays consistent; seeking backwards means rewinding to the start first.
+
+use crate::session::capture::{CaptureReader, Direction};
+use crate::session::{Transport, async_trait};
+use anyhow::{Result, anyhow};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+/// How quickly a replay delivers packets while running.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    /// With the gaps between packets as recorded.
+    RealTime,
+    /// Recorded gaps divided by this factor.
+    Scaled(f64),
+    /// As fast as the session reads them.
+    Unlimited,
+}
+
+impl ReplaySpeed {
+    fn factor(&self) -> Option<f64> {
+        match self {
+            ReplaySpeed::RealTime => Some(1.0),
+            ReplaySpeed::Scaled(factor) if *factor > 0.0 => Some(*factor),
+            _ => None,
+        }
+    }
+
+    /// Twice as fast, up to unlimited.
+    pub fn faster(self) -> Self {
+        match self.factor() {
+            Some(f) if f * 2.0 > 64.0 => ReplaySpeed::Unlimited,
+            Some(f) => ReplaySpeed::Scaled(f * 2.0),
+            None => ReplaySpeed::Unlimited,
+        }
+    }
+
+    /// Half as fast, down from unlimited.
+    pub fn slower(self) -> Self {
+        match self.factor() {
+            Some(f) => ReplaySpeed::Scaled((f / 2.0).max(1.0 / 64.0)),
+            None => ReplaySpeed::Scaled(64.0),
+        }
+    }
+}
+
+impl std::fmt::Display for ReplaySpeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplaySpeed::RealTime => write!(f, "1x"),
+            ReplaySpeed::Scaled(factor) => write!(f, "{}x", factor),
+            ReplaySpeed::Unlimited => write!(f, "max"),
+        }
+    }
+}
+
+impl std::str::FromStr for ReplaySpeed {
+    type Err = anyhow::Error;
+
+    /// Accepts `real`, `max`, or a factor such as `10` or `0.5x`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "real" | "realtime" | "1" | "1x" => Ok(ReplaySpeed::RealTime),
+            "max" | "fast" | "unlimited" => Ok(ReplaySpeed::Unlimited),
+            other => {
+                let factor: f64 = other
+                    .trim_end_matches('x')
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid replay speed '{}'", s))?;
+                if factor > 0.0 {
+                    Ok(ReplaySpeed::Scaled(factor))
+                } else {
+                    Err(anyhow!("Replay speed must be positive"))
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayStatus {
+    /// Inbound packets delivered so far.
+    pub index: usize,
+    pub total: usize,
+    /// Capture time of the last delivered packet, relative to the first.
+    pub position: Duration,
+    pub duration: Duration,
+    pub paused: bool,
+    pub speed: ReplaySpeed,
+    /// Whether a seek is still fast-forwarding.
+    pub seeking: bool,
+}
+
+#[derive(Debug)]
+struct ReplayState {
+    cursor: usize,
+    paused: bool,
+    /// Packets still to release while paused.
+    steps: usize,
+    speed: ReplaySpeed,
+    /// Wall-clock instant paired with the capture timestamp it corresponds to.
+    anchor: Option<(Instant, u64)>,
+    /// Deliver without pacing until reaching this capture timestamp, then pause.
+    fast_forward_until: Option<u64>,
+    /// Wait for further instructions at the end instead of ending the session.
+    hold_at_end: bool,
+}
+
+struct Shared {
+    state: Mutex<ReplayState>,
+    changed: Notify,
+}
+
+/// Inbound packets of a capture, loaded up front so the replay can be rewound.
+struct Timeline {
+    packets: Vec<(u64, SocketAddr, Vec<u8>)>,
+}
+
+impl Timeline {
+    fn start(&self) -> u64 {
+        self.packets.first().map_or(0, |p| p.0)
+    }
+
+    fn offset(&self, timestamp_us: u64) -> Duration {
+        Duration::from_micros(timestamp_us.saturating_sub(self.start()))
+    }
+}
+
+/// Handle for steering a running replay.
+#[derive(Clone)]
+pub struct ReplayControl {
+    shared: Arc<Shared>,
+    timeline: Arc<Timeline>,
+}
+
+impl ReplayControl {
+    fn update(&self, f: impl FnOnce(&mut ReplayState)) {
+        f(&mut self.shared.state.lock().unwrap());
+        self.shared.changed.notify_waiters();
+    }
+
+    pub fn status(&self) -> ReplayStatus {
+        let state = self.shared.state.lock().unwrap();
+        let position = match state.cursor.checked_sub(1) {
+            Some(last) => self.timeline.offset(self.timeline.packets[last].0),
+            None => Duration::ZERO,
+        };
+        let duration = self
+            .timeline
+            .packets
+            .last()
+            .map_or(Duration::ZERO, |p| self.timeline.offset(p.0));
+        ReplayStatus {
+            index: state.cursor,
+            total: self.timeline.packets.len(),
+            position,
+            duration,
+            paused: state.paused,
+            speed: state.speed,
+            seeking: state.fast_forward_until.is_some(),
+        }
+    }
+
+    /// Where the capture starts, i.e. the server the client first talked to.
+    pub fn start_addr(&self) -> Option<SocketAddr> {
+        self.timeline.packets.first().map(|p| p.1)
+    }
+
+    pub fn pause(&self) {
+        self.update(|s| {
+            s.paused = true;
+            s.fast_forward_until = None;
+        });
+    }
+
+    pub fn resume(&self) {
+        self.update(|s| {
+            s.paused = false;
+            s.steps = 0;
+            s.anchor = None;
+        });
+    }
+
+    pub fn toggle_pause(&self) {
+        if self.status().paused {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+
+    /// Pauses, then lets exactly one more packet through.
+    pub fn step(&self) {
+        self.update(|s| {
+            s.paused = true;
+            s.steps += 1;
+        });
+    }
+
+    pub fn set_speed(&self, speed: ReplaySpeed) {
+        self.update(|s| {
+            s.speed = speed;
+            s.anchor = None;
+        });
+    }
+
+    pub fn set_hold_at_end(&self, hold: bool) {
+        self.update(|s| s.hold_at_end = hold);
+    }
+
+    /// Fast-forwards to `position` (relative to the start of the capture) and pauses there.
+    /// Returns `false` if that is behind the replay, which has to be rewound first.
+    pub fn seek(&self, position: Duration) -> bool {
+        if position < self.status().position {
+            return false;
+        }
+        let target = self.timeline.start() + position.as_micros() as u64;
+        self.update(|s| s.fast_forward_until = Some(target));
+        true
+    }
+
+    /// Fast-forwards until stopped by `pause`, e.g. once a message count is reached.
+    pub fn fast_forward(&self) {
+        self.update(|s| s.fast_forward_until = Some(u64::MAX));
+    }
+
+    /// Moves back to the first packet. Whatever consumed the replay must be reset too.
+    pub fn rewind(&self) {
+        self.update(|s| {
+            s.cursor = 0;
+            s.steps = 0;
+            s.anchor = None;
+        });
+    }
+}
+
+pub struct ReplayTransport {
+    control: ReplayControl,
+}
+
+impl ReplayTransport {
+    pub fn open(path: &str) -> Result<Self> {
+        let mut reader = CaptureReader::open(path)?;
+        let mut packets = Vec::new();
+        while let Some(entry) = reader.read_next()? {
+            if entry.direction == Direction::Inbound {
+                packets.push((entry.timestamp_us, entry.addr, entry.data));
+            }
+        }
+        Ok(Self::from_timeline(Timeline { packets }))
+    }
+
+    fn from_timeline(timeline: Timeline) -> Self {
+        let state = ReplayState {
+            cursor: 0,
+            paused: false,
+            steps: 0,
+            speed: ReplaySpeed::Unlimited,
+            anchor: None,
+            fast_forward_until: None,
+            hold_at_end: false,
+        };
+        Self {
+            control: ReplayControl {
+                shared: Arc::new(Shared {
+                    state: Mutex::new(state),
+                    changed: Notify::new(),
+                }),
+                timeline: Arc::new(timeline),
+            },
+        }
+    }
+
+    pub fn control(&self) -> ReplayControl {
+        self.control.clone()
+    }
+}
+
+enum Next {
+    Deliver(usize),
+    WaitUntil(Instant),
+    Wait,
+    End,
+}
+
+impl ReplayState {
+    fn next(&mut self, timeline: &Timeline) -> Next {
+        let Some(&(timestamp, _, _)) = timeline.packets.get(self.cursor) else {
+            if self.hold_at_end {
+                self.fast_forward_until = None;
+                return Next::Wait;
+            }
+            return Next::End;
+        };
+
+        if let Some(target) = self.fast_forward_until {
+            if timestamp <= target {
+                self.cursor += 1;
+                return Next::Deliver(self.cursor - 1);
+            }
+            self.fast_forward_until = None;
+            self.paused = true;
+            self.anchor = None;
+        }
+
+        if self.paused {
+            if self.steps == 0 {
+                return Next::Wait;
+            }
+            self.steps -= 1;
+            self.cursor += 1;
+            return Next::Deliver(self.cursor - 1);
+        }
+
+        if let Some(factor) = self.speed.factor() {
+            let now = Instant::now();
+            // Pace from the packet delivered last, so the gap before this one is kept
+            let previous = match self.cursor.checked_sub(1) {
+                Some(last) => timeline.packets[last].0,
+                None => timestamp,
+            };
+            let (anchor_at, anchor_ts) = *self.anchor.get_or_insert((now, previous));
+            let gap = timestamp.saturating_sub(anchor_ts) as f64 / factor;
+            let due = anchor_at + Duration::from_micros(gap as u64);
+            if now < due {
+                return Next::WaitUntil(due);
+            }
+        }
+        self.cursor += 1;
+        Next::Deliver(self.cursor - 1)
+    }
+}
+
+#[async_trait]
+impl Transport for ReplayTransport {
+    async fn send_to(&self, buf: &[u8], _addr: SocketAddr) -> Result<usize> {
+        Ok(buf.len())
+    }
+
+    async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        let shared = &self.control.shared;
+        let timeline = &self.control.timeline;
+        loop {
+            // Register for wake-ups before looking, so a control change in between is not lost
+            let changed = shared.changed.notified();
+            let next = shared.state.lock().unwrap().next(timeline);
+            match next {
+                Next::Deliver(index) => {
+                    let (_, addr, data) = &timeline.packets[index];
+                    let len = data.len().min(buf.len());
+                    buf[..len].copy_from_slice(&data[..len]);
+                    return Ok((len, *addr));
+                }
+                Next::End => return Err(anyhow!("End of capture")),
+                Next::Wait => changed.await,
+                Next::WaitUntil(due) => {
+                    tokio::select! {
+                        _ = changed => {}
+                        _ = tokio::time::sleep_until(due) => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transport(gaps_ms: &[u64]) -> ReplayTransport {
+        let addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let mut timestamp = 1_000_000;
+        let packets = std::iter::once(0)
+            .chain(gaps_ms.iter().copied())
+            .enumerate()
+            .map(|(i, gap)| {
+                timestamp += gap * 1000;
+                (timestamp, addr, vec![i as u8])
+            })
+            .collect();
+        ReplayTransport::from_timeline(Timeline { packets })
+    }
+
+    async fn next(transport: &ReplayTransport) -> u8 {
+        let mut buf = [0u8; 4];
+        transport.recv_from(&mut buf).await.unwrap();
+        buf[0]
+    }
+
+    async fn pending(transport: &ReplayTransport) -> bool {
+        let mut buf = [0u8; 4];
+        tokio::time::timeout(Duration::from_millis(1), transport.recv_from(&mut buf))
+            .await
+            .is_err()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_paced_replay_keeps_recorded_gaps() {
+        let transport = transport(&[1000, 3000]);
+        let control = transport.control();
+        control.set_speed(ReplaySpeed::RealTime);
+
+        let started = Instant::now();
+        assert_eq!(next(&transport).await, 0);
+        assert_eq!(next(&transport).await, 1);
+        assert_eq!(started.elapsed(), Duration::from_secs(1));
+
+        control.set_speed(ReplaySpeed::Scaled(10.0));
+        assert_eq!(next(&transport).await, 2);
+        assert_eq!(started.elapsed(), Duration::from_millis(1300));
+
+        let mut buf = [0u8; 4];
+        assert!(transport.recv_from(&mut buf).await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pause_step_and_resume() {
+        let transport = transport(&[10, 10, 10]);
+        let control = transport.control();
+        control.pause();
+        assert!(pending(&transport).await);
+
+        control.step();
+        assert_eq!(next(&transport).await, 0);
+        assert!(pending(&transport).await);
+        assert_eq!(control.status().index, 1);
+        assert!(control.status().paused);
+
+        control.resume();
+        assert_eq!(next(&transport).await, 1);
+        assert_eq!(next(&transport).await, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_seek_fast_forwards_then_pauses() {
+        let transport = transport(&[60_000, 60_000, 60_000, 60_000]);
+        let control = transport.control();
+        control.set_speed(ReplaySpeed::RealTime);
+        control.set_hold_at_end(true);
+
+        let started = Instant::now();
+        assert!(control.seek(Duration::from_secs(120)));
+        assert_eq!(next(&transport).await, 0);
+        assert_eq!(next(&transport).await, 1);
+        assert_eq!(next(&transport).await, 2);
+        assert!(pending(&transport).await);
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        let status = control.status();
+        assert_eq!(status.position, Duration::from_secs(120));
+        assert!(status.paused && !status.seeking);
+        assert!(!control.seek(Duration::from_secs(60)));
+
+        control.rewind();
+        control.step();
+        assert_eq!(next(&transport).await, 0);
+    }
+
+    #[test]
+    fn test_parse_replay_speed() {
+        assert_eq!(
+            "real".parse::<ReplaySpeed>().unwrap(),
+            ReplaySpeed::RealTime
+        );
+        assert_eq!(
+            "max".parse::<ReplaySpeed>().unwrap(),
+            ReplaySpeed::Unlimited
+        );
+        assert_eq!(
+            "10x".parse::<ReplaySpeed>().unwrap(),
+            ReplaySpeed::Scaled(10.0)
+        );
+        assert!("-2".parse::<ReplaySpeed>().is_err());
+        assert_eq!(
+            ReplaySpeed::Scaled(32.0).faster(),
+            ReplaySpeed::Scaled(64.0)
+        );
+        assert_eq!(ReplaySpeed::Scaled(64.0).faster(), ReplaySpeed::Unlimited);
+    }
+}
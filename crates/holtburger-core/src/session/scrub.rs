@@ -0,0 +1,939 @@
+//! Redacting credentials and identities from captured traffic.
+//!
+//! Captures and message dumps get attached to bug reports. `CaptureWriter` scrubs packets
+//! as they are written according to a `ScrubLevel`, and `anonymize` rewrites an existing
+//! capture so the account, its characters' names and their GUIDs are replaced by stand-ins
+//! throughout.
+//!
+//! Replacements inside game messages always keep the original length, so message layouts
+//! and fragment boundaries are untouched; only packet checksums need recomputing, which is
+//! done with the ISAAC key recovered from the original checksum.
+
+use crate::protocol::bodies;
+use crate::protocol::messages::{
+    GameActionKind, GameMessage, HEADER_SIZE, LoginRequest, PacketHeader, flags,
+    game_event_opcodes, opcodes, read_string16,
+};
+use crate::session::capture::{
+    CaptureHeader, CaptureReader, CaptureRecord, CaptureWriter, Direction, FLAG_COMPRESSED,
+    account_hash,
+};
+use crate::session::{calculate_payload_hash, split_fragments};
+use anyhow::{Result, anyhow};
+use binrw::{BinRead, BinWrite};
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::net::SocketAddr;
+
+/// Stands in for a redacted password or account name in a LOGIN_REQUEST.
+pub const REDACTED: &str = "<redacted>";
+/// First GUID handed out to anonymized characters. It sits below ACE's player range
+/// (0x50000001 and up) and every other range the server assigns.
+pub const STAND_IN_GUID_BASE: u32 = 0x4F000001;
+
+/// What to redact from captures and message dumps as they are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScrubLevel {
+    Off,
+    /// The login password only.
+    #[default]
+    Password,
+    /// The login password and the account name.
+    Account,
+}
+
+impl std::str::FromStr for ScrubLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "off" | "none" => Ok(ScrubLevel::Off),
+            "password" => Ok(ScrubLevel::Password),
+            "account" => Ok(ScrubLevel::Account),
+            _ => Err(anyhow!(
+                "Invalid scrub level '{}' (expected off, password or account)",
+                s
+            )),
+        }
+    }
+}
+
+/// The ISAAC key a packet's checksum was sealed with, or 0 for unencrypted packets.
+fn packet_key(packet: &[u8]) -> u32 {
+    let header = PacketHeader::unpack(&packet[..HEADER_SIZE]);
+    header.checksum.wrapping_sub(header.calculate_checksum())
+        ^ calculate_payload_hash(header.flags, &packet[HEADER_SIZE..])
+}
+
+/// Re-packs a packet's header around `payload`, sealing the checksum with `key`.
+fn seal_packet(original: &[u8], payload: &[u8], key: u32) -> Vec<u8> {
+    let mut header = PacketHeader::unpack(&original[..HEADER_SIZE]);
+    header.size = payload.len() as u16;
+    header.checksum = header
+        .calculate_checksum()
+        .wrapping_add(calculate_payload_hash(header.flags, payload) ^ key);
+    let mut packet = vec![0u8; HEADER_SIZE];
+    header.pack(&mut packet);
+    packet.extend_from_slice(payload);
+    packet
+}
+
+/// Re-packs a packet around a modified payload. The checksum is recomputed with the same
+/// ISAAC key the original was sealed with (none for unencrypted packets), so it still
+/// verifies against the session keys.
+pub fn reseal_packet(original: &[u8], payload: &[u8]) -> Vec<u8> {
+    seal_packet(original, payload, packet_key(original))
+}
+
+/// The login request carried by a LOGIN_REQUEST packet.
+pub fn login_request(data: &[u8]) -> Option<LoginRequest> {
+    if data.len() < HEADER_SIZE
+        || PacketHeader::unpack(&data[..HEADER_SIZE]).flags & flags::LOGIN_REQUEST == 0
+    {
+        return None;
+    }
+    LoginRequest::unpack(&data[HEADER_SIZE..])
+}
+
+/// Rewrites the credentials in a LOGIN_REQUEST packet. Returns `None` if the packet is not
+/// a login request or there is nothing to change.
+fn rewrite_login(data: &[u8], password: bool, account: Option<&str>) -> Option<Vec<u8>> {
+    let mut login = login_request(data)?;
+    if password {
+        login.password = REDACTED.to_string();
+    }
+    if let Some(account) = account {
+        login.account = account.to_string();
+    }
+    Some(reseal_packet(data, &login.pack()))
+}
+
+/// Redacts what `level` asks for from an outbound LOGIN_REQUEST packet. Returns `None` if
+/// the packet is not a login request or nothing is to be scrubbed.
+pub fn scrub_login_packet(data: &[u8], level: ScrubLevel) -> Option<Vec<u8>> {
+    match level {
+        ScrubLevel::Off => None,
+        ScrubLevel::Password => rewrite_login(data, true, None),
+        ScrubLevel::Account => rewrite_login(data, true, Some(REDACTED)),
+    }
+}
+
+/// Masks the account name in the string fields of a game message body (e.g. CharacterList),
+/// keeping its length. Only the `Account` level touches message bodies.
+pub fn scrub_message(body: &mut [u8], account: &str, level: ScrubLevel) {
+    if level == ScrubLevel::Account && !account.is_empty() {
+        let mask = vec![b'*'; account.len()];
+        replace_names(body, &[(account.as_bytes().to_vec(), mask)]);
+    }
+}
+
+/// Masks the account name in every message fragment of a captured packet, resealing its
+/// checksum. Returns `None` if nothing was masked. A message split across several
+/// fragments is left as is.
+pub fn scrub_packet_messages(data: &[u8], account: &str, level: ScrubLevel) -> Option<Vec<u8>> {
+    if level != ScrubLevel::Account || account.is_empty() || data.len() < HEADER_SIZE {
+        return None;
+    }
+    let header = PacketHeader::unpack(&data[..HEADER_SIZE]);
+    let base = data[HEADER_SIZE..].as_ptr() as usize;
+    let ranges: Vec<(usize, usize)> = split_fragments(header.flags, &data[HEADER_SIZE..])
+        .into_iter()
+        .map(|(_, fragment)| (fragment.as_ptr() as usize - base, fragment.len()))
+        .collect();
+
+    let mut payload = data[HEADER_SIZE..].to_vec();
+    for (start, len) in ranges {
+        scrub_message(&mut payload[start..start + len], account, level);
+    }
+    (payload != data[HEADER_SIZE..]).then(|| reseal_packet(data, &payload))
+}
+
+/// Byte offsets of the GUID fields in a message that can name a character: each entry of a
+/// CharacterList, and the object that messages about a single object lead with.
+fn guid_offsets(body: &[u8]) -> Vec<usize> {
+    if body.len() < 8 {
+        return Vec::new();
+    }
+    match LittleEndian::read_u32(body) {
+        opcodes::CHARACTER_LIST => {
+            let mut offsets = Vec::new();
+            let count = body.get(8..12).map_or(0, LittleEndian::read_u32);
+            let mut offset = 12;
+            for _ in 0..count {
+                if offset + 4 > body.len() {
+                    break;
+                }
+                offsets.push(offset);
+                offset += 4;
+                read_string16(body, &mut offset);
+                offset += 4;
+            }
+            offsets
+        }
+        opcodes::PARENT_EVENT if body.len() >= 12 => vec![4, 8],
+        opcodes::CHARACTER_ENTER_WORLD
+        | opcodes::CHARACTER_ENTER_WORLD_REQUEST
+        | opcodes::OBJECT_CREATE
+        | opcodes::PLAYER_CREATE
+        | opcodes::OBJECT_DELETE
+        | opcodes::PARENT_EVENT
+        | opcodes::PICKUP_EVENT
+        | opcodes::SET_STATE
+        | opcodes::UPDATE_OBJECT
+        | opcodes::PLAY_EFFECT
+        | opcodes::GAME_EVENT
+        | opcodes::UPDATE_MOTION
+        | opcodes::UPDATE_POSITION
+        | opcodes::VECTOR_UPDATE => vec![4],
+        _ => Vec::new(),
+    }
+}
+
+/// Swaps GUIDs found in `body`'s known GUID fields for their stand-ins. Returns the number
+/// of fields rewritten.
+fn replace_guids(body: &mut [u8], guids: &HashMap<u32, u32>) -> usize {
+    let mut count = 0;
+    for offset in guid_offsets(body) {
+        let field = &mut body[offset..offset + 4];
+        if let Some(&stand_in) = guids.get(&LittleEndian::read_u32(field)) {
+            LittleEndian::write_u32(field, stand_in);
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Replaces names in the strings that `fields` picks out of the `T` at `body[offset..]`.
+/// Nothing is written unless `T` reads and writes back byte for byte, so fields the
+/// layout does not describe are never touched. Returns the number of replacements.
+fn replace_in_strings<T>(
+    body: &mut [u8],
+    offset: usize,
+    replacements: &[(Vec<u8>, Vec<u8>)],
+    fields: fn(&mut T) -> Vec<&mut String>,
+) -> usize
+where
+    T: for<'a> BinRead<Args<'a> = ()> + for<'a> BinWrite<Args<'a> = ()>,
+{
+    let Some(original) = body.get(offset..) else {
+        return 0;
+    };
+    let mut cursor = Cursor::new(original);
+    let Ok(mut value) = T::read_le(&mut cursor) else {
+        return 0;
+    };
+    let len = cursor.position() as usize;
+    let pack = |value: &T| {
+        let mut out = Cursor::new(Vec::new());
+        value.write_le(&mut out).ok().map(|_| out.into_inner())
+    };
+    if pack(&value).as_deref() != Some(&original[..len]) {
+        return 0;
+    }
+
+    let mut count = 0;
+    for field in fields(&mut value) {
+        let mut bytes = std::mem::take(field).into_bytes();
+        count += replace_all(&mut bytes, replacements);
+        *field = String::from_utf8_lossy(&bytes).into_owned();
+    }
+    match pack(&value) {
+        Some(packed) if count > 0 && packed.len() == len => {
+            body[offset..offset + len].copy_from_slice(&packed);
+            count
+        }
+        _ => 0,
+    }
+}
+
+/// Replaces names in the string fields of the messages that carry them: the character
+/// list and world entry, object names, the player's string properties, friends, fellows,
+/// and chat either way. Returns the number of replacements.
+fn replace_names(body: &mut [u8], replacements: &[(Vec<u8>, Vec<u8>)]) -> usize {
+    if body.len() < 4 || replacements.is_empty() {
+        return 0;
+    }
+    match LittleEndian::read_u32(body) {
+        opcodes::CHARACTER_LIST => {
+            replace_in_strings(body, 4, replacements, |list: &mut bodies::CharacterList| {
+                let trailer = list.trailer.as_mut().map(|t| &mut t.account);
+                list.characters
+                    .iter_mut()
+                    .map(|c| &mut c.name)
+                    .chain(trailer)
+                    .collect()
+            })
+        }
+        opcodes::CHARACTER_ENTER_WORLD => replace_in_strings(
+            body,
+            4,
+            replacements,
+            |enter: &mut bodies::CharacterEnterWorld| vec![&mut enter.account],
+        ),
+        opcodes::OBJECT_CREATE | opcodes::UPDATE_OBJECT => replace_in_strings(
+            body,
+            4,
+            replacements,
+            |object: &mut bodies::ObjectCreate| {
+                let weenie = &mut object.weenie;
+                std::iter::once(&mut weenie.name)
+                    .chain(weenie.plural_name.as_mut())
+                    .collect()
+            },
+        ),
+        opcodes::HEAR_SPEECH => {
+            replace_in_strings(body, 4, replacements, |speech: &mut bodies::HearSpeech| {
+                vec![&mut speech.message, &mut speech.sender]
+            })
+        }
+        opcodes::SOUL_EMOTE => {
+            replace_in_strings(body, 4, replacements, |emote: &mut bodies::SoulEmote| {
+                vec![&mut emote.sender_name, &mut emote.text]
+            })
+        }
+        opcodes::GAME_EVENT if body.len() >= 16 => match LittleEndian::read_u32(&body[12..]) {
+            game_event_opcodes::PLAYER_DESCRIPTION => replace_in_strings(
+                body,
+                16,
+                replacements,
+                |description: &mut bodies::PlayerDescription| {
+                    description
+                        .string_properties
+                        .iter_mut()
+                        .flat_map(|table| table.entries.iter_mut().map(|p| &mut p.value))
+                        .collect()
+                },
+            ),
+            game_event_opcodes::FRIENDS_LIST_UPDATE => replace_in_strings(
+                body,
+                16,
+                replacements,
+                |update: &mut bodies::FriendsListUpdate| {
+                    update.friends.iter_mut().map(|f| &mut f.name).collect()
+                },
+            ),
+            game_event_opcodes::FELLOWSHIP_UPDATE_FELLOW => replace_in_strings(
+                body,
+                16,
+                replacements,
+                |update: &mut bodies::FellowshipUpdateFellow| vec![&mut update.fellow.name],
+            ),
+            game_event_opcodes::CHANNEL_BROADCAST => replace_in_strings(
+                body,
+                16,
+                replacements,
+                |broadcast: &mut bodies::ChannelBroadcast| {
+                    vec![&mut broadcast.sender, &mut broadcast.message]
+                },
+            ),
+            game_event_opcodes::TELL => {
+                replace_in_strings(body, 16, replacements, |tell: &mut bodies::Tell| {
+                    vec![&mut tell.message, &mut tell.sender]
+                })
+            }
+            game_event_opcodes::WEENIE_ERROR_WITH_STRING => replace_in_strings(
+                body,
+                16,
+                replacements,
+                |error: &mut bodies::WeenieErrorWithString| vec![&mut error.text],
+            ),
+            _ => 0,
+        },
+        opcodes::GAME_ACTION => replace_in_strings(
+            body,
+            8,
+            replacements,
+            |action: &mut GameActionKind| match action {
+                GameActionKind::Talk { message }
+                | GameActionKind::TellById { message, .. }
+                | GameActionKind::ChatChannel { message, .. }
+                | GameActionKind::Emote { message }
+                | GameActionKind::SoulEmote { message } => vec![message],
+                GameActionKind::Tell { message, target } => vec![message, target],
+                GameActionKind::AddFriend { name }
+                | GameActionKind::FellowshipCreate { name, .. } => vec![name],
+                _ => Vec::new(),
+            },
+        ),
+        _ => 0,
+    }
+}
+
+/// Replaces every occurrence of each pattern with its same-length substitute in a single
+/// pass, so substitutes are never themselves replaced. Longer patterns win over shorter
+/// ones at the same position. Returns the number of replacements.
+fn replace_all(data: &mut [u8], replacements: &[(Vec<u8>, Vec<u8>)]) -> usize {
+    let mut ordered: Vec<_> = replacements
+        .iter()
+        .filter(|(from, to)| !from.is_empty() && from.len() == to.len())
+        .collect();
+    ordered.sort_by_key(|(from, _)| std::cmp::Reverse(from.len()));
+
+    let mut count = 0;
+    let mut i = 0;
+    while i < data.len() {
+        match ordered.iter().find(|(from, _)| data[i..].starts_with(from)) {
+            Some((from, to)) => {
+                data[i..i + from.len()].copy_from_slice(to);
+                i += from.len();
+                count += 1;
+            }
+            None => i += 1,
+        }
+    }
+    count
+}
+
+/// A stand-in of exactly `len` bytes, e.g. `Char2xxx`, or the prefix's initial followed by
+/// letters derived from `index` when `len` is too short to hold the whole prefix.
+fn pseudonym(prefix: &str, index: usize, len: usize) -> Vec<u8> {
+    let tag = format!("{}{}", prefix, index);
+    if tag.len() <= len {
+        return tag
+            .bytes()
+            .chain(std::iter::repeat(b'x'))
+            .take(len)
+            .collect();
+    }
+    (0..len)
+        .map(|i| match i {
+            0 => prefix.as_bytes()[0],
+            _ => b'a' + ((index / 26usize.pow(i as u32 - 1)) % 26) as u8,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnonymizeReport {
+    /// The stand-in the account name was replaced with.
+    pub account: Option<String>,
+    pub characters: usize,
+    /// Occurrences replaced across all messages.
+    pub replacements: usize,
+    pub packets_rewritten: usize,
+}
+
+/// A fragment's bytes within a packet record.
+struct FragmentLocation {
+    record: usize,
+    index: u16,
+    start: usize,
+    len: usize,
+}
+
+/// Replaces the account name, character names and character GUIDs in a capture with
+/// consistent stand-ins, and redacts the login password.
+pub fn anonymize(records: &mut [CaptureRecord]) -> AnonymizeReport {
+    let mut report = AnonymizeReport::default();
+
+    // Group fragments into messages. Fragment sequences restart with every connection,
+    // so the peer and the login they follow are part of the key.
+    let mut messages: HashMap<(Direction, SocketAddr, usize, u32, u16), Vec<FragmentLocation>> =
+        HashMap::new();
+    let mut login = 0;
+    let mut account = None;
+    for (record, entry) in records.iter().enumerate() {
+        let CaptureRecord::Packet(entry) = entry else {
+            continue;
+        };
+        if entry.data.len() < HEADER_SIZE {
+            continue;
+        }
+        let header = PacketHeader::unpack(&entry.data[..HEADER_SIZE]);
+        if entry.direction == Direction::Outbound && header.flags & flags::LOGIN_REQUEST != 0 {
+            login += 1;
+            if let Some(request) = LoginRequest::unpack(&entry.data[HEADER_SIZE..]) {
+                account.get_or_insert(request.account);
+            }
+        }
+        let base = entry.data.as_ptr() as usize;
+        for (fragment, data) in split_fragments(header.flags, &entry.data[HEADER_SIZE..]) {
+            let key = (
+                entry.direction,
+                entry.addr,
+                login,
+                fragment.sequence,
+                fragment.count,
+            );
+            messages.entry(key).or_default().push(FragmentLocation {
+                record,
+                index: fragment.index,
+                start: data.as_ptr() as usize - base,
+                len: data.len(),
+            });
+        }
+    }
+
+    // Reassemble each complete message once, from the first copy of each fragment, noting
+    // where in the body every copy belongs. Copies with an index past the fragment count or
+    // a different length from the first copy are left out, so they are never rewritten.
+    let bodies: Vec<_> = messages
+        .iter()
+        .filter_map(|(&(.., count), locations)| {
+            let mut body = Vec::new();
+            let mut copies = Vec::new();
+            for index in 0..count {
+                let first = locations.iter().find(|l| l.index == index)?;
+                let CaptureRecord::Packet(entry) = &records[first.record] else {
+                    return None;
+                };
+                let offset = body.len();
+                body.extend_from_slice(&entry.data[first.start..first.start + first.len]);
+                copies.extend(
+                    locations
+                        .iter()
+                        .filter(|l| l.index == index && l.len == first.len)
+                        .map(|l| (offset, l)),
+                );
+            }
+            Some((body, copies))
+        })
+        .collect();
+
+    let mut replacements = Vec::new();
+    if let Some(account) = account.as_deref().filter(|a| !a.is_empty()) {
+        let stand_in = pseudonym("Account", 1, account.len());
+        report.account = Some(String::from_utf8_lossy(&stand_in).into_owned());
+        replacements.push((account.as_bytes().to_vec(), stand_in));
+    }
+    let mut characters: Vec<(u32, String)> = Vec::new();
+    for (body, _) in &bodies {
+        if let GameMessage::CharacterList { characters: list } = GameMessage::unpack(body) {
+            for character in list {
+                if !characters.iter().any(|(id, _)| *id == character.0) {
+                    characters.push(character);
+                }
+            }
+        }
+    }
+    // Stand-ins that already occur anywhere in the capture would be mistaken for the
+    // objects they belong to
+    let mut guids = HashMap::new();
+    let mut candidates = (STAND_IN_GUID_BASE..).filter(|candidate| {
+        let bytes = candidate.to_le_bytes();
+        !bodies
+            .iter()
+            .any(|(body, _)| body.windows(4).any(|w| w == bytes))
+    });
+    for (i, (guid, name)) in characters.iter().enumerate() {
+        if let Some(stand_in) = candidates.next() {
+            guids.insert(*guid, stand_in);
+        }
+        if !name.is_empty() {
+            replacements.push((
+                name.as_bytes().to_vec(),
+                pseudonym("Char", i + 1, name.len()),
+            ));
+        }
+    }
+    report.characters = characters.len();
+
+    // Write rewritten messages back over every copy of their fragments, noting each
+    // packet's key first since it can only be recovered from the original bytes
+    let keys: Vec<Option<u32>> = records
+        .iter()
+        .map(|record| match record {
+            CaptureRecord::Packet(entry) if entry.data.len() >= HEADER_SIZE => {
+                Some(packet_key(&entry.data))
+            }
+            _ => None,
+        })
+        .collect();
+    let mut dirty = vec![false; records.len()];
+    for (mut body, copies) in bodies {
+        let count = replace_names(&mut body, &replacements) + replace_guids(&mut body, &guids);
+        if count == 0 {
+            continue;
+        }
+        report.replacements += count;
+        for (from, location) in copies {
+            let CaptureRecord::Packet(entry) = &mut records[location.record] else {
+                continue;
+            };
+            entry.data[location.start..location.start + location.len]
+                .copy_from_slice(&body[from..from + location.len]);
+            dirty[location.record] = true;
+        }
+    }
+
+    for ((record, dirty), key) in records.iter_mut().zip(dirty).zip(keys) {
+        let CaptureRecord::Packet(entry) = record else {
+            continue;
+        };
+        if entry.direction == Direction::Outbound
+            && let Some(data) = rewrite_login(&entry.data, true, report.account.as_deref())
+        {
+            entry.data = data;
+            report.packets_rewritten += 1;
+        } else if dirty && let Some(key) = key {
+            entry.data = seal_packet(&entry.data, &entry.data[HEADER_SIZE..], key);
+            report.packets_rewritten += 1;
+        }
+    }
+    report
+}
+
+/// Anonymizes a capture file into a new v2 capture.
+pub fn anonymize_file(input: &str, output: &str, compress: bool) -> Result<AnonymizeReport> {
+    let mut reader = CaptureReader::open(input)?;
+    let header = reader.header().cloned();
+    let mut records = Vec::new();
+    while let Some(record) = reader.read_record()? {
+        records.push(record);
+    }
+    let report = anonymize(&mut records);
+
+    let server = records
+        .iter()
+        .find_map(|r| match r {
+            CaptureRecord::Packet(entry) => Some(entry.addr),
+            _ => None,
+        })
+        .unwrap_or_else(|| "127.0.0.1:9000".parse().unwrap());
+    let mut header = header.unwrap_or_else(|| CaptureHeader::new(server, ""));
+    header.flags &= !FLAG_COMPRESSED;
+    if let Some(account) = &report.account {
+        header.account_hash = account_hash(account);
+    }
+    let mut writer = CaptureWriter::create(output, header, compress)?;
+    for record in &records {
+        writer.write_record(record)?;
+    }
+    writer.finish()?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::messages::{
+        FRAGMENT_HEADER_SIZE, FragmentHeader, build_login_payload, opcodes, write_string16,
+    };
+    use crate::session::capture::CaptureEntry;
+
+    const KEY: u32 = 0x1234ABCD;
+
+    fn seal(mut header: PacketHeader, payload: &[u8], key: u32) -> Vec<u8> {
+        header.size = payload.len() as u16;
+        header.checksum = header
+            .calculate_checksum()
+            .wrapping_add(calculate_payload_hash(header.flags, payload) ^ key);
+        let mut packet = vec![0u8; HEADER_SIZE];
+        header.pack(&mut packet);
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn login_packet(account: &str, password: &str) -> Vec<u8> {
+        let header = PacketHeader {
+            flags: flags::LOGIN_REQUEST,
+            ..Default::default()
+        };
+        seal(header, &build_login_payload(account, password, 0), 0)
+    }
+
+    /// A packet carrying one fragment of message `sequence`.
+    fn fragment_packet(
+        sequence: u32,
+        message: u32,
+        count: u16,
+        index: u16,
+        body: &[u8],
+    ) -> Vec<u8> {
+        let mut payload = vec![0u8; FRAGMENT_HEADER_SIZE];
+        FragmentHeader {
+            sequence: message,
+            id: 0x80000000 | message,
+            count,
+            size: (FRAGMENT_HEADER_SIZE + body.len()) as u16,
+            index,
+            queue: 1,
+        }
+        .pack(&mut payload);
+        payload.extend_from_slice(body);
+        let header = PacketHeader {
+            sequence,
+            flags: flags::BLOB_FRAGMENTS | flags::ENCRYPTED_CHECKSUM,
+            id: 1,
+            ..Default::default()
+        };
+        seal(header, &payload, KEY)
+    }
+
+    fn record(direction: Direction, data: Vec<u8>) -> CaptureRecord {
+        CaptureRecord::Packet(CaptureEntry {
+            direction,
+            timestamp_us: 0,
+            addr: "127.0.0.1:9000".parse().unwrap(),
+            data,
+        })
+    }
+
+    fn character_list(characters: &[(u32, &str)], account: &str) -> Vec<u8> {
+        let mut list = Vec::new();
+        list.extend_from_slice(&opcodes::CHARACTER_LIST.to_le_bytes());
+        list.extend_from_slice(&0u32.to_le_bytes());
+        list.extend_from_slice(&(characters.len() as u32).to_le_bytes());
+        for (id, name) in characters {
+            list.extend_from_slice(&id.to_le_bytes());
+            write_string16(&mut list, name);
+            list.extend_from_slice(&0u32.to_le_bytes());
+        }
+        list.extend_from_slice(&[0; 8]);
+        write_string16(&mut list, account);
+        list.extend_from_slice(&[0; 8]);
+        list
+    }
+
+    fn enter_world(id: u32, account: &str) -> Vec<u8> {
+        let mut enter = opcodes::CHARACTER_ENTER_WORLD.to_le_bytes().to_vec();
+        enter.extend_from_slice(&id.to_le_bytes());
+        write_string16(&mut enter, account);
+        enter
+    }
+
+    fn packet_data(record: &CaptureRecord) -> &[u8] {
+        match record {
+            CaptureRecord::Packet(entry) => &entry.data,
+            _ => panic!("Expected a packet"),
+        }
+    }
+
+    #[test]
+    fn test_login_scrubbing() {
+        let packet = login_packet("Tester", "hunter2");
+        assert!(scrub_login_packet(&packet, ScrubLevel::Off).is_none());
+        assert!(
+            scrub_login_packet(&fragment_packet(1, 1, 1, 0, &[0; 4]), ScrubLevel::Account)
+                .is_none()
+        );
+
+        let scrubbed = scrub_login_packet(&packet, ScrubLevel::Password).unwrap();
+        let login = LoginRequest::unpack(&scrubbed[HEADER_SIZE..]).unwrap();
+        assert_eq!(
+            (login.account.as_str(), login.password.as_str()),
+            ("Tester", REDACTED)
+        );
+        assert_eq!(packet_key(&scrubbed), 0);
+
+        let scrubbed = scrub_login_packet(&packet, ScrubLevel::Account).unwrap();
+        let login = LoginRequest::unpack(&scrubbed[HEADER_SIZE..]).unwrap();
+        assert_eq!(login.account, REDACTED);
+
+        let mut body = enter_world(0x50000001, "Tester");
+        scrub_message(&mut body, "Tester", ScrubLevel::Password);
+        assert_eq!(body, enter_world(0x50000001, "Tester"));
+        scrub_message(&mut body, "Tester", ScrubLevel::Account);
+        assert_eq!(body, enter_world(0x50000001, "******"));
+    }
+
+    #[test]
+    fn test_anonymize_replaces_identities_consistently() {
+        let list = character_list(
+            &[(0x50000002, "Bob"), (0x50000001, "Longname Here")],
+            "Tester",
+        );
+        // The list is split mid-name across two packets, and the second is retransmitted
+        let (head, tail) = list.split_at(30);
+        let enter = enter_world(0x50000002, "Tester");
+        // A character's GUID outside a GUID field is left alone, and the first stand-in is
+        // skipped because it already occurs in the capture
+        let mut event = opcodes::GAME_EVENT.to_le_bytes().to_vec();
+        for value in [0x50000001u32, 7, 0x01C0, 0x50000002, STAND_IN_GUID_BASE] {
+            event.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut records = vec![
+            record(Direction::Outbound, login_packet("Tester", "hunter2")),
+            record(Direction::Inbound, fragment_packet(1, 1, 2, 0, head)),
+            record(Direction::Inbound, fragment_packet(2, 1, 2, 1, tail)),
+            record(Direction::Inbound, fragment_packet(2, 1, 2, 1, tail)),
+            record(Direction::Outbound, fragment_packet(1, 1, 1, 0, &enter)),
+            record(Direction::Inbound, fragment_packet(3, 2, 1, 0, &event)),
+        ];
+
+        let report = anonymize(&mut records);
+        assert_eq!(report.account.as_deref(), Some("Abaaaa"));
+        assert_eq!(report.characters, 2);
+        assert_eq!(report.packets_rewritten, 6);
+
+        let login = LoginRequest::unpack(&packet_data(&records[0])[HEADER_SIZE..]).unwrap();
+        assert_eq!(
+            (login.account.as_str(), login.password.as_str()),
+            ("Abaaaa", REDACTED)
+        );
+
+        let mut decoder = crate::session::decode::CaptureDecoder::new();
+        let mut messages = Vec::new();
+        for record in &records {
+            let CaptureRecord::Packet(entry) = record else {
+                continue;
+            };
+            messages.extend(decoder.decode(entry).unwrap().messages);
+            let header = PacketHeader::unpack(&entry.data[..HEADER_SIZE]);
+            if header.flags & flags::ENCRYPTED_CHECKSUM != 0 {
+                assert_eq!(packet_key(&entry.data), KEY);
+            }
+        }
+        let GameMessage::CharacterList { characters } = GameMessage::unpack(&messages[0]) else {
+            panic!("Expected a character list");
+        };
+        let (bob, longname) = (STAND_IN_GUID_BASE + 1, STAND_IN_GUID_BASE + 2);
+        assert_eq!(
+            characters,
+            vec![
+                (bob, "Cba".to_string()),
+                (longname, "Char2xxxxxxxx".to_string()),
+            ]
+        );
+        let list = bodies::CharacterList::read_le(&mut Cursor::new(&messages[0][4..])).unwrap();
+        assert_eq!(list.trailer.unwrap().account, "Abaaaa");
+        let GameMessage::CharacterEnterWorld { id, account } = GameMessage::unpack(&messages[1])
+        else {
+            panic!("Expected CharacterEnterWorld");
+        };
+        assert_eq!((id, account.as_str()), (bob, "Abaaaa"));
+        assert_eq!(LittleEndian::read_u32(&messages[2][4..]), longname);
+        assert_eq!(&messages[2][8..], &event[8..]);
+    }
+
+    #[test]
+    fn test_anonymize_rewrites_names_only_in_string_fields() {
+        let list = character_list(&[(0x50000001, "Bob")], "Tester");
+        // The name's bytes also make up the sender's id, which must survive
+        let bob_bytes = u32::from_le_bytes(*b"Bob!");
+        let mut tell = opcodes::GAME_EVENT.to_le_bytes().to_vec();
+        for value in [0x50000001u32, 1, game_event_opcodes::TELL] {
+            tell.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut cursor = Cursor::new(Vec::new());
+        bodies::Tell {
+            message: "Bob says hi".to_string(),
+            sender: "Bob".to_string(),
+            sender_id: bob_bytes,
+            target_id: 0x50000001,
+            chat_type: 3,
+            secret_flags: 0,
+        }
+        .write_le(&mut cursor)
+        .unwrap();
+        tell.extend_from_slice(&cursor.into_inner());
+        let mut talk = opcodes::GAME_ACTION.to_le_bytes().to_vec();
+        talk.extend_from_slice(&bob_bytes.to_le_bytes());
+        let mut cursor = Cursor::new(Vec::new());
+        GameActionKind::Talk {
+            message: "I am Bob".to_string(),
+        }
+        .write_le(&mut cursor)
+        .unwrap();
+        talk.extend_from_slice(&cursor.into_inner());
+        // A message that carries no names keeps them even in its payload
+        let mut health = opcodes::GAME_EVENT.to_le_bytes().to_vec();
+        for value in [0x50000001u32, 2, game_event_opcodes::UPDATE_HEALTH] {
+            health.extend_from_slice(&value.to_le_bytes());
+        }
+        health.extend_from_slice(b"Bob\0");
+        let mut records = vec![
+            record(Direction::Outbound, login_packet("Tester", "hunter2")),
+            record(Direction::Inbound, fragment_packet(1, 1, 1, 0, &list)),
+            record(Direction::Inbound, fragment_packet(2, 2, 1, 0, &tell)),
+            record(Direction::Outbound, fragment_packet(1, 1, 1, 0, &talk)),
+            record(Direction::Inbound, fragment_packet(3, 3, 1, 0, &health)),
+        ];
+
+        anonymize(&mut records);
+
+        let mut decoder = crate::session::decode::CaptureDecoder::new();
+        let mut messages = Vec::new();
+        for record in &records {
+            let CaptureRecord::Packet(entry) = record else {
+                continue;
+            };
+            messages.extend(decoder.decode(entry).unwrap().messages);
+        }
+        let tell = bodies::Tell::read_le(&mut Cursor::new(&messages[1][16..])).unwrap();
+        assert_eq!(
+            (tell.message.as_str(), tell.sender.as_str()),
+            ("Cba says hi", "Cba")
+        );
+        assert_eq!(tell.sender_id, bob_bytes);
+        assert_eq!(&messages[2][4..8], b"Bob!");
+        assert_eq!(
+            GameActionKind::read_le(&mut Cursor::new(&messages[2][8..])).unwrap(),
+            GameActionKind::Talk {
+                message: "I am Cba".to_string()
+            }
+        );
+        assert!(messages[3].ends_with(b"Bob\0"));
+    }
+
+    #[test]
+    fn test_anonymize_skips_malformed_fragments() {
+        let list = character_list(&[(0x50000001, "Bob")], "Tester");
+        let mut records = vec![
+            record(Direction::Inbound, fragment_packet(1, 1, 1, 0, &list)),
+            // A copy of the only fragment with a different length, and a fragment whose
+            // index is past the message's count
+            record(Direction::Inbound, fragment_packet(2, 1, 1, 0, &list[..20])),
+            record(Direction::Inbound, fragment_packet(3, 1, 1, 4, &list)),
+        ];
+        let originals: Vec<Vec<u8>> = records.iter().map(|r| packet_data(r).to_vec()).collect();
+
+        let report = anonymize(&mut records);
+        assert_eq!(report.characters, 1);
+        assert_eq!(report.packets_rewritten, 1);
+        assert_ne!(packet_data(&records[0]), originals[0]);
+        assert_eq!(packet_data(&records[1]), originals[1]);
+        assert_eq!(packet_data(&records[2]), originals[2]);
+    }
+
+    #[test]
+    fn test_capture_masks_account_everywhere() {
+        let list = character_list(&[(0x50000001, "Bob")], "Tester");
+        let enter = enter_world(0x50000001, "Tester");
+        let records = [
+            record(Direction::Outbound, login_packet("Tester", "hunter2")),
+            record(Direction::Inbound, fragment_packet(1, 1, 1, 0, &list)),
+            record(Direction::Outbound, fragment_packet(1, 1, 1, 0, &enter)),
+        ];
+
+        for (level, visible) in [(ScrubLevel::Password, true), (ScrubLevel::Account, false)] {
+            let path = std::env::temp_dir()
+                .join(format!(
+                    "holtburger-scrub-{:?}-{}.cap",
+                    level,
+                    std::process::id()
+                ))
+                .to_string_lossy()
+                .into_owned();
+            let header = CaptureHeader::new("127.0.0.1:9000".parse().unwrap(), "Tester");
+            let mut writer = CaptureWriter::create(&path, header, false).unwrap();
+            writer.set_scrub(level);
+            for record in &records {
+                writer.write_record(record).unwrap();
+            }
+            drop(writer);
+
+            let written = std::fs::read(&path).unwrap();
+            let mut reader = CaptureReader::open(&path).unwrap();
+            let mut packets = 0;
+            while let Some(record) = reader.read_record().unwrap() {
+                let CaptureRecord::Packet(entry) = record else {
+                    continue;
+                };
+                let header = PacketHeader::unpack(&entry.data[..HEADER_SIZE]);
+                if header.flags & flags::ENCRYPTED_CHECKSUM != 0 {
+                    // Masked packets are resealed with their original key
+                    assert_eq!(packet_key(&entry.data), KEY);
+                }
+                packets += 1;
+            }
+            std::fs::remove_file(&path).unwrap();
+
+            let found = written.windows(6).any(|w| w == b"Tester");
+            assert_eq!(found, visible, "{:?}", level);
+            assert!(!written.windows(7).any(|w| w == b"hunter2"));
+            assert_eq!(packets, 3);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::protocol::messages::FlowHeader;
+use std::time::Duration;
+
+/// How often the session measures round-trip time and reports flow to the server.
+pub const ECHO_INTERVAL: Duration = Duration::from_secs(5);
+/// Echo requests remembered while waiting for their responses.
+pub const MAX_PENDING_ECHOES: usize = 8;
+
+/// Snapshot of connection quality counters for a session.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectionStats {
+    pub packets_sent: u64,
+    pub packets_received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Packets we resent because the server asked for them.
+    pub retransmits_sent: u64,
+    /// Server sequences we asked to have resent.
+    pub retransmits_requested: u64,
+    /// Server packets dropped because we had already processed them.
+    pub duplicates_dropped: u64,
+    /// Server packets dropped because their checksum did not verify.
+    pub packets_rejected: u64,
+    /// Most recent echo round-trip time.
+    pub rtt: Option<Duration>,
+    /// Exponentially smoothed round-trip time.
+    pub smoothed_rtt: Option<Duration>,
+    /// Most recent FLOW report from the server.
+    pub server_flow: Option<FlowHeader>,
+}
+
+impl ConnectionStats {
+    pub fn record_rtt(&mut self, sample: Duration) {
+        self.rtt = Some(sample);
+        self.smoothed_rtt = Some(match self.smoothed_rtt {
+            Some(smoothed) => (smoothed * 7 + sample) / 8,
+            None => sample,
+        });
+    }
+
+    /// Share of server packets that went missing and had to be requested again.
+    pub fn loss_ratio(&self) -> f64 {
+        let expected = self.packets_received + self.retransmits_requested;
+        if expected == 0 {
+            0.0
+        } else {
+            self.retransmits_requested as f64 / expected as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rtt_smoothing() {
+        let mut stats = ConnectionStats::default();
+        stats.record_rtt(Duration::from_millis(80));
+        assert_eq!(stats.smoothed_rtt, Some(Duration::from_millis(80)));
+
+        stats.record_rtt(Duration::from_millis(160));
+        assert_eq!(stats.rtt, Some(Duration::from_millis(160)));
+        assert_eq!(stats.smoothed_rtt, Some(Duration::from_millis(90)));
+    }
+
+    #[test]
+    fn test_loss_ratio() {
+        let mut stats = ConnectionStats::default();
+        assert_eq!(stats.loss_ratio(), 0.0);
+
+        stats.packets_received = 95;
+        stats.retransmits_requested = 5;
+        assert!((stats.loss_ratio() - 0.05).abs() < f64::EPSILON);
+    }
+}
@@ -1,6 +1,10 @@
 use crate::math::Vector3;
+use crate::protocol::bodies::{HouseRestrictions, ObjDesc};
+use crate::protocol::movement::{MovementData, MovementSequences};
+use crate::protocol::properties::PropertyInt;
 use crate::world::position::WorldPosition;
 use crate::world::properties::{ItemType, ObjectDescriptionFlag, PhysicsState};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
@@ -9,15 +13,28 @@ pub struct Entity {
     pub wcid: Option<u32>,
     pub name: String,
     pub position: WorldPosition,
+    /// Height of the last position the server gave. With no terrain to land on, locally
+    /// simulated falls come to rest here.
+    pub server_z: f32,
 
     pub velocity: Vector3,
+    /// Angular velocity, in radians per second about each axis.
+    pub omega: Vector3,
+    /// The GfxObj of the first part, when the object's appearance swaps one in.
     pub gfx_id: Option<u32>,
+    /// Palette, texture and model swaps.
+    pub obj_desc: ObjDesc,
+    pub physics: PhysicsDescription,
+    pub weenie: WeenieDescription,
     pub flags: ObjectDescriptionFlag,
     pub item_type: Option<ItemType>,
     pub physics_state: PhysicsState,
     pub physics_parent_id: Option<u32>,
     pub container_id: Option<u32>,
     pub wielder_id: Option<u32>,
+    pub sequences: PhysicsSequences,
+    /// The last motion the server described for the entity.
+    pub movement: Option<MovementData>,
 
     pub int_properties: HashMap<u32, i32>,
     pub bool_properties: HashMap<u32, bool>,
@@ -34,18 +51,25 @@ impl Entity {
             wcid: None,
             name,
             position,
+            server_z: position.coords.z,
             velocity: Vector3 {
                 x: 0.0,
                 y: 0.0,
                 z: 0.0,
             },
+            omega: Vector3::zero(),
             gfx_id: None,
+            obj_desc: ObjDesc::default(),
+            physics: PhysicsDescription::default(),
+            weenie: WeenieDescription::default(),
             flags: ObjectDescriptionFlag::empty(),
             item_type: None,
             physics_state: crate::world::properties::PhysicsState::NONE,
             physics_parent_id: None,
             container_id: None,
             wielder_id: None,
+            sequences: PhysicsSequences::default(),
+            movement: None,
             int_properties: HashMap::new(),
             bool_properties: HashMap::new(),
             float_properties: HashMap::new(),
@@ -54,6 +78,242 @@ impl Entity {
             iid_properties: HashMap::new(),
         }
     }
+
+    /// The value in pyreals, following property updates after the object was created.
+    pub fn value(&self) -> Option<u32> {
+        self.int_property(PropertyInt::Value).or(self.weenie.value)
+    }
+
+    pub fn burden(&self) -> Option<u32> {
+        self.int_property(PropertyInt::EncumbranceVal)
+            .or(self.weenie.burden.map(u32::from))
+    }
+
+    pub fn stack_size(&self) -> Option<u32> {
+        self.int_property(PropertyInt::StackSize)
+            .or(self.weenie.stack_size.map(u32::from))
+    }
+
+    fn int_property(&self, property: PropertyInt) -> Option<u32> {
+        self.int_properties
+            .get(&(property as u32))
+            .map(|&value| value as u32)
+    }
+}
+
+/// How an object is put together physically, as described when it was created. The
+/// parts that change as it moves (state, velocity and omega) are kept on the entity.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhysicsDescription {
+    pub setup_id: Option<u32>,
+    pub motion_table_id: Option<u32>,
+    pub sound_table_id: Option<u32>,
+    pub physics_script_table_id: Option<u32>,
+    /// The animation frame to hold, for objects that are not moving.
+    pub animation_frame: Option<u32>,
+    /// Where on its parent the object is attached.
+    pub parent_location: Option<u32>,
+    /// Attached objects, as (guid, location) pairs.
+    pub children: Vec<(u32, u32)>,
+    pub object_scale: Option<f32>,
+    pub friction: Option<f32>,
+    pub elasticity: Option<f32>,
+    pub translucency: Option<f32>,
+    pub acceleration: Option<Vector3>,
+    pub default_script: Option<u32>,
+    pub default_script_intensity: Option<f32>,
+}
+
+/// The optional fields of an object's weenie header, each present when the server sent it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WeenieDescription {
+    pub icon: u32,
+    pub plural_name: Option<String>,
+    pub items_capacity: Option<u32>,
+    pub containers_capacity: Option<u32>,
+    pub ammo_type: Option<u16>,
+    pub value: Option<u32>,
+    pub usable: Option<u32>,
+    pub use_radius: Option<f32>,
+    pub target_type: Option<u32>,
+    pub ui_effects: Option<u32>,
+    pub combat_use: Option<u8>,
+    pub structure: Option<u16>,
+    pub max_structure: Option<u16>,
+    pub stack_size: Option<u16>,
+    pub max_stack_size: Option<u16>,
+    pub valid_locations: Option<u32>,
+    pub currently_wielded_location: Option<u32>,
+    pub priority: Option<u32>,
+    pub radar_blip_color: Option<u8>,
+    pub radar_behavior: Option<u8>,
+    pub pscript: Option<u32>,
+    pub workmanship: Option<f32>,
+    pub burden: Option<u16>,
+    pub spell: Option<u16>,
+    pub house_owner: Option<u32>,
+    pub house_restrictions: Option<HouseRestrictions>,
+    pub hook_item_types: Option<u32>,
+    pub monarch: Option<u32>,
+    pub hook_type: Option<u32>,
+    pub icon_overlay: Option<u32>,
+    pub material_type: Option<u32>,
+    pub icon_underlay: Option<u32>,
+    pub cooldown: Option<u32>,
+    pub cooldown_duration: Option<f64>,
+    pub pet_owner: Option<u32>,
+}
+
+/// The latest of each physics sequence the server sent for an entity. Updates carry the
+/// sequence of their kind, so out-of-order ones can be told apart.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhysicsSequences {
+    pub position: u16,
+    pub movement: u16,
+    pub state: u16,
+    pub vector: u16,
+    pub teleport: u16,
+    pub server_control: u16,
+    pub force_position: u16,
+    pub objdesc: u16,
+    pub instance: u16,
+}
+
+impl PhysicsSequences {
+    /// The sequences the client echoes back in its own movement actions.
+    pub fn for_actions(&self) -> MovementSequences {
+        MovementSequences {
+            instance: self.instance,
+            server_control: self.server_control,
+            teleport: self.teleport,
+            force_position: self.force_position,
+        }
+    }
+
+    /// Records the sequences of a position update, or returns false if it is stale. A newer
+    /// teleport always wins; otherwise the position sequence has to move on.
+    pub fn update_position(
+        &mut self,
+        instance: u16,
+        position: u16,
+        teleport: u16,
+        force_position: u16,
+    ) -> bool {
+        let fresh = self.check_instance(instance).unwrap_or_else(|| {
+            match compare_sequence(teleport, self.teleport) {
+                Ordering::Greater => true,
+                Ordering::Equal => is_newer_sequence(position, self.position),
+                Ordering::Less => false,
+            }
+        });
+        if fresh {
+            self.instance = instance;
+            self.position = position;
+            self.teleport = teleport;
+            self.force_position = force_position;
+        }
+        fresh
+    }
+
+    /// Records the sequences of a motion update, or returns false if it is stale.
+    pub fn update_motion(&mut self, instance: u16, movement: u16, server_control: u16) -> bool {
+        let fresh = self.check_instance(instance).unwrap_or_else(|| {
+            compare_sequence(server_control, self.server_control) != Ordering::Less
+                && is_newer_sequence(movement, self.movement)
+        });
+        if fresh {
+            self.instance = instance;
+            self.movement = movement;
+            self.server_control = server_control;
+        }
+        fresh
+    }
+
+    /// Records the sequences of a velocity update, or returns false if it is stale.
+    pub fn update_vector(&mut self, instance: u16, vector: u16) -> bool {
+        let fresh = self
+            .check_instance(instance)
+            .unwrap_or_else(|| is_newer_sequence(vector, self.vector));
+        if fresh {
+            self.instance = instance;
+            self.vector = vector;
+        }
+        fresh
+    }
+
+    /// Records the sequences of a physics state update, or returns false if it is stale.
+    pub fn update_state(&mut self, instance: u16, state: u16) -> bool {
+        let fresh = self
+            .check_instance(instance)
+            .unwrap_or_else(|| is_newer_sequence(state, self.state));
+        if fresh {
+            self.instance = instance;
+            self.state = state;
+        }
+        fresh
+    }
+
+    /// Records the sequence of a teleport, or returns false if it is stale.
+    pub fn update_teleport(&mut self, teleport: u16) -> bool {
+        let fresh = is_newer_sequence(teleport, self.teleport);
+        if fresh {
+            self.teleport = teleport;
+        }
+        fresh
+    }
+
+    /// An update for a newer instance of the object is always fresh and one for an older
+    /// instance always stale. For the same instance it depends on the other sequences.
+    fn check_instance(&self, instance: u16) -> Option<bool> {
+        match compare_sequence(instance, self.instance) {
+            Ordering::Greater => Some(true),
+            Ordering::Less => Some(false),
+            Ordering::Equal => None,
+        }
+    }
+}
+
+/// Compares two physics sequences. They wrap around, so `a` is newer than `b` when it is
+/// less than half the range ahead of it.
+pub fn compare_sequence(a: u16, b: u16) -> Ordering {
+    (a.wrapping_sub(b) as i16).cmp(&0)
+}
+
+pub fn is_newer_sequence(a: u16, b: u16) -> bool {
+    compare_sequence(a, b) == Ordering::Greater
+}
+
+/// In the order they are sent in an object description.
+impl From<[u16; 9]> for PhysicsSequences {
+    fn from(s: [u16; 9]) -> Self {
+        Self {
+            position: s[0],
+            movement: s[1],
+            state: s[2],
+            vector: s[3],
+            teleport: s[4],
+            server_control: s[5],
+            force_position: s[6],
+            objdesc: s[7],
+            instance: s[8],
+        }
+    }
+}
+
+impl From<PhysicsSequences> for [u16; 9] {
+    fn from(s: PhysicsSequences) -> Self {
+        [
+            s.position,
+            s.movement,
+            s.state,
+            s.vector,
+            s.teleport,
+            s.server_control,
+            s.force_position,
+            s.objdesc,
+            s.instance,
+        ]
+    }
 }
 
 pub struct EntityManager {
@@ -1,4 +1,5 @@
 pub mod entity;
+pub mod movement;
 pub mod physics_types;
 pub mod player;
 pub mod position;
@@ -8,6 +9,7 @@ pub mod state;
 pub mod stats;
 
 use crate::protocol::messages::Enchantment;
+use crate::protocol::movement::MovementData;
 use crate::world::entity::Entity;
 use crate::world::position::WorldPosition;
 use crate::world::properties::PropertyValue;
@@ -20,6 +22,17 @@ pub enum WorldEvent {
         pos: WorldPosition,
     },
     EntityDespawned(u32),
+    /// The server described how an entity is moving: its stance, what it is doing and
+    /// anything it is moving towards.
+    EntityMotionChanged {
+        guid: u32,
+        movement: MovementData,
+    },
+    /// The server listed what a container holds, usually after it was opened.
+    ContainerContents {
+        container: u32,
+        items: Vec<u32>,
+    },
     VitalUpdated(stats::Vital),
     AttributeUpdated(stats::Attribute),
     SkillUpdated(stats::Skill),
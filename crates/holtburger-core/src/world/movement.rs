@@ -0,0 +1,213 @@
+//! Client-side movement: what the player asked their character to do, how that is sent,
+//! and how fast it moves them until the server says otherwise.
+
+use crate::math::Vector3;
+use crate::protocol::movement::{RawMotionState, hold_keys, motion_commands};
+
+/// Walking speed, in meters per second.
+pub const WALK_SPEED: f32 = 3.12;
+/// Running speed before the run rate is applied.
+pub const RUN_SPEED: f32 = 4.0;
+pub const SIDESTEP_SPEED: f32 = 1.25;
+/// Backwards motion is this much slower than forwards.
+pub const BACKWARDS_FACTOR: f32 = 0.65;
+/// Turning speed while walking, in radians per second. Running turns half as fast again.
+pub const TURN_SPEED: f32 = std::f32::consts::FRAC_PI_2;
+const MAX_SIDESTEP_SPEED: f32 = 3.0;
+/// Downward acceleration, in meters per second squared.
+pub const GRAVITY: f32 = 9.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForwardMotion {
+    Forward,
+    Backward,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// The motion the player is holding down. The default is standing still.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MotionInput {
+    pub forward: Option<ForwardMotion>,
+    pub sidestep: Option<Side>,
+    pub turn: Option<Side>,
+    /// Run rather than walk.
+    pub run: bool,
+}
+
+impl MotionInput {
+    pub fn walk_forward() -> Self {
+        Self {
+            forward: Some(ForwardMotion::Forward),
+            ..Self::default()
+        }
+    }
+
+    pub fn run_forward() -> Self {
+        Self {
+            run: true,
+            ..Self::walk_forward()
+        }
+    }
+
+    pub fn walk_backward() -> Self {
+        Self {
+            forward: Some(ForwardMotion::Backward),
+            ..Self::default()
+        }
+    }
+
+    pub fn sidestep(side: Side) -> Self {
+        Self {
+            sidestep: Some(side),
+            ..Self::default()
+        }
+    }
+
+    pub fn turn(side: Side) -> Self {
+        Self {
+            turn: Some(side),
+            ..Self::default()
+        }
+    }
+
+    /// The same motion while also turning, or no longer turning for `None`.
+    pub fn with_turn(self, turn: Option<Side>) -> Self {
+        Self { turn, ..self }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.forward.is_none() && self.sidestep.is_none() && self.turn.is_none()
+    }
+
+    /// The motion as sent in MoveToState. Running is sent as walking with run held.
+    pub fn to_raw(&self) -> RawMotionState {
+        let mut raw = RawMotionState::default();
+        let hold_key = if self.run {
+            raw.set_current_hold_key(hold_keys::RUN);
+            hold_keys::RUN
+        } else {
+            hold_keys::NONE
+        };
+        match self.forward {
+            Some(ForwardMotion::Forward) => {
+                raw.set_forward(motion_commands::WALK_FORWARD, hold_key)
+            }
+            Some(ForwardMotion::Backward) => {
+                raw.set_forward(motion_commands::WALK_BACKWARDS, hold_key)
+            }
+            None => {}
+        }
+        match self.sidestep {
+            Some(Side::Left) => raw.set_sidestep(motion_commands::SIDESTEP_LEFT, hold_key),
+            Some(Side::Right) => raw.set_sidestep(motion_commands::SIDESTEP_RIGHT, hold_key),
+            None => {}
+        }
+        match self.turn {
+            Some(Side::Left) => raw.set_turn(motion_commands::TURN_LEFT, hold_key),
+            Some(Side::Right) => raw.set_turn(motion_commands::TURN_RIGHT, hold_key),
+            None => {}
+        }
+        raw
+    }
+
+    /// Velocity relative to the character, with +Y ahead and +X to the right.
+    pub fn local_velocity(&self, run_rate: f32) -> Vector3 {
+        let rate = if self.run { run_rate } else { 1.0 };
+        let forward = match self.forward {
+            Some(ForwardMotion::Forward) if self.run => RUN_SPEED * run_rate,
+            Some(ForwardMotion::Forward) => WALK_SPEED,
+            Some(ForwardMotion::Backward) => -WALK_SPEED * BACKWARDS_FACTOR * rate,
+            None => 0.0,
+        };
+        let sidestep_speed = if self.run {
+            (SIDESTEP_SPEED * run_rate * 0.5).min(MAX_SIDESTEP_SPEED)
+        } else {
+            SIDESTEP_SPEED
+        };
+        let side = match self.sidestep {
+            Some(Side::Left) => -sidestep_speed,
+            Some(Side::Right) => sidestep_speed,
+            None => 0.0,
+        };
+        Vector3::new(side, forward, 0.0)
+    }
+
+    /// How fast the character turns, in radians per second counter-clockwise.
+    pub fn turn_rate(&self) -> f32 {
+        let speed = if self.run {
+            TURN_SPEED * 1.5
+        } else {
+            TURN_SPEED
+        };
+        match self.turn {
+            Some(Side::Left) => speed,
+            Some(Side::Right) => -speed,
+            None => 0.0,
+        }
+    }
+}
+
+/// How much faster than walking pace a character with `run_skill` runs, unburdened.
+pub fn run_rate(run_skill: u32) -> f32 {
+    if run_skill >= 800 {
+        return 18.0 / 4.0;
+    }
+    let skill = run_skill as f32;
+    (skill / (skill + 200.0) * 11.0 + 4.0) / 4.0
+}
+
+/// The upward speed of a jump charged to `extent` (0 to 1) by a character with
+/// `jump_skill`, unburdened.
+pub fn jump_velocity(jump_skill: u32, extent: f32) -> f32 {
+    let skill = jump_skill as f32;
+    let height = ((skill / (skill + 1300.0) * 22.2 + 0.05) * extent.clamp(0.0, 1.0)).max(0.35);
+    (height * 2.0 * GRAVITY).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::properties::RawMotionFlags;
+
+    #[test]
+    fn test_motion_input_to_raw() {
+        assert_eq!(MotionInput::default().to_raw(), RawMotionState::default());
+
+        let raw = MotionInput::run_forward()
+            .with_turn(Some(Side::Right))
+            .to_raw();
+        assert_eq!(raw.current_hold_key, Some(hold_keys::RUN));
+        assert_eq!(raw.forward_command, Some(motion_commands::WALK_FORWARD));
+        assert_eq!(raw.forward_hold_key, Some(hold_keys::RUN));
+        assert_eq!(raw.turn_command, Some(motion_commands::TURN_RIGHT));
+        assert!(!raw.flags.contains(RawMotionFlags::SIDESTEP_COMMAND));
+
+        let raw = MotionInput::sidestep(Side::Left).to_raw();
+        assert_eq!(raw.current_hold_key, None);
+        assert_eq!(raw.sidestep_command, Some(motion_commands::SIDESTEP_LEFT));
+        assert_eq!(raw.sidestep_hold_key, Some(hold_keys::NONE));
+    }
+
+    #[test]
+    fn test_speeds() {
+        assert_eq!(run_rate(0), 1.0);
+        assert_eq!(run_rate(200), 2.375);
+        assert_eq!(run_rate(1000), 4.5);
+
+        let walk = MotionInput::walk_forward().local_velocity(2.0);
+        assert_eq!(walk, Vector3::new(0.0, WALK_SPEED, 0.0));
+        let run = MotionInput::run_forward().local_velocity(2.0);
+        assert_eq!(run, Vector3::new(0.0, RUN_SPEED * 2.0, 0.0));
+        assert!(MotionInput::walk_backward().local_velocity(2.0).y < 0.0);
+        assert!(MotionInput::turn(Side::Right).turn_rate() < 0.0);
+
+        // The weakest jump still leaves the ground
+        assert!(jump_velocity(0, 0.0) > 2.5);
+        assert!(jump_velocity(300, 1.0) > jump_velocity(300, 0.5));
+    }
+}
@@ -1,6 +1,8 @@
 use super::WorldEvent;
+use super::movement::MotionInput;
 use super::stats;
-use crate::protocol::messages::{Enchantment, GameMessage};
+use crate::protocol::bodies::{Fellow, Friend};
+use crate::protocol::messages::{Enchantment, GameEventKind, GameMessage};
 use crate::world::properties::EnchantmentTypeFlags;
 use std::collections::HashMap;
 
@@ -28,6 +30,21 @@ pub struct PlayerState {
     /// Stores the raw ranks and init for skills so they can be recalculated
     pub skill_bases: HashMap<stats::SkillType, SkillBase>,
     pub enchantments: Vec<Enchantment>,
+    pub current_title: Option<u32>,
+    pub titles: Vec<u32>,
+    pub friends: HashMap<u32, Friend>,
+    /// Members of the player's fellowship, by guid.
+    pub fellows: HashMap<u32, Fellow>,
+    /// The motion last asked of the player's character.
+    pub motion: MotionInput,
+}
+
+/// How a FriendsListUpdate applies to the friends list.
+pub mod friends_update_types {
+    pub const FULL: u32 = 0x0000;
+    pub const ADDED: u32 = 0x0001;
+    pub const REMOVED: u32 = 0x0002;
+    pub const LOGIN_CHANGE: u32 = 0x0004;
 }
 
 impl Default for PlayerState {
@@ -47,6 +64,11 @@ impl PlayerState {
             skills: HashMap::new(),
             skill_bases: HashMap::new(),
             enchantments: Vec::new(),
+            current_title: None,
+            titles: Vec::new(),
+            friends: HashMap::new(),
+            fellows: HashMap::new(),
+            motion: MotionInput::default(),
         }
     }
 
@@ -467,11 +489,40 @@ impl PlayerState {
                     return true;
                 }
             }
-            GameMessage::MagicUpdateEnchantment {
-                target,
-                enchantment,
-            } => {
-                if *target == self.guid as u64 {
+            GameMessage::GameEvent { guid, event, .. } => {
+                return self.handle_game_event(*guid, event, events);
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Applies a game event addressed to `guid`, returning whether it was consumed.
+    pub fn handle_game_event(
+        &mut self,
+        guid: u32,
+        event: &GameEventKind,
+        events: &mut Vec<WorldEvent>,
+    ) -> bool {
+        match event {
+            GameEventKind::MagicUpdateEnchantment { enchantment } if guid == self.guid => {
+                if let Some(existing) = self
+                    .enchantments
+                    .iter_mut()
+                    .find(|e| e.spell_id == enchantment.spell_id && e.layer == enchantment.layer)
+                {
+                    *existing = enchantment.clone();
+                } else {
+                    self.enchantments.push(enchantment.clone());
+                }
+                events.push(WorldEvent::EnchantmentUpdated(enchantment.clone()));
+                self.emit_derived_stats(events);
+                return true;
+            }
+            GameEventKind::MagicUpdateMultipleEnchantments { enchantments }
+                if guid == self.guid =>
+            {
+                for enchantment in enchantments {
                     if let Some(existing) = self.enchantments.iter_mut().find(|e| {
                         e.spell_id == enchantment.spell_id && e.layer == enchantment.layer
                     }) {
@@ -480,108 +531,97 @@ impl PlayerState {
                         self.enchantments.push(enchantment.clone());
                     }
                     events.push(WorldEvent::EnchantmentUpdated(enchantment.clone()));
-                    self.emit_derived_stats(events);
-                    return true;
                 }
+                self.emit_derived_stats(events);
+                return true;
             }
-            GameMessage::MagicUpdateMultipleEnchantments {
-                target,
-                enchantments,
-            } => {
-                if *target == self.guid as u64 {
-                    for enchantment in enchantments {
-                        if let Some(existing) = self.enchantments.iter_mut().find(|e| {
-                            e.spell_id == enchantment.spell_id && e.layer == enchantment.layer
-                        }) {
-                            *existing = enchantment.clone();
-                        } else {
-                            self.enchantments.push(enchantment.clone());
-                        }
-                        events.push(WorldEvent::EnchantmentUpdated(enchantment.clone()));
-                    }
-                    self.emit_derived_stats(events);
-                    return true;
-                }
+            GameEventKind::MagicRemoveEnchantment { spell_id, layer } if guid == self.guid => {
+                self.enchantments
+                    .retain(|e| e.spell_id != *spell_id || e.layer != *layer);
+                events.push(WorldEvent::EnchantmentRemoved {
+                    spell_id: *spell_id,
+                    layer: *layer,
+                });
+                self.emit_derived_stats(events);
+                return true;
             }
-            GameMessage::MagicRemoveEnchantment {
-                target,
-                spell_id,
-                layer,
-            } => {
-                if *target == self.guid as u64 {
+            GameEventKind::MagicRemoveMultipleEnchantments { spells } if guid == self.guid => {
+                for spell in spells {
                     self.enchantments
-                        .retain(|e| e.spell_id != *spell_id || e.layer != *layer);
+                        .retain(|e| e.spell_id != spell.spell_id || e.layer != spell.layer);
                     events.push(WorldEvent::EnchantmentRemoved {
-                        spell_id: *spell_id,
-                        layer: *layer,
+                        spell_id: spell.spell_id,
+                        layer: spell.layer,
                     });
-                    self.emit_derived_stats(events);
-                    return true;
                 }
+                self.emit_derived_stats(events);
+                return true;
             }
-            GameMessage::MagicRemoveMultipleEnchantments { target, spells } => {
-                if *target == self.guid as u64 {
-                    for spell in spells {
-                        self.enchantments
-                            .retain(|e| e.spell_id != spell.spell_id || e.layer != spell.layer);
-                        events.push(WorldEvent::EnchantmentRemoved {
-                            spell_id: spell.spell_id,
-                            layer: spell.layer,
-                        });
-                    }
-                    self.emit_derived_stats(events);
-                    return true;
-                }
+            GameEventKind::MagicPurgeEnchantments if guid == self.guid => {
+                self.enchantments.clear();
+                events.push(WorldEvent::EnchantmentsPurged);
+                self.emit_derived_stats(events);
+                return true;
             }
-            GameMessage::MagicPurgeEnchantments { target } => {
-                if *target == self.guid as u64 {
-                    self.enchantments.clear();
-                    events.push(WorldEvent::EnchantmentsPurged);
-                    self.emit_derived_stats(events);
-                    return true;
-                }
+            GameEventKind::MagicPurgeBadEnchantments if guid == self.guid => {
+                self.enchantments
+                    .retain(|e| (e.stat_mod_type & EnchantmentTypeFlags::BENEFICIAL.bits()) != 0);
+                events.push(WorldEvent::EnchantmentsPurged);
+                self.emit_derived_stats(events);
+                return true;
             }
-            GameMessage::MagicPurgeBadEnchantments { target } => {
-                if *target == self.guid as u64 {
-                    self.enchantments.retain(|e| {
-                        (e.stat_mod_type & EnchantmentTypeFlags::BENEFICIAL.bits()) != 0
-                    });
-                    events.push(WorldEvent::EnchantmentsPurged);
-                    self.emit_derived_stats(events);
-                    return true;
-                }
+            GameEventKind::MagicDispelEnchantment { spell_id, layer } if guid == self.guid => {
+                self.enchantments
+                    .retain(|e| e.spell_id != *spell_id || e.layer != *layer);
+                events.push(WorldEvent::EnchantmentRemoved {
+                    spell_id: *spell_id,
+                    layer: *layer,
+                });
+                self.emit_derived_stats(events);
+                return true;
             }
-            GameMessage::MagicDispelEnchantment {
-                target,
-                spell_id,
-                layer,
-            } => {
-                if *target == self.guid as u64 {
+            GameEventKind::MagicDispelMultipleEnchantments { spells } if guid == self.guid => {
+                for spell in spells {
                     self.enchantments
-                        .retain(|e| e.spell_id != *spell_id || e.layer != *layer);
+                        .retain(|e| e.spell_id != spell.spell_id || e.layer != spell.layer);
                     events.push(WorldEvent::EnchantmentRemoved {
-                        spell_id: *spell_id,
-                        layer: *layer,
+                        spell_id: spell.spell_id,
+                        layer: spell.layer,
                     });
-                    self.emit_derived_stats(events);
-                    return true;
                 }
+                self.emit_derived_stats(events);
+                return true;
             }
-            GameMessage::MagicDispelMultipleEnchantments { target, spells } => {
-                if *target == self.guid as u64 {
-                    for spell in spells {
-                        self.enchantments
-                            .retain(|e| e.spell_id != spell.spell_id || e.layer != spell.layer);
-                        events.push(WorldEvent::EnchantmentRemoved {
-                            spell_id: spell.spell_id,
-                            layer: spell.layer,
-                        });
+            GameEventKind::CharacterTitle { current, titles } => {
+                self.current_title = Some(*current);
+                self.titles = titles.clone();
+                return true;
+            }
+            GameEventKind::FriendsListUpdate {
+                friends,
+                update_type,
+            } => {
+                if *update_type == friends_update_types::FULL {
+                    self.friends.clear();
+                }
+                for friend in friends {
+                    if *update_type == friends_update_types::REMOVED {
+                        self.friends.remove(&friend.guid);
+                    } else {
+                        self.friends.insert(friend.guid, friend.clone());
                     }
-                    self.emit_derived_stats(events);
-                    return true;
                 }
+                return true;
+            }
+            GameEventKind::FellowshipUpdateFellow {
+                guid: fellow_guid,
+                fellow,
+                ..
+            } => {
+                self.fellows.insert(*fellow_guid, fellow.clone());
+                return true;
             }
-            GameMessage::UpdateHealth { target, health } => {
+            GameEventKind::UpdateHealth { target, health } => {
                 let target_guid = if *target == 0 { self.guid } else { *target };
 
                 if target_guid == self.guid
@@ -1,15 +1,95 @@
 use crate::math::{Quaternion, Vector3};
+use crate::protocol::parse::Stream;
 use crate::world::properties::UpdatePositionFlag;
-use byteorder::{ByteOrder, LittleEndian};
+use binrw::{BinRead, BinWrite, binrw, io::Cursor};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+/// A landblock, origin and full rotation, as embedded in object and player descriptions.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, BinRead, BinWrite, PartialEq, Default)]
+#[brw(little)]
 pub struct WorldPosition {
     pub landblock_id: u32,
     pub coords: Vector3,
     pub rotation: Quaternion,
 }
 
+/// A position as sent in UpdatePosition. Rotation components that are zero are flagged
+/// rather than sent, and velocity and placement are only present when flagged.
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionPack {
+    #[br(map = UpdatePositionFlag::from_bits_retain)]
+    #[bw(map = |f| f.bits())]
+    pub flags: UpdatePositionFlag,
+    pub landblock_id: u32,
+    pub origin: Vector3,
+    #[br(if(!flags.contains(UpdatePositionFlag::ORIENTATION_HAS_NO_W)))]
+    pub qw: Option<f32>,
+    #[br(if(!flags.contains(UpdatePositionFlag::ORIENTATION_HAS_NO_X)))]
+    pub qx: Option<f32>,
+    #[br(if(!flags.contains(UpdatePositionFlag::ORIENTATION_HAS_NO_Y)))]
+    pub qy: Option<f32>,
+    #[br(if(!flags.contains(UpdatePositionFlag::ORIENTATION_HAS_NO_Z)))]
+    pub qz: Option<f32>,
+    #[br(if(flags.contains(UpdatePositionFlag::HAS_VELOCITY)))]
+    pub velocity: Option<Vector3>,
+    #[br(if(flags.contains(UpdatePositionFlag::HAS_PLACEMENT_ID)))]
+    pub placement_id: Option<u32>,
+    pub instance_sequence: u16,
+    pub position_sequence: u16,
+    pub teleport_sequence: u16,
+    pub force_position_sequence: u16,
+}
+
+impl From<WorldPosition> for PositionPack {
+    fn from(pos: WorldPosition) -> Self {
+        let mut flags = UpdatePositionFlag::NONE;
+        let mut component = |v: f32, missing| {
+            if v == 0.0 {
+                flags |= missing;
+                None
+            } else {
+                Some(v)
+            }
+        };
+        let qw = component(pos.rotation.w, UpdatePositionFlag::ORIENTATION_HAS_NO_W);
+        let qx = component(pos.rotation.x, UpdatePositionFlag::ORIENTATION_HAS_NO_X);
+        let qy = component(pos.rotation.y, UpdatePositionFlag::ORIENTATION_HAS_NO_Y);
+        let qz = component(pos.rotation.z, UpdatePositionFlag::ORIENTATION_HAS_NO_Z);
+        Self {
+            flags,
+            landblock_id: pos.landblock_id,
+            origin: pos.coords,
+            qw,
+            qx,
+            qy,
+            qz,
+            velocity: None,
+            placement_id: None,
+            instance_sequence: 0,
+            position_sequence: 0,
+            teleport_sequence: 0,
+            force_position_sequence: 0,
+        }
+    }
+}
+
+impl From<PositionPack> for WorldPosition {
+    fn from(pack: PositionPack) -> Self {
+        Self {
+            landblock_id: pack.landblock_id,
+            coords: pack.origin,
+            rotation: Quaternion {
+                w: pack.qw.unwrap_or(0.0),
+                x: pack.qx.unwrap_or(0.0),
+                y: pack.qy.unwrap_or(0.0),
+                z: pack.qz.unwrap_or(0.0),
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum WorldCoordinates {
     Indoor {
@@ -116,93 +196,46 @@ impl WorldPosition {
         (dx * dx + dy * dy + dz * dz).sqrt()
     }
 
+    /// Reads a raw position at `offset`, or the default position if the data is too short.
     pub fn read_raw(data: &[u8], offset: &mut usize) -> Self {
-        if data.len() < *offset + 32 {
-            return Self::default();
-        }
-        let landblock_id = LittleEndian::read_u32(&data[*offset..*offset + 4]);
-        *offset += 4;
-        let x = LittleEndian::read_f32(&data[*offset..*offset + 4]);
-        let y = LittleEndian::read_f32(&data[*offset + 4..*offset + 8]);
-        let z = LittleEndian::read_f32(&data[*offset + 8..*offset + 12]);
-        *offset += 12;
-        let qw = LittleEndian::read_f32(&data[*offset..*offset + 4]);
-        let qx = LittleEndian::read_f32(&data[*offset + 4..*offset + 8]);
-        let qy = LittleEndian::read_f32(&data[*offset + 8..*offset + 12]);
-        let qz = LittleEndian::read_f32(&data[*offset + 12..*offset + 16]);
-        *offset += 16;
-
-        Self {
-            landblock_id,
-            coords: Vector3 { x, y, z },
-            rotation: Quaternion {
-                w: qw,
-                x: qx,
-                y: qy,
-                z: qz,
-            },
-        }
+        Self::read_with::<Self>(data, offset)
     }
 
+    /// Reads a PositionPack at `offset`, or the default position if the data is too short.
     pub fn read(data: &[u8], offset: &mut usize) -> Self {
-        if data.len() < *offset + 8 {
-            return Self::default();
-        }
-        let raw_flags = LittleEndian::read_u32(&data[*offset..*offset + 4]);
-        let flags = UpdatePositionFlag::from_bits_retain(raw_flags);
-        *offset += 4;
-        let landblock_id = LittleEndian::read_u32(&data[*offset..*offset + 4]);
-        *offset += 4;
-
-        // Origin position (always present in PositionPack)
-        let x = LittleEndian::read_f32(&data[*offset..*offset + 4]);
-        let y = LittleEndian::read_f32(&data[*offset + 4..*offset + 8]);
-        let z = LittleEndian::read_f32(&data[*offset + 8..*offset + 12]);
-        *offset += 12;
-
-        let mut qw = 0.0;
-        let mut qx = 0.0;
-        let mut qy = 0.0;
-        let mut qz = 0.0;
-
-        if !flags.contains(UpdatePositionFlag::ORIENTATION_HAS_NO_W) {
-            qw = LittleEndian::read_f32(&data[*offset..*offset + 4]);
-            *offset += 4;
-        }
-        if !flags.contains(UpdatePositionFlag::ORIENTATION_HAS_NO_X) {
-            qx = LittleEndian::read_f32(&data[*offset..*offset + 4]);
-            *offset += 4;
-        }
-        if !flags.contains(UpdatePositionFlag::ORIENTATION_HAS_NO_Y) {
-            qy = LittleEndian::read_f32(&data[*offset..*offset + 4]);
-            *offset += 4;
-        }
-        if !flags.contains(UpdatePositionFlag::ORIENTATION_HAS_NO_Z) {
-            qz = LittleEndian::read_f32(&data[*offset..*offset + 4]);
-            *offset += 4;
-        }
+        Self::read_with::<PositionPack>(data, offset)
+    }
 
-        // Handle the rest of the PositionPack (Velocity, Placement, Sequences)
-        if flags.contains(UpdatePositionFlag::HAS_VELOCITY) {
-            *offset += 12; // Skip velocity
-        }
-        if flags.contains(UpdatePositionFlag::HAS_PLACEMENT_ID) {
-            *offset += 4; // Skip placement id
+    fn read_with<T>(data: &[u8], offset: &mut usize) -> Self
+    where
+        T: for<'a> BinRead<Args<'a> = ()> + Into<Self>,
+    {
+        let mut s = Stream::new(data.get(*offset..).unwrap_or_default(), 0);
+        match s.read::<T>() {
+            Ok(pos) => {
+                *offset += s.offset();
+                pos.into()
+            }
+            Err(_) => Self::default(),
         }
+    }
 
-        // Fixed sequences at the end of every PositionPack
-        *offset += 8;
+    pub fn pack_raw(&self, buf: &mut Vec<u8>) {
+        Self::pack_with(self, buf)
+    }
 
-        Self {
-            landblock_id,
-            coords: Vector3 { x, y, z },
-            rotation: Quaternion {
-                w: qw,
-                x: qx,
-                y: qy,
-                z: qz,
-            },
-        }
+    /// Writes a PositionPack, omitting zero rotation components the way ACE does.
+    pub fn pack(&self, buf: &mut Vec<u8>) {
+        Self::pack_with(&PositionPack::from(*self), buf)
+    }
+
+    fn pack_with<T: for<'a> BinWrite<Args<'a> = ()>>(value: &T, buf: &mut Vec<u8>) {
+        let mut cursor = Cursor::new(std::mem::take(buf));
+        cursor.set_position(cursor.get_ref().len() as u64);
+        value
+            .write_le(&mut cursor)
+            .expect("writing to memory cannot fail");
+        *buf = cursor.into_inner();
     }
 }
 
@@ -367,4 +400,31 @@ mod tests {
         let p = WorldPosition::read_raw(&data, &mut offset);
         assert_eq!(p, WorldPosition::default());
     }
+
+    #[test]
+    fn test_pack_roundtrip() {
+        let pos = WorldPosition {
+            landblock_id: 0xDA55001D,
+            coords: Vector3::new(84.8, 98.2, 20.0),
+            rotation: Quaternion {
+                w: 0.7,
+                x: 0.0,
+                y: 0.0,
+                z: -0.7,
+            },
+        };
+
+        let mut buf = Vec::new();
+        pos.pack(&mut buf);
+        assert_eq!(buf.len(), 4 + 4 + 12 + 8 + 8);
+        let mut offset = 0;
+        assert_eq!(WorldPosition::read(&buf, &mut offset), pos);
+        assert_eq!(offset, buf.len());
+
+        let mut buf = Vec::new();
+        pos.pack_raw(&mut buf);
+        let mut offset = 0;
+        assert_eq!(WorldPosition::read_raw(&buf, &mut offset), pos);
+        assert_eq!(offset, 32);
+    }
 }
@@ -198,6 +198,40 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Which fields of a RawMotionState are sent. The bits above these hold the number of
+    /// queued motion commands.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+    pub struct RawMotionFlags: u32 {
+        const CURRENT_HOLD_KEY = 0x001;
+        const CURRENT_STYLE = 0x002;
+        const FORWARD_COMMAND = 0x004;
+        const FORWARD_HOLD_KEY = 0x008;
+        const FORWARD_SPEED = 0x010;
+        const SIDESTEP_COMMAND = 0x020;
+        const SIDESTEP_HOLD_KEY = 0x040;
+        const SIDESTEP_SPEED = 0x080;
+        const TURN_COMMAND = 0x100;
+        const TURN_HOLD_KEY = 0x200;
+        const TURN_SPEED = 0x400;
+    }
+}
+
+bitflags! {
+    /// Which fields of an InterpretedMotionState are sent. The bits above these hold the
+    /// number of queued motion commands.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+    pub struct InterpretedMotionFlags: u32 {
+        const CURRENT_STYLE = 0x01;
+        const FORWARD_COMMAND = 0x02;
+        const FORWARD_SPEED = 0x04;
+        const SIDESTEP_COMMAND = 0x08;
+        const SIDESTEP_SPEED = 0x10;
+        const TURN_COMMAND = 0x20;
+        const TURN_SPEED = 0x40;
+    }
+}
+
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
     pub struct IdentifyResponseFlags: u32 {
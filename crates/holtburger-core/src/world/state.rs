@@ -1,16 +1,18 @@
 use super::WorldEvent;
 use super::entity::{Entity, EntityManager};
+use super::movement::{self, MotionInput};
 use super::player::PlayerState;
 use super::position::WorldPosition;
-use super::properties::{ObjectDescriptionFlag, PropertyValue};
+use super::properties::{ObjectDescriptionFlag, PhysicsState, PropertyValue};
 use super::spatial::SpatialScene;
 use super::stats;
 use crate::dat::DatDatabase;
 use crate::math::{Quaternion, Vector3};
+use crate::protocol::movement::MovementSequences;
 use crate::protocol::properties::PropertyInstanceId;
 use std::sync::Arc;
 
-use crate::protocol::messages::GameMessage;
+use crate::protocol::messages::{GameEventKind, GameMessage};
 
 pub struct ServerTimeSync {
     pub server_time: f64,
@@ -23,6 +25,8 @@ pub struct WorldState {
     pub server_time: Option<ServerTimeSync>,
     pub dat: Option<Arc<DatDatabase>>,
     pub scene: SpatialScene,
+    /// Sequence of the last game event applied, to drop repeats.
+    pub game_event_sequence: Option<u32>,
 }
 
 impl WorldState {
@@ -33,6 +37,7 @@ impl WorldState {
             server_time: None,
             dat,
             scene: SpatialScene::new(),
+            game_event_sequence: None,
         }
     }
 
@@ -52,6 +57,20 @@ impl WorldState {
         }
     }
 
+    /// Records the sequence of an incoming game event, returning false if it has already
+    /// been seen. Game event sequences count up by one per event, so anything at or behind
+    /// the last one is a repeat and should be dropped before it is handled.
+    pub fn accept_game_event(&mut self, sequence: u32) -> bool {
+        if self
+            .game_event_sequence
+            .is_some_and(|last| (sequence.wrapping_sub(last) as i32) <= 0)
+        {
+            return false;
+        }
+        self.game_event_sequence = Some(sequence);
+        true
+    }
+
     /// Primary entry point for messages reassembled by the Session.
     /// Returns a list of side-effects/events for the UI to consume.
     pub fn handle_message(&mut self, msg: GameMessage) -> Vec<WorldEvent> {
@@ -73,6 +92,14 @@ impl WorldState {
                 wielder_id,
                 flags,
                 item_type,
+                sequences,
+                movement,
+                obj_desc,
+                physics_state,
+                velocity,
+                omega,
+                physics,
+                weenie,
                 ..
             } => {
                 let entity_name = name.unwrap_or_else(|| "Unknown".to_string());
@@ -92,6 +119,19 @@ impl WorldState {
                 entity.physics_parent_id = parent_id;
                 entity.container_id = container_id;
                 entity.wielder_id = wielder_id;
+                entity.sequences = sequences;
+                entity.movement = movement;
+                entity.gfx_id = obj_desc
+                    .model_changes
+                    .iter()
+                    .find(|change| change.part == 0)
+                    .map(|change| change.model);
+                entity.obj_desc = *obj_desc;
+                entity.physics_state = physics_state;
+                entity.velocity = velocity.unwrap_or_default();
+                entity.omega = omega.unwrap_or_default();
+                entity.physics = *physics;
+                entity.weenie = *weenie;
 
                 self.add_entity(entity.clone());
                 events.push(WorldEvent::EntitySpawned(Box::new(entity)));
@@ -118,22 +158,97 @@ impl WorldState {
                     events.push(WorldEvent::EntityDespawned(guid));
                 }
             }
-            GameMessage::UpdatePosition { guid, pos } => {
+            GameMessage::UpdatePosition {
+                guid,
+                pos,
+                velocity,
+                instance_sequence,
+                position_sequence,
+                teleport_sequence,
+                force_position_sequence,
+            } => {
+                let is_player = guid == self.player.guid;
                 if let Some(entity) = self.entities.get_mut(guid) {
+                    if !entity.sequences.update_position(
+                        instance_sequence,
+                        position_sequence,
+                        teleport_sequence,
+                        force_position_sequence,
+                    ) {
+                        log::debug!(
+                            "Dropping stale position for {:08X} (seq {})",
+                            guid,
+                            position_sequence
+                        );
+                        return events;
+                    }
                     let old_lb = entity.position.landblock_id;
                     entity.position = pos;
+                    entity.server_z = pos.coords.z;
+                    // The player's velocity is predicted from their own motion
+                    if !is_player {
+                        entity.velocity = velocity.unwrap_or_default();
+                    }
                     self.scene.update_entity(guid, old_lb, pos.landblock_id);
                     events.push(WorldEvent::EntityMoved { guid, pos });
                 }
             }
-            GameMessage::PlayerDescription {
+            GameMessage::VectorUpdate {
                 guid,
-                name,
-                wee_type: _,
-                pos,
-                attributes,
-                skills,
-                enchantments,
+                velocity,
+                omega,
+                instance_sequence,
+                vector_sequence,
+            } => {
+                let is_player = guid == self.player.guid;
+                // The player's sequences are kept, but not the velocity
+                if let Some(entity) = self.entities.get_mut(guid)
+                    && entity
+                        .sequences
+                        .update_vector(instance_sequence, vector_sequence)
+                    && !is_player
+                {
+                    entity.velocity = velocity;
+                    entity.omega = omega;
+                }
+            }
+            GameMessage::UpdateMotion {
+                guid,
+                instance_sequence,
+                movement_sequence,
+                server_control_sequence,
+                movement,
+                ..
+            } => {
+                if let Some(entity) = self.entities.get_mut(guid) {
+                    if !entity.sequences.update_motion(
+                        instance_sequence,
+                        movement_sequence,
+                        server_control_sequence,
+                    ) {
+                        log::debug!(
+                            "Dropping stale motion for {:08X} (seq {})",
+                            guid,
+                            movement_sequence
+                        );
+                        return events;
+                    }
+                    entity.movement = Some(movement.clone());
+                    events.push(WorldEvent::EntityMotionChanged { guid, movement });
+                }
+            }
+            GameMessage::GameEvent {
+                guid,
+                event:
+                    GameEventKind::PlayerDescription {
+                        name,
+                        wee_type: _,
+                        pos,
+                        attributes,
+                        skills,
+                        enchantments,
+                    },
+                ..
             } => {
                 self.player.guid = guid;
                 self.player.name = name.clone();
@@ -162,6 +277,7 @@ impl WorldState {
 
                 if let Some(p) = pos {
                     player_entity.position = p;
+                    player_entity.server_z = p.coords.z;
                 }
                 self.add_entity(player_entity);
 
@@ -254,10 +370,37 @@ impl WorldState {
 
                 self.player.emit_derived_stats(&mut events);
             }
-            GameMessage::SetState { guid, state } => {
-                if let Some(entity) = self.entities.get_mut(guid) {
-                    entity.physics_state =
-                        crate::world::properties::PhysicsState::from_bits_retain(state);
+            GameMessage::GameEvent {
+                event: GameEventKind::ViewContents { container, items },
+                ..
+            } => {
+                for item in &items {
+                    if let Some(entity) = self.entities.get_mut(item.guid) {
+                        entity.container_id = Some(container);
+                    }
+                }
+                events.push(WorldEvent::ContainerContents {
+                    container,
+                    items: items.iter().map(|item| item.guid).collect(),
+                });
+            }
+            GameMessage::SetState {
+                guid,
+                state,
+                instance_sequence,
+                state_sequence,
+            } => {
+                if let Some(entity) = self.entities.get_mut(guid)
+                    && entity
+                        .sequences
+                        .update_state(instance_sequence, state_sequence)
+                {
+                    entity.physics_state = PhysicsState::from_bits_retain(state);
+                }
+            }
+            GameMessage::PlayerTeleport { teleport_sequence } => {
+                if let Some(player) = self.entities.get_mut(self.player.guid) {
+                    player.sequences.update_teleport(teleport_sequence);
                 }
             }
             GameMessage::UpdatePropertyInt {
@@ -450,28 +593,144 @@ impl WorldState {
         false
     }
 
+    /// Sets the motion the player is holding down and predicts the velocity it gives them.
+    pub fn set_player_motion(&mut self, motion: MotionInput) {
+        self.player.motion = motion;
+        self.update_player_velocity();
+    }
+
+    /// Launches the player upward at `vertical` m/s. They fall back under gravity until
+    /// they reach the height the server last put them at.
+    pub fn predict_player_jump(&mut self, vertical: f32) {
+        if let Some(player) = self.entities.get_mut(self.player.guid) {
+            player.velocity.z = vertical;
+        }
+    }
+
+    /// Whether the player is mid-jump.
+    pub fn player_airborne(&self) -> bool {
+        self.entities.get(self.player.guid).is_some_and(|player| {
+            player.velocity.z != 0.0 || player.position.coords.z > player.server_z
+        })
+    }
+
+    /// The player's sequences to send with a movement action.
+    pub fn player_movement_sequences(&self) -> MovementSequences {
+        self.entities
+            .get(self.player.guid)
+            .map(|player| player.sequences.for_actions())
+            .unwrap_or_default()
+    }
+
+    /// How much faster than walking pace the player runs.
+    pub fn player_run_rate(&self) -> f32 {
+        movement::run_rate(self.player_skill(stats::SkillType::Run))
+    }
+
+    /// The player's current value in a skill, or 0 before their description arrives.
+    pub fn player_skill(&self, skill: stats::SkillType) -> u32 {
+        self.player.skills.get(&skill).map_or(0, |s| s.current)
+    }
+
+    /// Points the player's velocity the way their motion takes them.
+    fn update_player_velocity(&mut self) {
+        let run_rate = self.player_run_rate();
+        let motion = self.player.motion;
+        if let Some(player) = self.entities.get_mut(self.player.guid) {
+            // Motion only steers along the ground; a jump keeps its vertical speed
+            let vertical = player.velocity.z;
+            player.velocity = player
+                .position
+                .rotation
+                .rotate(motion.local_velocity(run_rate));
+            player.velocity.z = vertical;
+        }
+    }
+
     /// Advance the world simulation by `dt` seconds.
     pub fn tick(&mut self, dt: f32, radius: f32) {
+        self.tick_entities(dt);
+        self.tick_player(dt, radius);
+    }
+
+    /// Moves and spins everything out in the world but the player by its velocity and
+    /// omega, pulling down those under gravity, until the server next places them.
+    fn tick_entities(&mut self, dt: f32) {
+        let player_guid = self.player.guid;
+        for entity in self.entities.entities.values_mut() {
+            if entity.guid == player_guid
+                || entity.container_id.is_some()
+                || entity.wielder_id.is_some()
+                || entity.physics_parent_id.is_some()
+            {
+                continue;
+            }
+
+            if entity.velocity.length_squared() >= 0.0001 {
+                let gravity = entity.physics_state.contains(PhysicsState::GRAVITY);
+                if gravity {
+                    entity.velocity.z -= movement::GRAVITY * dt;
+                }
+                entity.position.coords = entity.position.coords + entity.velocity * dt;
+                if gravity && entity.position.coords.z <= entity.server_z {
+                    entity.position.coords.z = entity.server_z;
+                    entity.velocity = Vector3::zero();
+                }
+            }
+
+            let spin = entity.omega.length();
+            if spin >= 0.0001 {
+                let rotation = Quaternion::from_axis_angle(entity.omega / spin, spin * dt)
+                    * entity.position.rotation;
+                entity.position.rotation = rotation.normalize();
+            }
+        }
+    }
+
+    /// Moves the player by their predicted motion, stopping them at anything solid and
+    /// landing them from a jump.
+    fn tick_player(&mut self, dt: f32, radius: f32) {
         if self.player.guid == 0 {
             return;
         }
 
-        let (vel, coords, lb) = if let Some(player) = self.entities.get(self.player.guid) {
-            (
-                player.velocity,
-                player.position.coords,
-                player.position.landblock_id,
-            )
-        } else {
-            return;
-        };
+        let turn_rate = self.player.motion.turn_rate();
+        if turn_rate != 0.0 {
+            if let Some(player) = self.entities.get_mut(self.player.guid) {
+                let rotation = Quaternion::from_yaw(turn_rate * dt) * player.position.rotation;
+                player.position.rotation = rotation.normalize();
+            }
+            self.update_player_velocity();
+        }
+
+        let airborne = self.player_airborne();
+        let (mut vel, coords, lb, server_z) =
+            if let Some(player) = self.entities.get(self.player.guid) {
+                (
+                    player.velocity,
+                    player.position.coords,
+                    player.position.landblock_id,
+                    player.server_z,
+                )
+            } else {
+                return;
+            };
 
-        if vel.length_squared() < 0.0001 {
+        if vel.length_squared() < 0.0001 && !airborne {
             return;
         }
 
-        let step = vel * dt;
-        let next_coords = coords + step;
+        if airborne {
+            vel.z -= movement::GRAVITY * dt;
+        }
+        let mut next_coords = coords + vel * dt;
+        if airborne && next_coords.z <= server_z {
+            next_coords.z = server_z;
+            vel.z = 0.0;
+        }
+        if let Some(player) = self.entities.get_mut(self.player.guid) {
+            player.velocity = vel;
+        }
 
         if !self.is_colliding(&next_coords, lb, radius) {
             if let Some(player) = self.entities.get_mut(self.player.guid) {
@@ -490,6 +749,9 @@ mod tests {
     use crate::dat::file_type::gfx_obj::GfxObj;
     use crate::dat::graphics::CVertexArray;
     use crate::dat::physics::{BspLeaf, BspNode};
+    use crate::protocol::movement::MovementData;
+    use crate::protocol::properties::PropertyInt;
+    use crate::world::movement::Side;
     use crate::world::physics_types::Sphere;
     use crate::world::properties::ObjectDescriptionFlag;
     use std::collections::HashMap;
@@ -575,4 +837,299 @@ mod tests {
         assert!(player.position.coords.x < 1.0);
         assert_eq!(player.velocity.x, 0.0);
     }
+
+    #[test]
+    fn test_player_motion_follows_heading() {
+        let mut world = WorldState::new(None);
+        world.player.guid = 0x1;
+        world.add_entity(Entity::new(
+            0x1,
+            "Player".to_string(),
+            WorldPosition {
+                landblock_id: 1,
+                coords: Vector3::zero(),
+                rotation: Quaternion::from_yaw(std::f32::consts::FRAC_PI_2),
+            },
+        ));
+
+        // Facing west, walking forward heads along -X
+        world.set_player_motion(MotionInput::walk_forward());
+        let velocity = world.entities.get(0x1).unwrap().velocity;
+        assert!((velocity.x + movement::WALK_SPEED).abs() < 0.001);
+        assert!(velocity.y.abs() < 0.001);
+
+        // A quarter second turning right swings the heading back towards north
+        world.set_player_motion(MotionInput::walk_forward().with_turn(Some(Side::Right)));
+        world.tick(0.25, 0.5);
+        let player = world.entities.get(0x1).unwrap();
+        assert!(player.velocity.y > 0.0);
+        assert!(player.position.coords.x < 0.0);
+
+        world.set_player_motion(MotionInput::default());
+        assert_eq!(world.entities.get(0x1).unwrap().velocity, Vector3::zero());
+    }
+
+    #[test]
+    fn test_player_jump_lands() {
+        let mut world = WorldState::new(None);
+        world.player.guid = 0x1;
+        world.add_entity(Entity::new(
+            0x1,
+            "Player".to_string(),
+            WorldPosition {
+                landblock_id: 1,
+                coords: Vector3::new(0.0, 0.0, 10.0),
+                rotation: Quaternion::identity(),
+            },
+        ));
+        world.set_player_motion(MotionInput::walk_forward());
+        world.predict_player_jump(4.9);
+        assert!(world.player_airborne());
+
+        // Steering mid-air keeps the jump going
+        world.set_player_motion(MotionInput::run_forward());
+        for _ in 0..5 {
+            world.tick(0.1, 0.5);
+        }
+        let player = world.entities.get(0x1).unwrap();
+        assert!(player.position.coords.z > 10.5);
+        assert!(player.velocity.y > movement::WALK_SPEED);
+
+        for _ in 0..10 {
+            world.tick(0.1, 0.5);
+        }
+        let player = world.entities.get(0x1).unwrap();
+        assert_eq!(player.position.coords.z, 10.0);
+        assert_eq!(player.velocity.z, 0.0);
+        assert!(player.position.coords.y > 5.0);
+        assert!(!world.player_airborne());
+    }
+
+    #[test]
+    fn test_vector_update_moves_entities() {
+        let mut world = WorldState::new(None);
+        world.player.guid = 0x50000001;
+        let mut rock = Entity::new(0x80000001, "Rock".to_string(), WorldPosition::default());
+        rock.physics_state = PhysicsState::GRAVITY;
+        world.add_entity(rock);
+        world.add_entity(Entity::new(
+            0x80000002,
+            "Top".to_string(),
+            WorldPosition {
+                landblock_id: 0,
+                coords: Vector3::zero(),
+                rotation: Quaternion::identity(),
+            },
+        ));
+
+        world.handle_message(GameMessage::VectorUpdate {
+            guid: 0x80000001,
+            velocity: Vector3::new(2.0, 0.0, 4.9),
+            omega: Vector3::zero(),
+            instance_sequence: 1,
+            vector_sequence: 1,
+        });
+        world.handle_message(GameMessage::VectorUpdate {
+            guid: 0x80000002,
+            velocity: Vector3::zero(),
+            omega: Vector3::new(0.0, 0.0, std::f32::consts::PI),
+            instance_sequence: 1,
+            vector_sequence: 1,
+        });
+        assert_eq!(world.entities.get(0x80000001).unwrap().sequences.vector, 1);
+
+        // Thrown up at 4.9 m/s, the rock lands after a second and stays there
+        for _ in 0..5 {
+            world.tick(0.1, 0.5);
+        }
+        assert!(world.entities.get(0x80000001).unwrap().position.coords.z > 0.5);
+        for _ in 0..15 {
+            world.tick(0.1, 0.5);
+        }
+        let rock = world.entities.get(0x80000001).unwrap();
+        assert!((rock.position.coords.x - 2.0).abs() < 0.25);
+        assert_eq!(rock.position.coords.z, 0.0);
+        assert_eq!(rock.velocity, Vector3::zero());
+
+        // Half a turn a second, so facing the same way again after two
+        let top = world.entities.get(0x80000002).unwrap();
+        let facing = top.position.rotation.rotate(Vector3::new(0.0, 1.0, 0.0));
+        assert!((facing.y - 1.0).abs() < 0.001);
+        assert_eq!(top.position.coords, Vector3::zero());
+
+        // A position without a velocity puts the rock at rest
+        world.handle_message(GameMessage::UpdatePosition {
+            guid: 0x80000001,
+            pos: WorldPosition::default(),
+            velocity: None,
+            instance_sequence: 1,
+            position_sequence: 1,
+            teleport_sequence: 0,
+            force_position_sequence: 0,
+        });
+        assert_eq!(
+            world.entities.get(0x80000001).unwrap().velocity,
+            Vector3::zero()
+        );
+    }
+
+    #[test]
+    fn test_movement_sequences_track_updates() {
+        let mut world = WorldState::new(None);
+        world.player.guid = 0x50000001;
+        world.add_entity(Entity::new(
+            0x50000001,
+            "Player".to_string(),
+            WorldPosition::default(),
+        ));
+        world.entities.get_mut(0x50000001).unwrap().sequences = [1, 2, 3, 4, 5, 6, 7, 8, 9].into();
+        assert_eq!(
+            world.player_movement_sequences(),
+            MovementSequences {
+                instance: 9,
+                server_control: 6,
+                teleport: 5,
+                force_position: 7,
+            }
+        );
+
+        world.handle_message(GameMessage::UpdatePosition {
+            guid: 0x50000001,
+            pos: WorldPosition::default(),
+            velocity: None,
+            instance_sequence: 9,
+            position_sequence: 10,
+            teleport_sequence: 6,
+            force_position_sequence: 8,
+        });
+        let events = world.handle_message(GameMessage::UpdateMotion {
+            guid: 0x50000001,
+            instance_sequence: 9,
+            movement_sequence: 3,
+            server_control_sequence: 7,
+            autonomous: false,
+            movement: MovementData::default(),
+        });
+        assert!(matches!(
+            events.as_slice(),
+            [WorldEvent::EntityMotionChanged {
+                guid: 0x50000001,
+                ..
+            }]
+        ));
+        let player = world.entities.get(0x50000001).unwrap();
+        assert_eq!(player.movement, Some(MovementData::default()));
+        let sequences = world.player_movement_sequences();
+        assert_eq!(sequences.server_control, 7);
+        assert_eq!(sequences.teleport, 6);
+        assert_eq!(sequences.force_position, 8);
+    }
+
+    #[test]
+    fn test_object_create_fills_entity() {
+        let mut world = WorldState::new(None);
+        let coat = "45f7000058010080110706017e008710500c8710600c8710740c8710d8189310480893106c089310ae0c00d503fe1a00d403fc1a00b00bf91a00be0cfd1a00c402fa1a00cc02fb1a00740400011802001404000065000000140000202b000034d40000020000000000000000000000000000000000000000184025000c0041636164656d7920436f617400009d33151f0200000012000000960000000100000001000050001e0000003c000058020000210000000000008001008c0000000a00";
+        let msg = GameMessage::try_unpack(&hex::decode(coat).unwrap()).unwrap();
+        world.handle_message(msg);
+
+        let coat = world.entities.get(0x80000158).unwrap();
+        assert_eq!(coat.gfx_id, Some(0x01000474));
+        assert_eq!(coat.obj_desc.base_palette, Some(0x0400007E));
+        assert_eq!(coat.physics.setup_id, Some(0x020000D4));
+        assert_eq!(coat.weenie.icon, 0x06001F15);
+        assert_eq!(coat.value(), Some(150));
+        assert_eq!(coat.burden(), Some(600));
+        assert_eq!(coat.stack_size(), None);
+
+        // Later property updates win over the header
+        world.handle_message(GameMessage::UpdatePropertyInt {
+            guid: 0x80000158,
+            property: PropertyInt::Value as u32,
+            value: 200,
+        });
+        assert_eq!(world.entities.get(0x80000158).unwrap().value(), Some(200));
+    }
+
+    #[test]
+    fn test_stale_updates_are_dropped() {
+        let mut world = WorldState::new(None);
+        let mut door = Entity::new(0x80000001, "Door".to_string(), WorldPosition::default());
+        door.sequences = [0xFFFE, 1, 1, 1, 1, 1, 1, 1, 2].into();
+        world.add_entity(door);
+        let position = |x, instance, position, teleport| GameMessage::UpdatePosition {
+            guid: 0x80000001,
+            pos: WorldPosition {
+                landblock_id: 0,
+                coords: Vector3::new(x, 0.0, 0.0),
+                rotation: Quaternion::identity(),
+            },
+            velocity: None,
+            instance_sequence: instance,
+            position_sequence: position,
+            teleport_sequence: teleport,
+            force_position_sequence: 1,
+        };
+        let x = |world: &WorldState| world.entities.get(0x80000001).unwrap().position.coords.x;
+
+        // Position sequences wrap around
+        assert_eq!(world.handle_message(position(1.0, 2, 0x0001, 1)).len(), 1);
+        assert_eq!(x(&world), 1.0);
+        // A late update from before the wrap, a repeat and one for an older instance
+        assert!(world.handle_message(position(2.0, 2, 0xFFFF, 1)).is_empty());
+        assert!(world.handle_message(position(2.0, 2, 0x0001, 1)).is_empty());
+        assert!(world.handle_message(position(2.0, 1, 0x0002, 1)).is_empty());
+        assert_eq!(x(&world), 1.0);
+        // A teleport moves it whatever its position sequence
+        world.handle_message(position(3.0, 2, 0x0001, 2));
+        assert_eq!(x(&world), 3.0);
+        assert!(world.handle_message(position(2.0, 2, 0x0005, 1)).is_empty());
+        assert_eq!(x(&world), 3.0);
+
+        let state = |state, state_sequence| GameMessage::SetState {
+            guid: 0x80000001,
+            state,
+            instance_sequence: 2,
+            state_sequence,
+        };
+        world.handle_message(state(PhysicsState::ETHEREAL.bits(), 3));
+        world.handle_message(state(0, 2));
+        let door = world.entities.get(0x80000001).unwrap();
+        assert_eq!(door.physics_state, PhysicsState::ETHEREAL);
+
+        let vector = |x, vector_sequence| GameMessage::VectorUpdate {
+            guid: 0x80000001,
+            velocity: Vector3::new(x, 0.0, 0.0),
+            omega: Vector3::zero(),
+            instance_sequence: 2,
+            vector_sequence,
+        };
+        world.handle_message(vector(1.0, 2));
+        world.handle_message(vector(5.0, 1));
+        assert_eq!(world.entities.get(0x80000001).unwrap().velocity.x, 1.0);
+
+        let motion = |movement_sequence| GameMessage::UpdateMotion {
+            guid: 0x80000001,
+            instance_sequence: 2,
+            movement_sequence,
+            server_control_sequence: 1,
+            autonomous: false,
+            movement: MovementData::default(),
+        };
+        assert_eq!(world.handle_message(motion(2)).len(), 1);
+        assert!(world.handle_message(motion(2)).is_empty());
+    }
+
+    #[test]
+    fn test_repeated_game_events_are_refused() {
+        let mut world = WorldState::new(None);
+        assert!(world.accept_game_event(5));
+        assert!(!world.accept_game_event(5));
+        assert!(!world.accept_game_event(4));
+        assert!(world.accept_game_event(6));
+        assert!(!world.accept_game_event(6));
+        assert_eq!(world.game_event_sequence, Some(6));
+        // Sequences wrap around
+        world.game_event_sequence = Some(u32::MAX);
+        assert!(world.accept_game_event(0));
+    }
 }
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use clap::Parser;
+use holtburger_test_server::{ServerConfig, ServerEvent, TestServer, messages};
+use std::net::SocketAddr;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Local stand-in ACE server for client testing", long_about = None)]
+struct Args {
+    #[arg(short, long, default_value = "127.0.0.1")]
+    bind: String,
+    /// Login port; the handshake also uses the next port up
+    #[arg(short, long, default_value_t = 9000)]
+    port: u16,
+    /// Character names offered in the character list
+    #[arg(short, long, default_value = "Tester")]
+    character: Vec<String>,
+    /// Server messages sent once the player enters the world
+    #[arg(short, long)]
+    message: Vec<String>,
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let log_level = if args.verbose {
+        log::LevelFilter::Debug
+    } else {
+        log::LevelFilter::Info
+    };
+    env_logger::Builder::new().filter(None, log_level).init();
+
+    let config = ServerConfig {
+        characters: args
+            .character
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (0x50000001 + i as u32, name.clone()))
+            .collect(),
+        world_messages: args
+            .message
+            .iter()
+            .map(|text| messages::server_message(text))
+            .collect(),
+        ..Default::default()
+    };
+
+    let addr: SocketAddr = format!("{}:{}", args.bind, args.port).parse()?;
+    let (server, mut events) = TestServer::bind(addr, config).await?;
+    log::info!("Test server listening on {}", server.local_addr()?);
+
+    tokio::spawn(async move {
+        while let Some(event) = events.recv().await {
+            match event {
+                ServerEvent::Message(msg) => log::debug!("<<< {:?}", msg),
+                other => log::info!("{:?}", other),
+            }
+        }
+    });
+
+    server.run().await
+}
@@ -0,0 +1,495 @@
+//! A minimal ACE-compatible stand-in server.
+//!
+//! Speaks the real packet layer (connect handshake, ISAAC-keyed checksums, fragments) over
+//! UDP and scripts just enough of the login flow to take a client from login to in-world:
+//! character list, enter-world handshake and player creation, followed by any scripted
+//! messages. Only one client is served at a time.
+
+pub mod messages;
+
+use anyhow::{Result, anyhow};
+use byteorder::{ByteOrder, LittleEndian};
+use holtburger_core::protocol::crypto::{Isaac, IsaacVerifier};
+use holtburger_core::protocol::messages::*;
+use holtburger_core::session::{calculate_payload_hash, get_payload_offset};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub characters: Vec<(u32, String)>,
+    /// Raw game messages sent right after the player is created.
+    pub world_messages: Vec<Vec<u8>>,
+    pub cookie: u64,
+    pub client_id: u16,
+    /// Seed for packets we send (the client's `server_seed`).
+    pub server_seed: u32,
+    /// Seed for packets the client sends.
+    pub client_seed: u32,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            characters: vec![(0x50000001, "Tester".to_string())],
+            world_messages: Vec::new(),
+            cookie: 0x0123456789ABCDEF,
+            client_id: 1,
+            server_seed: 0xC83824AB,
+            client_seed: 0xFBD52C87,
+        }
+    }
+}
+
+/// What the server saw, for tests to assert on.
+#[derive(Debug, Clone)]
+pub enum ServerEvent {
+    LoginRequest { account: String },
+    Connected,
+    Message(Box<GameMessage>),
+    EnteredWorld { character_id: u32 },
+    RejectedPacket,
+    Disconnected,
+}
+
+struct Connection {
+    addr: SocketAddr,
+    account: String,
+    client_id: u16,
+    /// Keys the packets we send.
+    isaac: Isaac,
+    /// Checks the packets the client sends.
+    verifier: IsaacVerifier,
+    packet_sequence: u32,
+    fragment_sequence: u32,
+    last_client_seq: u32,
+    connected: bool,
+    partial: HashMap<u32, Vec<Option<Vec<u8>>>>,
+}
+
+pub struct TestServer {
+    /// Login/world socket; handshake responses arrive on the next port up.
+    socket: UdpSocket,
+    activation: UdpSocket,
+    config: ServerConfig,
+    events: mpsc::UnboundedSender<ServerEvent>,
+    connection: Option<Connection>,
+}
+
+impl TestServer {
+    /// Binds the server socket and its activation socket on the following port.
+    /// With port 0 a free pair of adjacent ports is chosen.
+    pub async fn bind(
+        addr: SocketAddr,
+        config: ServerConfig,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<ServerEvent>)> {
+        let (socket, activation) = if addr.port() == 0 {
+            Self::bind_adjacent(addr).await?
+        } else {
+            let mut activation_addr = addr;
+            activation_addr.set_port(addr.port() + 1);
+            (
+                UdpSocket::bind(addr).await?,
+                UdpSocket::bind(activation_addr).await?,
+            )
+        };
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        Ok((
+            Self {
+                socket,
+                activation,
+                config,
+                events: tx,
+                connection: None,
+            },
+            rx,
+        ))
+    }
+
+    async fn bind_adjacent(addr: SocketAddr) -> Result<(UdpSocket, UdpSocket)> {
+        for _ in 0..32 {
+            let socket = UdpSocket::bind(addr).await?;
+            let port = socket.local_addr()?.port();
+            if port == u16::MAX {
+                continue;
+            }
+            let mut activation_addr = addr;
+            activation_addr.set_port(port + 1);
+            if let Ok(activation) = UdpSocket::bind(activation_addr).await {
+                return Ok((socket, activation));
+            }
+        }
+        Err(anyhow!("Could not find a free pair of adjacent ports"))
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    pub async fn run(mut self) -> Result<()> {
+        let mut buf = [0u8; 1024 * 64];
+        let mut activation_buf = [0u8; 1024];
+        loop {
+            let (data, addr) = tokio::select! {
+                res = self.socket.recv_from(&mut buf) => {
+                    let (len, addr) = res?;
+                    (buf[..len].to_vec(), addr)
+                }
+                res = self.activation.recv_from(&mut activation_buf) => {
+                    let (len, addr) = res?;
+                    (activation_buf[..len].to_vec(), addr)
+                }
+            };
+            if data.len() < HEADER_SIZE {
+                continue;
+            }
+            if let Err(e) = self.handle_packet(&data, addr).await {
+                log::warn!("Test server failed to handle packet from {}: {}", addr, e);
+            }
+        }
+    }
+
+    fn emit(&self, event: ServerEvent) {
+        let _ = self.events.send(event);
+    }
+
+    async fn handle_packet(&mut self, packet: &[u8], addr: SocketAddr) -> Result<()> {
+        let header = PacketHeader::unpack(&packet[..HEADER_SIZE]);
+        let data = &packet[HEADER_SIZE..];
+
+        if header.flags & flags::LOGIN_REQUEST != 0 {
+            return self.handle_login_request(data, addr).await;
+        }
+
+        let Some(conn) = self.connection.as_mut() else {
+            return Ok(());
+        };
+        if conn.addr != addr {
+            return Ok(());
+        }
+
+        if !conn.verify(&header, data) {
+            log::warn!("Test server rejected packet Seq={}", header.sequence);
+            self.emit(ServerEvent::RejectedPacket);
+            return Ok(());
+        }
+
+        if header.flags & flags::CONNECT_RESPONSE != 0 {
+            return self.handle_connect_response(data).await;
+        }
+
+        if header.flags & flags::DISCONNECT != 0 {
+            self.connection = None;
+            self.emit(ServerEvent::Disconnected);
+            return Ok(());
+        }
+
+        if header.flags & flags::BLOB_FRAGMENTS != 0 && header.sequence > conn.last_client_seq {
+            conn.last_client_seq = header.sequence;
+        }
+
+        let optional = OptionalHeaders::unpack(header.flags, data);
+        if let Some(client_time) = optional.echo_request {
+            let mut payload = Vec::new();
+            payload.extend_from_slice(&client_time.to_le_bytes());
+            payload.extend_from_slice(&0f32.to_le_bytes());
+            conn.send(&self.socket, flags::ECHO_RESPONSE, &payload, false)
+                .await?;
+        }
+
+        if header.flags & flags::BLOB_FRAGMENTS != 0 {
+            for message in conn.reassemble(header.flags, data) {
+                self.handle_message(&message).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_login_request(&mut self, data: &[u8], addr: SocketAddr) -> Result<()> {
+        let account = LoginRequest::unpack(data)
+            .map(|login| login.account)
+            .unwrap_or_default();
+        log::debug!("Test server login request for {} from {}", account, addr);
+
+        let config = &self.config;
+        let mut conn = Connection {
+            addr,
+            account: account.clone(),
+            client_id: config.client_id,
+            isaac: Isaac::new(config.server_seed),
+            verifier: IsaacVerifier::new(config.client_seed),
+            packet_sequence: 0,
+            fragment_sequence: 1,
+            last_client_seq: 0,
+            connected: false,
+            partial: HashMap::new(),
+        };
+
+        let mut payload = vec![0u8; 32];
+        LittleEndian::write_f64(&mut payload[OFF_CONNECT_TIME..], 0.0);
+        LittleEndian::write_u64(&mut payload[OFF_CONNECT_COOKIE..], config.cookie);
+        LittleEndian::write_u32(
+            &mut payload[OFF_CONNECT_CLIENT_ID..],
+            config.client_id as u32,
+        );
+        LittleEndian::write_u32(&mut payload[OFF_CONNECT_SERVER_SEED..], config.server_seed);
+        LittleEndian::write_u32(&mut payload[OFF_CONNECT_CLIENT_SEED..], config.client_seed);
+        conn.send_unencrypted(&self.socket, flags::CONNECT_REQUEST, &payload)
+            .await?;
+
+        self.connection = Some(conn);
+        self.emit(ServerEvent::LoginRequest { account });
+        Ok(())
+    }
+
+    async fn handle_connect_response(&mut self, data: &[u8]) -> Result<()> {
+        let cookie = if data.len() >= 8 {
+            LittleEndian::read_u64(&data[..8])
+        } else {
+            0
+        };
+        let Some(conn) = self.connection.as_mut() else {
+            return Ok(());
+        };
+        if cookie != self.config.cookie {
+            return Err(anyhow!(
+                "Connect response with wrong cookie {:016X}",
+                cookie
+            ));
+        }
+        if conn.connected {
+            return Ok(());
+        }
+        conn.connected = true;
+        let _ = self.events.send(ServerEvent::Connected);
+
+        let list = messages::character_list(&conn.account, &self.config.characters);
+        conn.send_message(&self.socket, &list).await
+    }
+
+    async fn handle_message(&mut self, data: &[u8]) -> Result<()> {
+        let message = GameMessage::unpack(data);
+        self.emit(ServerEvent::Message(Box::new(message.clone())));
+        let Some(conn) = self.connection.as_mut() else {
+            return Ok(());
+        };
+
+        match message {
+            GameMessage::CharacterEnterWorldRequest { .. } => {
+                conn.send_message(&self.socket, &messages::enter_world_server_ready())
+                    .await?;
+            }
+            GameMessage::CharacterEnterWorld { id, .. } => {
+                if !self.config.characters.iter().any(|(c, _)| *c == id) {
+                    return Err(anyhow!("Unknown character {:08X}", id));
+                }
+                conn.send_message(&self.socket, &messages::player_create(id))
+                    .await?;
+                for scripted in &self.config.world_messages {
+                    conn.send_message(&self.socket, scripted).await?;
+                }
+                self.emit(ServerEvent::EnteredWorld { character_id: id });
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl Connection {
+    fn verify(&mut self, header: &PacketHeader, data: &[u8]) -> bool {
+        let header_hash = header.calculate_checksum();
+        let payload_hash = calculate_payload_hash(header.flags, data);
+        if header.flags & flags::ENCRYPTED_CHECKSUM == 0 {
+            return header.checksum == header_hash.wrapping_add(payload_hash);
+        }
+        let key = header.checksum.wrapping_sub(header_hash) ^ payload_hash;
+        self.verifier.try_consume(key)
+            || (header.flags & flags::RETRANSMISSION != 0 && self.verifier.was_consumed(key))
+    }
+
+    /// Extracts complete messages from the fragments in a client packet.
+    fn reassemble(&mut self, packet_flags: u32, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut complete = Vec::new();
+        let mut offset = get_payload_offset(packet_flags, data);
+        while offset + FRAGMENT_HEADER_SIZE <= data.len() {
+            let frag = FragmentHeader::unpack(&data[offset..offset + FRAGMENT_HEADER_SIZE]);
+            let end = offset + frag.size as usize;
+            if end > data.len() || (frag.size as usize) < FRAGMENT_HEADER_SIZE {
+                break;
+            }
+            let chunk = data[offset + FRAGMENT_HEADER_SIZE..end].to_vec();
+            offset = end;
+
+            if frag.count <= 1 {
+                complete.push(chunk);
+                continue;
+            }
+            let parts = self
+                .partial
+                .entry(frag.sequence)
+                .or_insert_with(|| vec![None; frag.count as usize]);
+            if let Some(slot) = parts.get_mut(frag.index as usize) {
+                *slot = Some(chunk);
+            }
+            if parts.iter().all(Option::is_some)
+                && let Some(parts) = self.partial.remove(&frag.sequence)
+            {
+                complete.push(parts.into_iter().flatten().flatten().collect());
+            }
+        }
+        complete
+    }
+
+    async fn send_unencrypted(
+        &mut self,
+        socket: &UdpSocket,
+        flags: u32,
+        payload: &[u8],
+    ) -> Result<()> {
+        let mut header = PacketHeader {
+            flags,
+            id: 0,
+            size: payload.len() as u16,
+            ..Default::default()
+        };
+        header.checksum = header
+            .calculate_checksum()
+            .wrapping_add(calculate_payload_hash(flags, payload));
+        self.transmit(socket, &header, payload).await
+    }
+
+    /// Sends an ISAAC-keyed packet, acking the client's latest sequence.
+    async fn send(
+        &mut self,
+        socket: &UdpSocket,
+        mut flags: u32,
+        body: &[u8],
+        sequenced: bool,
+    ) -> Result<()> {
+        let mut payload = Vec::new();
+        if self.last_client_seq > 0 {
+            flags |= flags::ACK_SEQUENCE;
+            payload.extend_from_slice(&self.last_client_seq.to_le_bytes());
+        }
+        payload.extend_from_slice(body);
+
+        // Unsequenced packets repeat the current sequence instead of taking a new one
+        if sequenced {
+            self.packet_sequence += 1;
+        }
+        let mut header = PacketHeader {
+            sequence: self.packet_sequence,
+            flags: flags | flags::ENCRYPTED_CHECKSUM,
+            id: self.client_id,
+            size: payload.len() as u16,
+            ..Default::default()
+        };
+        let key = self.isaac.current_key;
+        self.isaac.consume_key();
+        header.checksum = header
+            .calculate_checksum()
+            .wrapping_add(calculate_payload_hash(header.flags, &payload) ^ key);
+        self.transmit(socket, &header, &payload).await
+    }
+
+    /// Sends a game message, one fragment per packet.
+    async fn send_message(&mut self, socket: &UdpSocket, message: &[u8]) -> Result<()> {
+        let chunks: Vec<&[u8]> = message.chunks(MAX_FRAGMENT_DATA_SIZE).collect();
+        let count = chunks.len() as u16;
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let frag = FragmentHeader {
+                sequence: self.fragment_sequence,
+                id: self.fragment_sequence,
+                count,
+                index: index as u16,
+                size: (chunk.len() + FRAGMENT_HEADER_SIZE) as u16,
+                queue: queues::GENERAL,
+            };
+            let mut body = vec![0u8; FRAGMENT_HEADER_SIZE];
+            frag.pack(&mut body);
+            body.extend_from_slice(chunk);
+            self.send(socket, flags::BLOB_FRAGMENTS, &body, true)
+                .await?;
+        }
+        self.fragment_sequence += 1;
+        Ok(())
+    }
+
+    async fn transmit(
+        &mut self,
+        socket: &UdpSocket,
+        header: &PacketHeader,
+        payload: &[u8],
+    ) -> Result<()> {
+        let mut packet = vec![0u8; HEADER_SIZE];
+        header.pack(&mut packet);
+        packet.extend_from_slice(payload);
+        socket.send_to(&packet, self.addr).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use holtburger_core::{Client, ClientEvent};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_client_enters_world() {
+        let config = ServerConfig {
+            world_messages: vec![messages::server_message("Welcome to the test server")],
+            ..Default::default()
+        };
+        let (server, mut server_events) = TestServer::bind("127.0.0.1:0".parse().unwrap(), config)
+            .await
+            .unwrap();
+        let port = server.local_addr().unwrap().port();
+        tokio::spawn(server.run());
+
+        let mut client = Client::new("127.0.0.1", port, "tester", Some("Tester".into()))
+            .await
+            .unwrap();
+        let (tx, mut client_events) = mpsc::unbounded_channel();
+        client.set_event_tx(tx);
+        tokio::spawn(async move { client.run("password").await });
+
+        let (mut entered, mut welcomed) = (false, false);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !(entered && welcomed) {
+                match client_events.recv().await {
+                    Some(ClientEvent::PlayerEntered { .. }) => entered = true,
+                    Some(ClientEvent::Message(msg)) if msg.text.contains("test server") => {
+                        welcomed = true
+                    }
+                    Some(_) => {}
+                    None => break,
+                }
+            }
+        })
+        .await
+        .expect("client never entered the world");
+        assert!(entered && welcomed);
+
+        let mut saw_login_complete = false;
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while let Some(event) = server_events.recv().await {
+                assert!(!matches!(event, ServerEvent::RejectedPacket));
+                if let ServerEvent::Message(message) = event
+                    && let GameMessage::GameAction { action, .. } = *message
+                    && action == GameActionKind::LoginComplete
+                {
+                    saw_login_complete = true;
+                    break;
+                }
+            }
+        })
+        .await
+        .expect("server never saw login complete");
+        assert!(saw_login_complete);
+    }
+}
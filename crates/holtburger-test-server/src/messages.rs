@@ -0,0 +1,49 @@
+//! Builders for the server-to-client game messages the stand-in server scripts.
+
+use holtburger_core::protocol::bodies::{CharacterIdentity, CharacterList, CharacterListTrailer};
+use holtburger_core::protocol::messages::{GameMessage, opcodes};
+use holtburger_core::protocol::parse::pack_body;
+
+pub fn character_list(account: &str, characters: &[(u32, String)]) -> Vec<u8> {
+    let body = CharacterList {
+        unknown: 0,
+        characters: characters
+            .iter()
+            .map(|(id, name)| CharacterIdentity {
+                id: *id,
+                name: name.clone(),
+                delete_time: 0,
+            })
+            .collect(),
+        trailer: Some(CharacterListTrailer {
+            unknown: 0,
+            slots: 11,
+            account: account.to_string(),
+            use_turbine_chat: 1,
+            has_throne_of_destiny: 1,
+        }),
+    };
+    pack_body(opcodes::CHARACTER_LIST, &body)
+}
+
+pub fn enter_world_server_ready() -> Vec<u8> {
+    GameMessage::CharacterEnterWorldServerReady.pack()
+}
+
+pub fn player_create(guid: u32) -> Vec<u8> {
+    GameMessage::PlayerCreate { player_id: guid }.pack()
+}
+
+pub fn server_message(text: &str) -> Vec<u8> {
+    GameMessage::ServerMessage {
+        message: text.to_string(),
+    }
+    .pack()
+}
+
+pub fn boot_account(reason: &str) -> Vec<u8> {
+    GameMessage::BootAccount {
+        reason: reason.to_string(),
+    }
+    .pack()
+}
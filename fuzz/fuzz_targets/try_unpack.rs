@@ -0,0 +1,8 @@
+#![no_main]
+
+use holtburger_core::protocol::messages::GameMessage;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = GameMessage::try_unpack(data);
+});
@@ -0,0 +1,40 @@
+#![no_main]
+
+//! Mutates the parser test vectors rather than starting from random bytes, so that the
+//! fuzzer spends its time deep inside ObjectCreate and PlayerDescription bodies.
+//!
+//! Input layout: [vector index:u8][truncate to:u16][(position:u16, value:u8)...]
+
+use holtburger_core::protocol::messages::GameMessage;
+use libfuzzer_sys::fuzz_target;
+use std::sync::LazyLock;
+
+static VECTORS: LazyLock<Vec<Vec<u8>>> = LazyLock::new(|| {
+    include_str!("../../crates/holtburger-core/testdata/messages.hex")
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            (0..line.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&line[i..i + 2], 16).unwrap())
+                .collect()
+        })
+        .collect()
+});
+
+fuzz_target!(|input: &[u8]| {
+    let [index, len_lo, len_hi, edits @ ..] = input else {
+        return;
+    };
+    let vector = &VECTORS[*index as usize % VECTORS.len()];
+    let len = u16::from_le_bytes([*len_lo, *len_hi]) as usize % (vector.len() + 1);
+    let mut data = vector[..len].to_vec();
+    for edit in edits.chunks_exact(3) {
+        if data.is_empty() {
+            break;
+        }
+        let position = u16::from_le_bytes([edit[0], edit[1]]) as usize % data.len();
+        data[position] = edit[2];
+    }
+    let _ = GameMessage::try_unpack(&data);
+});